@@ -1,6 +1,9 @@
-use std::io;
+use std::fs;
+use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
 use std::collections::{HashSet, HashMap};
+use std::sync::{Mutex, atomic::{AtomicUsize, Ordering}};
+use std::time::UNIX_EPOCH;
 use walkdir::{WalkDir, DirEntry};
 use clap::{Command, Arg, value_parser};
 use byteorder::{LittleEndian, WriteBytesExt};
@@ -8,6 +11,9 @@ use cdragon_prop::{
     is_binfile_path,
     BinEntryPath,
     BinClassName,
+    BinHashMappers,
+    BinSerializer,
+    JsonSerializer,
     PropFile,
 };
 use cdragon_utils::GuardedFile;
@@ -135,6 +141,280 @@ fn build_entrydb<P: AsRef<Path>, Q: AsRef<Path>>(root: P, output: Q, verbose: bo
 }
 
 
+/// Name of the file listing exported bin files, in the output directory
+const EXPORT_MANIFEST_NAME: &str = "export-manifest.txt";
+
+/// Source bin file of exported entries
+struct ExportedFile {
+    /// Modification time, in nanoseconds since the Unix epoch
+    mtime: u128,
+    size: u64,
+    entries: Vec<BinEntryPath>,
+}
+
+/// List of exported bin files, used to skip unchanged files
+///
+/// Each line describes a source file, with tab-separated fields:
+/// path (relative to the bin directory), mtime, size, exported entries (comma-separated)
+#[derive(Default)]
+struct ExportManifest {
+    files: HashMap<String, ExportedFile>,
+}
+
+impl ExportManifest {
+    /// Load a manifest, return an empty one if the file does not exist
+    fn load(path: &Path) -> Result<Self> {
+        let file = match fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut files = HashMap::new();
+        for line in io::BufReader::new(file).lines() {
+            let line = line?;
+            let mut fields = line.split('\t');
+            let (Some(path), Some(mtime), Some(size), Some(entries), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next(), fields.next()) else {
+                return Err(format!("invalid export manifest line: {:?}", line).into());
+            };
+            let entries = entries.split(',')
+                .filter(|s| !s.is_empty())
+                .map(|s| u32::from_str_radix(s, 16).map(BinEntryPath::from))
+                .collect::<Result<_, _>>()?;
+            files.insert(path.to_owned(), ExportedFile { mtime: mtime.parse()?, size: size.parse()?, entries });
+        }
+        Ok(Self { files })
+    }
+
+    /// Map each entry to the file it is exported from: the first one, sorted by path
+    fn entry_owners(&self) -> HashMap<BinEntryPath, &str> {
+        let mut owners = HashMap::<BinEntryPath, &str>::new();
+        for (filepath, file) in &self.files {
+            for hpath in &file.entries {
+                owners.entry(*hpath)
+                    .and_modify(|owner| *owner = (*owner).min(filepath.as_str()))
+                    .or_insert(filepath.as_str());
+            }
+        }
+        owners
+    }
+
+    /// Write the manifest, files are sorted by path
+    fn write<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        let mut paths: Vec<&String> = self.files.keys().collect();
+        paths.sort();
+        for path in paths {
+            let file = &self.files[path];
+            let entries: Vec<String> = file.entries.iter().map(|h| format!("{:08x}", h.hash)).collect();
+            writeln!(w, "{}\t{}\t{}\t{}", path, file.mtime, file.size, entries.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the modification time and size of a file
+fn file_mtime_and_size(path: &Path) -> io::Result<(u128, u64)> {
+    let metadata = fs::metadata(path)?;
+    let mtime = metadata.modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    Ok((mtime, metadata.len()))
+}
+
+/// Scan entries of a bin file, return their paths
+///
+/// Return `None` for patch files, which are not exported.
+fn scan_binfile_entries(path: &Path) -> Result<Option<Vec<BinEntryPath>>> {
+    let scanner = PropFile::scan_entries_from_path(path)?;
+    if scanner.is_patch {
+        return Ok(None);
+    }
+    let entries = scanner.headers()
+        .map(|result| result.map(|(hpath, _)| hpath))
+        .collect::<Result<_, _>>()?;
+    Ok(Some(entries))
+}
+
+/// Export entries of a bin file to JSON files, one per entry
+///
+/// Only entries for which `is_owned` returns `true` are written. Return the number of written
+/// entries.
+/// The whole file is parsed before writing anything, so that invalid files are not partially exported.
+fn export_binfile_entries(path: &Path, output: &Path, hmappers: &BinHashMappers, is_owned: impl Fn(BinEntryPath) -> bool) -> Result<usize> {
+    let scanner = PropFile::scan_entries_from_path(path)?;
+    let entries = scanner.filter_parse(|hpath, _| is_owned(hpath)).collect::<Result<Vec<_>, _>>()?;
+    for entry in &entries {
+        let mut buf = Vec::new();
+        JsonSerializer::new(&mut buf, hmappers).write_entry(entry)?;
+        fs::write(output.join(format!("{:08x}.json", entry.path.hash)), buf)?;
+    }
+    Ok(entries.len())
+}
+
+/// Run a function on items, in parallel, return results in item order
+fn parallel_map<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next_item = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::<(usize, R)>::with_capacity(items.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()).max(1) {
+            scope.spawn(|| {
+                loop {
+                    let index = next_item.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else { break };
+                    let result = f(item);
+                    results.lock().unwrap().push((index, result));
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Counters reported by [`export_entries()`]
+#[derive(Debug, Default, PartialEq)]
+struct ExportStats {
+    /// Bin files exported
+    exported_files: usize,
+    /// Bin files unchanged since the previous export
+    unchanged_files: usize,
+    /// Bin files which failed to be exported
+    failed_files: usize,
+    /// JSON files written
+    written_entries: usize,
+    /// JSON files removed
+    removed_entries: usize,
+    /// Entries exported, in total
+    total_entries: usize,
+    /// Entries also found in another bin file
+    duplicate_entries: usize,
+}
+
+/// Export all entries of a bin directory to JSON files
+///
+/// Entries are written to `<output>/<hex-path>.json`.
+/// Bin files unchanged since the previous export (same mtime and size) are skipped, unless
+/// `force` is set. JSON files of entries that are not exported anymore are removed.
+///
+/// Entries found in several bin files are exported from the first file, sorted by path, so that
+/// the result does not depend on the order files are processed in.
+///
+/// Exported files are not used by the viewer, which still fetches entries from bin files.
+fn export_entries(root: &Path, output: &Path, hmappers: &BinHashMappers, jobs: usize, force: bool) -> Result<ExportStats> {
+    fs::create_dir_all(output)?;
+    let manifest_path = output.join(EXPORT_MANIFEST_NAME);
+    // Always needed to remove JSON files of entries which are not exported anymore
+    let previous = ExportManifest::load(&manifest_path)?;
+
+    // Collect bin files, keep unchanged ones
+    let mut manifest = ExportManifest::default();
+    let mut paths = HashMap::<String, PathBuf>::new();
+    let mut changed = Vec::<(String, u128, u64)>::new();
+    for entry in WalkDir::new(root).into_iter().filter_entry(is_binfile_direntry) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue
+        }
+        let path = entry.into_path();
+        let filepath = normalize_binfile_path(path.strip_prefix(root)?);
+        let (mtime, size) = file_mtime_and_size(&path)?;
+        match previous.files.get(&filepath) {
+            Some(file) if !force && file.mtime == mtime && file.size == size => {
+                let entries = file.entries.clone();
+                manifest.files.insert(filepath.clone(), ExportedFile { mtime, size, entries });
+            }
+            _ => changed.push((filepath.clone(), mtime, size)),
+        }
+        paths.insert(filepath, path);
+    }
+    let unchanged_count = manifest.files.len();
+
+    // Scan entries of changed files, in parallel
+    // Failed files are reported but not added to the manifest, to be retried on the next run
+    let mut failed_count = 0;
+    let scanned = parallel_map(&changed, jobs, |(filepath, _, _)| scan_binfile_entries(&paths[filepath]).map_err(|e| e.to_string()));
+    for ((filepath, mtime, size), result) in changed.iter().zip(scanned) {
+        match result {
+            Ok(Some(entries)) => {
+                manifest.files.insert(filepath.clone(), ExportedFile { mtime: *mtime, size: *size, entries });
+            }
+            Ok(None) => {}  // patch file
+            Err(e) => {
+                eprintln!("failed to export entries from '{}': {}", filepath, e);
+                failed_count += 1;
+            }
+        }
+    }
+
+    // Export changed files, and unchanged files owning entries previously exported from another file
+    let owners = manifest.entry_owners();
+    let previous_owners = previous.entry_owners();
+    let changed: HashSet<&str> = changed.iter().map(|(filepath, _, _)| filepath.as_str()).collect();
+    let mut to_export: Vec<String> = manifest.files.iter()
+        .filter(|(filepath, file)| {
+            changed.contains(filepath.as_str()) || file.entries.iter().any(|hpath| {
+                owners[hpath] == filepath.as_str() && previous_owners.get(hpath) != Some(&filepath.as_str())
+            })
+        })
+        .map(|(filepath, _)| filepath.clone())
+        .collect();
+    to_export.sort_unstable();
+    let results = parallel_map(&to_export, jobs, |filepath| {
+        export_binfile_entries(&paths[filepath], output, hmappers, |hpath| owners.get(&hpath) == Some(&filepath.as_str()))
+            .map_err(|e| e.to_string())
+    });
+    let mut exported_count = 0;
+    let mut written_count = 0;
+    let mut failed_files = Vec::new();
+    for (filepath, result) in to_export.iter().zip(results) {
+        match result {
+            Ok(n) => {
+                exported_count += 1;
+                written_count += n;
+            }
+            Err(e) => {
+                eprintln!("failed to export entries from '{}': {}", filepath, e);
+                failed_files.push(filepath);
+                failed_count += 1;
+            }
+        }
+    }
+    let duplicate_count = manifest.files.values().map(|f| f.entries.len()).sum::<usize>() - owners.len();
+    drop(owners);
+    for filepath in failed_files {
+        manifest.files.remove(filepath);
+    }
+
+    // Remove JSON files of entries which are not exported anymore
+    let current: HashSet<BinEntryPath> = manifest.files.values().flat_map(|f| f.entries.iter().copied()).collect();
+    let mut removed_count = 0;
+    for hpath in previous.files.values().flat_map(|f| f.entries.iter()) {
+        if !current.contains(hpath) {
+            match fs::remove_file(output.join(format!("{:08x}.json", hpath.hash))) {
+                Ok(()) => removed_count += 1,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    GuardedFile::for_scope(&manifest_path, |file| {
+        manifest.write(io::BufWriter::new(file))
+    })?;
+
+    Ok(ExportStats {
+        exported_files: exported_count,
+        unchanged_files: unchanged_count,
+        failed_files: failed_count,
+        written_entries: written_count,
+        removed_entries: removed_count,
+        total_entries: current.len(),
+        duplicate_entries: duplicate_count,
+    })
+}
+
+
 fn main() {
     let appm = Command::new("cdragon-binviewer")
         .about("Tools for CDragon bin viewer")
@@ -157,6 +437,36 @@ fn main() {
                  .value_parser(value_parser!(PathBuf))
                  .help("root path for BIN files"))
             )
+        .subcommand(
+            Command::new("export-entries")
+            .about("export bin entries to JSON files, one per entry (not used by the viewer)")
+            .arg(Arg::new("output")
+                 .short('o')
+                 .value_name("DIR")
+                 .value_parser(value_parser!(PathBuf))
+                 .default_value("entries")
+                 .help("output directory, entries are written to `<hex-path>.json`"))
+            .arg(Arg::new("hashes")
+                 .short('H')
+                 .value_name("DIR")
+                 .required(true)
+                 .value_parser(value_parser!(PathBuf))
+                 .help("directory with hash lists"))
+            .arg(Arg::new("jobs")
+                 .short('j')
+                 .value_name("N")
+                 .value_parser(value_parser!(usize))
+                 .help("number of threads (default: number of CPUs)"))
+            .arg(Arg::new("force")
+                 .long("force")
+                 .action(clap::ArgAction::SetTrue)
+                 .help("export all files, even unchanged ones (e.g. after a hash list update)"))
+            .arg(Arg::new("dir")
+                 .value_name("DIR")
+                 .required(true)
+                 .value_parser(value_parser!(PathBuf))
+                 .help("root path for BIN files"))
+            )
         .get_matches();
 
     let verbose = appm.get_flag("verbose");
@@ -167,6 +477,17 @@ fn main() {
             let dbpath = subm.get_one::<PathBuf>("db").unwrap();
             build_entrydb(dirpath, dbpath, verbose).unwrap();
         },
+        Some(("export-entries", subm)) => {
+            let dirpath = subm.get_one::<PathBuf>("dir").unwrap();
+            let output = subm.get_one::<PathBuf>("output").unwrap();
+            let hmappers = BinHashMappers::from_dirpath(subm.get_one::<PathBuf>("hashes").unwrap()).unwrap();
+            let jobs = subm.get_one::<usize>("jobs").copied()
+                .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+            let stats = export_entries(dirpath, output, &hmappers, jobs, subm.get_flag("force")).unwrap();
+            println!("Entries exported to {}", output.display());
+            println!("  files: {} exported, {} unchanged, {} failed", stats.exported_files, stats.unchanged_files, stats.failed_files);
+            println!("  entries: {} written, {} removed, {} total, {} duplicates", stats.written_entries, stats.removed_entries, stats.total_entries, stats.duplicate_entries);
+        },
         _ => {
             eprintln!("Unexpected subcommand");
             std::process::exit(2);
@@ -174,3 +495,96 @@ fn main() {
     }
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use cdragon_prop::{compute_binhash, testing::{TestEntry, write_bin_file}};
+
+    /// Write a bin file with empty entries, given by path
+    fn write_entries_file(path: &Path, paths: &[&str]) {
+        let entries: Vec<TestEntry> = paths.iter().map(|path| ("TestData", *path, &[][..])).collect();
+        write_bin_file(path, &entries);
+    }
+
+    /// Name of the JSON file of an entry
+    fn entry_file_name(path: &str) -> String {
+        format!("{:08x}.json", compute_binhash(path))
+    }
+
+    /// List file names of a directory
+    fn list_files(dir: &Path) -> BTreeSet<String> {
+        fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn export_twice() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("bins");
+        let output = tmp.path().join("entries");
+        fs::create_dir(&root).unwrap();
+        let hmappers = BinHashMappers::default();
+
+        // Entry 2 is in both files, it is exported from the first one
+        write_entries_file(&root.join("a.bin"), &["Entry1", "Entry2"]);
+        write_entries_file(&root.join("b.bin"), &["Entry2", "Entry3"]);
+        fs::write(root.join("c.bin"), b"PROP\x01\0\0\0").unwrap();
+
+        let stats = export_entries(&root, &output, &hmappers, 2, false).unwrap();
+        assert_eq!(stats, ExportStats {
+            exported_files: 2,
+            unchanged_files: 0,
+            failed_files: 1,
+            written_entries: 3,
+            removed_entries: 0,
+            total_entries: 3,
+            duplicate_entries: 1,
+        });
+        assert_eq!(list_files(&output), BTreeSet::from([
+            entry_file_name("Entry1"),
+            entry_file_name("Entry2"),
+            entry_file_name("Entry3"),
+            EXPORT_MANIFEST_NAME.into(),
+        ]));
+        let manifest = ExportManifest::load(&output.join(EXPORT_MANIFEST_NAME)).unwrap();
+        assert_eq!(manifest.files.keys().map(String::as_str).collect::<BTreeSet<_>>(), BTreeSet::from(["a.bin", "b.bin"]));
+
+        // Remove the first file: its entries are removed, entry 2 is now exported from the second one
+        // Fix the failed file, it is retried
+        fs::remove_file(root.join("a.bin")).unwrap();
+        write_entries_file(&root.join("c.bin"), &["Entry4"]);
+
+        let stats = export_entries(&root, &output, &hmappers, 2, false).unwrap();
+        assert_eq!(stats, ExportStats {
+            exported_files: 2,
+            unchanged_files: 1,
+            failed_files: 0,
+            written_entries: 3,
+            removed_entries: 1,
+            total_entries: 3,
+            duplicate_entries: 0,
+        });
+        assert_eq!(list_files(&output), BTreeSet::from([
+            entry_file_name("Entry2"),
+            entry_file_name("Entry3"),
+            entry_file_name("Entry4"),
+            EXPORT_MANIFEST_NAME.into(),
+        ]));
+
+        // Nothing changed, everything is skipped
+        let stats = export_entries(&root, &output, &hmappers, 2, false).unwrap();
+        assert_eq!(stats, ExportStats {
+            exported_files: 0,
+            unchanged_files: 2,
+            failed_files: 0,
+            written_entries: 0,
+            removed_entries: 0,
+            total_entries: 3,
+            duplicate_entries: 0,
+        });
+    }
+}
@@ -37,38 +37,23 @@ impl<'a> BinViewBuilder<'a> {
     }
 
     pub fn format_entry_path(&self, h: BinEntryPath) -> String {
-        match h.get_str(self.hash_mappers) {
-            Some(s) => s.to_string(),
-            _ => format!("{{{:x}}}", h),
-        }
+        h.seek_str(self.hash_mappers).to_string()
     }
 
     pub fn format_type_name(&self, h: BinClassName) -> String {
-        match h.get_str(self.hash_mappers) {
-            Some(s) => s.to_string(),
-            _ => format!("{{{:x}}}", h),
-        }
+        h.seek_str(self.hash_mappers).to_string()
     }
 
     pub fn format_field_name(&self, h: BinFieldName) -> String {
-        match h.get_str(self.hash_mappers) {
-            Some(s) => s.to_string(),
-            _ => format!("{{{:x}}}", h),
-        }
+        h.seek_str(self.hash_mappers).to_string()
     }
 
     pub fn format_hash_value(&self, h: BinHashValue) -> String {
-        match h.get_str(self.hash_mappers) {
-            Some(s) => s.to_string(),
-            _ => format!("{{{:x}}}", h),
-        }
+        h.seek_str(self.hash_mappers).to_string()
     }
 
     pub fn format_path_value(&self, h: BinPathValue) -> String {
-        match h.get_str(self.hash_mappers) {
-            Some(s) => s.to_string(),
-            _ => format!("{{{:x}}}", h),
-        }
+        h.seek_str(self.hash_mappers).to_string()
     }
 }
 
@@ -120,6 +105,11 @@ pub fn view_binfield(b: &mut BinViewBuilder, field: &BinField) -> Html {
         let v = field.downcast::<T>().unwrap();
         (T::NESTED, v.view_type(b), v.view_field_value(b))
     });
+    // `BinList` is used for both list types, `List2` is only known from the field type
+    let v_type = match field.downcast::<BinList>() {
+        Some(v) if field.vtype == BinType::List2 => view_list_type("list2", v.vtype),
+        _ => v_type,
+    };
 
     let fname = html! { <span class="bin-field-name">{ b.format_field_name(field.name) }</span> };
     let ftype = html! { <span class="bin-field-type">{ v_type }</span> };
@@ -193,23 +183,34 @@ impl_viewable!(BinColor, BinType::Color, this => {
 });
 impl_viewable!(BinString, BinType::String, (this, b) => {
     let this = &this.0;
-    if this.ends_with(".dds") || this.ends_with(".DDS") || this.ends_with(".tex") {
-        let path = this[..this.len()-4].to_lowercase();
-        let url = format!("{}/{}.png", b.assets_base_url, path);
-        html! {
-            <a href={url.clone()} class="tooltipped">{ this }<br/><img src={url} /></a>
-        }
-    } else {
-        this.into()
+    match settings::asset_preview_url(&b.assets_base_url, this) {
+        Some(url) => view_asset_preview(this, url),
+        None => this.into(),
     }
 });
-impl_viewable!(BinHash, BinType::Hash, (this, b) => html! {
-    <span class="bin-hash-value">{ b.format_hash_value(this.0) }</span>
+impl_viewable!(BinHash, BinType::Hash, (this, b) => {
+    let s = b.format_hash_value(this.0);
+    // Hash values may be hashed asset paths
+    match settings::asset_preview_url(&b.assets_base_url, &s) {
+        Some(url) if this.0.get_str(b.hash_mappers).is_some() => html! {
+            <span class="bin-hash-value">{ view_asset_preview(&s, url) }</span>
+        },
+        _ => html! {
+            <span class="bin-hash-value">{ s }</span>
+        },
+    }
 });
 impl_viewable!(BinPath, BinType::Hash, (this, b) => html! {
     <span class="bin-path-value">{ b.format_path_value(this.0) }</span>
 });
 
+/// View an asset path, with an image preview tooltip
+fn view_asset_preview(path: &str, url: String) -> Html {
+    html! {
+        <a href={url.clone()} class="tooltipped">{ path }<br/><img src={url} /></a>
+    }
+}
+
 impl BinViewable for BinList {
     const NESTED: bool = true;
 
@@ -220,13 +221,18 @@ impl BinViewable for BinList {
     }
 
     fn view_type(&self, _b: &BinViewBuilder) -> Html {
-        html! {
-            <span>
-                <span class="bin-container-type">{ "list" }</span>
-                {" "}
-                <span class="bin-struct-type">{ basic_bintype_name(self.vtype) }</span>
-            </span>
-        }
+        view_list_type("list", self.vtype)
+    }
+}
+
+/// View the type of a list, `container` is the name of the list type
+fn view_list_type(container: &'static str, vtype: BinType) -> Html {
+    html! {
+        <span>
+            <span class="bin-container-type">{ container }</span>
+            {" "}
+            <span class="bin-struct-type">{ basic_bintype_name(vtype) }</span>
+        </span>
     }
 }
 
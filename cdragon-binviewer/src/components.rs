@@ -1,5 +1,6 @@
 pub use searchbar::SearchBar;
 pub use resultentry::ResultEntry;
+pub use hashesstatus::HashesStatus;
 
 mod searchbar {
     use yew::prelude::*;
@@ -12,7 +13,7 @@ mod searchbar {
         pub on_search: Callback<String>,
     }
 
-    const PLACEHOLDER: &str = "Search entries: path string/hash, type hash, .bin path; prefix with `-` to exclude";
+    const PLACEHOLDER: &str = "Search entries: path string/hash, type hash, `type:NAME`, .bin path; prefix with `-` to exclude";
 
     #[function_component(SearchBar)]
     pub fn search_bar(props: &Props) -> Html {
@@ -33,3 +34,75 @@ mod searchbar {
 
 mod resultentry;
 
+mod hashesstatus {
+    use yew::prelude::*;
+    use cdragon_hashes::bin::BinHashKind;
+
+    #[derive(Clone, PartialEq, Properties)]
+    pub struct Props {
+        /// Hash mappers loaded so far
+        pub loaded: Vec<BinHashKind>,
+        /// Hash mappers which failed to load
+        pub failed: Vec<BinHashKind>,
+    }
+
+    /// Indicate which hash mappers are still being loaded, or failed to load
+    ///
+    /// Nothing is displayed once all mappers are loaded.
+    #[function_component(HashesStatus)]
+    pub fn hashes_status(props: &Props) -> Html {
+        match status_text(&props.loaded, &props.failed) {
+            None => html! {},
+            Some(text) => html! {
+                <div id="hashes-status">{ text }</div>
+            },
+        }
+    }
+
+    /// Get the status text, `None` if all mappers are loaded
+    pub(crate) fn status_text(loaded: &[BinHashKind], failed: &[BinHashKind]) -> Option<String> {
+        let label = |kind: &BinHashKind| match kind {
+            BinHashKind::EntryPath => "entry paths",
+            BinHashKind::ClassName => "class names",
+            BinHashKind::FieldName => "field names",
+            BinHashKind::HashValue => "hash values",
+        };
+        let pending: Vec<&str> = BinHashKind::VARIANTS
+            .iter()
+            .filter(|kind| !loaded.contains(kind) && !failed.contains(kind))
+            .map(label)
+            .collect();
+        let failed: Vec<&str> = BinHashKind::VARIANTS
+            .iter()
+            .filter(|kind| failed.contains(kind))
+            .map(label)
+            .collect();
+
+        let mut parts = Vec::new();
+        if !pending.is_empty() {
+            parts.push(format!("Loading hashes: {}", pending.join(", ")));
+        }
+        if !failed.is_empty() {
+            parts.push(format!("Failed to load hashes: {}", failed.join(", ")));
+        }
+        if parts.is_empty() { None } else { Some(parts.join("; ")) }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn failed_mappers_are_not_pending() {
+            use BinHashKind::*;
+            assert_eq!(status_text(&[], &[]).as_deref(),
+                Some("Loading hashes: entry paths, class names, field names, hash values"));
+            assert_eq!(status_text(&[EntryPath, ClassName], &[FieldName]).as_deref(),
+                Some("Loading hashes: hash values; Failed to load hashes: field names"));
+            assert_eq!(status_text(&[EntryPath, ClassName, HashValue], &[FieldName]).as_deref(),
+                Some("Failed to load hashes: field names"));
+            assert_eq!(status_text(&[EntryPath, ClassName, FieldName, HashValue], &[]), None);
+        }
+    }
+}
+
@@ -4,6 +4,7 @@ use yew::prelude::*;
 use wasm_bindgen::{JsValue, UnwrapThrowExt};
 use cdragon_prop::{
     BinEntryPath,
+    BinClassName,
     BinEntry,
 };
 use crate::{
@@ -21,8 +22,12 @@ pub struct Props {
     /// Send back actions to the app
     pub dispatch: Callback<AppAction>,
     pub hpath: BinEntryPath,
+    /// Type of the entry
+    pub htype: BinClassName,
     /// True to forcily open the entry and jump to it when loaded
     pub focus: bool,
+    /// Number of loaded hash mappers, used to render again when new names are available
+    pub hashes_loaded: usize,
 }
 
 
@@ -51,17 +56,16 @@ impl State {
 #[function_component(ResultEntry)]
 pub fn result_entry(props: &Props) -> Html {
     let services = use_context::<AppContext>().unwrap();
+    let hmappers = services.hmappers.borrow();
     let state = use_state(|| State::Empty);
-
-    let (htype, ifile) = services.entrydb.get_entry(props.hpath).unwrap();
+    let htype = props.htype;
 
     let load_entry = {
         let services = services.clone();
         let state = state.clone();
         let hpath = props.hpath;
-        let ifile = ifile;
         use_async(async move {
-            let file = services.entrydb.get_filename(ifile).unwrap();
+            let file = services.entrydb.file_for_entry(hpath).unwrap();
             let result = services.fetch_entry(file, hpath).await;
             state.set(match result {
                 Ok(entry) => State::Opened(entry.into()),
@@ -96,7 +100,7 @@ pub fn result_entry(props: &Props) -> Html {
 
     // No type should match an entry, so this should be fine
     let (type_href, on_type_click) = {
-        let hstr = htype.seek_str(&services.hmappers);
+        let hstr = htype.seek_str(&hmappers);
         let pattern = format!("{}", hstr);
         (
             build_app_url(&pattern, None),
@@ -104,9 +108,9 @@ pub fn result_entry(props: &Props) -> Html {
         )
     };
 
-    let file = services.entrydb.get_filename(ifile).unwrap();
+    let file = services.entrydb.file_for_entry(props.hpath).unwrap();
     let (file_href, on_file_click, file_json_href) = {
-        let file2 = file.clone();
+        let file2 = file.to_owned();
         (
             build_app_url(file, None),
             handle_normal_click(props.dispatch.reform(move |_| AppAction::SearchEntries(file2.clone()))),
@@ -140,7 +144,7 @@ pub fn result_entry(props: &Props) -> Html {
         );
     }
 
-    let mut b = BinViewBuilder::new(&services.hmappers, on_link_click);
+    let mut b = BinViewBuilder::new(&hmappers, on_link_click);
     let entry = state.entry();
     let item_class = if state.closed() { Some("closed") } else { None };
     let element_id = entry_element_id(props.hpath);
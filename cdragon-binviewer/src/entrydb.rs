@@ -120,13 +120,42 @@ impl EntryDatabase {
         self.filenames.get(ifile)
     }
 
+    /// Get the path of the file containing an entry
+    pub fn file_for_entry(&self, hash: BinEntryPath) -> Option<&str> {
+        let (_, ifile) = self.entries.get(&hash)?;
+        self.filenames.get(*ifile).map(|s| s.as_str())
+    }
+
     /// Return the number of entries
     pub fn entry_count(&self) -> usize {
         self.entries.len()
     }
 
+    /// Return all the entry types
+    pub fn types(&self) -> &[BinClassName] {
+        &self.types
+    }
+
     /// Run a "smart" search on words
-    pub fn search_words<'a>(&'a self, words: &'a [&str], mappers: &'a BinHashMappers) -> Result<impl Iterator<Item=BinEntryPath> + 'a> {
+    ///
+    /// Return the path and type of matching entries.
+    ///
+    /// Supported words:
+    /// - entry path hash, or entry type hash
+    /// - `type:NAME`, to search for entries of the given type (name or hash)
+    /// - `.bin` file path
+    /// - entry path substring
+    /// - `-NAME`, `-type:NAME`: exclude entries of given type or path
+    pub fn search_words<'a>(&'a self, words: &'a [&str], mappers: &'a BinHashMappers) -> Result<impl Iterator<Item=(BinEntryPath, BinClassName)> + 'a> {
+        self.search_words_impl(words, None, mappers)
+    }
+
+    /// Same as [Self::search_words()], but only return entries of the given type
+    pub fn search_words_of_type<'a>(&'a self, words: &'a [&str], htype: BinClassName, mappers: &'a BinHashMappers) -> Result<impl Iterator<Item=(BinEntryPath, BinClassName)> + 'a> {
+        self.search_words_impl(words, Some(htype), mappers)
+    }
+
+    fn search_words_impl<'a>(&'a self, words: &'a [&str], required_type: Option<BinClassName>, mappers: &'a BinHashMappers) -> Result<impl Iterator<Item=(BinEntryPath, BinClassName)> + 'a> {
         #[derive(Default)]
         struct MergedCriteria<'a> {
             entry_paths: Vec<&'a str>,
@@ -169,6 +198,7 @@ impl EntryDatabase {
                 let file = &self.filenames[*findex];
                 // Don't bother too much using a "smart" filtering
                 // Keep in my that results are "truncated".
+                required_type.map(|t| t == *htype).unwrap_or(true) &&
                 (criterias.entry_types.is_empty() || criterias.entry_types.contains(htype)) &&
                 !criterias.excluded_entry_types.contains(htype) &&
                 (criterias.entry_hpaths.is_empty() || criterias.entry_hpaths.contains(hpath)) &&
@@ -177,7 +207,7 @@ impl EntryDatabase {
                 })) &&
                 regex_include.as_ref().map(|re| hpath.get_str(mappers).map(|s| re.is_match(s)).unwrap_or(false)).unwrap_or(true) &&
                 !regex_exclude.as_ref().map(|re| hpath.get_str(mappers).map(|s| re.is_match(s)).unwrap_or(false)).unwrap_or(false)
-            }).map(|(hpath, _)| *hpath);
+            }).map(|(hpath, (htype, _))| (*hpath, *htype));
         Ok(it)
     }
 
@@ -199,7 +229,11 @@ impl EntryDatabase {
 
     /// Parse a search criteria, using database information to resolve hashes
     fn parse_criteria<'a>(&'a self, word: &'a str) -> SearchCriteria<'a> {
-        if let Some(hash) = word.strip_prefix('-') {
+        if let Some(name) = word.strip_prefix("type:") {
+            SearchCriteria::EntryType(binhash_from_str(name).into())
+        } else if let Some(name) = word.strip_prefix("-type:") {
+            SearchCriteria::ExcludeEntryType(binhash_from_str(name).into())
+        } else if let Some(hash) = word.strip_prefix('-') {
             let htype = BinClassName::hashed(hash);
             if self.types.contains(&htype) {
                 SearchCriteria::ExcludeEntryType(htype)
@@ -232,3 +266,54 @@ enum SearchCriteria<'a> {
     ExcludeEntryPath(&'a str),
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cdragon_hashes::binh;
+
+    fn test_db() -> EntryDatabase {
+        let entries = HashMap::from([
+            (binh!("Items/1001"), (binh!("ItemData"), 0)),
+            (binh!("Items/1002"), (binh!("ItemData"), 0)),
+            (binh!("Characters/Ahri/CharacterRecords/Root"), (binh!("CharacterRecord"), 1)),
+        ]);
+        EntryDatabase {
+            entries,
+            types: vec![binh!("ItemData"), binh!("CharacterRecord")],
+            filenames: vec!["data/items.bin".into(), "data/characters/ahri/ahri.bin".into()],
+        }
+    }
+
+    #[test]
+    fn parse_type_criteria() {
+        let db = test_db();
+        assert!(matches!(db.parse_criteria("type:ItemData"), SearchCriteria::EntryType(h) if h == binh!("ItemData")));
+        assert!(matches!(db.parse_criteria("type:{12345678}"), SearchCriteria::EntryType(h) if h.hash == 0x12345678));
+        assert!(matches!(db.parse_criteria("-type:ItemData"), SearchCriteria::ExcludeEntryType(h) if h == binh!("ItemData")));
+        // Unknown type names are still hashed
+        assert!(matches!(db.parse_criteria("type:UnknownType"), SearchCriteria::EntryType(h) if h == binh!("UnknownType")));
+        assert!(matches!(db.parse_criteria("-type:UnknownType"), SearchCriteria::ExcludeEntryType(h) if h == binh!("UnknownType")));
+        // Without prefix, unknown types are entry paths
+        assert!(matches!(db.parse_criteria("-UnknownType"), SearchCriteria::ExcludeEntryPath("UnknownType")));
+        assert!(matches!(db.parse_criteria("-ItemData"), SearchCriteria::ExcludeEntryType(h) if h == binh!("ItemData")));
+    }
+
+    #[test]
+    fn search_by_type() {
+        let db = test_db();
+        let mappers = BinHashMappers::default();
+        let search = |words: &[&str]| -> Vec<BinEntryPath> {
+            let mut paths: Vec<_> = db.search_words(words, &mappers).unwrap().map(|(path, _)| path).collect();
+            paths.sort_by_key(|p| p.hash);
+            paths
+        };
+        let mut items = vec![binh!("Items/1001"), binh!("Items/1002")];
+        items.sort_by_key(|p: &BinEntryPath| p.hash);
+        assert_eq!(search(&["type:ItemData"]), items);
+        assert_eq!(search(&["-type:CharacterRecord"]), items);
+        assert_eq!(search(&["type:UnknownType"]), []);
+        assert_eq!(search(&["-type:UnknownType"]).len(), 3);
+    }
+}
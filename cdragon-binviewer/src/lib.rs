@@ -16,6 +16,7 @@ use wasm_bindgen::{
     UnwrapThrowExt,
     closure::Closure,
 };
+use cdragon_hashes::bin::BinHashKind;
 use cdragon_prop::data::*;
 
 use services::Services;
@@ -27,9 +28,19 @@ type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
 
 pub enum AppAction {
     /// Switch the state to a loaded one
-    ServicesLoaded(Services),
+    ServicesLoaded(Rc<Services>),
+    /// A hash mapper has been loaded
+    HashesLoaded(BinHashKind),
+    /// A hash mapper failed to load
+    HashesLoadFailed(BinHashKind),
     /// Search for entries
     SearchEntries(String),
+    /// Display more results for the current search
+    LoadMoreResults,
+    /// Change the maximum number of search results (per page)
+    SetMaxResults(usize),
+    /// Only search entries of the given type
+    SetTypeFilter(Option<BinClassName>),
     /// Load given entry (if needed) then focus it
     FollowLink(BinEntryPath),
     /// Load given history state
@@ -42,43 +53,75 @@ pub struct AppState {
     services: Rc<Services>,
     /// Current search pattern
     search_pattern: String,
-    /// Result entries, displayed
-    result_entries: Vec<BinEntryPath>,
+    /// Only search entries of this type, if set
+    type_filter: Option<BinClassName>,
+    /// Result entries (path and type), displayed
+    result_entries: Vec<(BinEntryPath, BinClassName)>,
+    /// Maximum number of result entries
+    result_limit: usize,
+    /// `true` if there are more results than displayed
+    more_results: bool,
     /// Entry to forcily open and jump to
     focused_entry: Option<BinEntryPath>,
+    /// Hash mappers loaded so far
+    loaded_hashes: Vec<BinHashKind>,
+    /// Hash mappers which failed to load
+    failed_hashes: Vec<BinHashKind>,
 }
 
 impl AppState {
     /// Parse search from location, search and return a new instance
-    fn from_location(services: Rc<Services>) -> Self {
+    fn with_location(&self) -> Self {
         let (pattern, focus) = parse_app_url();
-        Self::from_search(services, pattern, focus)
+        self.with_search(pattern, focus)
     }
 
     /// Search and return a new instance
-    fn from_search(services: Rc<Services>, pattern: String, focus: Option<BinEntryPath>) -> Self {
+    fn with_search(&self, pattern: String, focus: Option<BinEntryPath>) -> Self {
+        self.with_search_limit(pattern, focus, settings::max_search_results())
+    }
+
+    /// Search and return a new instance, with given maximum number of results
+    fn with_search_limit(&self, pattern: String, focus: Option<BinEntryPath>, limit: usize) -> Self {
+        let services = self.services.clone();
         let words: Vec<&str> = pattern.split_whitespace().collect();
-        let result_entries = if words.is_empty() {
+        // Note: with a type filter, an empty search returns all entries of the type
+        let mut result_entries: Vec<(BinEntryPath, BinClassName)> = if words.is_empty() && self.type_filter.is_none() {
             Vec::new()
         } else {
-            match services.entrydb.search_words(&words, &services.hmappers) {
-                Ok(it) => it.take(settings::max_search_results()).collect(),
+            let hmappers = services.hmappers.borrow();
+            let entries = match self.type_filter {
+                Some(htype) => services.entrydb.search_words_of_type(&words, htype, &hmappers)
+                    .map(|it| Box::new(it) as Box<dyn Iterator<Item=_>>),
+                None => services.entrydb.search_words(&words, &hmappers)
+                    .map(|it| Box::new(it) as Box<dyn Iterator<Item=_>>),
+            };
+            // Get an additional result to know whether there are more results
+            match entries {
+                Ok(it) => it.take(limit + 1).collect(),
                 Err(e) => {
                     error!(format!("search failed: {}", e));
                     vec![]
                 }
             }
         };
+        let more_results = result_entries.len() > limit;
+        result_entries.truncate(limit);
         Self {
             services,
             search_pattern: pattern,
+            type_filter: self.type_filter,
             result_entries,
+            result_limit: limit,
+            more_results,
             focused_entry: focus,
+            loaded_hashes: self.loaded_hashes.clone(),
+            failed_hashes: self.failed_hashes.clone(),
         }
     }
 
     fn search_and_push(self: Rc<Self>, pattern: String, focus: Option<BinEntryPath>) -> Rc<Self> {
-        let this: Rc<Self> = Self::from_search(self.services.clone(), pattern, focus).into();
+        let this: Rc<Self> = self.with_search(pattern, focus).into();
         this.push_history().unwrap_throw();
         this
     }
@@ -99,7 +142,22 @@ impl Reducible for AppState {
         match action {
             AppAction::ServicesLoaded(services) => {
                 // Load the location after initial load, no need to preserve current state
-                Self::from_location(services.into()).into()
+                Self { services, ..Default::default() }.with_location().into()
+            }
+
+            AppAction::HashesLoaded(kind) => {
+                info!(format!("hashes loaded: {:?}", kind));
+                // Search again: new names may match, or not match anymore
+                let mut state = self.with_search_limit(self.search_pattern.clone(), self.focused_entry, self.result_limit);
+                state.loaded_hashes.push(kind);
+                state.into()
+            }
+
+            AppAction::HashesLoadFailed(kind) => {
+                // Names are not changed, no need to search again
+                let mut state = (*self).clone();
+                state.failed_hashes.push(kind);
+                state.into()
             }
 
             AppAction::SearchEntries(pattern) => {
@@ -107,23 +165,41 @@ impl Reducible for AppState {
                 self.search_and_push(pattern, None)
             }
 
+            AppAction::LoadMoreResults => {
+                let limit = self.result_limit + settings::max_search_results();
+                info!(format!("load more results: {}", limit));
+                self.with_search_limit(self.search_pattern.clone(), self.focused_entry, limit).into()
+            }
+
+            AppAction::SetTypeFilter(htype) => {
+                info!(format!("set type filter: {:?}", htype.map(|h| format!("{:x}", h))));
+                let mut state = (*self).clone();
+                state.type_filter = htype;
+                state.with_search(self.search_pattern.clone(), None).into()
+            }
+
+            AppAction::SetMaxResults(value) => {
+                info!(format!("set max results: {}", value));
+                settings::set_max_search_results(value);
+                self.with_search(self.search_pattern.clone(), self.focused_entry).into()
+            }
+
             AppAction::FollowLink(hpath) => {
                 info!(format!("follow link: {:x}", hpath));
-                if self.result_entries.contains(&hpath) {
+                if self.result_entries.iter().any(|(h, _)| *h == hpath) {
                     Rc::make_mut(&mut self).focused_entry = Some(hpath);
                     self.push_history().unwrap_throw();
                     self
                 } else {
                     // It could be nice to load the file, but it may have too many entries.
                     // Use a safe and predictable, behavior.
-                    let hstr = hpath.seek_str(&self.services.hmappers);
-                    let pattern = format!("{}", hstr);
+                    let pattern = format!("{}", hpath.seek_str(&self.services.hmappers.borrow()));
                     self.search_and_push(pattern, Some(hpath))
                 }
             }
 
             AppAction::LoadHistoryState => {
-                Self::from_location(self.services.clone()).into()
+                self.with_location().into()
             }
         }
     }
@@ -141,8 +217,20 @@ pub fn app() -> Html {
         let state = state.clone();
         move |_| {
             yew::platform::spawn_local(async move {
-                let services = Services::load().await;
-                state.dispatch(AppAction::ServicesLoaded(services));
+                let services: Rc<Services> = Services::load().await.into();
+                state.dispatch(AppAction::ServicesLoaded(services.clone()));
+                // Load hash mappers in the background, entries are updated as they are loaded
+                for kind in BinHashKind::VARIANTS {
+                    let services = services.clone();
+                    let state = state.clone();
+                    yew::platform::spawn_local(async move {
+                        if services.load_hash_mapper(kind).await {
+                            state.dispatch(AppAction::HashesLoaded(kind));
+                        } else {
+                            state.dispatch(AppAction::HashesLoadFailed(kind));
+                        }
+                    });
+                }
             });
         }
     });
@@ -159,6 +247,7 @@ pub fn app() -> Html {
 
     let services = state.services.clone();
     let focused_entry = state.focused_entry;
+    let hashes_loaded = state.loaded_hashes.len();
 
     // Setup listener for history change
     use_effect_with((), {
@@ -176,15 +265,17 @@ pub fn app() -> Html {
         <ContextProvider<AppContext> context={services.clone()}>
             <div>
                 <SearchBar value={state.search_pattern.clone()} {on_search} />
-                { html_result_count(&state) }
+                <HashesStatus loaded={state.loaded_hashes.clone()} failed={state.failed_hashes.clone()} />
+                { html_type_filter(&state, &dispatch) }
+                { html_result_count(&state, &dispatch) }
                 <div id="bindata-content">
                     if !state.result_entries.is_empty() {
                         <ul>
-                        { for state.result_entries.iter().map(move |hpath| {
+                        { for state.result_entries.iter().map(move |(hpath, htype)| {
                              if services.entrydb.has_entry(*hpath) {
                                  let focus = focused_entry == Some(*hpath);
                                  html! {
-                                     <ResultEntry key={hpath.hash} dispatch={dispatch.clone()} hpath={*hpath} {focus} />
+                                     <ResultEntry key={hpath.hash} dispatch={dispatch.clone()} hpath={*hpath} htype={*htype} {focus} {hashes_loaded} />
                                  }
                              } else {
                                  error!(format!("entry not found in database: {:x}", *hpath));
@@ -201,18 +292,72 @@ pub fn app() -> Html {
 }
 
 
+/// Return the entry type selector, used to filter search results
+fn html_type_filter(state: &AppState, dispatch: &Callback<AppAction>) -> Html {
+    let mut types: Vec<(BinClassName, String)> = {
+        let hmappers = state.services.hmappers.borrow();
+        state.services.entrydb.types().iter()
+            .map(|htype| (*htype, htype.seek_str(&hmappers).to_string()))
+            .collect()
+    };
+    types.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let onchange = Callback::from({
+        let dispatch = dispatch.clone();
+        move |e: Event| {
+            let target: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            let htype = u32::from_str_radix(&target.value(), 16).ok().map(BinClassName::from);
+            dispatch.emit(AppAction::SetTypeFilter(htype));
+        }
+    });
+
+    html! {
+        <div id="type-filter">
+            <label>
+                {"Entry type: "}
+                <select {onchange}>
+                    <option value="" selected={state.type_filter.is_none()}>{"All types"}</option>
+                    { for types.into_iter().map(|(htype, name)| html! {
+                        <option value={format!("{:x}", htype)} selected={state.type_filter == Some(htype)}>{ name }</option>
+                    }) }
+                </select>
+            </label>
+        </div>
+    }
+}
+
 /// Return the result count displayed under the search bar
-fn html_result_count(state: &AppState) -> Html {
+///
+/// Also provide controls to load more results and change the number of results per page.
+fn html_result_count(state: &AppState, dispatch: &Callback<AppAction>) -> Html {
     let entry_count = state.services.entrydb.entry_count();
-    let nresults = state.result_entries.len();
-    let mut results_count = format!("{}", nresults);
-    // assume there was additional results if result count is exactly max_search_results
-    if nresults >= settings::max_search_results() {
+    let mut results_count = format!("{}", state.result_entries.len());
+    if state.more_results {
         results_count.push('+');
     };
+    let page_size = settings::max_search_results();
+
+    let on_load_more = dispatch.reform(|_: MouseEvent| AppAction::LoadMoreResults);
+    let on_page_size_change = Callback::from({
+        let dispatch = dispatch.clone();
+        move |e: Event| {
+            let target: web_sys::HtmlInputElement = e.target_unchecked_into();
+            if let Some(value) = target.value().parse::<usize>().ok().filter(|&v| v > 0) {
+                dispatch.emit(AppAction::SetMaxResults(value));
+            }
+        }
+    });
+
     html! {
         <div id="result-count">
             <b>{ results_count }</b>{" results out of "}<b>{ entry_count }</b>{" entries"}
+            if state.more_results {
+                <button onclick={on_load_more}>{ format!("Load {} more", page_size) }</button>
+            }
+            <label>
+                {"Results per page: "}
+                <input type="number" min="1" value={page_size.to_string()} onchange={on_page_size_change} />
+            </label>
         </div>
     }
 }
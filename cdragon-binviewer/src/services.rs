@@ -1,18 +1,17 @@
 use std::rc::Rc;
 use std::cell::RefCell;
-use futures::try_join;
 use gloo_console::{debug, error};
 use gloo_net::http::Request;
 use thiserror::Error;
 use lru::LruCache;
-use cdragon_hashes::HashKind;
+use cdragon_hashes::{
+    HashKind,
+    bin::{BinHashKind, BinHashMapper},
+};
 use cdragon_prop::{
     PropFile,
     BinEntry,
     BinEntryPath,
-    BinHashKind,
-    BinHashKindMapping,
-    BinHashMapper,
     BinHashMappers,
 };
 use crate::{
@@ -25,8 +24,11 @@ use crate::{
 ///
 /// Static data must first be loaded. Then, data and `fetch_entry()` can be used.
 /// To avoid mutability issues, a default state can be created then swapped with a loaded one.
+///
+/// Hash mappers are loaded afterwards, in the background, using `load_hash_mapper()`.
+/// Until then, hashes are displayed as hex values.
 pub struct Services {
-    pub hmappers: BinHashMappers,
+    pub hmappers: RefCell<BinHashMappers>,
     pub entrydb: EntryDatabase,
     binfile_cache: RefCell<LruCache<String, Rc<Vec<u8>>>>,
 }
@@ -34,7 +36,7 @@ pub struct Services {
 impl Default for Services {
     fn default() -> Self {
         Self {
-            hmappers: BinHashMappers::default(),
+            hmappers: BinHashMappers::default().into(),
             entrydb: EntryDatabase::default(),
             binfile_cache: default_binfile_cache(),
         }
@@ -53,27 +55,10 @@ impl PartialEq for Services {
 
 impl Services {
     /// Load services data, asynchronously
+    ///
+    /// Hash mappers are not loaded.
     pub async fn load() -> Self {
-        let future_hmappers = fetch_hash_mappers();
-        let future_entrydb = fetch_entrydb();
-
-        let hmappers = match future_hmappers.await {
-            Ok(mappers) => {
-                BinHashMappers {
-                    entry_path: mappers.entry_path,
-                    class_name: mappers.class_name,
-                    field_name: mappers.field_name,
-                    hash_value: mappers.hash_value,
-                    path_value: Default::default(),
-                }
-            }
-            Err(e) => {
-                error!(format!("failed to load hash mappers: {}", e));
-                Default::default()
-            }
-        };
-
-        let entrydb = match future_entrydb.await {
+        let entrydb = match fetch_entrydb().await {
             Ok(db) => db,
             Err(e) => {
                 error!(format!("failed to load entry db: {}", e));
@@ -81,7 +66,23 @@ impl Services {
             }
         };
 
-        Self { hmappers, entrydb, binfile_cache: default_binfile_cache() }
+        Self { entrydb, ..Default::default() }
+    }
+
+    /// Load a single hash mapper, asynchronously
+    ///
+    /// Return true if the mapper has been loaded.
+    pub async fn load_hash_mapper(&self, kind: BinHashKind) -> bool {
+        match fetch_one_hash_mapper(kind).await {
+            Ok(mapper) => {
+                *self.hmappers.borrow_mut().get_mut(kind) = mapper;
+                true
+            }
+            Err(e) => {
+                error!(format!("failed to load hash mapper {:?}: {}", kind, e));
+                false
+            }
+        }
     }
 
     /// Fetch an entry from given file, use cache if possible
@@ -111,22 +112,6 @@ fn default_binfile_cache() -> RefCell<LruCache<String, Rc<Vec<u8>>>> {
 }
 
 
-type FetchedHashMappers = BinHashKindMapping<BinHashMapper, ()>;
-
-/// Load hash mappers, asynchronously
-async fn fetch_hash_mappers() -> Result<FetchedHashMappers> {
-    let (entry_path, class_name, field_name, hash_value) = try_join!(
-        fetch_one_hash_mapper(BinHashKind::EntryPath),
-        fetch_one_hash_mapper(BinHashKind::ClassName),
-        fetch_one_hash_mapper(BinHashKind::FieldName),
-        fetch_one_hash_mapper(BinHashKind::HashValue),
-    )?;
-    Ok(FetchedHashMappers {
-        entry_path, class_name, field_name, hash_value,
-        path_value: ()
-    })
-}
-
 async fn fetch_one_hash_mapper(kind: BinHashKind) -> Result<BinHashMapper> {
     let kind: HashKind = kind.into();
     let uri = settings::binviewer_static_url(&format!("hashes/{}", kind.mapping_path()));
@@ -8,7 +8,7 @@ extern "C" {
     /// - `staticBaseUrl`: base URL for hashes and entrydb (default: `"."`)
     /// - `binsBaseUrl`: base URL for bin files (default: `"game"`)
     /// - `assetsBaseUrl`: base URL for asset files (default: `"game"`)
-    /// - `maxResults`: maximum search results (default: `1000`)
+    /// - `maxResults`: maximum search results, per page (default: `1000`, can be changed by the user)
     static BINVIEWER: JsValue;
 }
 
@@ -29,9 +29,59 @@ pub fn assets_base_url() -> String {
     get_setting_str("assetsBaseUrl").unwrap_or_else(|| "game".into())
 }
 
-/// Get maximum number of search results
+/// Return the URL of an image preview for an asset path, if any
+///
+/// Paths are lowercased. Textures (`.dds`, `.tex`) are served as `.png` files; `.png`, `.jpg`
+/// and `.svg` files are served as-is. Other paths have no preview.
+///
+/// ```
+/// # use cdragon_binviewer::settings::asset_preview_url;
+/// assert_eq!(asset_preview_url("game", "ASSETS/Icons/Foo.dds").as_deref(), Some("game/assets/icons/foo.png"));
+/// assert_eq!(asset_preview_url("game", "assets/icons/foo.tex").as_deref(), Some("game/assets/icons/foo.png"));
+/// assert_eq!(asset_preview_url("game", "assets/icons/foo.png").as_deref(), Some("game/assets/icons/foo.png"));
+/// assert_eq!(asset_preview_url("game", "assets/icons/foo.svg").as_deref(), Some("game/assets/icons/foo.svg"));
+/// assert_eq!(asset_preview_url("game", "assets/icons/foo.JPG").as_deref(), Some("game/assets/icons/foo.jpg"));
+/// assert_eq!(asset_preview_url("game", "data/foo.bin"), None);
+/// assert_eq!(asset_preview_url("game", "dds"), None);
+/// ```
+pub fn asset_preview_url(base_url: &str, path: &str) -> Option<String> {
+    let path = path.to_lowercase();
+    let (stem, ext) = path.rsplit_once('.')?;
+    let ext = match ext {
+        "dds" | "tex" | "png" => "png",
+        "jpg" | "svg" => ext,
+        _ => return None,
+    };
+    Some(format!("{}/{}.{}", base_url, stem, ext))
+}
+
+/// Local storage key of the user-defined maximum number of search results
+const MAX_RESULTS_STORAGE_KEY: &str = "binviewer.maxResults";
+
+/// Get maximum number of search results (per page)
+///
+/// The value set by the user (see [set_max_search_results()]) takes precedence over the
+/// `maxResults` setting.
 pub fn max_search_results() -> usize {
-    get_setting_f64("maxResults").map(|v| v as usize).unwrap_or(1000)
+    local_storage()
+        .and_then(|storage| storage.get_item(MAX_RESULTS_STORAGE_KEY).ok().flatten())
+        .and_then(|v| v.parse::<usize>().ok())
+        .or_else(|| get_setting_f64("maxResults").map(|v| v as usize))
+        .filter(|&v| v > 0)
+        .unwrap_or(1000)
+}
+
+/// Set the maximum number of search results, persist it in local storage
+pub fn set_max_search_results(value: usize) {
+    if let Some(storage) = local_storage() {
+        // Ignore errors: the value will simply not be persisted
+        let _ = storage.set_item(MAX_RESULTS_STORAGE_KEY, &value.to_string());
+    }
+}
+
+/// Get the local storage, if available
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
 }
 
 
@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Local directory storing downloaded bundles
+///
+/// Bundles are stored as is, named after their ID (as on the CDN).
+/// When the cache exceeds its maximum size, least recently used bundles are removed.
+#[derive(Debug)]
+pub(crate) struct BundleCache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+}
+
+impl BundleCache {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir, max_size: None }
+    }
+
+    pub(crate) fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    /// Return the path of a bundle in the cache
+    pub(crate) fn bundle_path(&self, bundle_id: u64) -> PathBuf {
+        self.dir.join(format!("{:016X}.bundle", bundle_id))
+    }
+
+    /// Mark a cached bundle as recently used
+    pub(crate) fn touch(&self, path: &Path) -> std::io::Result<()> {
+        fs::File::options().write(true).open(path)?.set_modified(SystemTime::now())
+    }
+
+    /// Remove least recently used bundles until cache size is below the maximum
+    ///
+    /// `keep` is never removed, even if it is alone to exceed the maximum size.
+    pub(crate) fn evict(&self, keep: &Path) -> std::io::Result<()> {
+        let max_size = match self.max_size {
+            Some(size) => size,
+            None => return Ok(()),
+        };
+
+        let mut bundles = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "bundle") {
+                let metadata = entry.metadata()?;
+                bundles.push((metadata.modified()?, metadata.len(), path));
+            }
+        }
+
+        let mut total_size: u64 = bundles.iter().map(|(_, size, _)| size).sum();
+        bundles.sort_unstable();
+        for (_, size, path) in bundles {
+            if total_size <= max_size {
+                break;
+            }
+            if path != keep {
+                fs::remove_file(&path)?;
+                total_size -= size;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+use cdragon_rman::FileBundleRanges;
+use cdragon_utils::progress::{Progress, ProgressEvent};
+use crate::{
+    CdnDownloader,
+    CdnError,
+    Result,
+    guarded_map::GuardedMmap,
+    read_ranges_from_file,
+    split_file_ranges,
+};
+
+/// Local directory of bundle files, used to build files without a CDN
+///
+/// Bundles are looked up by their ID, either directly in the directory (e.g.
+/// `0123456789ABCDEF.bundle`, as written by [CdnDownloader::download_bundles()]), or using the
+/// CDN layout (e.g. `channels/public/bundles/0123456789ABCDEF.bundle`), to use a CDN mirror.
+///
+/// Files are built like [CdnDownloader::download_bundle_chunks()] does, but chunks are read from
+/// local bundles.
+///
+/// # Example
+/// ```no_run
+/// # use cdragon_cdn::LocalBundleStore;
+/// # use cdragon_rman::Rman;
+/// let rman = Rman::open("example.manifest").unwrap();
+/// let bundle_chunks = rman.bundle_chunks().unwrap();
+/// let file = rman.iter_files().next().unwrap().unwrap();
+/// let (file_size, ranges) = file.bundle_chunks(&bundle_chunks).unwrap();
+///
+/// let store = LocalBundleStore::new("bundles");
+/// if store.missing_bundles(&ranges).is_empty() {
+///     store.build_file(file_size as u64, &ranges, "out.bin".as_ref()).unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LocalBundleStore {
+    dir: PathBuf,
+}
+
+impl LocalBundleStore {
+    /// Use bundles from the given directory
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Return the path of a bundle, `None` if it is not in the store
+    pub fn bundle_path(&self, bundle_id: u64) -> Option<PathBuf> {
+        [
+            self.dir.join(format!("{:016X}.bundle", bundle_id)),
+            self.dir.join(CdnDownloader::bundle_path(bundle_id)),
+        ].into_iter().find(|path| path.is_file())
+    }
+
+    /// Return `true` if the store has the given bundle
+    pub fn has_bundle(&self, bundle_id: u64) -> bool {
+        self.bundle_path(bundle_id).is_some()
+    }
+
+    /// Return the IDs of bundles needed by a file but missing from the store, sorted
+    pub fn missing_bundles(&self, bundle_ranges: &FileBundleRanges) -> Vec<u64> {
+        let mut ids: Vec<u64> = bundle_ranges.keys().copied().filter(|id| !self.has_bundle(*id)).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Build a file from bundle chunks
+    pub fn build_file(&self, file_size: u64, bundle_ranges: &FileBundleRanges, path: &Path) -> Result<()> {
+        self.build_file_with_progress(file_size, bundle_ranges, path, &mut ())
+    }
+
+    /// Same as [Self::build_file()], but report progress
+    ///
+    /// The output path is used as file name. Transferred bytes are counted on uncompressed data.
+    pub fn build_file_with_progress(&self, file_size: u64, bundle_ranges: &FileBundleRanges, path: &Path, progress: &mut dyn Progress) -> Result<()> {
+        let bundle_paths = self.bundle_paths(bundle_ranges)?;
+        progress.on_event(ProgressEvent::FileStarted {
+            name: &path.to_string_lossy(),
+            total_bytes: Some(file_size),
+        });
+        let mut mmap = GuardedMmap::create(path, file_size)?;
+        Self::read_chunks_to_buffer(&bundle_paths, bundle_ranges, mmap.mmap(), progress)?;
+        mmap.persist()?;
+        progress.on_event(ProgressEvent::FileFinished);
+        Ok(())
+    }
+
+    /// Read bundle chunks, return file data
+    ///
+    /// This is intended for small files. Use [Self::build_file()] to write large files directly
+    /// to the disk.
+    pub fn read_file(&self, file_size: u64, bundle_ranges: &FileBundleRanges) -> Result<Vec<u8>> {
+        let bundle_paths = self.bundle_paths(bundle_ranges)?;
+        let mut buf = vec![0; file_size as usize];
+        Self::read_chunks_to_buffer(&bundle_paths, bundle_ranges, &mut buf, &mut ())?;
+        Ok(buf)
+    }
+
+    /// Get the paths of all bundles of a file, fail if one is missing
+    fn bundle_paths(&self, bundle_ranges: &FileBundleRanges) -> Result<Vec<(u64, PathBuf)>> {
+        bundle_ranges.keys()
+            .map(|id| self.bundle_path(*id).map(|path| (*id, path)).ok_or(CdnError::BundleNotFound(*id)))
+            .collect()
+    }
+
+    /// Read bundle chunks to a buffer with the size of the file
+    fn read_chunks_to_buffer(bundle_paths: &[(u64, PathBuf)], bundle_ranges: &FileBundleRanges, buf: &mut [u8], progress: &mut dyn Progress) -> Result<()> {
+        for (bundle_id, path) in bundle_paths {
+            let ranges = split_file_ranges(&bundle_ranges[bundle_id], buf, 0);
+            read_ranges_from_file(path, ranges, None, progress)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::fixtures::*;
+
+    #[test]
+    fn file_is_built_from_bundles() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let mut file = TestFile::single_bundle();
+        file.write_bundles(dir);
+
+        let store = LocalBundleStore::new(dir);
+        assert!(store.has_bundle(0xb01));
+        assert!(!store.has_bundle(0xb02));
+        assert_eq!(store.read_file(13, &file.ranges).unwrap(), FILE_DATA);
+        store.build_file(13, &file.ranges, &dir.join("out.txt")).unwrap();
+        assert_eq!(fs::read(dir.join("out.txt")).unwrap(), FILE_DATA);
+
+        // Missing bundles are reported before reading anything
+        file.ranges.insert(0xb02, vec![]);
+        assert_eq!(store.missing_bundles(&file.ranges), [0xb02]);
+        assert!(matches!(store.read_file(13, &file.ranges), Err(CdnError::BundleNotFound(0xb02))));
+    }
+}
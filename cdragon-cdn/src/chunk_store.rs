@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufReader, Read, Write};
+use std::path::PathBuf;
+use cdragon_utils::GuardedFile;
+use crate::{CdnError, Result};
+
+/// Local directory of chunks, shared by files of all manifests
+///
+/// Chunks are identified by their ID, and stored compressed, as in bundles. Files of different
+/// manifest versions share most of their chunks: once stored, a chunk is never downloaded again.
+///
+/// Chunks are sharded in subdirectories named after the first two hexadecimal digits of their ID
+/// (e.g. `01/0123456789ABCDEF.chunk`).
+///
+/// See [CdnDownloader::download_bundle_chunks_with_store()](crate::CdnDownloader::download_bundle_chunks_with_store()).
+///
+/// # Example
+/// ```no_run
+/// # use std::collections::HashSet;
+/// # use cdragon_cdn::{CdnDownloader, ChunkStore};
+/// # use cdragon_rman::Rman;
+/// let rman = Rman::open("example.manifest").unwrap();
+/// let bundle_chunks = rman.bundle_chunks().unwrap();
+/// let file = rman.iter_files().next().unwrap().unwrap();
+/// let (file_size, ranges) = file.bundle_chunks(&bundle_chunks).unwrap();
+///
+/// // Only chunks missing from the store are downloaded
+/// let store = ChunkStore::new("chunks");
+/// let cdn = CdnDownloader::new().unwrap();
+/// cdn.download_bundle_chunks_with_store(file_size as u64, &ranges, "out.bin".as_ref(), &store).unwrap();
+///
+/// // Remove chunks not used by the manifest anymore
+/// let keep: HashSet<u64> = bundle_chunks.keys().copied().collect();
+/// store.gc(&keep).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ChunkStore {
+    dir: PathBuf,
+}
+
+impl ChunkStore {
+    /// Use chunks from the given directory
+    ///
+    /// The directory is created when the first chunk is stored.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Return the path of a chunk in the store
+    pub fn chunk_path(&self, chunk_id: u64) -> PathBuf {
+        self.dir.join(format!("{:02X}", chunk_id >> 56)).join(format!("{:016X}.chunk", chunk_id))
+    }
+
+    /// Return `true` if the store has the given chunk
+    pub fn has(&self, chunk_id: u64) -> bool {
+        self.chunk_path(chunk_id).is_file()
+    }
+
+    /// Read a chunk, compressed
+    pub fn get(&self, chunk_id: u64) -> Result<impl Read> {
+        match fs::File::open(self.chunk_path(chunk_id)) {
+            Ok(file) => Ok(BufReader::new(file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(CdnError::ChunkNotFound(chunk_id)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Add a chunk to the store, replace it if it already exists
+    ///
+    /// Data must be compressed, as in bundles.
+    pub fn put(&self, chunk_id: u64, data: &[u8]) -> Result<()> {
+        GuardedFile::for_scope(self.chunk_path(chunk_id), |file| file.write_all(data))?;
+        Ok(())
+    }
+
+    /// Remove chunks not in `keep`
+    ///
+    /// Return the number of removed chunks and their total size.
+    /// Files that are not chunks are left untouched.
+    pub fn gc(&self, keep: &HashSet<u64>) -> Result<(u64, u64)> {
+        let (mut count, mut size) = (0, 0);
+        if !self.dir.is_dir() {
+            return Ok((count, size));
+        }
+        for shard in fs::read_dir(&self.dir)? {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(shard.path())? {
+                let entry = entry?;
+                let chunk_id = entry.file_name().to_str()
+                    .and_then(|name| name.strip_suffix(".chunk"))
+                    .filter(|id| id.len() == 16)
+                    .and_then(|id| u64::from_str_radix(id, 16).ok());
+                if chunk_id.is_some_and(|id| !keep.contains(&id)) {
+                    size += entry.metadata()?.len();
+                    fs::remove_file(entry.path())?;
+                    count += 1;
+                }
+            }
+        }
+        Ok((count, size))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CdnDownloader;
+    use crate::fixtures::*;
+
+    #[test]
+    fn stored_chunks_are_read() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = ChunkStore::new(tmp.path());
+        let file = TestFile::single_bundle();
+        store.put(0xc01, &file.chunks[0]).unwrap();
+        store.put(0xc02, &file.chunks[1]).unwrap();
+        assert!(store.has(0xc01));
+        assert!(!store.has(0xc03));
+        assert!(tmp.path().join("00/0000000000000C01.chunk").is_file());
+
+        let mut data = Vec::new();
+        store.get(0xc02).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, file.chunks[1]);
+
+        // All chunks are stored: the file is built without any request
+        let cdn = CdnDownloader::from_base_url("http://localhost:1/").unwrap();
+        let output = tmp.path().join("out.txt");
+        cdn.download_bundle_chunks_with_store(13, &file.ranges, &output, &store).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), FILE_DATA);
+    }
+
+    #[test]
+    fn downloaded_chunks_are_stored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache_dir = tmp.path().join("cache");
+        let store = ChunkStore::new(tmp.path().join("store"));
+        let output = tmp.path().join("out.txt");
+
+        // Bundle in the cache, no request is needed
+        let file = TestFile::single_bundle();
+        let (len1, len2) = (file.chunks[0].len() as u64, file.chunks[1].len() as u64);
+        file.write_bundles(&cache_dir);
+        let cdn = CdnDownloader::from_base_url("http://localhost:1/").unwrap().with_cache(&cache_dir);
+        let read_stored = |chunk_id| {
+            let mut data = Vec::new();
+            store.get(chunk_id).unwrap().read_to_end(&mut data).unwrap();
+            data
+        };
+
+        cdn.download_bundle_chunks_with_store(13, &file.ranges, &output, &store).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), FILE_DATA);
+        assert_eq!(read_stored(0xc01), file.chunks[0]);
+        assert_eq!(read_stored(0xc02), file.chunks[1]);
+
+        // Invalid stored chunks are read from the bundle again, and replaced
+        store.put(0xc02, b"invalid").unwrap();
+        fs::remove_file(&output).unwrap();
+        cdn.download_bundle_chunks_with_store(13, &file.ranges, &output, &store).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), FILE_DATA);
+        assert_eq!(read_stored(0xc02), file.chunks[1]);
+
+        // Unused chunks are removed, other files are kept
+        fs::write(tmp.path().join("store/notes.txt"), "not a chunk").unwrap();
+        assert_eq!(store.gc(&HashSet::from([0xc01])).unwrap(), (1, len2));
+        assert!(store.has(0xc01) && !store.has(0xc02));
+        assert!(tmp.path().join("store/notes.txt").is_file());
+        assert_eq!(store.gc(&HashSet::new()).unwrap(), (1, len1));
+    }
+}
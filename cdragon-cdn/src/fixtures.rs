@@ -0,0 +1,54 @@
+//! Test fixtures: file data stored in local bundles
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use cdragon_rman::{FileBundleRanges, FileChunkRange};
+
+/// Data of test files
+pub const FILE_DATA: &[u8] = b"Hello, world!";
+
+/// Test file, split in zstd-compressed chunks stored in bundles
+pub struct TestFile {
+    /// Compressed chunks, with IDs `0xc01`, `0xc02`, ...
+    pub chunks: Vec<Vec<u8>>,
+    /// Bundle data, by bundle ID
+    pub bundles: BTreeMap<u64, Vec<u8>>,
+    /// Chunk ranges of the file
+    pub ranges: FileBundleRanges,
+}
+
+impl TestFile {
+    /// Split [FILE_DATA] in chunks, append each chunk to the given bundle
+    pub fn new(parts: &[(&str, u64)]) -> Self {
+        assert_eq!(parts.iter().map(|(s, _)| *s).collect::<String>().as_bytes(), FILE_DATA);
+        let mut file = Self { chunks: Vec::new(), bundles: BTreeMap::new(), ranges: FileBundleRanges::new() };
+        let mut offset = 0;
+        for (i, (part, bundle_id)) in parts.iter().enumerate() {
+            let chunk = zstd::encode_all(part.as_bytes(), 0).unwrap();
+            let bundle = file.bundles.entry(*bundle_id).or_default();
+            let target = offset + part.len() as u32;
+            file.ranges.entry(*bundle_id).or_default().push(FileChunkRange {
+                chunk_id: 0xc01 + i as u64,
+                bundle: (bundle.len() as u32, (bundle.len() + chunk.len()) as u32),
+                target: (offset, target),
+            });
+            bundle.extend_from_slice(&chunk);
+            file.chunks.push(chunk);
+            offset = target;
+        }
+        file
+    }
+
+    /// File with two chunks in bundle `0xb01`
+    pub fn single_bundle() -> Self {
+        Self::new(&[("Hello, ", 0xb01), ("world!", 0xb01)])
+    }
+
+    /// Write bundles to a directory, named after their ID
+    pub fn write_bundles(&self, dir: &Path) {
+        fs::create_dir_all(dir).unwrap();
+        for (id, data) in &self.bundles {
+            fs::write(dir.join(format!("{:016X}.bundle", id)), data).unwrap();
+        }
+    }
+}
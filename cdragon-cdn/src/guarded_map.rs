@@ -1,11 +1,18 @@
+use std::io::{Error, ErrorKind};
 use std::path::Path;
 use cdragon_utils::GuardedFile;
 use memmap::MmapMut;
 
 /// Same as `GuardedFile`, but return a memory mapping
+///
+/// The file is resized to the requested size when created. If it is truncated afterwards (e.g. by
+/// another process), accessing the mapped memory past the end of the file is undefined behavior
+/// (`SIGBUS` on Unix), and [Self::persist()] fails.
 pub struct GuardedMmap<P: AsRef<Path>> {
     gfile: GuardedFile<P>,
-    mmap: MmapMut,
+    /// Memory mapping, `None` for empty files which cannot be mapped
+    mmap: Option<MmapMut>,
+    size: u64,
 }
 
 impl<P: AsRef<Path>> GuardedMmap<P> {
@@ -16,18 +23,109 @@ impl<P: AsRef<Path>> GuardedMmap<P> {
         let mut gfile = GuardedFile::create(path)?;
         let file = gfile.as_file_mut();
         file.set_len(size)?;
-        let mmap = unsafe { MmapMut::map_mut(file)? };
-        Ok(Self { gfile, mmap })
+        let mmap = if size == 0 {
+            None
+        } else {
+            Some(unsafe { MmapMut::map_mut(file)? })
+        };
+        Ok(Self { gfile, mmap, size })
     }
 
     /// Return a reference to the underlying memory buffer
-    pub fn mmap(&mut self) -> &mut MmapMut {
-        &mut self.mmap
+    pub fn mmap(&mut self) -> &mut [u8] {
+        match self.mmap {
+            Some(ref mut mmap) => mmap,
+            None => &mut [],
+        }
+    }
+
+    /// Flush modified memory to the file (`msync`)
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        match self.mmap {
+            Some(ref mmap) => mmap.flush(),
+            None => Ok(()),
+        }
     }
 
     /// Persist the memory mapped file
-    pub fn persist(self) {
-        self.gfile.persist();
+    ///
+    /// Memory is flushed and the file synced to the disk before it is renamed, so that a crash
+    /// never leaves an incomplete file at the final path. The parent directory is synced after the
+    /// rename (on Unix), so that the rename itself is durable.
+    /// Fail if the file is shorter than its expected size.
+    pub fn persist(mut self) -> std::io::Result<()> {
+        self.flush()?;
+        let file = self.gfile.as_file_mut();
+        let actual = file.metadata()?.len();
+        if actual < self.size {
+            return Err(Error::new(ErrorKind::UnexpectedEof, format!("file truncated to {} bytes, expected {}", actual, self.size)));
+        }
+        file.sync_all()?;
+        // Unmap before the file is renamed and closed
+        drop(self.mmap.take());
+        let path = self.gfile.path().to_owned();
+        self.gfile.persist()?;
+        sync_parent_dir(&path)
+    }
+}
+
+/// Sync the parent directory of a path, to make a rename durable
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => std::fs::File::open(dir)?.sync_all(),
+        _ => std::fs::File::open(".")?.sync_all(),
     }
 }
 
+/// Directories cannot be synced on other platforms
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn empty_file_is_not_mapped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("sub/empty.bin");
+        let mut mmap = GuardedMmap::create(&path, 0).unwrap();
+        assert!(mmap.mmap.is_none());
+        assert!(mmap.mmap().is_empty());
+        mmap.flush().unwrap();
+        mmap.persist().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"");
+    }
+
+    #[test]
+    fn flushed_data_is_in_the_temporary_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("data.bin");
+        let mut mmap = GuardedMmap::create(&path, 4).unwrap();
+        mmap.mmap().copy_from_slice(b"data");
+        mmap.flush().unwrap();
+        assert_eq!(fs::read(tmp.path().join("data.bin.tmp")).unwrap(), b"data");
+        assert!(!path.exists());
+        mmap.persist().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"data");
+        assert!(!tmp.path().join("data.bin.tmp").exists());
+    }
+
+    #[test]
+    fn persist_fails_on_truncated_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("data.bin");
+        let mmap = GuardedMmap::create(&path, 8192).unwrap();
+        // Truncated by someone else; mapped memory is not accessed afterwards
+        fs::File::options().write(true).open(tmp.path().join("data.bin.tmp")).unwrap().set_len(100).unwrap();
+        let err = mmap.persist().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert!(!path.exists());
+        assert!(!tmp.path().join("data.bin.tmp").exists());
+    }
+}
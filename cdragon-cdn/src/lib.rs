@@ -1,20 +1,31 @@
 //! Download game files from Riot's CDN
 
-use std::io::{Read, BufRead, BufReader, BufWriter};
-use std::path::Path;
+use std::io::{Read, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use reqwest::{header, IntoUrl, blocking::{Client, Response}};
 use url::Url;
 use thiserror::Error;
-use cdragon_utils::GuardedFile;
-use cdragon_rman::FileBundleRanges;
+use cdragon_utils::{
+    GuardedFile,
+    progress::{Progress, ProgressEvent},
+};
+use cdragon_rman::{FileBundleRanges, FileChunkRange};
 // Re-exports
 pub use serde_json;
 
 mod guarded_map;
 use guarded_map::GuardedMmap;
+mod bundle_cache;
+use bundle_cache::BundleCache;
+mod bundle_store;
+pub use bundle_store::LocalBundleStore;
+mod chunk_store;
+pub use chunk_store::ChunkStore;
 #[cfg(feature = "storage")]
 mod fstools;
+#[cfg(test)]
+mod fixtures;
 
 #[cfg(feature = "storage")]
 pub mod storage;
@@ -27,6 +38,9 @@ type Result<T, E = CdnError> = std::result::Result<T, E>;
 pub struct CdnDownloader {
     client: Client,
     url: Url,
+    cache: Option<BundleCache>,
+    /// Maximum size of the cache, kept to be applied whatever the builder order
+    cache_max_size: Option<u64>,
 }
 
 impl CdnDownloader {
@@ -42,7 +56,30 @@ impl CdnDownloader {
     pub fn from_base_url(url: &str) -> Result<Self> {
         let client = Client::new();
         let url = Url::parse(url)?;
-        Ok(Self { client, url })
+        Ok(Self { client, url, cache: None, cache_max_size: None })
+    }
+
+    /// Store downloaded bundles in a local cache directory
+    ///
+    /// When downloading bundle chunks, whole bundles are downloaded to the cache, then chunks are
+    /// read from it. Bundles already in the cache are not downloaded again.
+    pub fn with_cache<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        let mut cache = BundleCache::new(dir.into());
+        cache.set_max_size(self.cache_max_size);
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Set the maximum size of the bundle cache, in bytes
+    ///
+    /// Least recently used bundles are removed when the maximum size is exceeded.
+    /// It can be set before or after [Self::with_cache()]. It has no effect if no cache is used.
+    pub fn with_cache_max_size(mut self, max_size: u64) -> Self {
+        self.cache_max_size = Some(max_size);
+        if let Some(cache) = self.cache.as_mut() {
+            cache.set_max_size(self.cache_max_size);
+        }
+        self
     }
 
     /// Build a bundle URL path from its ID
@@ -67,6 +104,16 @@ impl CdnDownloader {
         Ok(())
     }
 
+    /// Download any URL to a writer, using the instance client
+    pub fn download_url_to_writer<U: IntoUrl>(&self, url: U, writer: &mut dyn Write) -> Result<()> {
+        let mut response = self.client
+            .get(url.into_url()?)
+            .send()?
+            .error_for_status()?;
+        std::io::copy(&mut response, writer)?;
+        Ok(())
+    }
+
     fn download_url_(&self, url: Url, output: &Path) -> Result<()> {
         let mut response = self.client
             .get(url)
@@ -81,32 +128,170 @@ impl CdnDownloader {
         Ok(())
     }
 
+    /// Download a whole bundle to a file
+    pub fn download_bundle(&self, bundle_id: u64, output: &Path) -> Result<()> {
+        self.download_path(&Self::bundle_path(bundle_id), output)
+    }
+
+    /// Download whole bundles to a directory
+    ///
+    /// Bundle files are named after their ID, as on the CDN (e.g. `0123456789ABCDEF.bundle`).
+    pub fn download_bundles(&self, bundle_ids: &[u64], dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        for bundle_id in bundle_ids {
+            let output = dir.join(format!("{:016X}.bundle", bundle_id));
+            self.download_bundle(*bundle_id, &output)?;
+        }
+        Ok(())
+    }
+
     /// Download bundle chunks to a file
     pub fn download_bundle_chunks(&self, file_size: u64, bundle_ranges: &FileBundleRanges, path: &Path) -> Result<()> {
+        self.download_bundle_chunks_with_progress(file_size, bundle_ranges, path, &mut ())
+    }
+
+    /// Same as [Self::download_bundle_chunks()], but report progress
+    ///
+    /// The output path is used as file name. Transferred bytes are counted on uncompressed data.
+    pub fn download_bundle_chunks_with_progress(&self, file_size: u64, bundle_ranges: &FileBundleRanges, path: &Path, progress: &mut dyn Progress) -> Result<()> {
+        progress.on_event(ProgressEvent::FileStarted {
+            name: &path.to_string_lossy(),
+            total_bytes: Some(file_size),
+        });
         // Open output file, map it to memory
         let mut mmap = GuardedMmap::create(path, file_size)?;
+        self.download_bundle_chunks_to_buffer(bundle_ranges, mmap.mmap(), None, progress)?;
+        mmap.persist()?;
+        progress.on_event(ProgressEvent::FileFinished);
+        Ok(())
+    }
+
+    /// Download bundle chunks to a file, using a chunk store
+    ///
+    /// Chunks already in the store are read from it, other chunks are downloaded then added to the
+    /// store. Stored chunks that fail to decompress are downloaded and replaced.
+    /// See [ChunkStore] for an example.
+    pub fn download_bundle_chunks_with_store(&self, file_size: u64, bundle_ranges: &FileBundleRanges, path: &Path, store: &ChunkStore) -> Result<()> {
+        self.download_bundle_chunks_with_store_and_progress(file_size, bundle_ranges, path, store, &mut ())
+    }
+
+    /// Same as [Self::download_bundle_chunks_with_store()], but report progress
+    ///
+    /// Transferred bytes are counted on uncompressed data, including chunks read from the store.
+    pub fn download_bundle_chunks_with_store_and_progress(&self, file_size: u64, bundle_ranges: &FileBundleRanges, path: &Path, store: &ChunkStore, progress: &mut dyn Progress) -> Result<()> {
+        progress.on_event(ProgressEvent::FileStarted {
+            name: &path.to_string_lossy(),
+            total_bytes: Some(file_size),
+        });
+        let mut mmap = GuardedMmap::create(path, file_size)?;
+        self.download_bundle_chunks_to_buffer(bundle_ranges, mmap.mmap(), Some(store), progress)?;
+        mmap.persist()?;
+        progress.on_event(ProgressEvent::FileFinished);
+        Ok(())
+    }
+
+    /// Download bundle chunks, return file data
+    ///
+    /// This is intended for small files. Use [Self::download_bundle_chunks()] to write large files
+    /// directly to the disk.
+    pub fn read_file(&self, file_size: u64, bundle_ranges: &FileBundleRanges) -> Result<Vec<u8>> {
+        let mut buf = vec![0; file_size as usize];
+        self.download_bundle_chunks_to_buffer(bundle_ranges, &mut buf, None, &mut ())?;
+        Ok(buf)
+    }
 
+    /// Download bundle chunks, write file data to a writer
+    ///
+    /// Chunks are downloaded in file order, consecutive chunks of the same bundle are requested
+    /// together. Data is written as soon as a group of chunks is decompressed, at most
+    /// [Self::WRITER_BUFFER_SIZE] bytes are buffered (unless a chunk is larger).
+    ///
+    /// ```no_run
+    /// # use cdragon_cdn::CdnDownloader;
+    /// # use cdragon_rman::Rman;
+    /// let rman = Rman::open("example.manifest").unwrap();
+    /// let bundle_chunks = rman.bundle_chunks().unwrap();
+    /// let file = rman.iter_files().next().unwrap().unwrap();
+    /// let (_, ranges) = file.bundle_chunks(&bundle_chunks).unwrap();
+    ///
+    /// let cdn = CdnDownloader::new().unwrap();
+    /// cdn.download_file_to_writer(&ranges, &mut std::io::stdout()).unwrap();
+    /// ```
+    pub fn download_file_to_writer(&self, bundle_ranges: &FileBundleRanges, writer: &mut dyn Write) -> Result<()> {
+        let mut ranges: Vec<(u64, &FileChunkRange)> = bundle_ranges
+            .iter()
+            .flat_map(|(bundle_id, ranges)| ranges.iter().map(move |range| (*bundle_id, range)))
+            .collect();
+        ranges.sort_unstable_by_key(|(_, range)| range.target.0);
+
+        let mut buf = Vec::new();
+        let mut ranges = &ranges[..];
+        while let Some((bundle_id, first)) = ranges.first() {
+            let begin = first.target.0;
+            let count = ranges
+                .iter()
+                .take_while(|(id, range)| id == bundle_id && range.target.1 - begin <= Self::WRITER_BUFFER_SIZE)
+                .count()
+                .max(1);
+            let group;
+            (group, ranges) = ranges.split_at(count);
+            buf.resize((group[count - 1].1.target.1 - begin) as usize, 0);
+            let group_ranges = split_file_ranges(group.iter().map(|(_, range)| *range), &mut buf, begin);
+            self.download_bundle_ranges(*bundle_id, group_ranges, None, &mut ())?;
+            writer.write_all(&buf)?;
+        }
+        Ok(())
+    }
+
+    /// Maximum size of file data buffered by [Self::download_file_to_writer()]
+    pub const WRITER_BUFFER_SIZE: u32 = 16 * 1024 * 1024;
+
+    /// Download bundle chunks to a buffer with the size of the file
+    ///
+    /// If a store is provided, chunks are read from it if possible, and downloaded chunks are added
+    /// to it. A stored chunk that cannot be read (e.g. corrupted) is downloaded again, and replaced
+    /// in the store.
+    fn download_bundle_chunks_to_buffer(&self, bundle_ranges: &FileBundleRanges, buf: &mut [u8], store: Option<&ChunkStore>, progress: &mut dyn Progress) -> Result<()> {
         // Download chunks, bundle per bundle
         for (bundle_id, ranges) in bundle_ranges {
-            let cdn_path = Self::bundle_path(*bundle_id);
-            // File ranges to slices
-            let buf: &mut [u8] = mmap.mmap();
-            let mut download_ranges = Vec::<((u32, u32), &mut [u8])>::with_capacity(ranges.len());
-            ranges
-                .iter()
-                .fold((buf, 0), |(buf, offset), range| {
-                    let (begin, end) = range.target;
-                    let (_, buf) = buf.split_at_mut((begin - offset) as usize);
-                    let (out, buf) = buf.split_at_mut((end - begin) as usize);
-                    download_ranges.push((range.bundle, out));
-                    (buf, end)
+            let mut download_ranges = split_file_ranges(ranges, buf, 0);
+            if let Some(store) = store {
+                download_ranges.retain_mut(|(range, buf)| {
+                    let stored = store.has(range.chunk_id) && store.get(range.chunk_id)
+                        .and_then(|reader| read_chunk(reader, range.chunk_id, buf, None, progress))
+                        .is_ok();
+                    !stored
                 });
-            self.download_ranges(&cdn_path, download_ranges)?;
+                if download_ranges.is_empty() {
+                    continue;
+                }
+            }
+            self.download_bundle_ranges(*bundle_id, download_ranges, store, progress)?;
         }
+        Ok(())
+    }
 
-        mmap.persist();
+    /// Download ranges of a bundle to the given buffers, use the cache if any
+    fn download_bundle_ranges(&self, bundle_id: u64, ranges: Vec<(&FileChunkRange, &mut [u8])>, store: Option<&ChunkStore>, progress: &mut dyn Progress) -> Result<()> {
+        match self.cache {
+            Some(ref cache) => {
+                let path = self.cached_bundle(cache, bundle_id)?;
+                read_ranges_from_file(&path, ranges, store, progress)
+            }
+            None => self.download_ranges(&Self::bundle_path(bundle_id), ranges, store, progress),
+        }
+    }
 
-        Ok(())
+    /// Return the path of a bundle in the cache, download it if needed
+    fn cached_bundle(&self, cache: &BundleCache, bundle_id: u64) -> Result<PathBuf> {
+        let path = cache.bundle_path(bundle_id);
+        if path.is_file() {
+            cache.touch(&path)?;
+        } else {
+            self.download_bundle(bundle_id, &path)?;
+            cache.evict(&path)?;
+        }
+        Ok(path)
     }
 
     /// Request a path from a CDN using given ranges
@@ -124,15 +309,15 @@ impl CdnDownloader {
     }
 
     /// Download multiple ranges of a bundle to the given buffers
-    fn download_ranges(&self, path: &str, ranges: Vec<((u32, u32), &mut [u8])>) -> Result<()> {
-        let cdn_ranges: Vec<(u32, u32)> = ranges.iter().map(|r| r.0).collect();
+    fn download_ranges(&self, path: &str, ranges: Vec<(&FileChunkRange, &mut [u8])>, store: Option<&ChunkStore>, progress: &mut dyn Progress) -> Result<()> {
+        let cdn_ranges: Vec<(u32, u32)> = ranges.iter().map(|r| r.0.bundle).collect();
         let response = self.get_ranges(path, &cdn_ranges)?;
 
         // Check for multipart response body
         let is_multipart = response.headers()
             .get(reqwest::header::CONTENT_TYPE)
             .and_then(|v| v.to_str().ok())
-            .map_or(false, |v| v.starts_with("multipart/byteranges; boundary="));
+            .is_some_and(|v| v.starts_with("multipart/byteranges; boundary="));
         let mut reader = BufReader::new(response);
 
         // Download individual chunks
@@ -162,15 +347,63 @@ impl CdnDownloader {
                 }
             }
 
-            let reader = (&mut reader).take((chunk_range.1 - chunk_range.0) as u64);
-            let mut decoder = zstd::stream::Decoder::new(reader)?;
-            decoder.read_exact(buf)?;
+            let (begin, end) = chunk_range.bundle;
+            let reader = (&mut reader).take((end - begin) as u64);
+            read_chunk(reader, chunk_range.chunk_id, buf, store, progress)?;
         }
 
         Ok(())
     }
 }
 
+/// Split a file buffer to the target slices of chunk ranges
+///
+/// Return chunk ranges with their target slice. `buf` starts at target offset `begin`.
+/// Ranges must be sorted by target offset, which is the case for ranges of a single bundle built
+/// by [cdragon_rman::FileEntry::bundle_chunks()].
+fn split_file_ranges<'r, 'a>(ranges: impl IntoIterator<Item=&'r FileChunkRange>, buf: &'a mut [u8], begin: u32) -> Vec<(&'r FileChunkRange, &'a mut [u8])> {
+    let mut result = Vec::new();
+    ranges
+        .into_iter()
+        .fold((buf, begin), |(buf, offset), range| {
+            let (begin, end) = range.target;
+            let (_, buf) = buf.split_at_mut((begin - offset) as usize);
+            let (out, buf) = buf.split_at_mut((end - begin) as usize);
+            result.push((range, out));
+            (buf, end)
+        });
+    result
+}
+
+/// Read ranges of a local bundle file to the given buffers
+fn read_ranges_from_file(path: &Path, ranges: Vec<(&FileChunkRange, &mut [u8])>, store: Option<&ChunkStore>, progress: &mut dyn Progress) -> Result<()> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    for (range, buf) in ranges.into_iter() {
+        let (begin, end) = range.bundle;
+        reader.seek(SeekFrom::Start(begin as u64))?;
+        let reader = (&mut reader).take((end - begin) as u64);
+        read_chunk(reader, range.chunk_id, buf, store, progress)?;
+    }
+    Ok(())
+}
+
+/// Read a compressed chunk, decompress it to the given buffer
+///
+/// If a store is provided, add the chunk to it, once decompressed successfully.
+fn read_chunk<R: Read>(mut reader: R, chunk_id: u64, buf: &mut [u8], store: Option<&ChunkStore>, progress: &mut dyn Progress) -> Result<()> {
+    match store {
+        None => zstd::stream::Decoder::new(reader)?.read_exact(buf)?,
+        Some(store) => {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            zstd::stream::Decoder::new(&data[..])?.read_exact(buf)?;
+            store.put(chunk_id, &data)?;
+        }
+    }
+    progress.on_event(ProgressEvent::BytesTransferred { n: buf.len() as u64 });
+    Ok(())
+}
+
 
 #[derive(Debug)]
 pub enum Product {
@@ -285,5 +518,28 @@ pub enum CdnError {
     Deserialize(#[from] serde_json::Error),
     #[error("invalid manifest URL")]
     InvalidManifestUrl,
+    #[error("bundle not found: {0:016X}")]
+    BundleNotFound(u64),
+    #[error("chunk not found: {0:016X}")]
+    ChunkNotFound(u64),
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::*;
+
+    #[test]
+    fn file_is_written_in_order() {
+        // Chunks of the file alternate between two bundles, already in the cache
+        let tmp = tempfile::tempdir().unwrap();
+        let file = TestFile::new(&[("Hello", 0xb01), (", ", 0xb02), ("world", 0xb01), ("!", 0xb02)]);
+        file.write_bundles(tmp.path());
+
+        let cdn = CdnDownloader::from_base_url("http://localhost:1/").unwrap().with_cache(tmp.path());
+        let mut output = Vec::new();
+        cdn.download_file_to_writer(&file.ranges, &mut output).unwrap();
+        assert_eq!(output, FILE_DATA);
+    }
+}
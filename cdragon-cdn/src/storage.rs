@@ -88,6 +88,7 @@ impl CdnStorage {
     /// Download bundles of a manifest
     fn download_manifest_bundles(&self, rman: &Rman) -> Result<()> {
         for entry in rman.iter_bundles() {
+            let entry = entry?;
             let path = CdnDownloader::bundle_path(entry.id);
             let fspath = self.conf.path.join(&path);
             if !fspath.exists() {
@@ -101,10 +102,11 @@ impl CdnStorage {
     ///
     /// Bundles are assumed to be available.
     fn extract_manifest_files(&self, rman: &Rman, output: &Path) -> Result<()> {
-        let dir_paths = rman.dir_paths();
-        let bundle_chunks = rman.bundle_chunks();
+        let dir_paths = rman.dir_paths()?;
+        let bundle_chunks = rman.bundle_chunks()?;
         for file_entry in rman.iter_files() {
-            let path = file_entry.path(&dir_paths);
+            let file_entry = file_entry?;
+            let path = file_entry.path(&dir_paths)?;
             // Note: some .dll/.exe are common to game and client manifests, but are slightly
             // different. Ignore if the target file already exists, even if symlinked.
             let target_path = output.join(&path);
@@ -113,9 +115,9 @@ impl CdnStorage {
             }
 
             // Group chunks by bundle ID to reduce open calls
-            let (file_size, ranges) = file_entry.bundle_chunks(&bundle_chunks);
+            let (file_size, ranges) = file_entry.bundle_chunks(&bundle_chunks)?;
             if self.conf.use_extract_symlinks {
-                let fspath = self.conf.path.join("cdragon/shared").join(Self::shared_file_hash(&file_entry));
+                let fspath = self.conf.path.join("cdragon/shared").join(Self::shared_file_hash(&file_entry)?);
                 self.extract_chunks_to_file(file_size as u64, &ranges, &fspath)?;
                 if let Some(parent) = target_path.parent() {
                     fs::create_dir_all(parent)?;
@@ -154,18 +156,18 @@ impl CdnStorage {
             }
         }
 
-        mmap.persist();
+        mmap.persist()?;
         Ok(())
     }
 
     /// Compute hash of an extracted file, from its chunks
-    fn shared_file_hash(file_entry: &FileEntry) -> String {
+    fn shared_file_hash(file_entry: &FileEntry) -> Result<String> {
         //XXX could be improved (or file hash format could change)
         let mut m = sha1_smol::Sha1::new();
         for chunk_id in file_entry.iter_chunks() {
-            m.update(format!("{:016X}", chunk_id).as_bytes());
+            m.update(format!("{:016X}", chunk_id?).as_bytes());
         }
-        m.hexdigest()
+        Ok(m.hexdigest())
     }
 }
 
@@ -7,7 +7,14 @@ use super::{HashKind, HashMapper};
 
 /// Compute a bin hash from a string
 ///
-/// The input string is assumed to be ASCII only.
+/// The input string is assumed to be ASCII only. Hash is case-insensitive.
+///
+/// ```
+/// # use cdragon_hashes::bin::compute_binhash;
+/// assert_eq!(compute_binhash(""), 0x811c9dc5);
+/// assert_eq!(compute_binhash("mSpellName"), 0xeb505619);
+/// assert_eq!(compute_binhash("mspellname"), 0xeb505619);
+/// ```
 pub fn compute_binhash(s: &str) -> u32 {
     s.to_ascii_lowercase().bytes()
         .fold(0x811c9dc5_u32, |h, b| (h ^ b as u32).wrapping_mul(0x01000193))
@@ -16,6 +23,12 @@ pub fn compute_binhash(s: &str) -> u32 {
 /// Same as `compute_binhash()` but const
 ///
 /// Implementation is less straightforward due to current limited support of const.
+///
+/// ```
+/// # use cdragon_hashes::bin::{compute_binhash, compute_binhash_const};
+/// const HASH: u32 = compute_binhash_const("mSpellName");
+/// assert_eq!(HASH, compute_binhash("mSpellName"));
+/// ```
 pub const fn compute_binhash_const(s: &str) -> u32 {
     let mut h = 0x811c9dc5_u32;
     let bytes = s.as_bytes();
@@ -28,6 +41,28 @@ pub const fn compute_binhash_const(s: &str) -> u32 {
     h
 }
 
+/// Compute the hash of a bin path value (`BinPathValue`)
+///
+/// Unlike other bin hashes, path values are 64-bit hashes of a file path in a WAD archive.
+/// They use the same scheme as WAD paths: the hash is case-sensitive, paths are expected to be
+/// normalized beforehand (see [crate::wad::normalize_wad_path()]).
+///
+/// Only available with the `wad` feature, which provides the hasher.
+///
+/// ```
+/// # use cdragon_hashes::bin::{compute_binhash, compute_binpath_hash};
+/// # use cdragon_hashes::wad::compute_wad_hash;
+/// assert_eq!(compute_binpath_hash("assets/foo.dds"), 0xabbc2d4d2f62b45a);
+/// assert_eq!(compute_binpath_hash("assets/foo.dds"), compute_wad_hash("assets/foo.dds"));
+/// // Case is significant, unlike other bin hashes
+/// assert_ne!(compute_binpath_hash("ASSETS/Foo.dds"), compute_binpath_hash("assets/foo.dds"));
+/// assert_eq!(compute_binhash("ASSETS/Foo.dds"), compute_binhash("assets/foo.dds"));
+/// ```
+#[cfg(feature = "wad")]
+pub fn compute_binpath_hash(s: &str) -> u64 {
+    crate::wad::compute_wad_hash(s)
+}
+
 /// Get a bin hash, either parsed from hex, or computed from a string
 ///
 /// A hex hash can be surrounded by braces (e.g. `{012345678}`).
@@ -6,16 +6,22 @@
 //! [HashMapper] manages a mapping to retrieve a string from a hash value.
 //! The type provides methods to load mapping files, check for known hashes, etc.
 //! update mapping files, etc.
+//!
+//! Mappers for each kind of hash (e.g. [wad::WadHashMapper], [bin::BinHashMapper]) are defined in
+//! per-format modules, which are their canonical path. Format crates re-export them in their
+//! `prelude` module, along with their own types.
 use std::fmt;
 use std::fs::File;
 use std::io::{BufReader, BufRead, BufWriter, Write};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::hash::Hash;
+use std::time::SystemTime;
 use num_traits::Num;
 use thiserror::Error;
 use cdragon_utils::GuardedFile;
 
+mod pattern;
 #[cfg(feature = "bin")]
 pub mod bin;
 #[cfg(feature = "rst")]
@@ -23,6 +29,8 @@ pub mod rst;
 #[cfg(feature = "wad")]
 pub mod wad;
 
+pub use pattern::HashValuePattern;
+
 type Result<T, E = HashError> = std::result::Result<T, E>;
 
 
@@ -38,6 +46,19 @@ pub enum HashError {
     InvalidHashLine(String),
     #[error("invalid hash value: {0:?}")]
     InvalidHashValue(String),
+    #[error("failed to load hash mapping {}", .path.display())]
+    MappingFile {
+        path: PathBuf,
+        #[source]
+        source: Box<HashError>,
+    },
+}
+
+impl HashError {
+    /// Wrap the error, to provide the path of the mapping file
+    pub fn with_mapping_path<P: Into<PathBuf>>(self, path: P) -> Self {
+        Self::MappingFile { path: path.into(), source: Box::new(self) }
+    }
 }
 
 
@@ -45,20 +66,68 @@ pub enum HashError {
 ///
 /// A hash mapping can be loaded from and written to files.
 /// Such files store one line per hash, formatted as `<hex-value> <string>`.
-#[derive(Default)]
+///
+/// `NBITS` is the number of bits actually used by hash values. It determines the number of hex
+/// characters used in mapping files (`NBITS / 4`, rounded up). It must not be larger than the bit
+/// size of `T`; this is checked at compile time when creating a mapper.
+///
+/// # Example: 39-bit hashes
+///
+/// RST hashes are 64-bit hashes truncated to 39 bits (40 bits for older files).
+/// They are stored in `u64` values, and formatted on 10 hex characters.
+/// ```
+/// # use cdragon_hashes::HashMapper;
+/// // 39-bit RST hash of `item_1001_name`
+/// let hash: u64 = 0x3376eae1da;
+///
+/// let mut mapper = HashMapper::<u64, 39>::new();
+/// mapper.insert(hash, "item_1001_name".to_string());
+/// let mut output = Vec::new();
+/// mapper.write(&mut output).unwrap();
+/// assert_eq!(output, b"3376eae1da item_1001_name\n");
+///
+/// let mapper = HashMapper::<u64, 39>::from_reader(output.as_slice()).unwrap();
+/// assert_eq!(mapper.get(0x3376eae1da), Some("item_1001_name"));
+/// ```
+///
+/// Using a too small hash type fails to compile, whichever constructor is used:
+/// ```compile_fail
+/// # use cdragon_hashes::HashMapper;
+/// let mapper = HashMapper::<u32, 39>::new();
+/// ```
+/// ```compile_fail
+/// # use cdragon_hashes::HashMapper;
+/// let mapper = HashMapper::<u32, 39>::default();
+/// ```
 pub struct HashMapper<T, const NBITS: usize> where T: Hash {
     map: HashMap<T, String>,
+    /// Modification time of the last loaded file
+    mtime: Option<SystemTime>,
+    /// Size of the last loaded file
+    file_size: Option<u64>,
 }
 
 impl<T, const NBITS: usize> HashMapper<T, NBITS> where T: Hash {
     /// Number of characters used to format the hash
     const NCHARS: usize = NBITS.div_ceil(4);
+
+    /// Check `NBITS` against `T` (evaluated at compile time, when used)
+    const CHECK_NBITS: () = assert!(NBITS > 0 && NBITS <= std::mem::size_of::<T>() * 8,
+                                    "NBITS must not exceed the bit size of the hash type");
+}
+
+impl<T, const N: usize> Default for HashMapper<T, N> where T: Eq + Hash + Copy {
+    fn default() -> Self {
+        let () = Self::CHECK_NBITS;
+        Self { map: HashMap::<T, String>::new(), mtime: None, file_size: None }
+    }
 }
 
 impl<T, const N: usize> HashMapper<T, N> where T: Eq + Hash + Copy {
     /// Create a new, empty mapping
     pub fn new() -> Self {
-        Self { map: HashMap::<T, String>::new() }
+        let () = Self::CHECK_NBITS;
+        Self::default()
     }
 
     /// Get a value from the mapping
@@ -71,7 +140,7 @@ impl<T, const N: usize> HashMapper<T, N> where T: Eq + Hash + Copy {
     /// Use this method to get a string representation with a fallback for unknown hashes.
     /// ```
     /// # use cdragon_hashes::HashMapper;
-    /// let mut mapper = HashMapper::<u16>::new();
+    /// let mut mapper = HashMapper::<u16, 16>::new();
     /// mapper.insert(42, "forty-two".to_string());
     /// assert_eq!(format!("{}", mapper.seek(42)), "forty-two");
     /// assert_eq!(format!("{}", mapper.seek(0x1234)), "{1234}");
@@ -83,6 +152,11 @@ impl<T, const N: usize> HashMapper<T, N> where T: Eq + Hash + Copy {
         }
     }
 
+    /// Get the modification time of the last file loaded with `load_path()`, if any
+    pub fn loaded_mtime(&self) -> Option<SystemTime> {
+        self.mtime
+    }
+
     /// Return `true` if the mapping is empty
     pub fn is_empty(&self) -> bool {
         self.map.is_empty()
@@ -99,6 +173,43 @@ impl<T, const N: usize> HashMapper<T, N> where T: Eq + Hash + Copy {
     pub fn insert(&mut self, hash: T, value: String) {
         self.map.insert(hash, value);
     }
+
+    /// Add a hash to the mapper, unless it is already mapped to another value
+    ///
+    /// **Important:** the caller must ensure the value matches the hash.
+    /// ```
+    /// # use cdragon_hashes::{HashMapper, HashInsertResult};
+    /// let mut mapper = HashMapper::<u16, 16>::new();
+    /// assert_eq!(mapper.insert_checked(42, "forty-two".to_string()), HashInsertResult::Inserted);
+    /// assert_eq!(mapper.insert_checked(42, "forty-two".to_string()), HashInsertResult::Duplicate);
+    /// assert_eq!(mapper.insert_checked(42, "other".to_string()),
+    ///            HashInsertResult::Collision { existing: "forty-two".to_string() });
+    /// assert_eq!(mapper.get(42), Some("forty-two"));
+    /// ```
+    pub fn insert_checked(&mut self, hash: T, value: String) -> HashInsertResult {
+        match self.map.get(&hash) {
+            Some(existing) if *existing == value => HashInsertResult::Duplicate,
+            Some(existing) => HashInsertResult::Collision { existing: existing.clone() },
+            None => {
+                self.map.insert(hash, value);
+                HashInsertResult::Inserted
+            }
+        }
+    }
+}
+
+/// Result of [HashMapper::insert_checked()]
+#[derive(Debug, PartialEq, Eq)]
+pub enum HashInsertResult {
+    /// Hash was unknown, value has been inserted
+    Inserted,
+    /// Hash was already mapped to the same value
+    Duplicate,
+    /// Hash was already mapped to another value, mapping has not been modified
+    Collision {
+        /// Value already mapped to the hash
+        existing: String,
+    },
 }
 
 impl<T, const N: usize> HashMapper<T, N> where T: Num + Eq + Hash + Copy {
@@ -117,39 +228,220 @@ impl<T, const N: usize> HashMapper<T, N> where T: Num + Eq + Hash + Copy {
     }
 
     /// Load hash mapping from a reader
+    ///
+    /// Each line is parsed as a hex hash value, followed by a space, followed by the string (which
+    /// is kept as-is, including spaces). Hash values don't need to be zero-padded and can use
+    /// uppercase letters, but they must fit in `NBITS` bits.
+    ///
+    /// ```
+    /// # use cdragon_hashes::{HashError, HashMapper};
+    /// let mut mapper = HashMapper::<u32, 32>::new();
+    /// mapper.load_reader(&b"004bca34 padded\n4bca35 short\nABCDEF01 upper\n00000001 \n"[..]).unwrap();
+    /// assert_eq!(mapper.get(0x4bca34), Some("padded"));
+    /// assert_eq!(mapper.get(0x4bca35), Some("short"));
+    /// assert_eq!(mapper.get(0xabcdef01), Some("upper"));
+    /// assert_eq!(mapper.get(1), Some(""));
+    ///
+    /// // Values must fit in NBITS
+    /// let mut mapper = HashMapper::<u64, 39>::new();
+    /// assert!(mapper.load_reader(&b"7fffffffff max\n"[..]).is_ok());
+    /// assert!(matches!(mapper.load_reader(&b"8000000000 overflow\n"[..]), Err(HashError::InvalidHashValue(_))));
+    /// assert!(matches!(mapper.load_reader(&b"0x1234 prefixed\n"[..]), Err(HashError::InvalidHashValue(_))));
+    /// assert!(matches!(mapper.load_reader(&b"1234\n"[..]), Err(HashError::InvalidHashLine(_))));
+    ///
+    /// // Output is always zero-padded, lowercase
+    /// let mut mapper = HashMapper::<u32, 32>::new();
+    /// mapper.load_reader(&b"4BCA34 value\n"[..]).unwrap();
+    /// let mut output = Vec::new();
+    /// mapper.write(&mut output).unwrap();
+    /// assert_eq!(output, b"004bca34 value\n");
+    /// ```
     pub fn load_reader<R: BufRead>(&mut self, reader: R) -> Result<(), HashError> {
+        self.load_reader_with(reader, str::to_string)
+    }
+
+    /// Load hash mapping from a reader, convert values before storing them
+    ///
+    /// Hashes are stored unchanged.
+    pub(crate) fn load_reader_with<R: BufRead, F: Fn(&str) -> String>(&mut self, reader: R, convert: F) -> Result<(), HashError> {
         for line in reader.lines() {
-            let l = line?;
-            if l.len() < Self::NCHARS + 1 {
-                return Err(HashError::InvalidHashLine(l));
-            }
-            let hash = T::from_str_radix(&l[..Self::NCHARS], 16).map_err(|_e| {
-                HashError::InvalidHashValue(l[..Self::NCHARS].to_string())
-            })?;
-            self.map.insert(hash, l[Self::NCHARS+1..].to_string());
+            let line = line?;
+            let (hash, value) = Self::parse_line(&line)?;
+            self.map.insert(hash, convert(value));
         }
         Ok(())
     }
 
+    /// Load a single line of a hash mapping
+    ///
+    /// The line is parsed as in [Self::load_reader()], without the trailing newline.
+    pub fn load_line(&mut self, line: &str) -> Result<(), HashError> {
+        let (hash, value) = Self::parse_line(line)?;
+        self.map.insert(hash, value.to_string());
+        Ok(())
+    }
+
+    /// Parse a mapping line, return the hash and the string
+    ///
+    /// The line is parsed as in [Self::load_reader()], without the trailing newline. The mapping
+    /// is not modified, this can be used to check a mapping file.
+    ///
+    /// ```
+    /// # use cdragon_hashes::HashMapper;
+    /// assert_eq!(HashMapper::<u32, 32>::parse_line("004bca34 value").unwrap(), (0x4bca34, "value"));
+    /// assert!(HashMapper::<u32, 16>::parse_line("004bca34 value").is_err());
+    /// ```
+    pub fn parse_line(line: &str) -> Result<(T, &str), HashError> {
+        let Some((hex, value)) = line.split_once(' ') else {
+            return Err(HashError::InvalidHashLine(line.to_string()));
+        };
+        let hash = Self::parse_hex_hash(hex).ok_or_else(|| HashError::InvalidHashValue(hex.to_string()))?;
+        Ok((hash, value))
+    }
+
+    /// Parse a hex hash value, check it fits in `NBITS`
+    fn parse_hex_hash(hex: &str) -> Option<T> {
+        if hex.is_empty() || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digits = hex.trim_start_matches('0');
+        if let Some(first) = digits.chars().next() {
+            let first_bits = 32 - first.to_digit(16)?.leading_zeros() as usize;
+            if 4 * (digits.len() - 1) + first_bits > N {
+                return None;
+            }
+        }
+        T::from_str_radix(hex, 16).ok()
+    }
+
     /// Load hash mapping from a file
+    ///
+    /// The modification time and size of the file are recorded, see [Self::reload_path_if_changed()].
     pub fn load_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.load_path_with(path, str::to_string)
+    }
+
+    /// Load hash mapping from a file, convert values before storing them
+    pub(crate) fn load_path_with<P: AsRef<Path>, F: Fn(&str) -> String>(&mut self, path: P, convert: F) -> Result<()> {
         let file = File::open(&path)?;
-        self.load_reader(BufReader::new(file))?;
+        let metadata = file.metadata()?;
+        let mtime = metadata.modified().ok();
+        self.load_reader_with(BufReader::new(file), convert)?;
+        self.mtime = mtime;
+        self.file_size = Some(metadata.len());
         Ok(())
     }
+
+    /// Reload the mapping from a file if it has been modified since it was loaded
+    ///
+    /// The file modification time and size are compared to the ones recorded by the last call to
+    /// `load_path()`. If one of them changed, the file is loaded again, to a new mapping which
+    /// replaces the current one. Entries that have been added to the mapping but not written are
+    /// lost. If the file cannot be loaded, the current mapping is left unchanged.
+    ///
+    /// If the platform does not provide modification times, only the size is compared: changes
+    /// which keep the same size are not detected.
+    ///
+    /// Return `true` if the mapping has been reloaded.
+    ///
+    /// ```
+    /// # use std::time::{Duration, SystemTime};
+    /// # use cdragon_hashes::HashMapper;
+    /// let tmp = tempfile::tempdir().unwrap();
+    /// let path = tmp.path().join("hashes.txt");
+    /// std::fs::write(&path, "0001 one\n").unwrap();
+    /// let mut mapper = HashMapper::<u16, 16>::from_path(&path).unwrap();
+    /// assert!(!mapper.reload_path_if_changed(&path).unwrap());
+    ///
+    /// std::fs::write(&path, "0002 two\n").unwrap();
+    /// # // Make sure the modification time changes
+    /// # let file = std::fs::File::options().write(true).open(&path).unwrap();
+    /// # file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+    /// assert!(mapper.reload_path_if_changed(&path).unwrap());
+    /// assert_eq!(mapper.get(1), None);
+    /// assert_eq!(mapper.get(2), Some("two"));
+    ///
+    /// // Invalid content, the mapping is not changed
+    /// std::fs::write(&path, "0003 three\ninvalid\n").unwrap();
+    /// assert!(mapper.reload_path_if_changed(&path).is_err());
+    /// assert_eq!(mapper.get(2), Some("two"));
+    /// assert_eq!(mapper.get(3), None);
+    /// ```
+    pub fn reload_path_if_changed<P: AsRef<Path>>(&mut self, path: P) -> Result<bool> {
+        if !self.is_file_changed(&path)? {
+            return Ok(false);
+        }
+        let mut mapper = Self::new();
+        mapper.load_path(path)?;
+        *self = mapper;
+        Ok(true)
+    }
+
+    /// Return `true` if a file differs from the last loaded one
+    ///
+    /// Compare modification times, if available, and sizes.
+    /// Files that have not been loaded with `load_path()` are always changed.
+    pub(crate) fn is_file_changed<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let metadata = std::fs::metadata(path)?;
+        if self.file_size != Some(metadata.len()) {
+            return Ok(true);
+        }
+        Ok(metadata.modified().ok() != self.mtime)
+    }
 }
 
 impl<T, const N: usize> HashMapper<T, N> where T: Eq + Hash + Copy + fmt::LowerHex {
     /// Write hash mapping to a writer
+    ///
+    /// Entries are sorted by string, output is deterministic.
     pub fn write<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.write_prefixed(writer, "")
+    }
+
+    /// Write hash mapping to a writer, prefix each line
+    ///
+    /// Entries are sorted by string, as with [Self::write()].
+    pub fn write_prefixed<W: Write>(&self, writer: &mut W, prefix: &str) -> std::io::Result<()> {
         let mut entries: Vec<_> = self.map.iter().collect();
         entries.sort_by_key(|kv| kv.1);
         for (h, s) in entries {
-            writeln!(writer, "{:0w$x} {}", h, s, w = Self::NCHARS)?;
+            writer.write_all(prefix.as_bytes())?;
+            Self::write_entry(writer, h, s)?;
+        }
+        Ok(())
+    }
+
+    /// Write hash mapping to a writer, without sorting entries
+    ///
+    /// This is faster than [Self::write()] for large mappings, but the order of entries is
+    /// unspecified and may change between calls: output is nondeterministic.
+    /// Use it only when order does not matter (e.g. for a file read back immediately).
+    ///
+    /// ```
+    /// # use cdragon_hashes::HashMapper;
+    /// let mut mapper = HashMapper::<u32, 32>::new();
+    /// mapper.insert(0x1234, "b".into());
+    /// mapper.insert(0xabcd, "a".into());
+    /// let mut sorted = Vec::new();
+    /// mapper.write(&mut sorted).unwrap();
+    /// assert_eq!(String::from_utf8(sorted).unwrap(), "0000abcd a\n00001234 b\n");
+    /// let mut unsorted = Vec::new();
+    /// mapper.write_unsorted(&mut unsorted).unwrap();
+    /// let mut lines: Vec<_> = std::str::from_utf8(&unsorted).unwrap().lines().collect();
+    /// lines.sort();
+    /// assert_eq!(lines, ["00001234 b", "0000abcd a"]);
+    /// ```
+    pub fn write_unsorted<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for (h, s) in self.map.iter() {
+            Self::write_entry(writer, h, s)?;
         }
         Ok(())
     }
 
+    fn write_entry<W: Write>(writer: &mut W, hash: &T, s: &str) -> std::io::Result<()> {
+        writeln!(writer, "{:0w$x} {}", hash, s, w = Self::NCHARS)
+    }
+
     /// Write hash map to a file
     ///
     /// File is upadeted atomically.
@@ -226,6 +518,23 @@ where H: Copy + fmt::LowerHex, S: AsRef<str> {
 /// - implicit conversion from/to hash integer type (`From<T>`)
 /// - [std::fmt::Debug] implementation
 /// - [std::fmt::LowerHex] implementation
+/// - `try_str()` and `display()` methods, to resolve the hash using a [HashMapper]
+///
+/// ```
+/// # use cdragon_hashes::{define_hash_type, HashDef, HashMapper};
+/// define_hash_type! {
+///     /// Sample hash type
+///     SampleHash(u32) => |s| s.len() as u32
+/// }
+///
+/// let mut mapper = HashMapper::<u32, 32>::new();
+/// mapper.insert(3, "foo".into());
+/// assert_eq!(SampleHash::hashed("foo"), SampleHash::from(3));
+/// assert_eq!(SampleHash::from(3).try_str(&mapper), Some("foo"));
+/// assert_eq!(SampleHash::from(4).try_str(&mapper), None);
+/// assert_eq!(SampleHash::from(3).display(&mapper).to_string(), "foo");
+/// assert_eq!(SampleHash::from(4).display(&mapper).to_string(), "{00000004}");
+/// ```
 #[macro_export]
 macro_rules! define_hash_type {
     (
@@ -260,6 +569,18 @@ macro_rules! define_hash_type {
             }
         }
 
+        impl $name {
+            /// Get the string associated to the hash
+            pub fn try_str<'a, const N: usize>(&self, mapper: &'a $crate::HashMapper<$T, N>) -> Option<&'a str> {
+                mapper.get(self.hash)
+            }
+
+            /// Get the string associated to the hash or fallback to the hash itself
+            pub fn display<'a, const N: usize>(&self, mapper: &'a $crate::HashMapper<$T, N>) -> $crate::HashOrStr<$T, &'a str> {
+                mapper.seek(self.hash)
+            }
+        }
+
         impl std::fmt::Debug for $name {
             fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
                 write!(f, concat!(stringify!($name), "({:x})"), self)
@@ -297,6 +618,17 @@ pub enum HashKind {
 }
 
 impl HashKind {
+    /// All kinds of hashes
+    pub const VARIANTS: [Self; 7] = [
+        Self::WadGame,
+        Self::WadLcu,
+        Self::BinEntryPath,
+        Self::BinClassName,
+        Self::BinFieldName,
+        Self::BinHashValue,
+        Self::Rst,
+    ];
+
     /// Return filename used by CDragon to store the mapping this kind of hash
     ///
     /// ```
@@ -304,7 +636,7 @@ impl HashKind {
     /// assert_eq!(HashKind::WadLcu.mapping_path(), "hashes.lcu.txt");
     /// assert_eq!(HashKind::BinEntryPath.mapping_path(), "hashes.binentries.txt");
     /// ```
-    pub fn mapping_path(&self) -> &'static str {
+    pub const fn mapping_path(&self) -> &'static str {
         match self {
             Self::WadGame => "hashes.game.txt",
             Self::WadLcu => "hashes.lcu.txt",
@@ -316,6 +648,15 @@ impl HashKind {
         }
     }
 
+    /// Convert a WAD hash kind, usable in const context
+    #[cfg(feature = "wad")]
+    pub(crate) const fn from_wad_kind(kind: wad::WadHashKind) -> Self {
+        match kind {
+            wad::WadHashKind::Game => Self::WadGame,
+            wad::WadHashKind::Lcu => Self::WadLcu,
+        }
+    }
+
     /// Return WAD hash kind from a WAD path
     ///
     /// The path is assumed to be a "regular" WAD path that follows Riot conventions.
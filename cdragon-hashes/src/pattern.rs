@@ -0,0 +1,55 @@
+//! Match hash values against hex values or path patterns
+use std::hash::Hash;
+use num_traits::Num;
+use cdragon_utils::pattern::PathPattern;
+use super::HashMapper;
+
+/// Match hash value against pattern
+///
+/// Pattern can be the hex representation of a hash value or a [PathPattern] (see its
+/// documentation for the supported syntax).
+/// A path pattern only matches hashes known by the mapper.
+///
+/// # Example
+/// ```
+/// # use cdragon_hashes::{HashMapper, HashValuePattern};
+/// let mut mapper = HashMapper::<u32, 32>::new();
+/// mapper.insert(0x1234, "data/foo.bin".to_string());
+/// assert!(HashValuePattern::<u32>::new("00001234").is_match(0x1234, &mapper));
+/// assert!(HashValuePattern::<u32>::new("data/*.bin").is_match(0x1234, &mapper));
+/// assert!(!HashValuePattern::<u32>::new("*.bin").is_match(0x1234, &mapper));
+/// assert!(!HashValuePattern::<u32>::new("**.bin").is_match(0x5678, &mapper));
+/// ```
+#[derive(Clone, Debug)]
+pub enum HashValuePattern<T: Num + Eq + Hash + Copy> {
+    /// Exact hash value
+    Hash(T),
+    /// Pattern on the hash string
+    Path(PathPattern),
+}
+
+impl<T: Num + Eq + Hash + Copy> HashValuePattern<T> {
+    /// Parse a pattern
+    ///
+    /// A pattern is parsed as a hash if it has the length of the hex representation of a hash
+    /// value and is a valid hex value.
+    pub fn new(pattern: &str) -> Self {
+        // If pattern matches a hash value, consider it's a hash
+        if pattern.len() == std::mem::size_of::<T>() * 2 {
+            if let Ok(hash) = T::from_str_radix(pattern, 16) {
+                return Self::Hash(hash);
+            }
+        }
+
+        // Otherwise, parse as a path pattern
+        Self::Path(PathPattern::new(pattern))
+    }
+
+    /// Return `true` if the hash matches the pattern
+    pub fn is_match<const N: usize>(&self, hash: T, mapper: &HashMapper<T, N>) -> bool {
+        match self {
+            Self::Hash(h) => hash == *h,
+            Self::Path(pattern) => mapper.get(hash).is_some_and(|path| pattern.is_match(path)),
+        }
+    }
+}
@@ -1,9 +1,10 @@
 //! Hashes used in RST files
 //!
 //! Keys are hashed using 64-bit xxHash, then truncated.
+use std::io::BufRead;
 use std::hash::Hasher;
 use twox_hash::XxHash64;
-use crate::HashMapper;
+use crate::{HashInsertResult, HashMapper, Result};
 
 /// Compute a hash for an RST file key, untruncated
 pub fn compute_rst_hash_full(s: &str) -> u64 {
@@ -13,10 +14,87 @@ pub fn compute_rst_hash_full(s: &str) -> u64 {
 }
 
 /// Compute a hash for an RST file key, truncated to `n` bits
+///
+/// If `bits` is 64 or more, the hash is not truncated.
+///
+/// ```
+/// # use cdragon_hashes::rst::*;
+/// assert_eq!(compute_rst_hash_n("item_1001_name", 39), 0x3376eae1da);
+/// assert_eq!(compute_rst_hash_n("item_1001_name", 64), compute_rst_hash_full("item_1001_name"));
+/// assert_eq!(compute_rst_hash_n("item_1001_name", 255), compute_rst_hash_full("item_1001_name"));
+/// ```
 pub fn compute_rst_hash_n(s: &str, bits: u8) -> u64 {
-    compute_rst_hash_full(s) & ((1 << bits) - 1)
+    let mask = 1u64.checked_shl(bits as u32).map_or(u64::MAX, |v| v - 1);
+    compute_rst_hash_full(s) & mask
 }
 
 /// Mapper for RST hashes, use current default hash size
 pub type RstHashMapper<const NBITS: usize = 39> = HashMapper<u64, NBITS>;
 
+/// Two different keys with the same truncated hash
+#[derive(Debug)]
+pub struct RstKeyCollision {
+    /// Truncated hash value
+    pub hash: u64,
+    /// Key already in the mapping
+    pub known: String,
+    /// Colliding key, not added to the mapping
+    pub key: String,
+}
+
+/// Hash keys read from a reader, add them to a mapper
+///
+/// Keys are read one per line, empty lines are ignored.
+/// Hashes are truncated to the mapper's bit size.
+///
+/// If a hash is already mapped to another key, the mapping is not modified and the collision is
+/// returned.
+/// ```
+/// use cdragon_hashes::rst::{RstHashMapper, insert_keys_from_reader};
+/// let mut mapper = RstHashMapper::<39>::new();
+/// let collisions = insert_keys_from_reader(&mut mapper, "item_1001_name\n".as_bytes()).unwrap();
+/// assert!(collisions.is_empty());
+/// assert_eq!(mapper.get(0x3376eae1da), Some("item_1001_name"));
+/// ```
+pub fn insert_keys_from_reader<R: BufRead, const NBITS: usize>(mapper: &mut RstHashMapper<NBITS>, reader: R) -> Result<Vec<RstKeyCollision>> {
+    let mut collisions = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let key = line.trim_end();
+        if key.is_empty() {
+            continue;
+        }
+        let hash = compute_rst_hash_n(key, NBITS as u8);
+        if let HashInsertResult::Collision { existing } = mapper.insert_checked(hash, key.to_owned()) {
+            collisions.push(RstKeyCollision {
+                hash,
+                known: existing,
+                key: key.to_owned(),
+            });
+        }
+    }
+    Ok(collisions)
+}
+
+impl<const N: usize> RstHashMapper<N> {
+    /// Build a mapper for hashes truncated to `bits`, keys are hashed again
+    ///
+    /// RST files may use a different hash size than the mapping file, see
+    /// `Rst::hash_bits()` in `cdragon-rst`.
+    ///
+    /// ```
+    /// # use cdragon_hashes::rst::{RstHashMapper, compute_rst_hash_n};
+    /// let mut mapper = RstHashMapper::<39>::new();
+    /// mapper.insert(0x3376eae1da, "item_1001_name".into());
+    /// let hash = compute_rst_hash_n("item_1001_name", 64);
+    /// assert_eq!(mapper.rehashed(64).get(hash), Some("item_1001_name"));
+    /// assert_eq!(mapper.rehashed(64).get(0x3376eae1da), None);
+    /// ```
+    pub fn rehashed(&self, bits: u8) -> RstHashMapper<64> {
+        let mut mapper = RstHashMapper::<64>::new();
+        for key in self.map.values() {
+            mapper.insert(compute_rst_hash_n(key, bits), key.clone());
+        }
+        mapper
+    }
+}
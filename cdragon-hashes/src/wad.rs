@@ -1,17 +1,390 @@
 //! Hashes used in WAD archives
 //!
 //! File paths in WAD archive are hashed using 64-bit xxHash
+//!
+//! Game (`.wad.client`) and launcher (`.wad`) archives use the same hashing: xxHash64 with a
+//! seed of 0, on the normalized path (see [normalize_wad_path()]). [WadHashKind] only selects
+//! the mapping file. A path missing from a list, but found in the other one, has the same hash
+//! in both; mismatches come from the paths themselves (e.g. a path that was not normalized).
 use std::hash::Hasher;
+use std::cell::OnceCell;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use twox_hash::XxHash64;
-use crate::HashMapper;
+use crate::{HashKind, HashMapper, Result};
 
 /// Compute a hash for a WAD file path
+///
+/// The hash is a 64-bit xxHash (seed 0) of the path. It is case-sensitive: paths are expected to
+/// be normalized beforehand (see [normalize_wad_path()]).
+///
+/// This is the hasher of [WadHashMapper] hashes, for both [WadHashKind] variants: game and
+/// launcher archives use the same algorithm and seed.
+///
+/// ```
+/// # use cdragon_hashes::wad::compute_wad_hash;
+/// assert_eq!(compute_wad_hash("assets/foo.dds"), 0xabbc2d4d2f62b45a);
+/// ```
 pub fn compute_wad_hash(s: &str) -> u64 {
     let mut h = XxHash64::with_seed(0);
     h.write(s.as_bytes());
     h.finish()
 }
 
+/// Compute a hash for a WAD file path, normalize it first
+///
+/// This is the same as `compute_wad_hash(&normalize_wad_path(s))`.
+/// See [normalize_wad_path()] for normalization rules.
+///
+/// ```
+/// # use cdragon_hashes::wad::{compute_wad_hash, compute_wad_hash_normalized};
+/// assert_eq!(compute_wad_hash_normalized("Assets\\Foo.DDS"), 0xabbc2d4d2f62b45a);
+/// assert_eq!(compute_wad_hash_normalized("DATA/Characters/Ahri/Ahri.bin"), 0xa847c7a46bc6730e);
+/// assert_eq!(compute_wad_hash_normalized("data/characters/ahri/ahri.bin"), compute_wad_hash("data/characters/ahri/ahri.bin"));
+/// ```
+pub fn compute_wad_hash_normalized(s: &str) -> u64 {
+    compute_wad_hash(&normalize_wad_path(s))
+}
+
+/// Normalize a path the way the game does before hashing it
+///
+/// Normalization rules are the following:
+///
+/// - ASCII letters are lowercased; other characters are kept as is
+/// - backslashes are replaced by forward slashes
+///
+/// Nothing else is changed: leading or duplicate slashes, `.` and `..` components are kept.
+/// [compute_wad_hash()] expects normalized paths.
+/// ```
+/// # use cdragon_hashes::wad::normalize_wad_path;
+/// assert_eq!(normalize_wad_path("ASSETS\\Characters/Foo.dds"), "assets/characters/foo.dds");
+/// assert_eq!(normalize_wad_path("Data//Foo\\..\\É.bin"), "data//foo/../É.bin");
+/// ```
+pub fn normalize_wad_path(s: &str) -> String {
+    s.to_ascii_lowercase().replace('\\', "/")
+}
+
+/// Build candidate WAD paths from a path string (e.g. found in bin files)
+///
+/// The path is normalized with [normalize_wad_path()], then variants are generated:
+///
+/// - the normalized path itself
+/// - for texture paths (`.dds`, `.tex` or `.png` extension), the same path with each of the
+///   other two extensions (bin files may reference `.dds` files stored as `.tex`, and
+///   CDragon exports textures as `.png`)
+///
+/// ```
+/// # use cdragon_hashes::wad::wad_path_variants;
+/// assert_eq!(wad_path_variants("data/Foo.bin"), ["data/foo.bin"]);
+/// assert_eq!(wad_path_variants("ASSETS/Foo.dds"), ["assets/foo.dds", "assets/foo.tex", "assets/foo.png"]);
+/// assert_eq!(wad_path_variants("assets/foo.tex"), ["assets/foo.tex", "assets/foo.dds", "assets/foo.png"]);
+/// ```
+pub fn wad_path_variants(s: &str) -> Vec<String> {
+    const TEXTURE_EXTENSIONS: [&str; 3] = [".dds", ".tex", ".png"];
+
+    let path = normalize_wad_path(s);
+    let mut variants = Vec::with_capacity(TEXTURE_EXTENSIONS.len());
+    if let Some(ext) = TEXTURE_EXTENSIONS.iter().find(|ext| path.ends_with(*ext)) {
+        let base = &path[..path.len() - ext.len()];
+        for other in TEXTURE_EXTENSIONS.iter().filter(|other| *other != ext) {
+            variants.push(format!("{}{}", base, other));
+        }
+    }
+    variants.insert(0, path);
+    variants
+}
+
 /// Mapper for WAD hashes
 pub type WadHashMapper = HashMapper<u64, 64>;
 
+
+impl WadHashMapper {
+    /// Load hash mapping from a reader, normalize paths
+    ///
+    /// Paths are normalized using [normalize_wad_path()]. Hashes are not modified.
+    /// This is intended for lists whose paths use backslashes or mixed case.
+    /// ```
+    /// # use cdragon_hashes::wad::WadHashMapper;
+    /// let mut mapper = WadHashMapper::new();
+    /// mapper.load_reader_normalized(&b"0123456789abcdef DATA\\Foo.bin\n"[..]).unwrap();
+    /// assert_eq!(mapper.get(0x0123456789abcdef), Some("data/foo.bin"));
+    /// ```
+    pub fn load_reader_normalized<R: BufRead>(&mut self, reader: R) -> Result<()> {
+        self.load_reader_with(reader, normalize_wad_path)
+    }
+
+    /// Load hash mapping from a file, normalize paths
+    ///
+    /// See [load_reader_normalized()](Self::load_reader_normalized()).
+    pub fn load_path_normalized<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.load_path_with(path, normalize_wad_path)
+    }
+
+    /// Load additional entries from a mapping file
+    ///
+    /// Entries are merged with the existing ones. Hashes already in the mapping are overridden
+    /// by the ones from the loaded file.
+    /// This can be used to overlay supplemental lists over the default ones.
+    ///
+    /// Unlike [load_path()](Self::load_path()), the file is not recorded:
+    /// [reload_path_if_changed()](Self::reload_path_if_changed()) still checks the main file, and
+    /// drops additional entries if it reloads it.
+    /// If the file cannot be loaded, the mapping is left unchanged.
+    ///
+    /// ```
+    /// # use cdragon_hashes::wad::WadHashMapper;
+    /// let tmp = tempfile::tempdir().unwrap();
+    /// let (main, extra) = (tmp.path().join("hashes.game.txt"), tmp.path().join("extra.txt"));
+    /// std::fs::write(&main, "0000000000000001 one\n0000000000000002 two\n").unwrap();
+    /// std::fs::write(&extra, "0000000000000002 deux\n0000000000000003 trois\n").unwrap();
+    ///
+    /// let mut mapper = WadHashMapper::from_path(&main).unwrap();
+    /// mapper.load_additional_path(&extra).unwrap();
+    /// assert_eq!(mapper.get(1), Some("one"));
+    /// assert_eq!(mapper.get(2), Some("deux"));
+    /// assert_eq!(mapper.get(3), Some("trois"));
+    /// assert!(!mapper.reload_path_if_changed(&main).unwrap());
+    ///
+    /// // Invalid content, the mapping is not changed
+    /// std::fs::write(&extra, "0000000000000004 quatre\ninvalid\n").unwrap();
+    /// assert!(mapper.load_additional_path(&extra).is_err());
+    /// assert_eq!(mapper.get(4), None);
+    /// ```
+    pub fn load_additional_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mut additional = Self::new();
+        additional.load_reader(BufReader::new(File::open(path)?))?;
+        self.map.extend(additional.map);
+        Ok(())
+    }
+}
+
+
+/// Hash mappers for both kinds of WAD archives
+///
+/// Mappers can be loaded lazily from a directory: each mapper is loaded on first access.
+/// Loading errors are then reported on access.
+#[derive(Debug, Default)]
+pub struct WadHashMappers {
+    /// Directory to load mappers from, on first access
+    dir: Option<PathBuf>,
+    /// If `true`, missing files are loaded as empty mappers
+    skip_missing: bool,
+    /// If `true`, paths are normalized when loaded
+    normalize: bool,
+    lcu: OnceCell<WadHashMapper>,
+    game: OnceCell<WadHashMapper>,
+}
+
+impl WadHashMappers {
+    /// Create mappers, load them from a directory path
+    ///
+    /// All mapping files must exist.
+    pub fn from_dirpath(path: &Path) -> Result<Self> {
+        let mut this = Self::default();
+        this.load_dirpath(path, false)?;
+        Ok(this)
+    }
+
+    /// Create mappers, to be loaded from a directory path on first access
+    ///
+    /// If `skip_missing` is `true`, missing mapping files are loaded as empty mappers.
+    pub fn lazy_from_dirpath<P: Into<PathBuf>>(path: P, skip_missing: bool) -> Self {
+        Self { dir: Some(path.into()), skip_missing, ..Self::default() }
+    }
+
+    /// Normalize paths of mappers loaded afterwards
+    ///
+    /// See [WadHashMapper::load_reader_normalized()].
+    pub fn with_path_normalization(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Load mappers from a directory path
+    ///
+    /// Entries are merged into already loaded mappers.
+    /// If `skip_missing` is `true`, missing mapping files are ignored.
+    pub fn load_dirpath(&mut self, path: &Path, skip_missing: bool) -> Result<()> {
+        for &kind in WadHashKind::all() {
+            let path = path.join(kind.mapping_path());
+            if skip_missing && !path.exists() {
+                continue;
+            }
+            let normalize = self.normalize;
+            let cell = self.cell_mut(kind);
+            if cell.get().is_none() {
+                let _ = cell.set(WadHashMapper::new());
+            }
+            let mapper = cell.get_mut().unwrap();
+            Self::load_mapper_path(mapper, &path, normalize).map_err(|e| e.with_mapping_path(path))?;
+        }
+        Ok(())
+    }
+
+    /// Get the mapper of a given kind, load it if needed
+    pub fn mapper(&self, kind: WadHashKind) -> Result<&WadHashMapper> {
+        let cell = self.cell(kind);
+        if let Some(mapper) = cell.get() {
+            return Ok(mapper);
+        }
+        let mut mapper = WadHashMapper::new();
+        if let Some(dir) = &self.dir {
+            let path = dir.join(kind.mapping_path());
+            if !self.skip_missing || path.exists() {
+                Self::load_mapper_path(&mut mapper, &path, self.normalize).map_err(|e| e.with_mapping_path(path))?;
+            }
+        }
+        let _ = cell.set(mapper);
+        Ok(cell.get().unwrap())
+    }
+
+    /// Get the mapper to use for a WAD path, load it if needed
+    ///
+    /// Return `None` if the WAD kind cannot be determined from the path.
+    /// See [HashKind::from_wad_path()].
+    pub fn mapper_from_wad_path<P: AsRef<Path>>(&self, path: P) -> Result<Option<&WadHashMapper>> {
+        match HashKind::from_wad_path(path).and_then(|kind| WadHashKind::try_from(kind).ok()) {
+            Some(kind) => Ok(Some(self.mapper(kind)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reload mappers whose file has been modified since they were loaded
+    ///
+    /// Only mappers already loaded are reloaded, from `path`; mappers are reloaded from scratch
+    /// (see [WadHashMapper::reload_path_if_changed()]).
+    ///
+    /// Return `true` if at least one mapper has been reloaded.
+    pub fn reload_if_changed(&mut self, path: &Path) -> Result<bool> {
+        let mut reloaded = false;
+        for kind in self.loaded_kinds() {
+            let path = path.join(kind.mapping_path());
+            if self.skip_missing && !path.exists() {
+                continue;
+            }
+            let normalize = self.normalize;
+            let mapper = self.cell_mut(kind).get_mut().unwrap();
+            if mapper.is_file_changed(&path).map_err(|e| e.with_mapping_path(&path))? {
+                let mut new_mapper = WadHashMapper::new();
+                Self::load_mapper_path(&mut new_mapper, &path, normalize).map_err(|e| e.with_mapping_path(&path))?;
+                *mapper = new_mapper;
+                reloaded = true;
+            }
+        }
+        Ok(reloaded)
+    }
+
+    /// Return the kinds of the mappers that have been loaded
+    pub fn loaded_kinds(&self) -> Vec<WadHashKind> {
+        WadHashKind::all()
+            .iter()
+            .copied()
+            .filter(|kind| self.cell(*kind).get().is_some())
+            .collect()
+    }
+
+    fn load_mapper_path(mapper: &mut WadHashMapper, path: &Path, normalize: bool) -> Result<()> {
+        if normalize {
+            mapper.load_path_normalized(path)
+        } else {
+            mapper.load_path(path)
+        }
+    }
+
+    fn cell(&self, kind: WadHashKind) -> &OnceCell<WadHashMapper> {
+        match kind {
+            WadHashKind::Lcu => &self.lcu,
+            WadHashKind::Game => &self.game,
+        }
+    }
+
+    fn cell_mut(&mut self, kind: WadHashKind) -> &mut OnceCell<WadHashMapper> {
+        match kind {
+            WadHashKind::Lcu => &mut self.lcu,
+            WadHashKind::Game => &mut self.game,
+        }
+    }
+}
+
+
+/// Enum with a variant for each kind of WAD hash
+///
+/// Each kind has its own mapping file, but all use [compute_wad_hash()], see
+/// [Self::compute_hash()].
+///
+/// ```
+/// # use cdragon_hashes::{HashKind, wad::WadHashKind};
+/// let paths: Vec<&str> = WadHashKind::all().iter().map(|k| k.mapping_path()).collect();
+/// assert_eq!(paths, ["hashes.game.txt", "hashes.lcu.txt"]);
+/// assert_eq!(HashKind::from(WadHashKind::Lcu), HashKind::WadLcu);
+/// assert_eq!(WadHashKind::try_from(HashKind::WadGame), Ok(WadHashKind::Game));
+/// assert!(WadHashKind::try_from(HashKind::Rst).is_err());
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum WadHashKind {
+    /// Hash for game WAD entries (`.wad.client`)
+    Game,
+    /// Hash for launcher WAD entries (`.wad`)
+    Lcu,
+}
+
+impl WadHashKind {
+    /// All kinds of WAD hashes
+    pub const VARIANTS: [Self; 2] = [
+        Self::Game,
+        Self::Lcu,
+    ];
+
+    /// Return all kinds of WAD hashes, for iteration
+    pub const fn all() -> &'static [Self] {
+        &Self::VARIANTS
+    }
+
+    /// Return filename used by CDragon to store the mapping of this kind of hash
+    ///
+    /// See [HashKind::mapping_path()].
+    pub const fn mapping_path(&self) -> &'static str {
+        HashKind::from_wad_kind(*self).mapping_path()
+    }
+
+    /// Compute the hash of a path, for this kind of WAD
+    ///
+    /// The path is normalized first, see [compute_wad_hash_normalized()].
+    /// All kinds use the same hashing: the result does not depend on the kind. This method is
+    /// provided for code handling both kinds, so that it does not have to assume it.
+    ///
+    /// ```
+    /// # use cdragon_hashes::wad::{WadHashKind, compute_wad_hash};
+    /// let path = "plugins/rcp-fe-lol-champ-select/global/default/index.js";
+    /// assert_eq!(WadHashKind::Lcu.compute_hash(path), compute_wad_hash(path));
+    /// assert_eq!(WadHashKind::Game.compute_hash("Assets\\Foo.DDS"), 0xabbc2d4d2f62b45a);
+    /// for path in ["data/characters/ahri/ahri.bin", "Plugins/RCP-FE-Lol-Home/global/default/trans.json"] {
+    ///     assert_eq!(WadHashKind::Game.compute_hash(path), WadHashKind::Lcu.compute_hash(path));
+    /// }
+    /// ```
+    pub fn compute_hash(&self, path: &str) -> u64 {
+        match self {
+            Self::Game | Self::Lcu => compute_wad_hash_normalized(path),
+        }
+    }
+}
+
+impl From<WadHashKind> for HashKind {
+    fn from(val: WadHashKind) -> Self {
+        Self::from_wad_kind(val)
+    }
+}
+
+impl TryFrom<HashKind> for WadHashKind {
+    type Error = HashKind;
+
+    /// Convert a hash kind, return it back if it's not a WAD hash kind
+    fn try_from(val: HashKind) -> std::result::Result<Self, HashKind> {
+        match val {
+            HashKind::WadGame => Ok(Self::Game),
+            HashKind::WadLcu => Ok(Self::Lcu),
+            kind => Err(kind),
+        }
+    }
+}
@@ -1,14 +1,16 @@
 //! Bin data definitions
 use std::any::Any;
 use num_enum::TryFromPrimitive;
-use super::BinHashMappers;
+use super::{
+    BinHashMappers,
+    binvalue_map_type,
+    binvalue_map_keytype,
+};
 use cdragon_hashes::{
     define_hash_type,
     HashOrStr,
-    bin::{BinHashKind, compute_binhash},
-    wad::compute_wad_hash,
+    bin::{BinHashKind, compute_binhash, compute_binpath_hash},
 };
-pub use cdragon_hashes::bin::BinHashMapper;
 
 
 /// Field value for an antry, a struct or an embed
@@ -18,16 +20,47 @@ pub struct BinField {
     pub name: BinFieldName,
     /// Field value type
     pub vtype: BinType,
-    pub(crate) value: Box<dyn Any>,  // Any = vtype
+    pub(crate) value: Box<dyn Any + Send + Sync>,  // Any = vtype
 }
 
 impl BinField {
+    /// Create a new field, infer its type from the value
+    ///
+    /// [BinList] values are always typed as [BinType::List].
+    ///
+    /// # Example
+    /// ```
+    /// # use cdragon_prop::data::*;
+    /// let field = BinField::new(0x1234.into(), BinU32(42));
+    /// assert_eq!(field.vtype, BinType::U32);
+    /// assert_eq!(field.downcast::<BinU32>(), Some(&BinU32(42)));
+    /// assert!(field.downcast::<BinS32>().is_none());
+    ///
+    /// let field = BinField::new(0x1234.into(), BinList::new(vec![BinString("a".into())]));
+    /// assert_eq!(field.vtype, BinType::List);
+    /// let list = field.downcast::<BinList>().unwrap();
+    /// assert_eq!(list.vtype, BinType::String);
+    /// assert_eq!(list.downcast::<BinString>(), Some(&vec![BinString("a".into())]));
+    /// ```
+    pub fn new<T: BinValue + 'static>(name: BinFieldName, value: T) -> Self {
+        Self { name, vtype: T::TYPE, value: Box::new(value) }
+    }
+
     /// Downcast the field value
     pub fn downcast<T: BinValue + 'static>(&self) -> Option<&T> {
         self.value.downcast_ref::<T>()
     }
 }
 
+impl Clone for BinField {
+    fn clone(&self) -> Self {
+        let value = binvalue_map_type!(self.vtype, T, {
+            Box::new(self.downcast::<T>().unwrap().clone()) as Box<dyn Any + Send + Sync>
+        });
+        Self { name: self.name, vtype: self.vtype, value }
+    }
+}
+
 
 /// Declare a bin hash type
 macro_rules! declare_bin_hash {
@@ -43,13 +76,13 @@ macro_rules! declare_bin_hash {
         impl $name {
             /// Hash kind, for use with [BinHashMappers]
             const KIND: BinHashKind = $kind;
-            /// Get the string associated to the hash
+            /// Get the string associated to the hash, see `try_str()`
             pub fn get_str<'a>(&self, mapper: &'a BinHashMappers) -> Option<&'a str> {
-                mapper.get(Self::KIND).get(self.hash)
+                self.try_str(mapper.get(Self::KIND))
             }
-            /// Get the string associated to the hash or fallback to the hash itself
+            /// Get the string associated to the hash or fallback to the hash itself, see `display()`
             pub fn seek_str<'a>(&self, mapper: &'a BinHashMappers) -> HashOrStr<u32, &'a str> {
-                mapper.get(Self::KIND).seek(self.hash)
+                self.display(mapper.get(Self::KIND))
             }
         }
     }
@@ -75,22 +108,24 @@ declare_bin_hash! {
 
 define_hash_type! {
     /// Hash of a [BinPath] value, put to a file in a [cdragon_wad::Wad] archive
-    BinPathValue(u64) => compute_wad_hash
+    BinPathValue(u64) => compute_binpath_hash
 }
 impl BinPathValue {
-    /// Get the path associated to the hash
+    /// Get the path associated to the hash, see `try_str()`
     pub fn get_str<'a>(&self, mapper: &'a BinHashMappers) -> Option<&'a str> {
-        mapper.path_value.get(self.hash)
+        self.try_str(&mapper.path_value)
     }
-    /// Get the path associated to the hash or fallback to the hash itself
+    /// Get the path associated to the hash or fallback to the hash itself, see `display()`
     pub fn seek_str<'a>(&self, mapper: &'a BinHashMappers) -> HashOrStr<u64, &'a str> {
-        mapper.path_value.seek(self.hash)
+        self.display(&mapper.path_value)
     }
 }
 
 
 /// Trait for values enumerated in [BinType]
-pub trait BinValue {
+///
+/// Values are `Send` and `Sync`, so that parsed entries can be moved between threads.
+pub trait BinValue: Send + Sync {
     /// Bin type associated to the value
     const TYPE: BinType;
 }
@@ -98,7 +133,7 @@ pub trait BinValue {
 macro_rules! declare_bintype_struct {
     ($type:ident ($t:ty) [$($d:ident),* $(,)?]) => {
         #[allow(missing_docs)]
-        #[derive(Debug,$($d),*)]
+        #[derive(Debug,Clone,$($d),*)]
         pub struct $type(pub $t);
         impl From<$t> for $type {
             fn from(v: $t) -> Self { Self(v) }
@@ -106,7 +141,7 @@ macro_rules! declare_bintype_struct {
     };
     ($type:ident ($($v:ident: $t:ty),* $(,)?)) => {
         #[allow(missing_docs)]
-        #[derive(Debug)]
+        #[derive(Debug,Clone)]
         pub struct $type($(pub $t,)*);
         impl From<($($t),*)> for $type {
             fn from(($($v),*): ($($t),*)) -> Self {
@@ -133,7 +168,7 @@ declare_bintype_struct!{ BinVec4(a: f32, b: f32, c: f32, d: f32) }
 declare_bintype_struct!{ BinMatrix([[f32; 4]; 4]) [] }
 /// Color bin value (RGBA)
 #[allow(missing_docs)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BinColor { pub r: u8, pub g: u8, pub b: u8, pub a: u8 }
 declare_bintype_struct!{ BinString(String) [Eq,PartialEq,Hash] }
 declare_bintype_struct!{ BinHash(BinHashValue) [Eq,PartialEq,Hash] }
@@ -145,20 +180,72 @@ declare_bintype_struct!{ BinFlag(bool) [Eq,PartialEq,Hash] }
 /// List of values, variable size
 ///
 /// This type is used for both [BinType::List] and [BinType::List2].
+/// Both types have the same binary layout, only the type of the field differs. It is kept in
+/// [BinField::vtype], lists cannot be nested in other containers.
+///
+/// Serializers showing value types (e.g. [TextTreeSerializer](crate::TextTreeSerializer)) tell
+/// them apart. JSON output has no type information and is the same for both.
+///
+/// ```
+/// # use cdragon_prop::prelude::*;
+/// # use cdragon_prop::testing::{list_value, raw_bin_file_data, raw_type};
+/// // Entry with two `u8` lists: field 0xa is a `List`, field 0xb a `List2`
+/// let list = list_value(BinType::U8, 2, &[1, 2]);
+/// let data = raw_bin_file_data(&[(0x10, 0x1, &[
+///     (0xa, raw_type(BinType::List), &list),
+///     (0xb, raw_type(BinType::List2), &list),
+/// ])]);
+///
+/// let prop = PropFile::from_slice(&data).unwrap();
+/// let fields = &prop.entries[0].fields;
+/// assert_eq!(fields[0].vtype, BinType::List);
+/// assert_eq!(fields[1].vtype, BinType::List2);
+/// for field in fields {
+///     let list = field.downcast::<BinList>().unwrap();
+///     assert_eq!(list.downcast::<BinU8>(), Some(&vec![BinU8(1), BinU8(2)]));
+/// }
+///
+/// let hmappers = BinHashMappers::default();
+/// let mut text = Vec::new();
+/// TextTreeSerializer::new(&mut text, &hmappers).write_entry(&prop.entries[0]).unwrap();
+/// let text = String::from_utf8(text).unwrap();
+/// assert!(text.contains("<{0000000a} LIST(U8) ["));
+/// assert!(text.contains("<{0000000b} LIST2(U8) ["));
+///
+/// let mut json = Vec::new();
+/// JsonSerializer::new(&mut json, &hmappers).write_entry(&prop.entries[0]).unwrap();
+/// let json = String::from_utf8(json).unwrap();
+/// assert_eq!(json, r#"{"__type":"{00000010}","{0000000a}":[1,2],"{0000000b}":[1,2]}"#);
+/// ```
 pub struct BinList {
     /// Type of values in the list
     pub vtype: BinType,
-    pub(crate) values: Box<dyn Any>,  // Any = Vec<vtype>
+    pub(crate) values: Box<dyn Any + Send + Sync>,  // Any = Vec<vtype>
 }
 
 impl BinList {
+    /// Create a new list, infer its value type from the values
+    pub fn new<T: BinValue + 'static>(values: Vec<T>) -> Self {
+        Self { vtype: T::TYPE, values: Box::new(values) }
+    }
+
     /// Downcast the list to a vector
     pub fn downcast<T: BinValue + 'static>(&self) -> Option<&Vec<T>> {
         self.values.downcast_ref::<Vec<T>>()
     }
 }
 
+impl Clone for BinList {
+    fn clone(&self) -> Self {
+        let values = binvalue_map_type!(self.vtype, T, {
+            Box::new(self.downcast::<T>().unwrap().clone()) as Box<dyn Any + Send + Sync>
+        });
+        Self { vtype: self.vtype, values }
+    }
+}
+
 /// Bin structure, referenced by pointer
+#[derive(Clone)]
 pub struct BinStruct {
     /// Class type of the struct
     pub ctype: BinClassName,
@@ -167,6 +254,11 @@ pub struct BinStruct {
 }
 
 impl BinStruct {
+    /// Create a new struct from its class type and fields
+    pub fn new(ctype: BinClassName, fields: Vec<BinField>) -> Self {
+        Self { ctype, fields }
+    }
+
     /// Get a field by its name
     pub fn get(&self, name: BinFieldName) -> Option<&BinField> {
         self.fields.iter().find(|f| f.name == name)
@@ -179,6 +271,7 @@ impl BinStruct {
 }
 
 /// Bin structure whose data is embedded directly
+#[derive(Clone)]
 pub struct BinEmbed {
     /// Class type of the embed
     pub ctype: BinClassName,
@@ -187,6 +280,11 @@ pub struct BinEmbed {
 }
 
 impl BinEmbed {
+    /// Create a new embed from its class type and fields
+    pub fn new(ctype: BinClassName, fields: Vec<BinField>) -> Self {
+        Self { ctype, fields }
+    }
+
     /// Get a field by its name
     pub fn get(&self, name: BinFieldName) -> Option<&BinField> {
         self.fields.iter().find(|f| f.name == name)
@@ -202,10 +300,27 @@ impl BinEmbed {
 pub struct BinOption {
     /// Type of the value in the option
     pub vtype: BinType,
-    pub(crate) value: Option<Box<dyn Any>>,  // Any = vtype
+    pub(crate) value: Option<Box<dyn Any + Send + Sync>>,  // Any = vtype
 }
 
 impl BinOption {
+    /// Create a new option, infer its value type from the value
+    ///
+    /// # Example
+    /// ```
+    /// # use cdragon_prop::data::*;
+    /// let option = BinOption::new(Some(BinFloat(1.5)));
+    /// assert_eq!(option.vtype, BinType::Float);
+    /// assert_eq!(option.downcast::<BinFloat>().map(|v| v.0), Some(1.5));
+    ///
+    /// let option = BinOption::new::<BinU8>(None);
+    /// assert_eq!(option.vtype, BinType::U8);
+    /// assert!(!option.is_some());
+    /// ```
+    pub fn new<T: BinValue + 'static>(value: Option<T>) -> Self {
+        Self { vtype: T::TYPE, value: value.map(|v| Box::new(v) as Box<dyn Any + Send + Sync>) }
+    }
+
     /// Return `true` if the option contains a value
     pub fn is_some(&self) -> bool {
         self.value.is_some()
@@ -220,6 +335,15 @@ impl BinOption {
     }
 }
 
+impl Clone for BinOption {
+    fn clone(&self) -> Self {
+        let value = self.value.as_ref().map(|_| binvalue_map_type!(self.vtype, T, {
+            Box::new(self.downcast::<T>().unwrap().clone()) as Box<dyn Any + Send + Sync>
+        }));
+        Self { vtype: self.vtype, value }
+    }
+}
+
 
 /// Map of values, with separate key and value types
 pub struct BinMap {
@@ -227,16 +351,45 @@ pub struct BinMap {
     pub ktype: BinType,
     /// Type of map values
     pub vtype: BinType,
-    pub(crate) values: Box<dyn Any>,  // Any = Vec<(ktype, vtype)>
+    pub(crate) values: Box<dyn Any + Send + Sync>,  // Any = Vec<(ktype, vtype)>
 }
 
 impl BinMap {
+    /// Create a new map, infer its key and value types from the values
+    ///
+    /// # Example
+    /// ```
+    /// # use cdragon_prop::data::*;
+    /// let map = BinMap::new(vec![
+    ///     (BinHash(0x10.into()), BinStruct::new(0x20.into(), vec![BinField::new(0x30.into(), BinBool(true))])),
+    /// ]);
+    /// assert_eq!((map.ktype, map.vtype), (BinType::Hash, BinType::Struct));
+    /// let values = map.downcast::<BinHash, BinStruct>().unwrap();
+    /// assert_eq!(values[0].0, BinHash(0x10.into()));
+    /// assert_eq!(values[0].1.getv::<BinBool>(0x30.into()), Some(&BinBool(true)));
+    /// assert!(map.downcast::<BinHash, BinEmbed>().is_none());
+    /// ```
+    pub fn new<K: BinValue + 'static, V: BinValue + 'static>(values: Vec<(K, V)>) -> Self {
+        Self { ktype: K::TYPE, vtype: V::TYPE, values: Box::new(values) }
+    }
+
     /// Downcast the map to a vector of `(key, value)` pairs
     pub fn downcast<K: BinValue + 'static, V: BinValue + 'static>(&self) -> Option<&Vec<(K, V)>> {
         self.values.downcast_ref::<Vec<(K, V)>>()
     }
 }
 
+impl Clone for BinMap {
+    fn clone(&self) -> Self {
+        let values = binvalue_map_keytype!(self.ktype, K, {
+            binvalue_map_type!(self.vtype, V, {
+                Box::new(self.downcast::<K, V>().unwrap().clone()) as Box<dyn Any + Send + Sync>
+            })
+        });
+        Self { ktype: self.ktype, vtype: self.vtype, values }
+    }
+}
+
 impl BinValue for BinNone { const TYPE: BinType = BinType::None; }
 impl BinValue for BinBool { const TYPE: BinType = BinType::Bool; }
 impl BinValue for BinS8 { const TYPE: BinType = BinType::S8; }
@@ -270,7 +423,7 @@ impl BinValue for BinFlag { const TYPE: BinType = BinType::Flag; }
 /// Variant values match the binary values used in PROP files.
 #[allow(dead_code, missing_docs)]
 #[repr(u8)]
-#[derive(Copy, Clone, Eq, PartialEq, TryFromPrimitive, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, TryFromPrimitive, Debug)]
 pub enum BinType {
     None = 0,
     Bool = 1,
@@ -293,7 +446,7 @@ pub enum BinType {
     Path = 18,  // introduced in 10.23
     // Complex types (shifted to 0x80+ in 9.23)
     List = 19,
-    List2 = 20,  // handled as List, introduced in 10.8
+    List2 = 20,  // same layout as List, introduced in 10.8
     Struct = 21,
     Embed = 22,
     Link = 23,
@@ -0,0 +1,143 @@
+//! Filter bin entries from their path and type
+use std::collections::HashSet;
+use std::ops::Not;
+use cdragon_hashes::bin::binhash_from_str;
+use super::data::{BinClassName, BinEntryPath};
+
+/// Filter on bin entries, built from their path and type
+///
+/// Filters can be combined with `!` (see [Not]), [Self::or()] and [Self::and()].
+/// Use [Self::predicate()] to filter scanned entries with
+/// [BinEntryScanner::filter_parse()](crate::BinEntryScanner::filter_parse()).
+///
+/// # Example
+/// ```
+/// # use cdragon_prop::prelude::*;
+/// let spell: BinClassName = binh!("SpellObject");
+/// let item: BinClassName = binh!("ItemData");
+/// let path: BinEntryPath = binh!("Items/1001");
+/// let other: BinEntryPath = binh!("Items/1004");
+///
+/// let filter = EntryFilter::with_type(spell);
+/// assert!(filter.matches(path, spell));
+/// assert!(!filter.matches(path, item));
+///
+/// // Items, except one
+/// let filter = EntryFilter::with_type(item).and(!EntryFilter::from_hash_list(["Items/1004"]));
+/// assert!(filter.matches(path, item));
+/// assert!(!filter.matches(other, item));
+///
+/// // Hash lists accept hex values too
+/// let filter = EntryFilter::from_hash_list([format!("{:x}", other)]).or(EntryFilter::with_type(spell));
+/// assert!(filter.matches(other, item));
+/// assert!(filter.matches(path, spell));
+/// assert!(!filter.matches(path, item));
+///
+/// let filter = EntryFilter::all();
+/// let predicate = filter.predicate();
+/// assert!(predicate(path, item));
+/// ```
+#[derive(Clone, Debug)]
+pub struct EntryFilter(FilterExpr);
+
+#[derive(Clone, Debug)]
+enum FilterExpr {
+    All,
+    Types(HashSet<BinClassName>),
+    Paths(HashSet<BinEntryPath>),
+    Not(Box<FilterExpr>),
+    Or(Vec<FilterExpr>),
+    And(Vec<FilterExpr>),
+}
+
+impl EntryFilter {
+    /// Match all entries
+    pub fn all() -> Self {
+        Self(FilterExpr::All)
+    }
+
+    /// Match entries of a given type
+    pub fn with_type(ctype: BinClassName) -> Self {
+        Self::with_types([ctype])
+    }
+
+    /// Match entries of any of the given types
+    pub fn with_types<I: IntoIterator<Item=BinClassName>>(ctypes: I) -> Self {
+        Self(FilterExpr::Types(ctypes.into_iter().collect()))
+    }
+
+    /// Match entries with any of the given paths
+    pub fn with_paths<I: IntoIterator<Item=BinEntryPath>>(paths: I) -> Self {
+        Self(FilterExpr::Paths(paths.into_iter().collect()))
+    }
+
+    /// Match entries with any of the given paths, provided as hex hashes or strings
+    ///
+    /// Values are converted using [binhash_from_str()].
+    pub fn from_hash_list<I: IntoIterator<Item=S>, S: AsRef<str>>(values: I) -> Self {
+        Self::with_paths(values.into_iter().map(|s| binhash_from_str(s.as_ref()).into()))
+    }
+
+    /// Match entries matched by either filter
+    pub fn or(self, other: Self) -> Self {
+        match self.0 {
+            FilterExpr::Or(mut exprs) => {
+                exprs.push(other.0);
+                Self(FilterExpr::Or(exprs))
+            }
+            expr => Self(FilterExpr::Or(vec![expr, other.0])),
+        }
+    }
+
+    /// Match entries matched by both filters
+    pub fn and(self, other: Self) -> Self {
+        match self.0 {
+            FilterExpr::And(mut exprs) => {
+                exprs.push(other.0);
+                Self(FilterExpr::And(exprs))
+            }
+            expr => Self(FilterExpr::And(vec![expr, other.0])),
+        }
+    }
+
+    /// Return true if an entry is matched by the filter
+    pub fn matches(&self, path: BinEntryPath, ctype: BinClassName) -> bool {
+        self.0.matches(path, ctype)
+    }
+
+    /// Return a predicate, suitable for [BinEntryScanner::filter_parse()](crate::BinEntryScanner::filter_parse())
+    pub fn predicate(&self) -> impl Fn(BinEntryPath, BinClassName) -> bool + '_ {
+        move |path, ctype| self.matches(path, ctype)
+    }
+}
+
+impl Default for EntryFilter {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl Not for EntryFilter {
+    type Output = Self;
+
+    /// Negate the filter
+    fn not(self) -> Self {
+        match self.0 {
+            FilterExpr::Not(expr) => Self(*expr),
+            expr => Self(FilterExpr::Not(Box::new(expr))),
+        }
+    }
+}
+
+impl FilterExpr {
+    fn matches(&self, path: BinEntryPath, ctype: BinClassName) -> bool {
+        match self {
+            Self::All => true,
+            Self::Types(ctypes) => ctypes.contains(&ctype),
+            Self::Paths(paths) => paths.contains(&path),
+            Self::Not(expr) => !expr.matches(path, ctype),
+            Self::Or(exprs) => exprs.iter().any(|e| e.matches(path, ctype)),
+            Self::And(exprs) => exprs.iter().all(|e| e.matches(path, ctype)),
+        }
+    }
+}
@@ -1,3 +1,7 @@
+//! Serialize bin data to JSON
+//!
+//! Use [JsonSerializer] to write JSON to a writer.
+//! With the `serde` feature, `to_value()` converts a field value to a `serde_json::Value`.
 use std::io;
 use std::io::Write;
 use super::{
@@ -53,38 +57,23 @@ impl<'a, W: Write> JsonSerializer<'a, W> {
     }
 
     fn write_entry_path(&mut self, h: BinEntryPath) -> io::Result<()> {
-        match h.get_str(self.hmappers) {
-            Some(s) => write!(self.writer, "\"{}\"", s),
-            _ => write!(self.writer, "\"{{{:x}}}\"", h),
-        }
+        write!(self.writer, "\"{}\"", h.seek_str(self.hmappers))
     }
 
     fn write_field_name(&mut self, h: BinFieldName) -> io::Result<()> {
-        match h.get_str(self.hmappers) {
-            Some(s) => write!(self.writer, "\"{}\"", s),
-            _ => write!(self.writer, "\"{{{:x}}}\"", h),
-        }
+        write!(self.writer, "\"{}\"", h.seek_str(self.hmappers))
     }
 
     fn write_type_name(&mut self, h: BinClassName) -> io::Result<()> {
-        match h.get_str(self.hmappers) {
-            Some(s) => write!(self.writer, "\"{}\"", s),
-            _ => write!(self.writer, "\"{{{:x}}}\"", h),
-        }
+        write!(self.writer, "\"{}\"", h.seek_str(self.hmappers))
     }
 
     fn write_hash_value(&mut self, h: BinHashValue) -> io::Result<()> {
-        match h.get_str(self.hmappers) {
-            Some(s) => write!(self.writer, "\"{}\"", s),
-            _ => write!(self.writer, "\"{{{:x}}}\"", h),
-        }
+        write!(self.writer, "\"{}\"", h.seek_str(self.hmappers))
     }
 
     fn write_path_value(&mut self, h: BinPathValue) -> io::Result<()> {
-        match h.get_str(self.hmappers) {
-            Some(s) => write!(self.writer, "\"{}\"", s),
-            _ => write!(self.writer, "\"{{{:x}}}\"", h),
-        }
+        write!(self.writer, "\"{}\"", h.seek_str(self.hmappers))
     }
 
     /// Write JSON string content, escape special chars
@@ -280,6 +269,30 @@ impl_bin_key_serializable!(BinHash, write_hash);
 impl_bin_key_serializable!(BinPath, write_path);
 
 
+/// Convert a field value to a JSON value
+///
+/// Output is the same as the one of [JsonSerializer].
+///
+/// # Example
+/// ```
+/// # use cdragon_prop::{json, prelude::*};
+/// let mut hmappers = BinHashMappers::default();
+/// hmappers.class_name.insert(0x10, "MyClass".to_string());
+/// let field = BinField::new(1.into(), BinStruct::new(0x10.into(), vec![
+///     BinField::new(2.into(), BinList::new(vec![BinU8(1), BinU8(2)])),
+/// ]));
+/// let value = json::to_value(&field, &hmappers);
+/// assert_eq!(value, serde_json::json!({"__type": "MyClass", "{00000002}": [1, 2]}));
+/// ```
+#[cfg(feature = "serde")]
+pub fn to_value(field: &BinField, hmappers: &BinHashMappers) -> serde_json::Value {
+    // Conversion cannot fail: map keys are always numbers or strings
+    serde_json::to_value(crate::serde_adapter::SerializableFieldValue(field, hmappers))
+        .expect("bin value conversion to JSON failed")
+}
+
+
+/// Serialize streamed entries to JSON, see [JsonSerializer::write_entries()]
 pub struct JsonEntriesSerializer<'a, W: Write> {
     parent: JsonSerializer<'a, W>,
     first: bool,
@@ -16,6 +16,8 @@
 //! Moreover, even if the embedded bin type is known by the container at run-time, the Rust type
 //! system requires the user to explicitely request a given type, one way or the other.
 //!
+//! Commonly used types and macros are available from the [prelude].
+//!
 //! The [`binget!()`] macro makes it easier to chain casts and should be enough when the names and
 //! types to get are known in advance.
 //!
@@ -42,7 +44,7 @@
 //!
 //! ## Examples
 //! ```
-//! # use cdragon_prop::{binvalue_map_keytype, binvalue_map_type, data::*};
+//! # use cdragon_prop::prelude::*;
 //! # fn test(field: BinField, map: BinMap) {
 //! binvalue_map_type!(field.vtype, T, {
 //!     let value: &T = field.downcast::<T>().unwrap();
@@ -65,33 +67,126 @@
 //! - [field names](BinFieldName)
 //! - ["bin hash" values](BinHashValue).
 //!
-//! Hash values can be computed at compile-time with [`cdragon_hashes::binh!()`], or
-//! using other methods from [`cdragon_hashes::bin`].
+//! Hash values can be computed at compile-time with [`binh!()`], or using
+//! [`compute_binhash()`]. Both are re-exported from [`cdragon_hashes::bin`].
+//!
+//! [Path values](BinPathValue) are different: they are 64-bit hashes of file paths in WAD
+//! archives, computed with [`compute_binpath_hash()`]. Unlike other bin hashes, they are
+//! case-sensitive.
+//!
+//! ```
+//! use cdragon_hashes::HashDef;
+//! use cdragon_prop::{compute_binhash, compute_binpath_hash, prelude::*};
+//! let name: BinFieldName = binh!("mSpellName");
+//! assert_eq!(name, BinFieldName::from(0xeb505619));
+//! assert_eq!(name.hash, compute_binhash("mspellname"));
+//! assert_eq!(BinPathValue::hashed("assets/foo.dds").hash, 0xabbc2d4d2f62b45a);
+//! assert_eq!(compute_binpath_hash("assets/foo.dds"), 0xabbc2d4d2f62b45a);
+//! ```
 //!
 //! A [`BinHashMappers`] gather all hash-to-string conversion needed by bin data.
+//!
+//! # Features
+//!
+//! APIs using the filesystem (e.g. [`PropFile::from_path()`] or
+//! [`BinHashMappers::from_dirpath()`]) require the `fs` feature, enabled by default.
+//! Without it, data can still be parsed from memory or readers (e.g. [`PropFile::from_slice()`],
+//! [`PropFile::scan_entries_from_reader()`]), which is enough for sandboxed environments such as
+//! WebAssembly.
+
+// `ReadArray::read_array()` collides with unstable `Read::read_array()`
+#![allow(unstable_name_collisions)]
 
 mod macros;
 mod parser;
 mod serializer;
 mod text_tree;
-mod json;
+pub mod json;
+mod patch;
+mod filter;
+#[cfg(feature = "serde")]
+mod serde_adapter;
 pub mod visitor;
 pub mod data;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
+use std::collections::HashMap;
 use std::io;
+#[cfg(feature = "fs")]
 use std::fs;
 use std::path::Path;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
 use thiserror::Error;
-use cdragon_hashes::{HashKind, HashMapper, HashError};
+use cdragon_hashes::{HashMapper, HashError, bin};
+#[cfg(feature = "fs")]
+use cdragon_hashes::HashKind;
 use cdragon_utils::parsing::ParseError;
-pub use cdragon_hashes::bin::{BinHashKind, BinHashMapper};
+#[cfg(feature = "fs")]
+use cdragon_utils::GuardedFile;
+pub use cdragon_hashes::bin::{
+    compute_binhash,
+    compute_binhash_const,
+    compute_binpath_hash,
+};
+pub use cdragon_hashes::binh;
 
 pub use serializer::{BinSerializer, BinEntriesSerializer};
 pub use data::*;
-pub use parser::{BinEntryScanner, BinEntryScannerItem};
+pub use parser::{BinEntryScanner, BinEntryScannerItem, BinEntryScanOwned, ScannedEntry, Strictness};
 pub use text_tree::TextTreeSerializer;
 pub use json::JsonSerializer;
-pub use visitor::{BinVisitor, BinTraversal};
+pub use visitor::{BinVisitor, BinTraversal, VisitOutcome};
+pub use filter::EntryFilter;
+#[cfg(feature = "serde")]
+pub use serde_adapter::SerializableEntry;
+
+/// Commonly used PROP types, data types, hash mappers and macros
+///
+/// Hash mappers and [BinHashKind] are defined in
+/// [cdragon_hashes::bin], which is their canonical path. They are re-exported here for
+/// convenience.
+///
+/// ```
+/// use cdragon_prop::prelude::*;
+/// let mut hmappers = BinHashMappers::default();
+/// hmappers.get_mut(BinHashKind::FieldName).insert(binh!(BinFieldName, "mSpellName").hash, "mSpellName".into());
+/// assert_eq!(hmappers.field_name.get(0xeb505619), Some("mSpellName"));
+///
+/// let data = BinStruct::new(binh!("Champion"), vec![BinField::new(binh!("mName"), BinString("Annie".into()))]);
+/// assert_eq!(binget!(data => mName(BinString)), Some(&BinString("Annie".into())));
+/// ```
+pub mod prelude {
+    pub use super::{
+        BinEntry,
+        BinEntryScanner,
+        BinEntriesSerializer,
+        BinHashMappers,
+        BinSerializer,
+        BinTraversal,
+        BinVisitor,
+        EntryFilter,
+        JsonSerializer,
+        PropError,
+        PropFile,
+        Strictness,
+        TextTreeSerializer,
+        VisitOutcome,
+        binget,
+        binvalue_map_keytype,
+        binvalue_map_type,
+    };
+    pub use super::data::*;
+    pub use cdragon_hashes::bin::{BinHashKind, BinHashMapper};
+    pub use cdragon_hashes::binh;
+}
+
+// Note: a type alias cannot be used to import enum variants, keep a plain re-export
+pub use cdragon_hashes::bin::BinHashKind;
+/// Deprecated re-export of [cdragon_hashes::bin::BinHashMapper]
+#[deprecated(note = "use cdragon_hashes::bin::BinHashMapper, or import it from cdragon_prop::prelude")]
+pub type BinHashMapper = bin::BinHashMapper;
 
 
 /// Result type for PROP file errors
@@ -150,9 +245,10 @@ impl<T: Default, U: Default> Default for BinHashKindMapping<T, U> {
 ///
 /// Each individual mapper can be accessed either directly through its field, or from a
 /// `BinHashKind` value.
-pub type BinHashMappers = BinHashKindMapping<BinHashMapper, HashMapper<u64, 64>>;
+pub type BinHashMappers = BinHashKindMapping<bin::BinHashMapper, HashMapper<u64, 64>>;
 
 impl BinHashMappers {
+    #[cfg(feature = "fs")]
     /// Create mapper, load all sub-mappers from a directory path
     pub fn from_dirpath(path: &Path) -> Result<Self, HashError> {
         let mut this = Self::default();
@@ -160,6 +256,7 @@ impl BinHashMappers {
         Ok(this)
     }
 
+    #[cfg(feature = "fs")]
     /// Load all sub-mappers from a directory path
     pub fn load_dirpath(&mut self, path: &Path) -> Result<(), HashError> {
         self.entry_path.load_path(path.join(HashKind::BinEntryPath.mapping_path()))?;
@@ -170,15 +267,161 @@ impl BinHashMappers {
         Ok(())
     }
 
+    #[cfg(feature = "fs")]
+    /// Reload sub-mappers whose file has been modified since they were loaded
+    ///
+    /// See [HashMapper::reload_path_if_changed()]: a reloaded sub-mapper is replaced by the content
+    /// of its file. Entries added after loading, including entries merged into
+    /// [path_value](Self::path_value) with
+    /// [load_additional_path()](cdragon_hashes::wad::WadHashMapper::load_additional_path()), are
+    /// lost and must be merged again by the caller. Sub-mappers whose file is unchanged are kept
+    /// as-is.
+    ///
+    /// Return `true` if at least one sub-mapper has been reloaded.
+    pub fn reload_if_changed(&mut self, path: &Path) -> Result<bool, HashError> {
+        let mut reloaded = false;
+        for kind in BinHashKind::VARIANTS {
+            let hkind = HashKind::from(kind);
+            reloaded |= self.get_mut(kind).reload_path_if_changed(path.join(hkind.mapping_path()))?;
+        }
+        reloaded |= self.path_value.reload_path_if_changed(path.join(HashKind::WadGame.mapping_path()))?;
+        Ok(reloaded)
+    }
+
+    #[cfg(feature = "fs")]
     /// Write all sub-mappers to a directory path
     pub fn write_dirpath(&self, path: &Path) -> Result<(), HashError> {
-        self.entry_path.write_path(path.join(HashKind::BinEntryPath.mapping_path()))?;
-        self.class_name.write_path(path.join(HashKind::BinClassName.mapping_path()))?;
-        self.field_name.write_path(path.join(HashKind::BinFieldName.mapping_path()))?;
-        self.hash_value.write_path(path.join(HashKind::BinHashValue.mapping_path()))?;
+        self.write_dirpath_selective(path, &BinHashKind::VARIANTS)?;
         self.path_value.write_path(path.join(HashKind::WadGame.mapping_path()))?;
         Ok(())
     }
+
+    #[cfg(feature = "fs")]
+    /// Write only the given sub-mappers to a directory path
+    ///
+    /// Use it to avoid rewriting large unchanged files. Path values are not written.
+    ///
+    /// ```
+    /// # use cdragon_prop::prelude::*;
+    /// let tmp = tempfile::tempdir().unwrap();
+    /// let dir = tmp.path();
+    /// let mut hmappers = BinHashMappers::default();
+    /// hmappers.entry_path.insert(0x1234, "Characters/Foo".into());
+    /// hmappers.write_dirpath_selective(dir, &[BinHashKind::EntryPath]).unwrap();
+    ///
+    /// let content = std::fs::read_to_string(dir.join("hashes.binentries.txt")).unwrap();
+    /// assert_eq!(content, "00001234 Characters/Foo\n");
+    /// assert!(!dir.join("hashes.binfields.txt").exists());
+    /// ```
+    pub fn write_dirpath_selective(&self, path: &Path, kinds: &[BinHashKind]) -> Result<(), HashError> {
+        for &kind in kinds {
+            self.get(kind).write_path(path.join(HashKind::from(kind).mapping_path()))?;
+        }
+        Ok(())
+    }
+
+    /// Tags used for each kind of sub-mapper in combined files
+    const COMBINED_TAGS: [(&'static str, Option<BinHashKind>); 5] = [
+        ("E", Some(BinHashKind::EntryPath)),
+        ("T", Some(BinHashKind::ClassName)),
+        ("F", Some(BinHashKind::FieldName)),
+        ("H", Some(BinHashKind::HashValue)),
+        ("P", None),  // path values
+    ];
+
+    #[cfg(feature = "fs")]
+    /// Create mapper, load all sub-mappers from a combined file
+    pub fn from_combined_path(path: &Path) -> Result<Self, HashError> {
+        let mut this = Self::default();
+        this.load_combined_path(path)?;
+        Ok(this)
+    }
+
+    #[cfg(feature = "fs")]
+    /// Load all sub-mappers from a single combined file
+    ///
+    /// See [Self::load_combined_reader()] for the format.
+    /// Per-kind files loaded by [Self::load_dirpath()] remain the default.
+    ///
+    /// ```
+    /// # use cdragon_prop::BinHashMappers;
+    /// let tmp = tempfile::tempdir().unwrap();
+    /// let path = tmp.path().join("hashes.bin.txt");
+    /// let mut hmappers = BinHashMappers::default();
+    /// hmappers.entry_path.insert(0xdeadbeef, "Characters/Foo".into());
+    /// hmappers.class_name.insert(0x1234, "SomeClass".into());
+    /// hmappers.field_name.insert(0x5678, "mName".into());
+    /// hmappers.path_value.insert(0x0123456789abcdef, "data/foo.bin".into());
+    /// hmappers.write_combined_path(&path).unwrap();
+    /// let content = std::fs::read_to_string(&path).unwrap();
+    /// assert_eq!(content, "\
+    ///     E deadbeef Characters/Foo\n\
+    ///     T 00001234 SomeClass\n\
+    ///     F 00005678 mName\n\
+    ///     P 0123456789abcdef data/foo.bin\n");
+    ///
+    /// let hmappers = BinHashMappers::from_combined_path(&path).unwrap();
+    /// assert_eq!(hmappers.entry_path.get(0xdeadbeef), Some("Characters/Foo"));
+    /// assert_eq!(hmappers.class_name.get(0x1234), Some("SomeClass"));
+    /// assert_eq!(hmappers.field_name.get(0x5678), Some("mName"));
+    /// assert!(hmappers.hash_value.is_empty());
+    /// assert_eq!(hmappers.path_value.get(0x0123456789abcdef), Some("data/foo.bin"));
+    ///
+    /// // Unknown tags are rejected
+    /// std::fs::write(&path, "X 00001234 SomeClass\n").unwrap();
+    /// assert!(BinHashMappers::from_combined_path(&path).is_err());
+    /// ```
+    pub fn load_combined_path(&mut self, path: &Path) -> Result<(), HashError> {
+        let file = fs::File::open(path)?;
+        self.load_combined_reader(io::BufReader::new(file))
+            .map_err(|e| e.with_mapping_path(path))
+    }
+
+    /// Load all sub-mappers from a reader on combined data
+    ///
+    /// Each line is prefixed by a tag for the kind of hash, followed by a space:
+    /// `E` for entry paths, `T` for class names, `F` for field names, `H` for hash values and `P`
+    /// for path values. The rest of the line uses the format of regular mapping files.
+    pub fn load_combined_reader<R: io::BufRead>(&mut self, reader: R) -> Result<(), HashError> {
+        for line in reader.lines() {
+            let line = line?;
+            let Some((tag, value)) = line.split_once(' ') else {
+                return Err(HashError::InvalidHashLine(line));
+            };
+            match Self::COMBINED_TAGS.iter().find(|(t, _)| *t == tag) {
+                Some((_, Some(kind))) => self.get_mut(*kind).load_line(value)?,
+                Some((_, None)) => self.path_value.load_line(value)?,
+                None => return Err(HashError::InvalidHashLine(line)),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    /// Write all sub-mappers to a single combined file
+    ///
+    /// See [Self::load_combined_reader()] for the format.
+    /// The file is updated atomically.
+    pub fn write_combined_path(&self, path: &Path) -> Result<(), HashError> {
+        GuardedFile::for_scope(path, |file| {
+            self.write_combined(&mut io::BufWriter::new(file))
+        })?;
+        Ok(())
+    }
+
+    /// Write all sub-mappers to a writer, as combined data
+    ///
+    /// See [Self::load_combined_reader()] for the format.
+    pub fn write_combined<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (tag, kind) in Self::COMBINED_TAGS {
+            let prefix = format!("{} ", tag);
+            match kind {
+                Some(kind) => self.get(kind).write_prefixed(writer, &prefix)?,
+                None => self.path_value.write_prefixed(writer, &prefix)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 /// PROP file, with entries
@@ -204,21 +447,147 @@ pub struct PropFile {
 
 impl PropFile {
     /// Parse a whole `PropFile` from data
+    ///
+    /// On error, entries are scanned to report which one is invalid, if possible.
+    ///
+    /// ```
+    /// # use cdragon_prop::{BinEntryPath, PropError, PropFile};
+    /// # use cdragon_prop::testing::raw_bin_file_data;
+    /// // PROP file with two entries, without fields; the second one is truncated
+    /// let mut data = raw_bin_file_data(&[(0x10, 0x11, &[]), (0x20, 0x22, &[])]);
+    /// data.pop();
+    /// let err = PropFile::from_slice(&data).unwrap_err();
+    /// assert!(matches!(err, PropError::TruncatedEntry {
+    ///     index: 1, path: Some(BinEntryPath { hash: 0x22 }), expected: 2, available: 1,
+    /// }));
+    /// assert_eq!(err.to_string(), "entry #1 (00000022) truncated: expected 2 bytes, 1 available");
+    ///
+    /// // Same error when scanning
+    /// let mut entries = PropFile::scan_entries_from_reader(&data[..]).unwrap().parse();
+    /// assert_eq!(entries.next().unwrap().unwrap().path, BinEntryPath { hash: 0x11 });
+    /// assert!(matches!(entries.next().unwrap(), Err(PropError::TruncatedEntry { index: 1, .. })));
+    ///
+    /// // Truncated in the entry header
+    /// let err = PropFile::from_slice(&data[..data.len() - 7]).unwrap_err();
+    /// assert!(matches!(err, PropError::TruncatedEntry { index: 1, path: None, expected: 8, available: 2 }));
+    /// ```
     pub fn from_slice(data: &[u8]) -> Result<PropFile> {
-        Ok(parser::binparse(data)?)
+        Self::from_slice_with(data, Strictness::Lenient)
     }
 
+    /// Parse a whole `PropFile` from data, with the given strictness
+    ///
+    /// [Self::from_slice()] is lenient: invalid UTF-8 sequences in strings are replaced by
+    /// `U+FFFD`. In strict mode, they are reported as invalid entries.
+    ///
+    /// ```
+    /// # use cdragon_prop::{PropError, PropFile, Strictness, data::*};
+    /// # use cdragon_prop::testing::raw_bin_file_data;
+    /// // Entry with a string field, not valid UTF-8
+    /// let data = raw_bin_file_data(&[(0x10, 0x11, &[(0x42, BinType::String as u8, &[3, 0, b'a', 0xff, b'b'])])]);
+    ///
+    /// let prop = PropFile::from_slice(&data).unwrap();
+    /// assert_eq!(prop.entries[0].getv::<BinString>(0x42.into()), Some(&BinString("a\u{fffd}b".into())));
+    ///
+    /// let err = PropFile::from_slice_with(&data, Strictness::Strict).unwrap_err();
+    /// assert!(matches!(err, PropError::InvalidEntry { index: 0, .. }));
+    /// ```
+    pub fn from_slice_with(data: &[u8], strictness: Strictness) -> Result<PropFile> {
+        parser::binparse(data, strictness).map_err(|err| {
+            BinEntryScanner::with_strictness(data, strictness).ok()
+                .and_then(|scanner| scanner.parse().find_map(|entry| entry.err()))
+                .unwrap_or(err.into())
+        })
+    }
+
+    #[cfg(feature = "fs")]
     /// Parse a whole `PropFile` from data
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<PropFile> {
         Self::from_slice(&fs::read(path.as_ref())?)
     }
 
+    #[cfg(feature = "fs")]
+    /// Load and parse linked files
+    ///
+    /// Linked paths are resolved relative to `base_dir`, which is usually the root directory of
+    /// extracted game files. If a path does not exist as-is, its lowercased version is tried,
+    /// to match files extracted by CDragon.
+    ///
+    /// If `skip_missing` is `true`, linked files that are not found are skipped.
+    /// Otherwise, a [PropError::LinkedFileNotFound] error is returned.
+    /// Other errors (I/O or parsing errors) are always returned.
+    ///
+    /// Linked files are returned in the order of [Self::linked_files].
+    /// Their own linked files are not loaded.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use cdragon_prop::PropFile;
+    /// let root = Path::new("extracted/game");
+    /// let prop = PropFile::from_path(root.join("data/characters/ahri/ahri.bin")).unwrap();
+    /// for linked in prop.load_linked(root, true).unwrap() {
+    ///     println!("{} linked entries", linked.entries.len());
+    /// }
+    /// ```
+    pub fn load_linked(&self, base_dir: &Path, skip_missing: bool) -> Result<Vec<PropFile>> {
+        let mut files = Vec::with_capacity(self.linked_files.len());
+        for linked in &self.linked_files {
+            match resolve_linked_path(base_dir, linked) {
+                Some(path) => files.push(Self::from_path(path)?),
+                None if skip_missing => {}
+                None => return Err(PropError::LinkedFileNotFound(linked.clone())),
+            }
+        }
+        Ok(files)
+    }
+
+    /// Iterate on entries of a given type
+    ///
+    /// Entries are scanned on each call. Use [Self::index_by_type()] to query types repeatedly.
+    pub fn entries_of_type(&self, ctype: BinClassName) -> impl Iterator<Item=&BinEntry> {
+        self.entries.iter().filter(move |e| e.ctype == ctype)
+    }
+
+    /// Build an index of entries, by type
+    ///
+    /// Entries are listed in file order.
+    ///
+    /// # Example
+    /// ```
+    /// # use cdragon_prop::{BinEntry, PropFile};
+    /// let prop = PropFile {
+    ///     version: 3,
+    ///     is_patch: false,
+    ///     linked_files: vec![],
+    ///     entries: vec![
+    ///         BinEntry::new(1.into(), 0x10.into(), vec![]),
+    ///         BinEntry::new(2.into(), 0x20.into(), vec![]),
+    ///         BinEntry::new(3.into(), 0x10.into(), vec![]),
+    ///     ],
+    /// };
+    /// let index = prop.index_by_type();
+    /// let paths: Vec<u32> = index[&0x10.into()].iter().map(|e| e.path.hash).collect();
+    /// assert_eq!(paths, [1, 3]);
+    /// assert_eq!(index[&0x20.into()].len(), 1);
+    /// assert!(!index.contains_key(&0x30.into()));
+    /// assert_eq!(prop.entries_of_type(0x10.into()).count(), 2);
+    /// ```
+    pub fn index_by_type(&self) -> HashMap<BinClassName, Vec<&BinEntry>> {
+        let mut index: HashMap<_, Vec<_>> = HashMap::new();
+        for entry in &self.entries {
+            index.entry(entry.ctype).or_default().push(entry);
+        }
+        index
+    }
+
     /// Iterate on entry headers (path and type) from a PROP reader
     pub fn scan_entries_from_reader<R: io::Read>(reader: R) -> Result<BinEntryScanner<R>> {
         let scanner = BinEntryScanner::new(reader)?;
         Ok(scanner)
     }
 
+    #[cfg(feature = "fs")]
     /// Iterate on entry headers (path and type) from a PROP file path
     pub fn scan_entries_from_path<P: AsRef<Path>>(path: P) -> Result<BinEntryScanner<io::BufReader<fs::File>>> {
         let file = fs::File::open(path)?;
@@ -232,7 +601,7 @@ impl PropFile {
 pub type BinEntryHeader = (BinEntryPath, BinClassName);
 
 /// Entry in a PROP file
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BinEntry {
     /// Entry path (hashed)
     pub path: BinEntryPath,
@@ -243,6 +612,11 @@ pub struct BinEntry {
 }
 
 impl BinEntry {
+    /// Create a new entry from its path, class type and fields
+    pub fn new(path: BinEntryPath, ctype: BinClassName, fields: Vec<BinField>) -> Self {
+        Self { path, ctype, fields }
+    }
+
     /// Get a field by its name
     pub fn get(&self, name: BinFieldName) -> Option<&BinField> {
         self.fields.iter().find(|f| f.name == name)
@@ -254,6 +628,20 @@ impl BinEntry {
     }
 }
 
+/// Resolve the path of a linked file, return `None` if not found
+#[cfg(feature = "fs")]
+fn resolve_linked_path(base_dir: &Path, linked: &str) -> Option<PathBuf> {
+    let path = base_dir.join(linked);
+    if path.is_file() {
+        return Some(path);
+    }
+    let path = base_dir.join(linked.to_lowercase());
+    if path.is_file() {
+        return Some(path);
+    }
+    None
+}
+
 /// Files known to not be PROP files, despite their extension
 pub const NON_PROP_BASENAMES: &[&str]  = &[
     "atlas_info.bin",
@@ -294,5 +682,98 @@ pub enum PropError {
     Io(#[from] std::io::Error),
     #[error("parsing error")]
     Parsing(#[from] ParseError),
+    #[error("linked file not found: {0}")]
+    LinkedFileNotFound(String),
+    #[error("entry #{index}{} truncated: expected {expected} bytes, {available} available", fmt_entry_path(.path))]
+    TruncatedEntry {
+        index: usize,
+        path: Option<BinEntryPath>,
+        expected: u64,
+        available: u64,
+    },
+    #[error("invalid entry #{index}{}", fmt_entry_path(.path))]
+    InvalidEntry {
+        index: usize,
+        path: Option<BinEntryPath>,
+        #[source]
+        source: ParseError,
+    },
 }
 
+/// Format an optional entry path, for error messages
+fn fmt_entry_path(path: &Option<BinEntryPath>) -> String {
+    path.map(|p| format!(" ({:x})", p)).unwrap_or_default()
+}
+
+
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::*;
+
+    /// Write a PROP file with a single empty entry
+    fn write_prop(path: &Path, entry_path: u32) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, testing::raw_bin_file_data(&[(0x10, entry_path, &[])])).unwrap();
+    }
+
+    #[test]
+    fn load_linked_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write_prop(&root.join("data/found.bin"), 1);
+        write_prop(&root.join("data/lower/lower.bin"), 2);
+
+        let linked_paths = |entries: &[PropFile]| -> Vec<u32> {
+            entries.iter().map(|p| p.entries[0].path.hash).collect()
+        };
+        let mut prop = PropFile {
+            version: 3,
+            is_patch: false,
+            linked_files: vec!["data/found.bin".into(), "DATA/Lower/Lower.bin".into()],
+            entries: vec![],
+        };
+        assert_eq!(linked_paths(&prop.load_linked(root, false).unwrap()), [1, 2]);
+        assert_eq!(linked_paths(&prop.load_linked(root, true).unwrap()), [1, 2]);
+
+        prop.linked_files.insert(1, "data/missing.bin".into());
+        assert_eq!(linked_paths(&prop.load_linked(root, true).unwrap()), [1, 2]);
+        let err = prop.load_linked(root, false).unwrap_err();
+        assert!(matches!(err, PropError::LinkedFileNotFound(path) if path == "data/missing.bin"));
+    }
+
+    #[test]
+    fn reload_changed_hash_mappers() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let entries_path = dir.join(HashKind::BinEntryPath.mapping_path());
+        fs::write(&entries_path, "00000001 one\n").unwrap();
+        fs::write(dir.join(HashKind::BinClassName.mapping_path()), "00000010 Class\n").unwrap();
+        fs::write(dir.join(HashKind::BinFieldName.mapping_path()), "").unwrap();
+        fs::write(dir.join(HashKind::BinHashValue.mapping_path()), "").unwrap();
+        fs::write(dir.join(HashKind::WadGame.mapping_path()), "").unwrap();
+
+        let mut hmappers = BinHashMappers::from_dirpath(dir).unwrap();
+        assert!(!hmappers.reload_if_changed(dir).unwrap());
+
+        // Entries not in the files, kept only if their mapper is not reloaded
+        hmappers.entry_path.insert(0x2, "two".into());
+        hmappers.class_name.insert(0x20, "Other".into());
+        hmappers.path_value.insert(0x3, "three".into());
+
+        fs::write(&entries_path, "00000001 uno\n00000004 four\n").unwrap();
+        // Make sure the modification time changes
+        let file = fs::File::options().write(true).open(&entries_path).unwrap();
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(10)).unwrap();
+
+        assert!(hmappers.reload_if_changed(dir).unwrap());
+        assert_eq!(hmappers.entry_path.get(0x1), Some("uno"));
+        assert_eq!(hmappers.entry_path.get(0x4), Some("four"));
+        assert_eq!(hmappers.entry_path.get(0x2), None);
+        assert_eq!(hmappers.class_name.get(0x10), Some("Class"));
+        assert_eq!(hmappers.class_name.get(0x20), Some("Other"));
+        assert_eq!(hmappers.path_value.get(0x3), Some("three"));
+
+        assert!(!hmappers.reload_if_changed(dir).unwrap());
+    }
+}
@@ -62,7 +62,7 @@ macro_rules! binvalue_map_keytype {
 /// # Examples
 ///
 /// ```no_run
-/// # use cdragon_prop::{binget, data::*, BinEntry};
+/// # use cdragon_prop::prelude::*;
 /// # fn test(entry: BinEntry, map: BinMap) {
 /// // Get an entry field value
 /// binget!(entry => mName(BinString));
@@ -85,7 +85,7 @@ macro_rules! binget {
     // `.`: intended to be used to chain field access, but actually ignored
     ($e:expr, . $($tail:tt)*) => { binget!($e, $($tail)*) };
     // `fieldName(Type)`: access field from struct-like
-    ($e:expr, $f:ident($t:ty) $($tail:tt)*) => { binget!($e.getv::<$t>(cdragon_hashes::binh!(stringify!($f)))?, $($tail)*) };
+    ($e:expr, $f:ident($t:ty) $($tail:tt)*) => { binget!($e.getv::<$t>($crate::binh!(stringify!($f)))?, $($tail)*) };
     ($e:expr, $x:literal($t:ty) $($tail:tt)*) => { binget!($e.getv::<$t>($x.into())?, $($tail)*) };
     // `(Type)`: downcast
     ($e:expr, ($t:ty) $($tail:tt)*) => { binget!($e.downcast::<$t>()?, $($tail)*) };
@@ -1,13 +1,14 @@
 use std::any::Any;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
 use nom::{
     number::complete::{le_u8, le_i8, le_u16, le_i16, le_u32, le_i32, le_u64, le_i64, le_f32},
     bytes::complete::{tag, take},
-    combinator::{map, flat_map, opt},
+    combinator::{map, flat_map, opt, verify},
     sequence::{pair, tuple},
     multi::count,
 };
 use super::{
+    PropError,
     PropFile,
     BinEntry,
     BinEntryHeader,
@@ -21,16 +22,31 @@ use cdragon_utils::{
     parse_buf,
 };
 
-type Result<T> = std::result::Result<T, ParseError>;
+type Result<T, E = ParseError> = std::result::Result<T, E>;
 
 
+/// How to handle invalid data that can still be parsed
+///
+/// Strings are expected to be valid UTF-8. In lenient mode, invalid sequences are replaced by
+/// `U+FFFD`. In strict mode, parsing fails.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Replace invalid UTF-8 sequences
+    #[default]
+    Lenient,
+    /// Fail on invalid UTF-8 strings
+    Strict,
+}
+
 /// Trait satisfied by values that can be parsed from binary data
+///
+/// `strictness` is used to parse strings, and passed down to nested values.
 pub(super) trait BinParsable where Self: Sized {
-    fn binparse(i: &[u8]) -> IResult<&[u8], Self>;
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self>;
 }
 
-pub(super) fn binparse<T: BinParsable>(i: &[u8]) -> Result<T> {
-    match T::binparse(i) {
+pub(super) fn binparse<T: BinParsable>(i: &[u8], strictness: Strictness) -> Result<T> {
+    match T::binparse(i, strictness) {
         Ok((i, v)) => {
             if !i.is_empty() {
                 Err(ParseError::TooMuchData)
@@ -61,7 +77,7 @@ where
 macro_rules! impl_binparsable {
     ($type:ty, $expr:expr) => {
         impl BinParsable for $type {
-            fn binparse(i: &[u8]) -> IResult<&[u8], Self> { $expr(i) }
+            fn binparse(i: &[u8], _: Strictness) -> IResult<&[u8], Self> { $expr(i) }
         }
     };
     ($type:ty, =$parser:expr) => {
@@ -73,13 +89,12 @@ macro_rules! impl_binparsable {
 }
 
 impl BinParsable for PropFile {
-    fn binparse(i: &[u8]) -> IResult<&[u8], Self> {
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self> {
         // Parse header
         let (i, opt_ptch) = opt(tag("PTCH"))(i)?;
         let (i, is_patch) = match opt_ptch {
             Some(_) => {
-                let (i, header) = tuple((le_u32, le_u32))(i)?;
-                assert_eq!(header, (1, 0));
+                let (i, _) = verify(tuple((le_u32, le_u32)), |header| *header == (1, 0))(i)?;
                 (i, true)
             }
             None => (i, false)
@@ -88,18 +103,18 @@ impl BinParsable for PropFile {
         let (i, (_, version)) = tuple((tag("PROP"), le_u32))(i)?;
         let (i, linked_files) =
             if version >= 2 {
-                length_count(le_u32, parse_binstring)(i)?
+                length_count(le_u32, |i| parse_binstring(i, strictness))(i)?
             } else {
                 (i, vec![])
             };
 
-        let (i, entry_types) = length_count(le_u32, BinClassName::binparse)(i)?;
+        let (i, entry_types) = length_count(le_u32, parse_hash::<BinClassName>)(i)?;
         // Parse entries
         let (i, entries) = {
             let (mut i, mut entries) = (i, Vec::<BinEntry>::with_capacity(entry_types.len()));
             for ctype in entry_types {
                 i = {
-                    let (i, entry) = parse_entry_from_type(i, ctype)?;
+                    let (i, entry) = parse_entry_from_type(i, ctype, strictness)?;
                     entries.push(entry);
                     i
                 }
@@ -116,7 +131,11 @@ impl BinParsable for PropFile {
 #[derive(Debug)]
 pub struct BinEntryScanner<R: Read> {
     reader: R,
-    htypes_iter: std::vec::IntoIter<BinClassName>,
+    htypes_iter: EntryTypesIter,
+    linked_files: Vec<String>,
+    /// Offset of the first entry in the file
+    offset: u64,
+    strictness: Strictness,
     /// `true` if scanning a patch
     ///
     /// See [PropFile::is_patch] for details.
@@ -125,7 +144,28 @@ pub struct BinEntryScanner<R: Read> {
 
 impl<R: Read> BinEntryScanner<R> {
     /// Create a scanner, parse the headers
-    pub fn new(mut reader: R) -> Result<Self> {
+    ///
+    /// Invalid UTF-8 strings are accepted, see [Strictness].
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_strictness(reader, Strictness::Lenient)
+    }
+
+    /// Create a scanner with the given strictness, parse the headers
+    ///
+    /// ```
+    /// # use cdragon_prop::{BinEntryScanner, PropError, Strictness, data::*};
+    /// # use cdragon_prop::testing::raw_bin_file_data;
+    /// // Entry with a string field, not valid UTF-8
+    /// let data = raw_bin_file_data(&[(0x10, 0x11, &[(0x42, BinType::String as u8, &[3, 0, b'a', 0xff, b'b'])])]);
+    ///
+    /// let mut entries = BinEntryScanner::new(&data[..]).unwrap().parse();
+    /// let entry = entries.next().unwrap().unwrap();
+    /// assert_eq!(entry.getv::<BinString>(0x42.into()), Some(&BinString("a\u{fffd}b".into())));
+    ///
+    /// let mut entries = BinEntryScanner::with_strictness(&data[..], Strictness::Strict).unwrap().parse();
+    /// assert!(matches!(entries.next(), Some(Err(PropError::InvalidEntry { index: 0, .. }))));
+    /// ```
+    pub fn with_strictness(mut reader: R, strictness: Strictness) -> Result<Self> {
         // Parse header
         let (is_patch, version): (bool, u32) = {
             let mut buf = [0u8; 4 + 4 + 4];  // maximum size needed
@@ -134,7 +174,9 @@ impl<R: Read> BinEntryScanner<R> {
                 Some(_) => {
                     reader.read_exact(&mut buf[8..12])?;
                     let header = parse_buf!(buf[4..12], tuple((le_u32, le_u32)));
-                    assert_eq!(header, (1, 0));
+                    if header != (1, 0) {
+                        return Err(ParseError::Error);
+                    }
                     reader.read_exact(&mut buf[..8])?;
                     true
                 }
@@ -144,16 +186,33 @@ impl<R: Read> BinEntryScanner<R> {
             let (_, version) = parse_buf!(buf[..8], tuple((tag("PROP"), le_u32)));
             (is_patch, version)
         };
+        let mut offset = if is_patch { 4 + 8 + 8 } else { 8 };
 
-        if version >= 2 {
-            // Skip linked files
+        let linked_files = if version >= 2 {
             let buf = reader.read_array::<4>()?;
             let n = parse_buf!(buf, le_u32);
+            // Note: don't trust the count to reserve memory, it is read from the file
+            let mut linked_files = Vec::new();
             for _ in 0..n {
-                let buf = reader.read_array::<2>()?;
+                let buf = reader.read_array::<2>().map_err(eof_as_not_enough_data)?;
                 let n = parse_buf!(buf, le_u16);
-                std::io::copy(&mut reader.by_ref().take(n as u64), &mut std::io::sink())?;
+                let mut buf = Vec::<u8>::with_capacity(n as usize);
+                reader.by_ref().take(n as u64).read_to_end(&mut buf)?;
+                if buf.len() != n as usize {
+                    return Err(ParseError::NotEnoughData);
+                }
+                offset += 2 + n as u64;
+                let path = match String::from_utf8(buf) {
+                    Ok(s) => s,
+                    Err(_) if strictness == Strictness::Strict => return Err(ParseError::Error),
+                    Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                };
+                linked_files.push(path);
             }
+            offset += 4;
+            linked_files
+        } else {
+            vec![]
         };
 
         // Parse entry types
@@ -162,21 +221,128 @@ impl<R: Read> BinEntryScanner<R> {
             let n = parse_buf!(buf, le_u32);
             let mut buf = Vec::<u8>::new();
             reader.by_ref().take(4 * n as u64).read_to_end(&mut buf)?;
-            let entry_types = parse_buf!(buf, count(BinClassName::binparse, n as usize));
+            let entry_types = parse_buf!(buf, count(parse_hash::<BinClassName>, n as usize));
+            offset += 4 + 4 * n as u64;
             entry_types
         };
 
-        Ok(Self { reader, htypes_iter: entry_types.into_iter(), is_patch })
+        Ok(Self { reader, htypes_iter: entry_types.into_iter().enumerate(), linked_files, offset, strictness, is_patch })
+    }
+
+    /// Get paths of the PROP files linked by the scanned file
+    ///
+    /// See [PropFile::linked_files].
+    ///
+    /// ```
+    /// # use cdragon_prop::BinEntryScanner;
+    /// // PROP header with two linked files and no entry
+    /// let data = b"PROP\x03\0\0\0\
+    ///     \x02\0\0\0\
+    ///     \x0a\0data/a.bin\
+    ///     \x0a\0data/b.bin\
+    ///     \0\0\0\0";
+    /// let scanner = BinEntryScanner::new(&data[..]).unwrap();
+    /// assert_eq!(scanner.linked_files(), ["data/a.bin", "data/b.bin"]);
+    /// ```
+    pub fn linked_files(&self) -> &[String] {
+        &self.linked_files
     }
 
     /// Scan entries, allow to parse or skip each entry
     ///
-    /// The result behaves provides `next()` but is not an `Iterator`.
+    /// The result provides `next()` but is not an `Iterator`, use [Self::scan_owned()] for that.
+    /// Each item provides the location of the entry fields in the scanned data.
+    ///
+    /// ```
+    /// # use cdragon_prop::{BinEntryScanner, data::*};
+    /// # use cdragon_prop::testing::raw_bin_file_data;
+    /// // Build a PROP file with 3 entries, each with a single `u8` field
+    /// let data = raw_bin_file_data(&[
+    ///     (10, 0, &[(42, BinType::U8 as u8, &[0])]),
+    ///     (10, 1, &[(42, BinType::U8 as u8, &[1])]),
+    ///     (10, 2, &[(42, BinType::U8 as u8, &[2])]),
+    /// ]);
+    ///
+    /// let mut scan = BinEntryScanner::new(&data[..]).unwrap().scan();
+    /// let mut locations = Vec::new();
+    /// while let Some(item) = scan.next() {
+    ///     let item = item.unwrap();
+    ///     locations.push((item.path, item.offset, item.length));
+    ///     if item.path == BinEntryPath::from(1) {
+    ///         let entry = item.read().unwrap();
+    ///         assert_eq!(entry.getv::<BinU8>(42.into()), Some(&BinU8(1)));
+    ///     }
+    /// }
+    /// // Header is 12 bytes, entry types 3 * 4 bytes, entry header (length and path) 8 bytes
+    /// assert_eq!(locations, [
+    ///     (BinEntryPath::from(0), 12 + 12 + 8, 8),
+    ///     (BinEntryPath::from(1), 12 + 12 + 8 + 8 + 8, 8),
+    ///     (BinEntryPath::from(2), 12 + 12 + 3 * 8 + 2 * 8, 8),
+    /// ]);
+    /// let (_, offset, length) = locations[2];
+    /// assert_eq!(data[(offset + length as u64 - 1) as usize], 2);
+    /// ```
     pub fn scan(self) -> BinEntryScanScan<R> {
         BinEntryScanScan {
             reader: self.reader,
             htypes_iter: self.htypes_iter,
-            length: None,
+            current: None,
+            offset: self.offset,
+            strictness: self.strictness,
+        }
+    }
+
+    /// Scan entries, iterate on owned entry locations
+    ///
+    /// Unlike [Self::scan()], the result is an `Iterator` and can be used with adapters.
+    /// Entry fields are skipped. They can be parsed later, from a seekable reader on the same
+    /// data, using [ScannedEntry::read_from()]. The scanner's reader can be retrieved with
+    /// [BinEntryScanOwned::into_reader()] for this purpose.
+    ///
+    /// ```
+    /// # use std::io::Cursor;
+    /// # use cdragon_prop::{BinEntryScanner, ScannedEntry, data::*};
+    /// # use cdragon_prop::testing::raw_bin_file_data;
+    /// // Build a PROP file with 4 entries, each with a single `u8` field
+    /// let data = raw_bin_file_data(&[
+    ///     (10, 0, &[(42, BinType::U8 as u8, &[0])]),
+    ///     (11, 1, &[(42, BinType::U8 as u8, &[1])]),
+    ///     (10, 2, &[(42, BinType::U8 as u8, &[2])]),
+    ///     (11, 3, &[(42, BinType::U8 as u8, &[3])]),
+    /// ]);
+    ///
+    /// let mut scan = BinEntryScanner::new(Cursor::new(data.clone())).unwrap().scan_owned();
+    /// let scanned: Vec<ScannedEntry> = scan
+    ///     .by_ref()
+    ///     .map(Result::unwrap)
+    ///     .filter(|e| e.ctype == BinClassName::from(11))
+    ///     .take(1)
+    ///     .collect();
+    /// assert_eq!(scanned.len(), 1);
+    /// assert_eq!(scanned[0].path, BinEntryPath::from(1));
+    /// assert_eq!(scanned[0].length, 2 + 4 + 1 + 1);
+    ///
+    /// // Parse the entry fields from the scanned data
+    /// let mut reader = scan.into_reader();
+    /// let entry = scanned[0].read_from(&mut reader).unwrap();
+    /// assert_eq!(entry.path, BinEntryPath::from(1));
+    /// assert_eq!(entry.getv::<BinU8>(42.into()), Some(&BinU8(1)));
+    ///
+    /// // Entries are independent from the scanner, which does not need to seek
+    /// let scanned: Vec<_> = BinEntryScanner::new(&data[..]).unwrap()
+    ///     .scan_owned()
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    /// assert_eq!(scanned.len(), 4);
+    /// let entry = scanned[2].read_from(&mut Cursor::new(&data)).unwrap();
+    /// assert_eq!(entry.getv::<BinU8>(42.into()), Some(&BinU8(2)));
+    /// ```
+    pub fn scan_owned(self) -> BinEntryScanOwned<R> {
+        BinEntryScanOwned {
+            reader: self.reader,
+            htypes_iter: self.htypes_iter,
+            offset: self.offset,
+            strictness: self.strictness,
         }
     }
 
@@ -195,78 +361,109 @@ impl<R: Read> BinEntryScanner<R> {
             reader: self.reader,
             htypes_iter: self.htypes_iter,
             filter: f,
+            strictness: self.strictness,
         }
     }
 
     /// Parse entries, iterate on them
+    ///
+    /// An invalid entry (for instance, using an unknown type) is returned as an error, then
+    /// scanning continues with the next entry. This allows to skip or report invalid entries.
+    ///
+    /// ```
+    /// # use cdragon_prop::{BinEntryPath, BinEntryScanner, PropError, PropFile};
+    /// # use cdragon_prop::testing::raw_bin_file_data;
+    /// // Two entries with a single field each, the first one with an unknown type (0x7f)
+    /// let data = raw_bin_file_data(&[
+    ///     (0x10, 0x11, &[(0x42, 0x7f, &[0x2a])]),
+    ///     (0x20, 0x22, &[(0x42, 3, &[0x2a])]),
+    /// ]);
+    ///
+    /// let mut entries = BinEntryScanner::new(&data[..]).unwrap().parse();
+    /// assert!(matches!(entries.next(), Some(Err(PropError::InvalidEntry { index: 0, .. }))));
+    /// assert_eq!(entries.next().unwrap().unwrap().path, BinEntryPath::from(0x22));
+    /// assert!(entries.next().is_none());
+    ///
+    /// // Parsing the whole file fails, without panicking
+    /// assert!(matches!(PropFile::from_slice(&data), Err(PropError::InvalidEntry { index: 0, .. })));
+    /// ```
     pub fn parse(self) -> BinEntryScanParse<R> {
         BinEntryScanParse {
             reader: self.reader,
             htypes_iter: self.htypes_iter,
+            strictness: self.strictness,
         }
     }
 }
 
 // Note: A trait alias would be better, but they are not available
 /// Item type for entry scanning
-pub type BinEntryScannerItem = Result<BinEntry>;
+pub type BinEntryScannerItem = Result<BinEntry, PropError>;
+
+/// Iterator on entry types, with entry index
+type EntryTypesIter = std::iter::Enumerate<std::vec::IntoIter<BinClassName>>;
 
 
 /// Common methods for BinEntryScanner iterators
+///
+/// Errors provide the index of the entry being parsed, and its path if it has been read.
 trait BinEntryScan {
     type Reader: Read;
     type Output;
 
     /// Read the next entry header, return the remaining length and the path
-    fn next_scan(reader: &mut Self::Reader) -> Result<(u32, BinEntryPath)> {
-        let buf = reader.read_array::<{4 + 4}>()?;
-        let (length, path) = parse_buf!(buf, tuple((le_u32, BinEntryPath::binparse)));
-        Ok((length - 4, path))  // path has been read, deduct it from length
+    fn next_scan(reader: &mut Self::Reader, index: usize) -> Result<(u32, BinEntryPath), PropError> {
+        let buf = read_entry_data(reader, index, None, 4 + 4)?;
+        let (length, path) = parse_buf!(buf, tuple((le_u32, parse_hash::<BinEntryPath>)));
+        // Path has been read, deduct it from length
+        let length = length.checked_sub(4)
+            .ok_or(PropError::InvalidEntry { index, path: Some(path), source: ParseError::Error })?;
+        Ok((length, path))
     }
 
     /// Read entry fields
-    fn read_fields(reader: &mut Self::Reader, length: u32) -> Result<Vec<BinField>> {
-        let mut buf = Vec::<u8>::new();
-        reader.by_ref().take(length as u64).read_to_end(&mut buf)?;
-        let fields = parse_buf!(buf, length_count(le_u16, BinField::binparse));
-        Ok(fields)
+    fn read_fields(reader: &mut Self::Reader, index: usize, path: BinEntryPath, length: u32, strictness: Strictness) -> Result<Vec<BinField>, PropError> {
+        read_entry_fields(reader, index, path, length, strictness)
     }
 
     /// Skip entry fields
-    fn skip_fields(reader: &mut Self::Reader, length: u32) -> Result<()> {
+    fn skip_fields(reader: &mut Self::Reader, index: usize, path: BinEntryPath, length: u32) -> Result<(), PropError> {
         // There is no seek-like method implemented on &[u8]
         //reader.seek(SeekFrom::Current(length as i64))?;
-        std::io::copy(&mut reader.by_ref().take(length as u64), &mut std::io::sink())?;
+        let skipped = std::io::copy(&mut reader.by_ref().take(length as u64), &mut std::io::sink())?;
+        if skipped != length as u64 {
+            return Err(PropError::TruncatedEntry { index, path: Some(path), expected: length as u64, available: skipped });
+        }
         Ok(())
     }
 
-    fn next_result(&mut self, ctype: BinClassName) -> Result<Self::Output>;
+    fn next_result(&mut self, index: usize, ctype: BinClassName) -> Result<Self::Output, PropError>;
 }
 
 
 pub struct BinEntryScanHeaders<R>
 where R: Read {
     reader: R,
-    htypes_iter: std::vec::IntoIter<BinClassName>,
+    htypes_iter: EntryTypesIter,
 }
 
 impl<R: Read> BinEntryScan for BinEntryScanHeaders<R> {
     type Reader = R;
     type Output = (BinEntryPath, BinClassName);
 
-    fn next_result(&mut self, ctype: BinClassName) -> Result<Self::Output> {
-        let (length, path) = Self::next_scan(&mut self.reader)?;
-        Self::skip_fields(&mut self.reader, length)?;
+    fn next_result(&mut self, index: usize, ctype: BinClassName) -> Result<Self::Output, PropError> {
+        let (length, path) = Self::next_scan(&mut self.reader, index)?;
+        Self::skip_fields(&mut self.reader, index, path, length)?;
         Ok((path, ctype))
     }
 }
 
 impl<R: Read> Iterator for BinEntryScanHeaders<R> {
-    type Item = Result<(BinEntryPath, BinClassName)>;
+    type Item = Result<(BinEntryPath, BinClassName), PropError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let ctype = self.htypes_iter.next()?;
-        Some(self.next_result(ctype))
+        let (index, ctype) = self.htypes_iter.next()?;
+        Some(self.next_result(index, ctype))
     }
 }
 
@@ -274,8 +471,9 @@ impl<R: Read> Iterator for BinEntryScanHeaders<R> {
 pub struct BinEntryScanFilterParse<R, F>
 where R: Read, F: Fn(BinEntryPath, BinClassName) -> bool {
     reader: R,
-    htypes_iter: std::vec::IntoIter<BinClassName>,
+    htypes_iter: EntryTypesIter,
     filter: F,
+    strictness: Strictness,
 }
 
 impl<R, F> BinEntryScan for BinEntryScanFilterParse<R, F>
@@ -283,13 +481,13 @@ where R: Read, F: Fn(BinEntryPath, BinClassName) -> bool {
     type Reader = R;
     type Output = Option<BinEntry>;
 
-    fn next_result(&mut self, ctype: BinClassName) -> Result<Self::Output> {
-        let (length, path) = Self::next_scan(&mut self.reader)?;
+    fn next_result(&mut self, index: usize, ctype: BinClassName) -> Result<Self::Output, PropError> {
+        let (length, path) = Self::next_scan(&mut self.reader, index)?;
         if (self.filter)(path, ctype) {
-            let fields = Self::read_fields(&mut self.reader, length)?;
+            let fields = Self::read_fields(&mut self.reader, index, path, length, self.strictness)?;
             Ok(Some(BinEntry { path, ctype, fields }))
         } else {
-            Self::skip_fields(&mut self.reader, length)?;
+            Self::skip_fields(&mut self.reader, index, path, length)?;
             Ok(None)
         }
     }
@@ -301,8 +499,8 @@ where R: Read, F: Fn(BinEntryPath, BinClassName) -> bool {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let ctype = self.htypes_iter.next()?;
-            match self.next_result(ctype) {
+            let (index, ctype) = self.htypes_iter.next()?;
+            match self.next_result(index, ctype) {
                 Ok(None) => continue,
                 Ok(Some(v)) => return Some(Ok(v)),
                 Err(e) => return Some(Err(e)),
@@ -315,16 +513,17 @@ where R: Read, F: Fn(BinEntryPath, BinClassName) -> bool {
 pub struct BinEntryScanParse<R>
 where R: Read {
     reader: R,
-    htypes_iter: std::vec::IntoIter<BinClassName>,
+    htypes_iter: EntryTypesIter,
+    strictness: Strictness,
 }
 
 impl<R: Read> BinEntryScan for BinEntryScanParse<R> {
     type Reader = R;
     type Output = BinEntry;
 
-    fn next_result(&mut self, ctype: BinClassName) -> Result<Self::Output> {
-        let (length, path) = Self::next_scan(&mut self.reader)?;
-        let fields = Self::read_fields(&mut self.reader, length)?;
+    fn next_result(&mut self, index: usize, ctype: BinClassName) -> Result<Self::Output, PropError> {
+        let (length, path) = Self::next_scan(&mut self.reader, index)?;
+        let fields = Self::read_fields(&mut self.reader, index, path, length, self.strictness)?;
         Ok(BinEntry { path, ctype, fields })
     }
 }
@@ -333,8 +532,8 @@ impl<R: Read> Iterator for BinEntryScanParse<R> {
     type Item = BinEntryScannerItem;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let ctype = self.htypes_iter.next()?;
-        Some(self.next_result(ctype))
+        let (index, ctype) = self.htypes_iter.next()?;
+        Some(self.next_result(index, ctype))
     }
 }
 
@@ -345,21 +544,35 @@ impl<R: Read> Iterator for BinEntryScanParse<R> {
 pub struct BinEntryScanScan<R>
 where R: Read {
     reader: R,
-    length: Option<u32>,
-    htypes_iter: std::vec::IntoIter<BinClassName>,
+    /// Index, path and fields length of the current entry, if not read yet
+    current: Option<(usize, BinEntryPath, u32)>,
+    htypes_iter: EntryTypesIter,
+    /// Offset of the next entry in the scanned data
+    offset: u64,
+    strictness: Strictness,
 }
 
 pub struct BinEntryScanItem<'a, R>
 where R: Read {
     owner: &'a mut BinEntryScanScan<R>,
+    index: usize,
     pub path: BinEntryPath,
     pub ctype: BinClassName,
+    /// Length of entry fields data
+    pub length: u32,
+    /// Offset of entry fields data in the scanned data
+    pub offset: u64,
 }
 
 impl<'a, R> BinEntryScanItem<'a, R>
 where R: Read {
-    pub fn read(self) -> Result<BinEntry> {
-        self.owner.read_entry(self.path, self.ctype)
+    pub fn read(self) -> Result<BinEntry, PropError> {
+        self.owner.read_entry(self.index, self.path, self.ctype)
+    }
+
+    /// Get the entry location, to read it later with [ScannedEntry::read_from()]
+    pub fn scanned(&self) -> ScannedEntry {
+        ScannedEntry { index: self.index, path: self.path, ctype: self.ctype, length: self.length, offset: self.offset, strictness: self.owner.strictness }
     }
 }
 
@@ -369,72 +582,193 @@ where R: Read {
     type Reader = R;
     type Output = (u32, BinEntryPath, BinClassName);
 
-    fn next_result(&mut self, ctype: BinClassName) -> Result<Self::Output> {
-        let (length, path) = Self::next_scan(&mut self.reader)?;
+    fn next_result(&mut self, index: usize, ctype: BinClassName) -> Result<Self::Output, PropError> {
+        let (length, path) = Self::next_scan(&mut self.reader, index)?;
         Ok((length, path, ctype))
     }
 }
 
 impl<R> BinEntryScanScan<R>
 where R: Read {
-    pub fn next(&mut self) -> Option<Result<BinEntryScanItem<'_, R>>> {
+    pub fn next(&mut self) -> Option<Result<BinEntryScanItem<'_, R>, PropError>> {
         // Note: the entry is skipped and thus fails at the next iteration
-        if let Some(length) = self.length.take() {
-            if let Err(err) = Self::skip_fields(&mut self.reader, length) {
+        if let Some((index, path, length)) = self.current.take() {
+            if let Err(err) = Self::skip_fields(&mut self.reader, index, path, length) {
                 return Some(Err(err));
             }
         }
-        let ctype = self.htypes_iter.next()?;
-        match self.next_result(ctype) {
+        let (index, ctype) = self.htypes_iter.next()?;
+        match self.next_result(index, ctype) {
             Ok((length, path, ctype)) => {
-                self.length = Some(length);
-                Some(Ok(BinEntryScanItem { owner: self, path, ctype }))
+                self.current = Some((index, path, length));
+                let offset = self.offset + 4 + 4;
+                self.offset = offset + length as u64;
+                Some(Ok(BinEntryScanItem { owner: self, index, path, ctype, length, offset }))
             }
             Err(err) => Some(Err(err)),
         }
     }
 
-    fn read_entry(&mut self, path: BinEntryPath, ctype: BinClassName) -> Result<BinEntry> {
+    fn read_entry(&mut self, index: usize, path: BinEntryPath, ctype: BinClassName) -> Result<BinEntry, PropError> {
         // Double calls are not possible using public API
-        let length = self.length.take().unwrap();
-        let fields = Self::read_fields(&mut self.reader, length)?;
+        let (_, _, length) = self.current.take().unwrap();
+        let fields = Self::read_fields(&mut self.reader, index, path, length, self.strictness)?;
         Ok(BinEntry { path, ctype, fields })
     }
 }
 
 
 
+/// Entry found by [BinEntryScanner::scan_owned()], or from an item of [BinEntryScanner::scan()]
+///
+/// Fields are not parsed, but can be read with [Self::read_from()].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ScannedEntry {
+    /// Index of the entry in the scanned file
+    pub index: usize,
+    /// Entry path
+    pub path: BinEntryPath,
+    /// Entry type
+    pub ctype: BinClassName,
+    /// Length of entry fields data
+    pub length: u32,
+    /// Offset of entry fields data in the scanned file
+    pub offset: u64,
+    /// Strictness of the scanner, used to parse fields
+    strictness: Strictness,
+}
+
+impl ScannedEntry {
+    /// Read and parse the entry from a seekable reader on the scanned data
+    pub fn read_from<R: Read + Seek>(&self, reader: &mut R) -> Result<BinEntry, PropError> {
+        reader.seek(SeekFrom::Start(self.offset))?;
+        let fields = read_entry_fields(reader, self.index, self.path, self.length, self.strictness)?;
+        Ok(BinEntry { path: self.path, ctype: self.ctype, fields })
+    }
+}
+
+/// Iterator on owned entry locations, see [BinEntryScanner::scan_owned()]
+pub struct BinEntryScanOwned<R>
+where R: Read {
+    reader: R,
+    htypes_iter: EntryTypesIter,
+    offset: u64,
+    strictness: Strictness,
+}
+
+impl<R: Read> BinEntryScanOwned<R> {
+    /// Consume the iterator, return the underlying reader
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> BinEntryScan for BinEntryScanOwned<R> {
+    type Reader = R;
+    type Output = ScannedEntry;
+
+    fn next_result(&mut self, index: usize, ctype: BinClassName) -> Result<Self::Output, PropError> {
+        let (length, path) = Self::next_scan(&mut self.reader, index)?;
+        Self::skip_fields(&mut self.reader, index, path, length)?;
+        let offset = self.offset + 4 + 4;
+        self.offset = offset + length as u64;
+        Ok(ScannedEntry { index, path, ctype, length, offset, strictness: self.strictness })
+    }
+}
+
+impl<R: Read> Iterator for BinEntryScanOwned<R> {
+    type Item = Result<ScannedEntry, PropError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, ctype) = self.htypes_iter.next()?;
+        Some(self.next_result(index, ctype))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.htypes_iter.len()))
+    }
+}
+
+
+/// Read and parse entry fields
+fn read_entry_fields<R: Read>(reader: &mut R, index: usize, path: BinEntryPath, length: u32, strictness: Strictness) -> Result<Vec<BinField>, PropError> {
+    let buf = read_entry_data(reader, index, Some(path), length)?;
+    let result: IResult<_, _> = length_count(le_u16, |i| BinField::binparse(i, strictness))(&buf[..]);
+    match result {
+        Ok((_, fields)) => Ok(fields),
+        Err(e) => Err(PropError::InvalidEntry { index, path: Some(path), source: e.into() }),
+    }
+}
+
+/// Read entry data, report truncated data
+fn read_entry_data<R: Read>(reader: &mut R, index: usize, path: Option<BinEntryPath>, length: u32) -> Result<Vec<u8>, PropError> {
+    let mut buf = Vec::<u8>::new();
+    reader.by_ref().take(length as u64).read_to_end(&mut buf)?;
+    if buf.len() != length as usize {
+        return Err(PropError::TruncatedEntry { index, path, expected: length as u64, available: buf.len() as u64 });
+    }
+    Ok(buf)
+}
+
+
+/// Map an unexpected end of file to [ParseError::NotEnoughData]
+fn eof_as_not_enough_data(e: std::io::Error) -> ParseError {
+    if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        ParseError::NotEnoughData
+    } else {
+        e.into()
+    }
+}
+
+
 /// Parse a single BinEntry, starts at its header
-fn parse_entry_from_type(i: &[u8], ctype: BinClassName) -> IResult<&[u8], BinEntry> {
-    let (i, (_length, path)) = tuple((le_u32, BinEntryPath::binparse))(i)?;
-    parse_entry_from_header(i, (path, ctype))
+fn parse_entry_from_type(i: &[u8], ctype: BinClassName, strictness: Strictness) -> IResult<&[u8], BinEntry> {
+    let (i, (_length, path)) = tuple((le_u32, parse_hash::<BinEntryPath>))(i)?;
+    parse_entry_from_header(i, (path, ctype), strictness)
 }
 
 /// Parse a single BinEntry, starts before its field count
-fn parse_entry_from_header(i: &[u8], (path, ctype): BinEntryHeader) -> IResult<&[u8], BinEntry> {
-    map(length_count(le_u16, BinField::binparse),
+fn parse_entry_from_header(i: &[u8], (path, ctype): BinEntryHeader, strictness: Strictness) -> IResult<&[u8], BinEntry> {
+    map(length_count(le_u16, |i| BinField::binparse(i, strictness)),
         |fields| BinEntry { path, ctype, fields })(i)
 }
 
-fn parse_binstring(i: &[u8]) -> IResult<&[u8], String> {
-    map(flat_map(le_u16, take), |s| std::str::from_utf8(s).expect("invalid UTF-8 string in BIN").to_string())(i)
+/// Parse a string, handle invalid UTF-8 according to strictness
+fn parse_binstring(i: &[u8], strictness: Strictness) -> IResult<&[u8], String> {
+    let (i, s) = flat_map(le_u16, take)(i)?;
+    match std::str::from_utf8(s) {
+        Ok(s) => Ok((i, s.to_string())),
+        Err(_) if strictness == Strictness::Strict => Err(nom::Err::Failure(())),
+        Err(_) => Ok((i, String::from_utf8_lossy(s).into_owned())),
+    }
+}
+
+
+/// Parse a hash, used for names and paths
+fn parse_hash<T: From<u32>>(i: &[u8]) -> IResult<&[u8], T> {
+    map(le_u32, T::from)(i)
+}
+
+/// Parse a value type
+fn parse_bintype(i: &[u8]) -> IResult<&[u8], BinType> {
+    let (i, mut v) = le_u8(i)?;
+    if v >= 0x80 {
+        v = v - 0x80 + BinType::List as u8;
+    }
+    // Unknown types (e.g. added by a new format version) fail, don't panic
+    let vtype = BinType::try_from(v).map_err(|_| nom::Err::Failure(()))?;
+    Ok((i, vtype))
 }
 
 
 impl BinParsable for BinField {
-    fn binparse(i: &[u8]) -> IResult<&[u8], Self> {
-        let (i, (name, vtype)) = tuple((BinFieldName::binparse, BinType::binparse))(i)?;
-        let (i, value) = binvalue_map_type!(vtype, T, map(T::binparse, |v| { Box::new(v) as Box<dyn Any> })(i)?);
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self> {
+        let (i, (name, vtype)) = tuple((parse_hash::<BinFieldName>, parse_bintype))(i)?;
+        let (i, value) = binvalue_map_type!(vtype, T, map(|i| T::binparse(i, strictness), |v| { Box::new(v) as Box<dyn Any + Send + Sync> })(i)?);
         Ok((i, Self { name, vtype, value }))
     }
 }
 
-impl_binparsable!(BinHashValue, map(le_u32, Self::from));
-impl_binparsable!(BinEntryPath, map(le_u32, Self::from));
-impl_binparsable!(BinClassName, map(le_u32, Self::from));
-impl_binparsable!(BinFieldName, map(le_u32, Self::from));
-impl_binparsable!(BinPathValue, map(le_u64, Self::from));
-
 impl_binparsable!(BinNone, map(take(6usize), |_| Self()));
 impl_binparsable!(BinBool, map(le_u8, |v| Self(v != 0u8)));
 impl_binparsable!(BinS8, =le_i8);
@@ -462,76 +796,128 @@ impl_binparsable!(BinMatrix, map(tuple((le_f32, le_f32, le_f32, le_f32,
                                            ));
 
 impl BinParsable for BinList {
-    fn binparse(i: &[u8]) -> IResult<&[u8], Self> {
-        let (i, (vtype, _)) = tuple((BinType::binparse, le_u32))(i)?;
-        let (i, values) = binvalue_map_type!(vtype, T, map(length_count(le_u32, T::binparse), |v| { Box::new(v) as Box<dyn Any> })(i)?);
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self> {
+        let (i, (vtype, _)) = tuple((parse_bintype, le_u32))(i)?;
+        let (i, values) = binvalue_map_type!(vtype, T, map(length_count(le_u32, |i| T::binparse(i, strictness)), |v| { Box::new(v) as Box<dyn Any + Send + Sync> })(i)?);
         Ok((i, Self { vtype, values }))
     }
 }
 
 impl BinParsable for BinStruct {
-    fn binparse(i: &[u8]) -> IResult<&[u8], Self> {
-        let (i, ctype) = BinClassName::binparse(i)?;
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self> {
+        let (i, ctype) = parse_hash::<BinClassName>(i)?;
         if ctype.is_null() {
             Ok((i, Self { ctype, fields: vec![] }))
         } else {
-            let (i, (_, fields)) = tuple((le_u32, length_count(le_u16, BinField::binparse)))(i)?;
+            let (i, (_, fields)) = tuple((le_u32, length_count(le_u16, |i| BinField::binparse(i, strictness))))(i)?;
             Ok((i, Self { ctype, fields }))
         }
     }
 }
 
 impl BinParsable for BinEmbed {
-    fn binparse(i: &[u8]) -> IResult<&[u8], Self> {
-        let (i, ctype) = BinClassName::binparse(i)?;
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self> {
+        let (i, ctype) = parse_hash::<BinClassName>(i)?;
         if ctype.is_null() {
             Ok((i, Self { ctype, fields: vec![] }))
         } else {
-            let (i, (_, fields)) = tuple((le_u32, length_count(le_u16, BinField::binparse)))(i)?;
+            let (i, (_, fields)) = tuple((le_u32, length_count(le_u16, |i| BinField::binparse(i, strictness))))(i)?;
             Ok((i, Self { ctype, fields }))
         }
     }
 }
 
 impl BinParsable for BinOption {
-    fn binparse(i: &[u8]) -> IResult<&[u8], Self> {
-        let (i, vtype) = BinType::binparse(i)?;
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self> {
+        let (i, vtype) = parse_bintype(i)?;
         let (i, n) = le_u8(i)?;
         let (i, value) = match n {
             0 => (i, None),
             1 => {
-                let (i, v) = binvalue_map_type!(vtype, T, map(T::binparse, |v| Box::new(v) as Box<dyn Any>)(i)?);
+                let (i, v) = binvalue_map_type!(vtype, T, map(|i| T::binparse(i, strictness), |v| Box::new(v) as Box<dyn Any + Send + Sync>)(i)?);
                 (i, Some(v))
             }
-            _ => panic!("unexpected option count: {}", n),
+            _ => return Err(nom::Err::Failure(())),
         };
         Ok((i, Self { vtype, value }))
     }
 }
 
 impl BinParsable for BinMap {
-    fn binparse(i: &[u8]) -> IResult<&[u8], Self> {
-        let (i, (ktype, vtype, _, n)) = tuple((BinType::binparse, BinType::binparse, le_u32, le_u32))(i)?;
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self> {
+        let (i, (ktype, vtype, _, n)) = tuple((parse_bintype, parse_bintype, le_u32, le_u32))(i)?;
+        if !is_map_key_type(ktype) {
+            return Err(nom::Err::Failure(()));
+        }
         let (i, values) =
             binvalue_map_keytype!(
                 ktype, K, binvalue_map_type!(
-                    vtype, V, map(count(pair(K::binparse, V::binparse), n as usize), |v| {
+                    vtype, V, map(count(pair(|i| K::binparse(i, strictness), |i| V::binparse(i, strictness)), n as usize), |v| {
                         let v: Vec<(K, V)> = v.into_iter().collect();
-                        Box::new(v) as Box<dyn Any>
+                        Box::new(v) as Box<dyn Any + Send + Sync>
                     })(i)?));
         Ok((i, Self { ktype, vtype, values }))
     }
 }
 
-impl_binparsable!(BinHash, =BinHashValue::binparse);
-impl_binparsable!(BinPath, =BinPathValue::binparse);
-impl_binparsable!(BinLink, =BinEntryPath::binparse);
+/// Return `true` if a type can be used for map keys, see `binvalue_map_keytype!`
+fn is_map_key_type(vtype: BinType) -> bool {
+    matches!(vtype,
+        BinType::S8 | BinType::U8 |
+        BinType::S16 | BinType::U16 |
+        BinType::S32 | BinType::U32 |
+        BinType::S64 | BinType::U64 |
+        BinType::Float | BinType::String | BinType::Hash)
+}
+
+impl_binparsable!(BinHash, =parse_hash::<BinHashValue>);
+impl_binparsable!(BinPath, =map(le_u64, BinPathValue::from));
+impl_binparsable!(BinLink, =parse_hash::<BinEntryPath>);
 impl_binparsable!(BinFlag, map(le_u8, |v| Self(v != 0u8)));
-impl_binparsable!(BinString, =parse_binstring);
-impl_binparsable!(BinType, map(le_u8, |mut v| {
-    if v >= 0x80 {
-        v = v - 0x80 + BinType::List as u8;
+
+impl BinParsable for BinString {
+    fn binparse(i: &[u8], strictness: Strictness) -> IResult<&[u8], Self> {
+        map(|i| parse_binstring(i, strictness), Self)(i)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{raw_bin_file_data, raw_type, list_value};
+
+    #[test]
+    fn nested_strings_use_strictness() {
+        // Invalid UTF-8 string, in a list
+        let value = list_value(BinType::String, 1, b"\x02\0\xff\xfe");
+        let data = raw_bin_file_data(&[(0x10, 0x11, &[(0x42, raw_type(BinType::List), &value)])]);
+
+        let (_, prop) = PropFile::binparse(&data, Strictness::Lenient).unwrap();
+        let list = prop.entries[0].getv::<BinList>(0x42.into()).unwrap();
+        assert_eq!(list.downcast::<BinString>(), Some(&vec![BinString("\u{FFFD}\u{FFFD}".into())]));
+        assert!(PropFile::binparse(&data, Strictness::Strict).is_err());
+    }
+
+    #[test]
+    fn invalid_patch_header() {
+        let data = b"PTCH\x02\0\0\0\0\0\0\0PROP\x01\0\0\0\0\0\0\0";
+        assert!(matches!(BinEntryScanner::new(&data[..]), Err(ParseError::Error)));
+        assert!(matches!(PropFile::from_slice(data), Err(PropError::Parsing(ParseError::Error))));
+
+        let data = b"PTCH\x01\0\0\0\0\0\0\0PROP\x01\0\0\0\0\0\0\0";
+        assert!(BinEntryScanner::new(&data[..]).unwrap().is_patch);
+        assert!(PropFile::from_slice(data).unwrap().is_patch);
     }
-    Self::try_from(v).expect("invalid BIN type")
-}));
 
+    #[test]
+    fn truncated_linked_files() {
+        // Large linked file count, no data
+        let result = BinEntryScanner::new(&b"PROP\x03\0\0\0\xff\xff\xff\xff"[..]);
+        assert!(matches!(result, Err(ParseError::NotEnoughData)));
+        // Truncated path
+        let result = BinEntryScanner::new(&b"PROP\x03\0\0\0\x01\0\0\0\x0a\0data"[..]);
+        assert!(matches!(result, Err(ParseError::NotEnoughData)));
+    }
+}
@@ -0,0 +1,187 @@
+//! Apply patch files onto regular PROP files
+
+use super::{
+    PropFile,
+    data::*,
+};
+
+impl PropFile {
+    /// Apply the entries of a patch file
+    ///
+    /// Merge rules are the following:
+    ///
+    /// - patch entries are matched to base entries by path;
+    ///   entries not present in the base are appended, in patch order
+    /// - fields of a matched entry are matched by name;
+    ///   fields not present in the base are appended, fields not present in the patch are kept
+    /// - if both base and patch fields are structs (or both are embeds) with the same class
+    ///   name, they are merged recursively, following the same rules
+    /// - otherwise, the base field is replaced by the patch field (containers such as lists and
+    ///   maps are replaced, not merged)
+    ///
+    /// The class name of a matched entry is not modified.
+    ///
+    /// The [is_patch](PropFile::is_patch) flag of `patch` is not checked: entries of a regular
+    /// file can be applied the same way. The flag of `self` is left unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// # use cdragon_prop::{PropFile, BinEntryPath, data::*};
+    /// # use cdragon_prop::testing::{raw_bin_file_data, raw_type, struct_value};
+    /// let (u32_type, struct_type) = (BinType::U32 as u8, raw_type(BinType::Struct));
+    /// // Base: entry 1 with fields `a`, `b` and struct `s` (with fields `x` and `y`)
+    /// let s = struct_value(0x20, &[(0x1, u32_type, &1u32.to_le_bytes()), (0x2, u32_type, &2u32.to_le_bytes())]);
+    /// let mut base = PropFile::from_slice(&raw_bin_file_data(&[
+    ///     (0x10, 1, &[(0xa, u32_type, &1u32.to_le_bytes()), (0xb, u32_type, &2u32.to_le_bytes()), (0x5, struct_type, &s)]),
+    /// ])).unwrap();
+    /// // Patch: update `b` and `s.y` of entry 1, add new entry 2
+    /// let s = struct_value(0x20, &[(0x2, u32_type, &30u32.to_le_bytes())]);
+    /// let patch = PropFile::from_slice(&raw_bin_file_data(&[
+    ///     (0x10, 1, &[(0xb, u32_type, &20u32.to_le_bytes()), (0x5, struct_type, &s)]),
+    ///     (0x10, 2, &[(0xa, u32_type, &5u32.to_le_bytes())]),
+    /// ])).unwrap();
+    /// base.apply_patch(&patch);
+    ///
+    /// assert_eq!(base.entries.len(), 2);
+    /// let entry = &base.entries[0];
+    /// assert_eq!(entry.getv::<BinU32>(0xa.into()), Some(&BinU32(1)));
+    /// assert_eq!(entry.getv::<BinU32>(0xb.into()), Some(&BinU32(20)));
+    /// let s = entry.getv::<BinStruct>(0x5.into()).unwrap();
+    /// assert_eq!(s.getv::<BinU32>(0x1.into()), Some(&BinU32(1)));
+    /// assert_eq!(s.getv::<BinU32>(0x2.into()), Some(&BinU32(30)));
+    /// let entry = &base.entries[1];
+    /// assert_eq!(entry.path, BinEntryPath::from(2));
+    /// assert_eq!(entry.getv::<BinU32>(0xa.into()), Some(&BinU32(5)));
+    /// ```
+    pub fn apply_patch(&mut self, patch: &PropFile) {
+        for patch_entry in &patch.entries {
+            match self.entries.iter_mut().find(|e| e.path == patch_entry.path) {
+                Some(entry) => merge_fields(&mut entry.fields, &patch_entry.fields),
+                None => self.entries.push(patch_entry.clone()),
+            }
+        }
+    }
+}
+
+/// Merge patch fields into base fields
+fn merge_fields(fields: &mut Vec<BinField>, patch_fields: &[BinField]) {
+    for patch_field in patch_fields {
+        match fields.iter_mut().find(|f| f.name == patch_field.name) {
+            Some(field) => merge_field(field, patch_field),
+            None => fields.push(patch_field.clone()),
+        }
+    }
+}
+
+/// Merge a patch field into a base field, with the same name
+fn merge_field(field: &mut BinField, patch_field: &BinField) {
+    match (field.vtype, patch_field.vtype) {
+        (BinType::Struct, BinType::Struct) => {
+            let base = field.value.downcast_mut::<BinStruct>().unwrap();
+            let patch = patch_field.downcast::<BinStruct>().unwrap();
+            if base.ctype == patch.ctype {
+                merge_fields(&mut base.fields, &patch.fields);
+            } else {
+                *base = patch.clone();
+            }
+        }
+        (BinType::Embed, BinType::Embed) => {
+            let base = field.value.downcast_mut::<BinEmbed>().unwrap();
+            let patch = patch_field.downcast::<BinEmbed>().unwrap();
+            if base.ctype == patch.ctype {
+                merge_fields(&mut base.fields, &patch.fields);
+            } else {
+                *base = patch.clone();
+            }
+        }
+        _ => *field = patch_field.clone(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BinEntry;
+
+    #[test]
+    fn patch_overrides_fields() {
+        let mut base = PropFile { version: 3, is_patch: false, linked_files: vec![], entries: vec![
+            BinEntry::new(1.into(), 0x10.into(), vec![
+                BinField::new(0xa.into(), BinU32(1)),
+                BinField::new(0xb.into(), BinU32(2)),
+            ]),
+        ]};
+        let patch = PropFile { version: 3, is_patch: true, linked_files: vec![], entries: vec![
+            BinEntry::new(1.into(), 0x10.into(), vec![
+                BinField::new(0xa.into(), BinString("a".into())),
+                BinField::new(0xb.into(), BinU32(20)),
+                BinField::new(0xc.into(), BinU32(30)),
+            ]),
+        ]};
+        base.apply_patch(&patch);
+
+        assert!(!base.is_patch);
+        assert_eq!(base.entries.len(), 1);
+        let entry = &base.entries[0];
+        let names: Vec<_> = entry.fields.iter().map(|f| f.name.hash).collect();
+        assert_eq!(names, vec![0xa, 0xb, 0xc]);
+        // Type of the field can change
+        assert_eq!(entry.getv::<BinString>(0xa.into()), Some(&BinString("a".into())));
+        assert_eq!(entry.getv::<BinU32>(0xb.into()), Some(&BinU32(20)));
+        assert_eq!(entry.getv::<BinU32>(0xc.into()), Some(&BinU32(30)));
+        // Patch is left untouched
+        assert_eq!(patch.entries[0].getv::<BinU32>(0xb.into()), Some(&BinU32(20)));
+    }
+
+    #[test]
+    fn patch_inserts_new_entries() {
+        let mut base = PropFile { version: 3, is_patch: false, linked_files: vec![], entries: vec![
+            BinEntry::new(1.into(), 0x10.into(), vec![BinField::new(0xa.into(), BinU32(1))]),
+        ]};
+        let patch = PropFile { version: 3, is_patch: true, linked_files: vec![], entries: vec![
+            BinEntry::new(3.into(), 0x10.into(), vec![BinField::new(0xa.into(), BinU32(3))]),
+            BinEntry::new(1.into(), 0x10.into(), vec![]),
+            BinEntry::new(2.into(), 0x10.into(), vec![BinField::new(0xa.into(), BinU32(2))]),
+        ]};
+        base.apply_patch(&patch);
+
+        let paths: Vec<_> = base.entries.iter().map(|e| e.path.hash).collect();
+        assert_eq!(paths, vec![1, 3, 2]);
+        assert_eq!(base.entries[0].getv::<BinU32>(0xa.into()), Some(&BinU32(1)));
+        assert_eq!(base.entries[1].getv::<BinU32>(0xa.into()), Some(&BinU32(3)));
+        assert_eq!(base.entries[2].getv::<BinU32>(0xa.into()), Some(&BinU32(2)));
+    }
+
+    #[test]
+    fn patch_replaces_nested_fields() {
+        let embed = |ctype: u32, fields: Vec<(u32, u32)>| BinField::new(0xe.into(), BinEmbed::new(ctype.into(),
+            fields.into_iter().map(|(name, value)| BinField::new(name.into(), BinU32(value))).collect()));
+        let list = |values: Vec<u32>| BinField::new(0xf.into(), BinList::new(values.into_iter().map(BinU32).collect()));
+
+        let mut base = PropFile { version: 3, is_patch: false, linked_files: vec![], entries: vec![
+            BinEntry::new(1.into(), 0x10.into(), vec![embed(0x20, vec![(0x1, 1), (0x2, 2)]), list(vec![1, 2, 3])]),
+            BinEntry::new(2.into(), 0x10.into(), vec![embed(0x20, vec![(0x1, 1), (0x2, 2)])]),
+        ]};
+        let patch = PropFile { version: 3, is_patch: true, linked_files: vec![], entries: vec![
+            // Same class: merged
+            BinEntry::new(1.into(), 0x10.into(), vec![embed(0x20, vec![(0x2, 20), (0x3, 30)]), list(vec![4])]),
+            // Different class: replaced
+            BinEntry::new(2.into(), 0x10.into(), vec![embed(0x21, vec![(0x2, 20)])]),
+        ]};
+        base.apply_patch(&patch);
+
+        let e = base.entries[0].getv::<BinEmbed>(0xe.into()).unwrap();
+        assert_eq!(e.ctype, 0x20.into());
+        assert_eq!(e.getv::<BinU32>(0x1.into()), Some(&BinU32(1)));
+        assert_eq!(e.getv::<BinU32>(0x2.into()), Some(&BinU32(20)));
+        assert_eq!(e.getv::<BinU32>(0x3.into()), Some(&BinU32(30)));
+        let l = base.entries[0].getv::<BinList>(0xf.into()).unwrap();
+        assert_eq!(l.downcast::<BinU32>(), Some(&vec![BinU32(4)]));
+
+        let e = base.entries[1].getv::<BinEmbed>(0xe.into()).unwrap();
+        assert_eq!(e.ctype, 0x21.into());
+        assert!(e.get(0x1.into()).is_none());
+        assert_eq!(e.getv::<BinU32>(0x2.into()), Some(&BinU32(20)));
+    }
+}
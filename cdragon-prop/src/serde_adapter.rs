@@ -0,0 +1,213 @@
+//! Serialize bin data with serde
+//!
+//! Hashes are resolved using [BinHashMappers], which is why values are not directly
+//! serializable, but must be wrapped with their mappers.
+use serde::ser::{Serialize, Serializer, SerializeMap};
+use super::{
+    BinEntry,
+    BinHashMappers,
+    data::*,
+    binvalue_map_keytype,
+    binvalue_map_type,
+};
+
+/// Serialize a [BinEntry] with serde, resolving hashes with the given mappers
+///
+/// Output follows the format of [JsonSerializer](super::JsonSerializer):
+///
+/// - entries, structs and embeds are serialized as maps, with their class name as `__type`,
+///   followed by fields
+/// - hashes are serialized as strings, unknown hashes as `{hex}`
+/// - vectors, matrices and colors are serialized as sequences (matrices are nested)
+/// - lists are sequences, maps are maps, options are optional values
+/// - map keys are serialized as-is, except floats which are converted to strings
+///
+/// # Example
+/// ```
+/// # use cdragon_hashes::HashDef;
+/// # use cdragon_prop::{binh, BinEntry, BinHashMappers, JsonSerializer, BinSerializer, SerializableEntry, data::*};
+/// let mut hmappers = BinHashMappers::default();
+/// hmappers.class_name.insert(0x1234, "MyClass".to_string());
+/// hmappers.field_name.insert(1, "known".to_string());
+/// let entry = BinEntry::new(0x1.into(), 0x1234.into(), vec![
+///     BinField::new(1.into(), BinU32(42)),
+///     BinField::new(2.into(), BinFloat(1.5)),
+///     BinField::new(3.into(), BinString("text \"quoted\"".to_string())),
+///     BinField::new(4.into(), BinVec3(1.5, 2.5, -3.5)),
+///     BinField::new(5.into(), BinMatrix([[0.5; 4]; 4])),
+///     BinField::new(6.into(), BinColor { r: 1, g: 2, b: 3, a: 4 }),
+///     BinField::new(7.into(), BinHash(binh!("someHash"))),
+///     BinField::new(8.into(), BinPath(BinPathValue::hashed("assets/foo.dds"))),
+///     BinField::new(9.into(), BinLink(0x42.into())),
+///     BinField::new(10.into(), BinList::new(vec![BinS8(-1), BinS8(2)])),
+///     BinField::new(11.into(), BinMap::new(vec![(BinU8(1), BinString("one".into())), (BinU8(2), BinString("two".into()))])),
+///     BinField::new(12.into(), BinMap::new(vec![(BinFloat(0.5), BinBool(true))])),
+///     BinField::new(13.into(), BinOption::new(Some(BinU16(7)))),
+///     BinField::new(14.into(), BinOption::new(None::<BinU16>)),
+///     BinField::new(15.into(), BinStruct::new(0x1234.into(), vec![BinField::new(1.into(), BinFlag(true))])),
+///     BinField::new(16.into(), BinEmbed::new(0x5678.into(), vec![])),
+///     BinField::new(17.into(), BinNone()),
+/// ]);
+///
+/// // Same output as `JsonSerializer`
+/// let json = serde_json::to_value(SerializableEntry(&entry, &hmappers)).unwrap();
+/// let mut expected = Vec::new();
+/// JsonSerializer::new(&mut expected, &hmappers).write_entry(&entry).unwrap();
+/// let expected: serde_json::Value = serde_json::from_slice(&expected).unwrap();
+/// assert_eq!(json, expected);
+/// assert_eq!(json["__type"], "MyClass");
+/// assert_eq!(json["known"], 42);
+/// assert_eq!(json["{00000002}"], 1.5);
+///
+/// // Other formats are supported too
+/// let data = rmp_serde::to_vec(&SerializableEntry(&entry, &hmappers)).unwrap();
+/// let value: rmpv::Value = rmp_serde::from_slice(&data).unwrap();
+/// assert_eq!(value.as_map().unwrap().len(), 18);
+/// ```
+#[derive(Clone, Copy)]
+pub struct SerializableEntry<'a>(pub &'a BinEntry, pub &'a BinHashMappers);
+
+impl Serialize for SerializableEntry<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        serialize_fields(self.0.ctype, &self.0.fields, self.1, s)
+    }
+}
+
+
+/// Serialize the value of a [BinField], resolving hashes with the given mappers
+///
+/// Output follows the format of [SerializableEntry].
+pub(crate) struct SerializableFieldValue<'a>(pub &'a BinField, pub &'a BinHashMappers);
+
+impl Serialize for SerializableFieldValue<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        binvalue_map_type!(self.0.vtype, T, {
+            WithMappers(self.0.downcast::<T>().unwrap(), self.1).serialize(s)
+        })
+    }
+}
+
+
+/// Bin value, serialized with hash mappers
+struct WithMappers<'a, T>(&'a T, &'a BinHashMappers);
+
+impl<T: SerdeValue> Serialize for WithMappers<'_, T> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_value(self.1, s)
+    }
+}
+
+/// Bin map key, serialized with hash mappers
+struct KeyWithMappers<'a, T>(&'a T, &'a BinHashMappers);
+
+impl<T: SerdeValue> Serialize for KeyWithMappers<'_, T> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_key(self.1, s)
+    }
+}
+
+/// Bin value that can be serialized with serde, using hash mappers
+trait SerdeValue {
+    fn serialize_value<S: Serializer>(&self, hmappers: &BinHashMappers, s: S) -> Result<S::Ok, S::Error>;
+
+    /// Serialize the value as a map key
+    fn serialize_key<S: Serializer>(&self, hmappers: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+        self.serialize_value(hmappers, s)
+    }
+}
+
+fn serialize_fields<S: Serializer>(ctype: BinClassName, fields: &[BinField], hmappers: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+    let mut map = s.serialize_map(Some(fields.len() + 1))?;
+    map.serialize_entry("__type", &ctype.seek_str(hmappers).to_string())?;
+    for field in fields {
+        map.serialize_key(&field.name.seek_str(hmappers).to_string())?;
+        binvalue_map_type!(field.vtype, T, {
+            map.serialize_value(&WithMappers(field.downcast::<T>().unwrap(), hmappers))?
+        });
+    }
+    map.end()
+}
+
+macro_rules! impl_serde_value {
+    ($type:ty, |$v:ident, $h:pat_param| $expr:expr) => {
+        impl SerdeValue for $type {
+            fn serialize_value<S: Serializer>(&self, $h: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+                let $v = self;
+                $expr.serialize(s)
+            }
+        }
+    };
+}
+
+impl_serde_value!(BinNone, |_v, _| ());
+impl_serde_value!(BinBool, |v, _| v.0);
+impl_serde_value!(BinS8, |v, _| v.0);
+impl_serde_value!(BinU8, |v, _| v.0);
+impl_serde_value!(BinS16, |v, _| v.0);
+impl_serde_value!(BinU16, |v, _| v.0);
+impl_serde_value!(BinS32, |v, _| v.0);
+impl_serde_value!(BinU32, |v, _| v.0);
+impl_serde_value!(BinS64, |v, _| v.0);
+impl_serde_value!(BinU64, |v, _| v.0);
+impl_serde_value!(BinVec2, |v, _| [v.0, v.1]);
+impl_serde_value!(BinVec3, |v, _| [v.0, v.1, v.2]);
+impl_serde_value!(BinVec4, |v, _| [v.0, v.1, v.2, v.3]);
+impl_serde_value!(BinMatrix, |v, _| v.0);
+impl_serde_value!(BinColor, |v, _| [v.r, v.g, v.b, v.a]);
+impl_serde_value!(BinString, |v, _| v.0);
+impl_serde_value!(BinHash, |v, h| v.0.seek_str(h).to_string());
+impl_serde_value!(BinPath, |v, h| v.0.seek_str(h).to_string());
+impl_serde_value!(BinLink, |v, h| v.0.seek_str(h).to_string());
+impl_serde_value!(BinFlag, |v, _| v.0);
+
+impl SerdeValue for BinFloat {
+    fn serialize_value<S: Serializer>(&self, _: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_f32(self.0)
+    }
+
+    fn serialize_key<S: Serializer>(&self, _: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+        // Float keys are not supported by all formats
+        s.collect_str(&self.0)
+    }
+}
+
+impl SerdeValue for BinList {
+    fn serialize_value<S: Serializer>(&self, hmappers: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+        binvalue_map_type!(self.vtype, T, {
+            let values = self.downcast::<T>().unwrap();
+            s.collect_seq(values.iter().map(|v| WithMappers(v, hmappers)))
+        })
+    }
+}
+
+impl SerdeValue for BinStruct {
+    fn serialize_value<S: Serializer>(&self, hmappers: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+        serialize_fields(self.ctype, &self.fields, hmappers, s)
+    }
+}
+
+impl SerdeValue for BinEmbed {
+    fn serialize_value<S: Serializer>(&self, hmappers: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+        serialize_fields(self.ctype, &self.fields, hmappers, s)
+    }
+}
+
+impl SerdeValue for BinOption {
+    fn serialize_value<S: Serializer>(&self, hmappers: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+        binvalue_map_type!(self.vtype, T, {
+            match self.downcast::<T>() {
+                Some(v) => s.serialize_some(&WithMappers(v, hmappers)),
+                None => s.serialize_none(),
+            }
+        })
+    }
+}
+
+impl SerdeValue for BinMap {
+    fn serialize_value<S: Serializer>(&self, hmappers: &BinHashMappers, s: S) -> Result<S::Ok, S::Error> {
+        binvalue_map_keytype!(self.ktype, K, binvalue_map_type!(self.vtype, V, {
+            let entries = self.downcast::<K, V>().unwrap();
+            s.collect_map(entries.iter().map(|(k, v)| (KeyWithMappers(k, hmappers), WithMappers(v, hmappers))))
+        }))
+    }
+}
@@ -4,6 +4,9 @@
 //!
 //! - [JsonSerializer], for JSON serialization, simpler but drop type details
 //! - [TextTreeSerializer], for custom text format that retains detailed type information
+//!
+//! Other formats can be supported through serde, using `SerializableEntry` (requires the `serde`
+//! feature).
 
 use std::io;
 use super::{
@@ -0,0 +1,114 @@
+//! Build PROP files for tests
+//!
+//! Available with the `testing` feature, for tests of dependent crates.
+use std::path::Path;
+use cdragon_hashes::bin::compute_binhash;
+use crate::data::BinType;
+
+/// Field of a test bin entry: name, raw type and raw value
+pub type TestField<'a> = (&'a str, u8, &'a [u8]);
+
+/// Entry of a test bin file: type, path and fields
+pub type TestEntry<'a> = (&'a str, &'a str, &'a [TestField<'a>]);
+
+/// Field of a test bin entry, with a raw name hash: name, raw type and raw value
+pub type RawTestField<'a> = (u32, u8, &'a [u8]);
+
+/// Entry of a test bin file, with raw hashes: type, path and fields
+pub type RawTestEntry<'a> = (u32, u32, &'a [RawTestField<'a>]);
+
+/// Build the data of a bin file, names are hashed
+pub fn bin_file_data(entries: &[TestEntry]) -> Vec<u8> {
+    let fields: Vec<Vec<RawTestField>> = entries.iter()
+        .map(|(_, _, fields)| fields.iter().map(|(name, vtype, value)| (compute_binhash(name), *vtype, *value)).collect())
+        .collect();
+    let entries: Vec<RawTestEntry> = entries.iter().zip(&fields)
+        .map(|((ctype, path, _), fields)| (compute_binhash(ctype), compute_binhash(path), &fields[..]))
+        .collect();
+    raw_bin_file_data(&entries)
+}
+
+/// Build the data of a bin file from raw hashes
+///
+/// ```
+/// # use cdragon_prop::{PropFile, data::*};
+/// # use cdragon_prop::testing::raw_bin_file_data;
+/// let data = raw_bin_file_data(&[(0x10, 0x11, &[(0x42, BinType::U8 as u8, &[7])])]);
+/// let prop = PropFile::from_slice(&data).unwrap();
+/// assert_eq!(prop.entries[0].path, BinEntryPath::from(0x11));
+/// assert_eq!(prop.entries[0].getv::<BinU8>(0x42.into()), Some(&BinU8(7)));
+/// ```
+pub fn raw_bin_file_data(entries: &[RawTestEntry]) -> Vec<u8> {
+    let mut data = b"PROP\x01\0\0\0".to_vec();
+    data.extend((entries.len() as u32).to_le_bytes());
+    for (ctype, _, _) in entries {
+        data.extend(ctype.to_le_bytes());
+    }
+    for (_, path, fields) in entries {
+        let size: usize = 4 + 2 + fields.iter().map(|(_, _, value)| 4 + 1 + value.len()).sum::<usize>();
+        data.extend((size as u32).to_le_bytes());
+        data.extend(path.to_le_bytes());
+        data.extend((fields.len() as u16).to_le_bytes());
+        for (name, vtype, value) in *fields {
+            data.extend(name.to_le_bytes());
+            data.push(*vtype);
+            data.extend(*value);
+        }
+    }
+    data
+}
+
+/// Write a bin file, create parent directories if needed
+pub fn write_bin_file(path: &Path, entries: &[TestEntry]) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    std::fs::write(path, bin_file_data(entries)).unwrap();
+}
+
+/// Raw value of a string field
+pub fn string_value(s: &str) -> Vec<u8> {
+    let mut value = (s.len() as u16).to_le_bytes().to_vec();
+    value.extend(s.as_bytes());
+    value
+}
+
+/// Raw value of a type, as stored in bin files
+///
+/// Complex types are shifted to `0x80` and above.
+pub fn raw_type(vtype: BinType) -> u8 {
+    if vtype as u8 >= BinType::List as u8 {
+        vtype as u8 - BinType::List as u8 + 0x80
+    } else {
+        vtype as u8
+    }
+}
+
+/// Raw value of a list field, from the raw data of its items
+pub fn list_value(vtype: BinType, count: usize, items: &[u8]) -> Vec<u8> {
+    let mut value = vec![raw_type(vtype)];
+    value.extend((4 + items.len() as u32).to_le_bytes());
+    value.extend((count as u32).to_le_bytes());
+    value.extend(items);
+    value
+}
+
+/// Raw value of a struct or embed field, from raw fields
+pub fn struct_value(ctype: u32, fields: &[RawTestField]) -> Vec<u8> {
+    let mut content = (fields.len() as u16).to_le_bytes().to_vec();
+    for (name, vtype, value) in fields {
+        content.extend(name.to_le_bytes());
+        content.push(*vtype);
+        content.extend(*value);
+    }
+    let mut value = ctype.to_le_bytes().to_vec();
+    value.extend((content.len() as u32).to_le_bytes());
+    value.extend(content);
+    value
+}
+
+/// Raw type of a list of hashes, and its value
+pub fn hash_list_field(hashes: &[u32]) -> (u8, Vec<u8>) {
+    let items: Vec<u8> = hashes.iter().flat_map(|hash| hash.to_le_bytes()).collect();
+    (raw_type(BinType::List), list_value(BinType::Hash, hashes.len(), &items))
+}
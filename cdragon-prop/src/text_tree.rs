@@ -39,6 +39,13 @@ macro_rules! serializeln {
 
 
 /// Serialize bin values to a human readable text tree
+///
+/// Fields are written with their type, e.g. `LIST(U8)` for a [BinType::List] of `u8`.
+///
+/// # Compatibility
+///
+/// [BinType::List2] fields are written as `LIST2(...)`. Previous versions wrote `LIST(...)` for
+/// both list types; tools parsing the output should accept both.
 #[derive(Debug)]
 pub struct TextTreeSerializer<'a, W: Write> {
     writer: W,
@@ -60,17 +67,11 @@ impl<'a, W: Write> TextTreeSerializer<'a, W> {
     }
 
     fn format_type_name(&self, h: BinClassName) -> String {
-        match h.get_str(self.hmappers) {
-            Some(s) => s.to_string(),
-            _ => format!("{{{:x}}}", h),
-        }
+        h.seek_str(self.hmappers).to_string()
     }
 
     fn format_field_name(&self, h: BinFieldName) -> String {
-        match h.get_str(self.hmappers) {
-            Some(s) => s.to_string(),
-            _ => format!("{{{:x}}}", h),
-        }
+        h.seek_str(self.hmappers).to_string()
     }
 
     fn format_hash_value(&self, h: BinHashValue) -> String {
@@ -147,7 +148,8 @@ impl<'a, W: Write> TextTreeSerializer<'a, W> {
             BinType::String => serialize_field!(BinString),
             BinType::Hash => serialize_field!(BinHash),
             BinType::Path => serialize_field!(BinPath),
-            BinType::List | BinType::List2 => serialize_field!(BinList: [v] => "LIST({}) ", basic_bintype_name(v.vtype)),
+            BinType::List => serialize_field!(BinList: [v] => "LIST({}) ", basic_bintype_name(v.vtype)),
+            BinType::List2 => serialize_field!(BinList: [v] => "LIST2({}) ", basic_bintype_name(v.vtype)),
             BinType::Struct => serialize_field!(BinStruct: {v} => "STRUCT {} ", self.format_type_name(v.ctype)),
             BinType::Embed => serialize_field!(BinEmbed: {v} => "EMBED {} ", self.format_type_name(v.ctype)),
             BinType::Link => serialize_field!(BinLink),
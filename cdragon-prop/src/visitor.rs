@@ -1,5 +1,7 @@
 //! Visit a nested bin value
 
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
 use super::{
     BinEntry,
     data::*,
@@ -7,9 +9,78 @@ use super::{
     binvalue_map_keytype,
 };
 
+/// Outcome of a visit, to control the traversal
+///
+/// ```
+/// # use std::ops::ControlFlow;
+/// # use cdragon_prop::{BinEntry, BinEntryPath, BinTraversal, BinVisitor, VisitOutcome};
+/// # use cdragon_prop::{data::*, visitor::VisitResult};
+/// // Return the first link of an entry, ignoring links in options
+/// struct FirstLinkVisitor;
+///
+/// impl BinVisitor for FirstLinkVisitor {
+///     type Error = ();
+///     type Break = BinEntryPath;
+///
+///     fn visit_option(&mut self, _value: &BinOption) -> VisitResult<Self> {
+///         Ok(VisitOutcome::SkipChildren)
+///     }
+///
+///     fn visit_link(&mut self, value: &BinLink) -> VisitResult<Self> {
+///         Ok(VisitOutcome::Stop(value.0))
+///     }
+/// }
+///
+/// // Entry 1 links to 2 and 3 (in a list), entry 2 links to 3 (in an option)
+/// let entry1 = BinEntry::new(1.into(), 0x10.into(), vec![
+///     BinField::new(0xaa.into(), BinList::new(vec![BinLink(2.into()), BinLink(3.into())])),
+/// ]);
+/// let entry2 = BinEntry::new(2.into(), 0x10.into(), vec![
+///     BinField::new(0xbb.into(), BinOption::new(Some(BinLink(3.into())))),
+/// ]);
+/// let mut visitor = FirstLinkVisitor;
+/// assert_eq!(entry1.traverse_bin(&mut visitor), Ok(ControlFlow::Break(BinEntryPath::from(2))));
+/// assert_eq!(entry2.traverse_bin(&mut visitor), Ok(ControlFlow::Continue(())));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisitOutcome<B> {
+    /// Continue the traversal, including nested values
+    Continue,
+    /// Don't visit nested values, continue with the next value
+    SkipChildren,
+    /// Stop the traversal, with a reason
+    Stop(B),
+}
+
+impl<B> VisitOutcome<B> {
+    /// Convert to the result of a traversal, children are not visited
+    fn into_control_flow(self) -> ControlFlow<B> {
+        match self {
+            Self::Stop(reason) => ControlFlow::Break(reason),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+impl<B> From<bool> for VisitOutcome<B> {
+    /// Return `Continue` for `true`, `SkipChildren` for `false`
+    fn from(visit: bool) -> Self {
+        if visit { Self::Continue } else { Self::SkipChildren }
+    }
+}
+
+/// Result of a [BinVisitor] visit method
+pub type VisitResult<V> = Result<VisitOutcome<<V as BinVisitor>::Break>, <V as BinVisitor>::Error>;
+
+/// Result of a traversal, [ControlFlow::Break] if it has been stopped
+pub type TraverseResult<V> = Result<ControlFlow<<V as BinVisitor>::Break>, <V as BinVisitor>::Error>;
+
 /// Interface to visit values of nested bin values
 ///
-/// Visit methods of nested types can return `false` to not visit nested values.
+/// Visit methods return a [VisitOutcome] to control the traversal: visit methods of nested
+/// types can return `SkipChildren` to not visit nested values, any visit method can return
+/// `Stop` to abort the whole traversal. The reason of the stop is returned by
+/// [BinTraversal::traverse_bin()], as a [ControlFlow::Break].
 /// By default, everything is visited.
 ///
 /// [visit_type()](Self::visit_type()) can be used to easily ignore some types.
@@ -17,75 +88,104 @@ use super::{
 #[allow(missing_docs)]
 pub trait BinVisitor {
     type Error;
+    /// Reason of a traversal stop, see [VisitOutcome::Stop]
+    type Break;
 
     /// Called to visit an entry
     ///
     /// This method exists so an implementation can execute code after an entry has been visited.
-    fn traverse_entry(&mut self, value: &BinEntry) -> Result<(), Self::Error> {
+    /// Callers iterating over multiple entries should stop on [ControlFlow::Break].
+    fn traverse_entry(&mut self, value: &BinEntry) -> TraverseResult<Self> {
         value.traverse_bin(self)
     }
 
     /// Return true to visit given type
     fn visit_type(&mut self, _btype: BinType) -> bool { true }
 
-    fn visit_entry(&mut self, _value: &BinEntry) -> Result<bool, Self::Error> { Ok(true) }
-    fn visit_field(&mut self, value: &BinField) -> Result<bool, Self::Error> { Ok(self.visit_type(value.vtype)) }
-
-    fn visit_none(&mut self, _value: &BinNone) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_bool(&mut self, _value: &BinBool) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_s8(&mut self, _value: &BinS8) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_u8(&mut self, _value: &BinU8) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_s16(&mut self, _value: &BinS16) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_u16(&mut self, _value: &BinU16) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_s32(&mut self, _value: &BinS32) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_u32(&mut self, _value: &BinU32) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_s64(&mut self, _value: &BinS64) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_u64(&mut self, _value: &BinU64) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_float(&mut self, _value: &BinFloat) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_vec2(&mut self, _value: &BinVec2) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_vec3(&mut self, _value: &BinVec3) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_vec4(&mut self, _value: &BinVec4) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_matrix(&mut self, _value: &BinMatrix) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_color(&mut self, _value: &BinColor) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_string(&mut self, _value: &BinString) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_hash(&mut self, _value: &BinHash) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_path(&mut self, _value: &BinPath) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_list(&mut self, value: &BinList) -> Result<bool, Self::Error> {
-        Ok(self.visit_type(BinType::List) && self.visit_type(value.vtype))
-    }
-    fn visit_struct(&mut self, _value: &BinStruct) -> Result<bool, Self::Error> {
-        Ok(self.visit_type(BinType::Struct))
-    }
-    fn visit_embed(&mut self, _value: &BinEmbed) -> Result<bool, Self::Error> {
-        Ok(self.visit_type(BinType::Embed))
-    }
-    fn visit_link(&mut self, _value: &BinLink) -> Result<(), Self::Error> { Ok(()) }
-    fn visit_option(&mut self, value: &BinOption) -> Result<bool, Self::Error> {
-        Ok(self.visit_type(BinType::Option) && self.visit_type(value.vtype))
-    }
-    fn visit_map(&mut self, _value: &BinMap) -> Result<bool, Self::Error> {
-        Ok(self.visit_type(BinType::Map))
-    }
-    fn visit_flag(&mut self, _value: &BinFlag) -> Result<(), Self::Error> { Ok(()) }
+    fn visit_entry(&mut self, _value: &BinEntry) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_field(&mut self, value: &BinField) -> VisitResult<Self> { Ok(self.visit_type(value.vtype).into()) }
+
+    fn visit_none(&mut self, _value: &BinNone) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_bool(&mut self, _value: &BinBool) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_s8(&mut self, _value: &BinS8) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_u8(&mut self, _value: &BinU8) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_s16(&mut self, _value: &BinS16) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_u16(&mut self, _value: &BinU16) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_s32(&mut self, _value: &BinS32) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_u32(&mut self, _value: &BinU32) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_s64(&mut self, _value: &BinS64) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_u64(&mut self, _value: &BinU64) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_float(&mut self, _value: &BinFloat) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_vec2(&mut self, _value: &BinVec2) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_vec3(&mut self, _value: &BinVec3) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_vec4(&mut self, _value: &BinVec4) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_matrix(&mut self, _value: &BinMatrix) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_color(&mut self, _value: &BinColor) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_string(&mut self, _value: &BinString) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_hash(&mut self, _value: &BinHash) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_path(&mut self, _value: &BinPath) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    /// Visit a list, either a [BinType::List] or a [BinType::List2]
+    ///
+    /// Both are visited as a `List`. Use [BinField::vtype] from [Self::visit_field()] to tell
+    /// them apart.
+    fn visit_list(&mut self, value: &BinList) -> VisitResult<Self> {
+        Ok((self.visit_type(BinType::List) && self.visit_type(value.vtype)).into())
+    }
+    fn visit_struct(&mut self, _value: &BinStruct) -> VisitResult<Self> {
+        Ok(self.visit_type(BinType::Struct).into())
+    }
+    fn visit_embed(&mut self, _value: &BinEmbed) -> VisitResult<Self> {
+        Ok(self.visit_type(BinType::Embed).into())
+    }
+    fn visit_link(&mut self, _value: &BinLink) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
+    fn visit_option(&mut self, value: &BinOption) -> VisitResult<Self> {
+        Ok((self.visit_type(BinType::Option) && self.visit_type(value.vtype)).into())
+    }
+    fn visit_map(&mut self, _value: &BinMap) -> VisitResult<Self> {
+        Ok(self.visit_type(BinType::Map).into())
+    }
+    fn visit_flag(&mut self, _value: &BinFlag) -> VisitResult<Self> { Ok(VisitOutcome::Continue) }
 }
 
 /// Interface to traverse nested bin values with a visitor
 pub trait BinTraversal<BV: BinVisitor + ?Sized> {
     /// Visit the value, recursively
-    fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error>;
+    ///
+    /// Return [ControlFlow::Break] if the traversal has been stopped by the visitor.
+    fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV>;
 }
 
 macro_rules! impl_traversal {
     ($t:ty, $visit:ident) => {
         impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for $t {
             #[inline]
-            fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error> {
-                visitor.$visit(self)
+            fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV> {
+                visitor.$visit(self).map(VisitOutcome::into_control_flow)
             }
         }
     }
 }
 
+/// Traverse a nested value, return early if the traversal is stopped
+macro_rules! traverse_nested {
+    ($value:expr, $visitor:expr) => {
+        if let ControlFlow::Break(reason) = $value.traverse_bin($visitor)? {
+            return Ok(ControlFlow::Break(reason));
+        }
+    }
+}
+
+/// Visit a value, return early unless nested values have to be traversed
+macro_rules! visit_nested {
+    ($visit:expr) => {
+        match $visit? {
+            VisitOutcome::Continue => {}
+            VisitOutcome::SkipChildren => return Ok(ControlFlow::Continue(())),
+            VisitOutcome::Stop(reason) => return Ok(ControlFlow::Break(reason)),
+        }
+    }
+}
+
 impl_traversal!(BinNone, visit_none);
 impl_traversal!(BinBool, visit_bool);
 impl_traversal!(BinS8, visit_s8);
@@ -110,88 +210,318 @@ impl_traversal!(BinFlag, visit_flag);
 
 
 impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for BinEntry {
-    fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error> {
-        if visitor.visit_entry(self)? {
-            for field in self.fields.iter() {
-                field.traverse_bin(visitor)?;
-            }
+    fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV> {
+        visit_nested!(visitor.visit_entry(self));
+        for field in self.fields.iter() {
+            traverse_nested!(field, visitor);
         }
-        Ok(())
+        Ok(ControlFlow::Continue(()))
     }
 }
 
 impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for BinField {
-    fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error> {
-        if visitor.visit_field(self)? {
-            binvalue_map_type!(self.vtype, T, {
-                self.downcast::<T>().unwrap().traverse_bin(visitor)?;
-            });
-        }
-        Ok(())
+    fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV> {
+        visit_nested!(visitor.visit_field(self));
+        binvalue_map_type!(self.vtype, T, {
+            self.downcast::<T>().unwrap().traverse_bin(visitor)
+        })
     }
 }
 
 impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for BinStruct {
-    fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error> {
-        if visitor.visit_struct(self)? {
-            for field in self.fields.iter() {
-                field.traverse_bin(visitor)?;
-            }
+    fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV> {
+        visit_nested!(visitor.visit_struct(self));
+        for field in self.fields.iter() {
+            traverse_nested!(field, visitor);
         }
-        Ok(())
+        Ok(ControlFlow::Continue(()))
     }
 }
 
 impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for BinEmbed {
-    fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error> {
-        if visitor.visit_embed(self)? {
-            for field in self.fields.iter() {
-                field.traverse_bin(visitor)?;
-            }
+    fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV> {
+        visit_nested!(visitor.visit_embed(self));
+        for field in self.fields.iter() {
+            traverse_nested!(field, visitor);
         }
-        Ok(())
+        Ok(ControlFlow::Continue(()))
     }
 }
 
 impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for BinOption {
-    fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error> {
-        if visitor.visit_option(self)? {
-            if self.value.is_some() {
-                binvalue_map_type!(self.vtype, V, {
-                    self.downcast::<V>().unwrap().traverse_bin(visitor)?;
-                });
-            }
+    fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV> {
+        visit_nested!(visitor.visit_option(self));
+        if self.value.is_none() {
+            return Ok(ControlFlow::Continue(()));
         }
-        Ok(())
+        binvalue_map_type!(self.vtype, V, {
+            self.downcast::<V>().unwrap().traverse_bin(visitor)
+        })
     }
 }
 
 impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for BinList {
-    fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error> {
-        if visitor.visit_list(self)? {
+    fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV> {
+        visit_nested!(visitor.visit_list(self));
+        binvalue_map_type!(self.vtype, V, {
+            for v in self.downcast::<V>().unwrap().iter() {
+                traverse_nested!(v, visitor);
+            }
+        });
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for BinMap {
+    fn traverse_bin(&self, visitor: &mut BV) -> TraverseResult<BV> {
+        visit_nested!(visitor.visit_map(self));
+        binvalue_map_keytype!(self.ktype, K, {
             binvalue_map_type!(self.vtype, V, {
-                for v in self.downcast::<V>().unwrap().iter() {
-                    v.traverse_bin(visitor)?;
+                for (k, v) in self.downcast::<K, V>().unwrap() {
+                    traverse_nested!(k, visitor);
+                    traverse_nested!(v, visitor);
                 }
-            });
+            })
+        });
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+
+
+/// Map each entry to the entries it links to
+pub type BinEntryLinks = HashMap<BinEntryPath, HashSet<BinEntryPath>>;
+
+/// Collect links between entries
+///
+/// All visited entries are added to the links map, even if they don't link to other entries.
+/// Links to the same entry are collected once. Empty links (null hash) are collected too, as
+/// links to path `0`.
+///
+/// ```
+/// # use cdragon_prop::{BinEntry, BinEntryPath, BinTraversal, data::*, visitor::CollectLinksVisitor};
+/// // Entry 1 links to 2 and 3 (in a list), entry 2 links to 3 (in an option)
+/// let entries = [
+///     BinEntry::new(1.into(), 0x10.into(), vec![
+///         BinField::new(0xaa.into(), BinList::new(vec![BinLink(2.into()), BinLink(3.into())])),
+///     ]),
+///     BinEntry::new(2.into(), 0x10.into(), vec![
+///         BinField::new(0xbb.into(), BinOption::new(Some(BinLink(3.into())))),
+///     ]),
+///     BinEntry::new(3.into(), 0x10.into(), vec![]),
+/// ];
+/// let mut visitor = CollectLinksVisitor::default();
+/// for entry in entries.iter() {
+///     entry.traverse_bin(&mut visitor).unwrap();
+/// }
+///
+/// let (e1, e2, e3) = (BinEntryPath::from(1), BinEntryPath::from(2), BinEntryPath::from(3));
+/// let links = visitor.links;
+/// assert_eq!(links[&e1], [e2, e3].into());
+/// assert_eq!(links[&e2], [e3].into());
+/// assert!(links[&e3].is_empty());
+///
+/// let reversed = CollectLinksVisitor::reverse_links(&links);
+/// assert!(reversed[&e1].is_empty());
+/// assert_eq!(reversed[&e2], [e1].into());
+/// assert_eq!(reversed[&e3], [e1, e2].into());
+/// ```
+#[derive(Default)]
+pub struct CollectLinksVisitor {
+    /// Collected links
+    pub links: BinEntryLinks,
+    current_entry: Option<BinEntryPath>,
+}
+
+impl CollectLinksVisitor {
+    /// Reverse links, map each entry to the entries linking to it
+    pub fn reverse_links(links: &BinEntryLinks) -> BinEntryLinks {
+        let mut reversed = BinEntryLinks::with_capacity(links.len());
+        for (source, targets) in links {
+            reversed.entry(*source).or_default();
+            for target in targets {
+                reversed.entry(*target).or_default().insert(*source);
+            }
         }
-        Ok(())
+        reversed
     }
 }
 
-impl<BV: BinVisitor + ?Sized> BinTraversal<BV> for BinMap {
-    fn traverse_bin(&self, visitor: &mut BV) -> Result<(), BV::Error> {
-        if visitor.visit_map(self)? {
-            binvalue_map_keytype!(self.ktype, K, {
-                binvalue_map_type!(self.vtype, V, {
-                    for (k, v) in self.downcast::<K, V>().unwrap() {
-                        k.traverse_bin(visitor)?;
-                        v.traverse_bin(visitor)?;
-                    }
-                })
-            });
+impl BinVisitor for CollectLinksVisitor {
+    type Error = std::convert::Infallible;
+    type Break = std::convert::Infallible;
+
+    fn visit_type(&mut self, btype: BinType) -> bool {
+        btype == BinType::Link || btype.is_nested()
+    }
+
+    fn visit_entry(&mut self, value: &BinEntry) -> VisitResult<Self> {
+        self.current_entry = Some(value.path);
+        self.links.entry(value.path).or_default();
+        Ok(VisitOutcome::Continue)
+    }
+
+    fn visit_link(&mut self, value: &BinLink) -> VisitResult<Self> {
+        if let Some(path) = self.current_entry {
+            self.links.entry(path).or_default().insert(value.0);
         }
-        Ok(())
+        Ok(VisitOutcome::Continue)
     }
 }
 
+
+/// Statistics on entries and fields, collected by [StatsVisitor]
+#[derive(Default, Debug)]
+pub struct BinStats {
+    /// Number of entries, per class name
+    pub entries: HashMap<BinClassName, usize>,
+    /// Number of fields, per class name, then per field name and type
+    ///
+    /// Fields of nested structs and embeds are counted under the class of the struct or embed.
+    pub fields: HashMap<BinClassName, HashMap<(BinFieldName, BinType), usize>>,
+}
+
+impl BinStats {
+    fn add_fields(&mut self, ctype: BinClassName, fields: &[BinField]) {
+        if fields.is_empty() {
+            return;
+        }
+        let counts = self.fields.entry(ctype).or_default();
+        for field in fields {
+            *counts.entry((field.name, field.vtype)).or_default() += 1;
+        }
+    }
+}
+
+/// Count entries and fields
+///
+/// ```
+/// # use cdragon_prop::{BinEntry, BinClassName, BinFieldName, BinTraversal, data::*, visitor::StatsVisitor};
+/// // Two entries of class 0x10, with a `u32` field 0xa; one of them has a struct field of class 0x20
+/// let entries = [
+///     BinEntry::new(1.into(), 0x10.into(), vec![BinField::new(0xa.into(), BinU32(1))]),
+///     BinEntry::new(2.into(), 0x10.into(), vec![
+///         BinField::new(0xa.into(), BinU32(2)),
+///         BinField::new(0xb.into(), BinStruct::new(0x20.into(), vec![BinField::new(0xa.into(), BinU32(3))])),
+///     ]),
+/// ];
+/// let mut visitor = StatsVisitor::default();
+/// for entry in entries.iter() {
+///     entry.traverse_bin(&mut visitor).unwrap();
+/// }
+///
+/// let stats = visitor.stats;
+/// let (c10, c20) = (BinClassName::from(0x10), BinClassName::from(0x20));
+/// assert_eq!(stats.entries[&c10], 2);
+/// assert_eq!(stats.fields[&c10][&(BinFieldName::from(0xa), BinType::U32)], 2);
+/// assert_eq!(stats.fields[&c10][&(BinFieldName::from(0xb), BinType::Struct)], 1);
+/// assert_eq!(stats.fields[&c20][&(BinFieldName::from(0xa), BinType::U32)], 1);
+/// ```
+#[derive(Default)]
+pub struct StatsVisitor {
+    /// Collected statistics
+    pub stats: BinStats,
+}
+
+impl BinVisitor for StatsVisitor {
+    type Error = std::convert::Infallible;
+    type Break = std::convert::Infallible;
+
+    fn visit_type(&mut self, btype: BinType) -> bool {
+        btype.is_nested()
+    }
+
+    fn visit_entry(&mut self, value: &BinEntry) -> VisitResult<Self> {
+        *self.stats.entries.entry(value.ctype).or_default() += 1;
+        self.stats.add_fields(value.ctype, &value.fields);
+        Ok(VisitOutcome::Continue)
+    }
+
+    fn visit_struct(&mut self, value: &BinStruct) -> VisitResult<Self> {
+        self.stats.add_fields(value.ctype, &value.fields);
+        Ok(VisitOutcome::Continue)
+    }
+
+    fn visit_embed(&mut self, value: &BinEmbed) -> VisitResult<Self> {
+        self.stats.add_fields(value.ctype, &value.fields);
+        Ok(VisitOutcome::Continue)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BinTraversal;
+
+    fn link(name: u32, path: u32) -> BinField {
+        BinField::new(name.into(), BinLink(path.into()))
+    }
+
+    fn collect_links(entries: &[BinEntry]) -> BinEntryLinks {
+        let mut visitor = CollectLinksVisitor::default();
+        for entry in entries {
+            entry.traverse_bin(&mut visitor).unwrap();
+        }
+        visitor.links
+    }
+
+    fn paths(paths: &[u32]) -> HashSet<BinEntryPath> {
+        paths.iter().map(|&p| p.into()).collect()
+    }
+
+    #[test]
+    fn collect_nested_links() {
+        let entries = [
+            BinEntry::new(1.into(), 0x10.into(), vec![
+                BinField::new(0xa.into(), BinMap::new(vec![
+                    (BinHash(0x1.into()), BinLink(2.into())),
+                    (BinHash(0x2.into()), BinLink(3.into())),
+                ])),
+                BinField::new(0xb.into(), BinStruct::new(0x20.into(), vec![
+                    link(0x1, 4),
+                    BinField::new(0x2.into(), BinEmbed::new(0x21.into(), vec![link(0x1, 5)])),
+                ])),
+                BinField::new(0xc.into(), BinOption::new(Some(BinLink(6.into())))),
+                BinField::new(0xd.into(), BinOption::new::<BinLink>(None)),
+                BinField::new(0xe.into(), BinList::new(vec![
+                    BinEmbed::new(0x21.into(), vec![link(0x1, 7)]),
+                ])),
+            ]),
+            BinEntry::new(2.into(), 0x10.into(), vec![
+                BinField::new(0xa.into(), BinMap::new(vec![
+                    (BinU32(1), BinStruct::new(0x20.into(), vec![link(0x1, 3)])),
+                ])),
+            ]),
+        ];
+        let links = collect_links(&entries);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[&1.into()], paths(&[2, 3, 4, 5, 6, 7]));
+        assert_eq!(links[&2.into()], paths(&[3]));
+    }
+
+    #[test]
+    fn collect_duplicate_and_empty_links() {
+        let entries = [
+            BinEntry::new(1.into(), 0x10.into(), vec![
+                link(0xa, 2),
+                BinField::new(0xb.into(), BinList::new(vec![BinLink(2.into()), BinLink(2.into())])),
+                BinField::new(0xc.into(), BinEmbed::new(0x20.into(), vec![link(0x1, 2)])),
+            ]),
+            BinEntry::new(2.into(), 0x10.into(), vec![link(0xa, 0)]),
+            BinEntry::new(3.into(), 0x10.into(), vec![]),
+        ];
+        let links = collect_links(&entries);
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[&1.into()], paths(&[2]));
+        assert_eq!(links[&2.into()], paths(&[0]));
+        assert_eq!(links[&3.into()], paths(&[]));
+
+        let reversed = CollectLinksVisitor::reverse_links(&links);
+        assert_eq!(reversed.len(), 4);
+        assert_eq!(reversed[&0.into()], paths(&[2]));
+        assert_eq!(reversed[&1.into()], paths(&[]));
+        assert_eq!(reversed[&2.into()], paths(&[1]));
+        assert_eq!(reversed[&3.into()], paths(&[]));
+    }
+}
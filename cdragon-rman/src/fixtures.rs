@@ -0,0 +1,162 @@
+//! Build RMAN data for tests
+use super::Rman;
+
+/// Raw data of an empty table
+pub const EMPTY_TABLE: &[u8] = &[0; 4];
+
+/// Build RMAN data from an uncompressed body
+///
+/// `body_length` is the decompressed body length stored in the header.
+pub fn rman_data(body: &[u8], body_length: usize) -> Vec<u8> {
+    let mut data = b"RMAN\x02\x00".to_vec();
+    data.extend(0x200u16.to_le_bytes());
+    data.extend(28u32.to_le_bytes());
+    data.extend((body.len() as u32).to_le_bytes());
+    data.extend(0x1234u64.to_le_bytes());
+    data.extend((body_length as u32).to_le_bytes());
+    data.extend(body);
+    data
+}
+
+/// Open an RMAN from an uncompressed body, check it
+pub fn open_body(body: &[u8]) -> Rman {
+    Rman::read_checked(rman_data(body, body.len()).as_slice()).unwrap()
+}
+
+/// Build a body from raw table data
+///
+/// Tables follow the root entry, their data must only use relative offsets.
+pub fn tables_body(tables: &[&[u8]]) -> Vec<u8> {
+    let n = tables.len();
+    let root = 4 + 4 + 2 * n;
+    let mut b = Vec::new();
+    b.extend((root as i32).to_le_bytes());
+    b.extend(((4 + 2 * n) as u16).to_le_bytes());
+    b.extend(((4 + 4 * n) as u16).to_le_bytes());
+    for i in 0..n {
+        b.extend(((4 + 4 * i) as u16).to_le_bytes());
+    }
+    b.extend(((root - 4) as i32).to_le_bytes());
+    // Offsets are relative to the field
+    let mut offset = root + 4 + 4 * n;
+    for (i, table) in tables.iter().enumerate() {
+        b.extend(((offset - (root + 4 + 4 * i)) as i32).to_le_bytes());
+        offset += table.len();
+    }
+    b.extend(tables.concat());
+    b
+}
+
+/// Build a minimal body, with the 4 required tables, all empty
+pub fn minimal_body() -> Vec<u8> {
+    tables_body(&[EMPTY_TABLE; 4])
+}
+
+/// Field of an object built by [obj()]
+pub enum F {
+    U64(u64),
+    /// Reference to a body offset
+    Ref(usize),
+}
+
+/// Append an object, return its offset
+///
+/// Objects are a vtable followed by 8-byte field slots; references are relative to the slot.
+pub fn obj(b: &mut Vec<u8>, fields: &[Option<F>]) -> usize {
+    let vt = b.len();
+    b.extend(((4 + 2 * fields.len()) as u16).to_le_bytes());
+    b.extend(((4 + 8 * fields.len()) as u16).to_le_bytes());
+    for (i, f) in fields.iter().enumerate() {
+        b.extend((if f.is_some() { 4 + 8 * i as u16 } else { 0 }).to_le_bytes());
+    }
+    let e = b.len();
+    b.extend(((e - vt) as i32).to_le_bytes());
+    for f in fields {
+        let pos = b.len() as i64;
+        match f {
+            Some(F::U64(v)) => b.extend(v.to_le_bytes()),
+            Some(F::Ref(t)) => b.extend((*t as i64 - pos).to_le_bytes()),
+            None => b.extend([0; 8]),
+        }
+    }
+    e
+}
+
+/// Append a string, return its offset
+pub fn string(b: &mut Vec<u8>, s: &[u8]) -> usize {
+    let p = b.len();
+    b.extend((s.len() as i32).to_le_bytes());
+    b.extend(s);
+    p
+}
+
+/// Append a flag entry, return its offset
+///
+/// Flag entries have fixed field offsets: the ID is at offset 7, the name reference at offset 8.
+pub fn flag(b: &mut Vec<u8>, id: u8, name: &[u8]) -> usize {
+    let name = string(b, name);
+    let e = b.len();
+    b.extend([0; 7]);
+    b.push(id);
+    let pos = b.len() as i64;
+    b.extend(((name as i64 - pos) as i32).to_le_bytes());
+    e
+}
+
+/// Append a table of objects, return its offset
+pub fn table(b: &mut Vec<u8>, items: &[usize]) -> usize {
+    let p = b.len();
+    b.extend((items.len() as u32).to_le_bytes());
+    for item in items {
+        b.extend(((*item as i64 - b.len() as i64) as i32).to_le_bytes());
+    }
+    p
+}
+
+/// Directory or file of a test manifest: ID, parent directory ID, name
+pub type ManifestItem<'a> = (u64, Option<u64>, &'a [u8]);
+
+/// Build manifest data from tables appended by a function
+///
+/// `build` is given the body, to append objects to, and the offset of an empty table.
+/// It returns the offsets of the bundles, flags, files and directories tables.
+pub fn manifest_tables_data(build: impl FnOnce(&mut Vec<u8>, usize) -> [usize; 4]) -> Vec<u8> {
+    let mut b = vec![0u8; 4];
+    let empty = table(&mut b, &[]);
+    let [bundles, flags, files, dirs] = build(&mut b, empty);
+    let root = obj(&mut b, &[Some(F::Ref(bundles)), Some(F::Ref(flags)), Some(F::Ref(files)), Some(F::Ref(dirs))]);
+    b[..4].copy_from_slice(&(root as i32).to_le_bytes());
+    rman_data(&b, b.len())
+}
+
+/// Same as [manifest_tables_data()], and open the manifest
+pub fn manifest_tables(build: impl FnOnce(&mut Vec<u8>, usize) -> [usize; 4]) -> Rman {
+    Rman::read_checked(manifest_tables_data(build).as_slice()).unwrap()
+}
+
+/// Build manifest data with given directories and files
+///
+/// Files are empty, without chunks.
+pub fn manifest_data(dirs: &[ManifestItem], files: &[ManifestItem]) -> Vec<u8> {
+    manifest_tables_data(|b, empty| {
+        let dirs: Vec<usize> = dirs.iter().map(|(id, parent, name)| {
+            let name = string(b, name);
+            obj(b, &[Some(F::U64(*id)), parent.map(F::U64), Some(F::Ref(name))])
+        }).collect();
+        let files: Vec<usize> = files.iter().map(|(id, dir, name)| {
+            let name = string(b, name);
+            obj(b, &[Some(F::U64(*id)), dir.map(F::U64), Some(F::U64(0)), Some(F::Ref(name)),
+                     None, None, None, Some(F::Ref(empty))])
+        }).collect();
+        [empty, empty, table(b, &files), table(b, &dirs)]
+    })
+}
+
+/// Same as [manifest_data()], but with string names, and open the manifest
+pub fn manifest(dirs: &[(u64, Option<u64>, &str)], files: &[(u64, Option<u64>, &str)]) -> Rman {
+    fn as_bytes<'a>(items: &[(u64, Option<u64>, &'a str)]) -> Vec<ManifestItem<'a>> {
+        items.iter().map(|(id, parent, name)| (*id, *parent, name.as_bytes())).collect()
+    }
+    let data = manifest_data(&as_bytes(dirs), &as_bytes(files));
+    Rman::read_checked(data.as_slice()).unwrap()
+}
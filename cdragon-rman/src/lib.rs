@@ -6,19 +6,26 @@
 //! When an instance is created, only the headers are read. Tables are then iterated on using the
 //! `iter_*()` methods.
 //!
+//! Commonly used types are available from the [prelude].
+//!
 //! # Example: list files
 //! ```no_run
-//! # use cdragon_rman::Rman;
+//! use cdragon_rman::prelude::*;
 //!
 //! let rman = Rman::open("example.manifest").expect("failed to open or read headers");
 //! // Directories are listed separately from files and their basenames
-//! let dir_paths = rman.dir_paths();
+//! let dir_paths = rman.dir_paths().expect("invalid directory entries");
 //! // Iterate on files, print the full paths
 //! for file in rman.iter_files() {
-//!     println!("{}", file.path(&dir_paths));
+//!     let file = file.expect("invalid file entry");
+//!     println!("{}", file.path(&dir_paths).expect("unknown file directory"));
 //! }
 //! ```
 
+// `ReadArray::read_array()` collides with unstable `Read::read_array()`
+#![allow(unstable_name_collisions)]
+
+use std::borrow::Cow;
 use std::io::{Read, BufReader};
 use std::path::Path;
 use std::convert::TryInto;
@@ -30,9 +37,29 @@ use nom::{
 };
 use thiserror::Error;
 use cdragon_utils::{
-    parsing::{ParseError, ReadArray},
+    parsing::{ByteCursor, ParseError, ReadArray},
     parse_buf,
 };
+pub use cdragon_utils::pattern::PathPattern;
+
+#[cfg(test)]
+mod fixtures;
+
+/// Commonly used RMAN types
+pub mod prelude {
+    pub use super::{
+        BundleEntry,
+        ChunkEntry,
+        DirectoryEntry,
+        FileBundleRanges,
+        FileChunkRange,
+        FileEntry,
+        FileFlags,
+        Rman,
+        RmanError,
+        RmanFileIndex,
+    };
+}
 
 /// Result type for RMAN errors
 type Result<T, E = RmanError> = std::result::Result<T, E>;
@@ -46,8 +73,9 @@ type Result<T, E = RmanError> = std::result::Result<T, E>;
 ///
 /// # Note on errors
 ///
-/// Most reading methods may panic on invalid offsets or invalid data.
-/// This is especially true for the `iter_*()` methods.
+/// Reading methods return an error on invalid offsets or invalid data.
+/// The `iter_*()` methods yield an error for entries that cannot be parsed.
+/// Error messages indicate the table being parsed and the invalid offset.
 pub struct Rman {
     /// RMAN version (`(major, minor)`)
     ///
@@ -60,10 +88,78 @@ pub struct Rman {
     /// Typically, it matches the manifest filename.
     pub manifest_id: u64,
     body: Vec<u8>,
+    header_len: i32,
     offset_bundles: i32,
     offset_flags: i32,
     offset_files: i32,
     offset_directories: i32,
+    offset_keys: Option<i32>,
+    offset_params: Option<i32>,
+}
+
+/// Fields parsed from RMAN header
+struct RmanHeader {
+    version: (u8, u8),
+    flags: u16,
+    manifest_id: u64,
+    zstd_length: u32,
+    body_length: u32,
+}
+
+/// Offsets parsed from RMAN body header
+struct RmanBodyHeader {
+    header_len: i32,
+    offset_bundles: i32,
+    offset_flags: i32,
+    offset_files: i32,
+    offset_directories: i32,
+    offset_keys: Option<i32>,
+    offset_params: Option<i32>,
+}
+
+/// Parameters from the trailing RMAN tables
+///
+/// These tables are not present in older files.
+/// Their semantics are not fully known, values are kept raw. Raw table data can be retrieved
+/// with [Rman::unknown_table()].
+#[derive(Clone, Debug, Default)]
+pub struct RmanParams {
+    /// Number of entries in the keys table, if present
+    ///
+    /// The content of entries is unknown; the table is usually empty.
+    pub keys_count: Option<u32>,
+    /// Chunking parameters, one item per entry of the parameters table
+    pub chunking: Vec<RmanChunkingParams>,
+}
+
+/// Chunking parameters, from the RMAN parameters table
+///
+/// Absent fields are set to 0.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RmanChunkingParams {
+    /// Unknown field
+    pub unk0: u16,
+    /// Hash type used for chunk IDs (raw value)
+    pub hash_type: u8,
+    /// Minimum chunk size (raw value)
+    pub min_chunk_size: u32,
+    /// Chunk size (raw value)
+    pub chunk_size: u32,
+    /// Maximum chunk size (raw value)
+    pub max_chunk_size: u32,
+}
+
+/// Low-level information on the RMAN body, for debugging
+#[derive(Debug)]
+pub struct RmanDebugInfo<'a> {
+    /// Length of the decompressed body
+    pub body_len: usize,
+    /// Raw body header length (first value of the body)
+    pub header_len: i32,
+    /// Unknown header bytes, skipped when parsing
+    pub header: &'a [u8],
+    /// Absolute body offsets of the tables (bundles, flags, files, directories, keys, params)
+    pub table_offsets: [Option<i32>; 6],
 }
 
 /// Map directory ID to full paths
@@ -85,6 +181,11 @@ pub struct BundleChunk {
 /// Map chunk IDs to their data
 pub type BundleChunks = HashMap<u64, BundleChunk>;
 
+/// Map of files using each bundle
+///
+/// For each entry in the map, key is the bundle ID and value the IDs of the files using it.
+pub type BundleFiles = HashMap<u64, Vec<u64>>;
+
 impl Rman {
     /// Open an RMAN file from path
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
@@ -95,25 +196,136 @@ impl Rman {
 
     /// Read an RMAN file, check header and decompress body
     ///
+    /// Body encoding is detected from its first bytes: it can be compressed with zstd (the
+    /// regular case) or gzip, or not compressed at all.
+    ///
     /// Body is assumed to have the expected size. It is not checked against header length values.
-    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
-        let (version, flags, manifest_id, body_length) = {
+    /// Use [Self::read_checked()] to check it.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cdragon_rman::Rman;
+    /// let file = std::fs::File::open("example.manifest").unwrap();
+    /// let rman = Rman::read(std::io::BufReader::new(file)).unwrap();
+    /// println!("manifest {:016X}", rman.manifest_id);
+    /// ```
+    pub fn read<R: Read>(reader: R) -> Result<Self> {
+        Self::read_impl(reader, false)
+    }
+
+    /// Same as [Self::read()], but check the decompressed body size
+    ///
+    /// Return a parsing error if the decompressed body length does not match the length from the
+    /// header.
+    ///
+    /// # Invalid data
+    ///
+    /// Reading returns an error, and never panics, on invalid body data.
+    /// Iterating on entries of an invalid body yields an error describing it, and never panics
+    /// either.
+    ///
+    /// # Strings
+    ///
+    /// Names of flags, files and directories are checked to be valid UTF-8.
+    /// [Self::read()] does not check them: invalid sequences are replaced by `U+FFFD` when
+    /// entries are iterated on.
+    pub fn read_checked<R: Read>(reader: R) -> Result<Self> {
+        Self::read_impl(reader, true)
+    }
+
+    fn read_impl<R: Read>(mut reader: R, checked: bool) -> Result<Self> {
+        let RmanHeader { version, flags, manifest_id, zstd_length, body_length } = {
             let r = reader.by_ref();
             Self::parse_header(r)?
         };
-        let body = zstd::stream::decode_all(reader.take(body_length as u64))?;
-        let offsets = Self::parse_body_header(&body);
-        Ok(Self {
-            version, flags, manifest_id, body,
-            offset_bundles: offsets.0,
-            offset_flags: offsets.1,
-            offset_files: offsets.2,
-            offset_directories: offsets.3,
-        })
+        let body = {
+            // Don't trust the header length to allocate: let the buffer grow with read data
+            let mut data = Vec::new();
+            reader.take(zstd_length as u64).read_to_end(&mut data)?;
+            if data.len() < zstd_length as usize {
+                return Err(ParseError::NotEnoughData.into());
+            }
+            Self::decode_body(data)?
+        };
+        if checked {
+            if body.len() < body_length as usize {
+                return Err(ParseError::NotEnoughData.into());
+            } else if body.len() > body_length as usize {
+                return Err(ParseError::TooMuchData.into());
+            }
+        }
+        let RmanBodyHeader {
+            header_len, offset_bundles, offset_flags, offset_files, offset_directories,
+            offset_keys, offset_params,
+        } = Self::parse_body_header(&body)?;
+        let this = Self {
+            version, flags, manifest_id, body, header_len,
+            offset_bundles, offset_flags, offset_files, offset_directories,
+            offset_keys, offset_params,
+        };
+        if checked {
+            this.check_strings()?;
+        }
+        Ok(this)
+    }
+
+    /// Check that strings of flags, files and directories are valid UTF-8
+    ///
+    /// Tables are walked without assuming the body is valid: errors are returned, there is no
+    /// panic.
+    fn check_strings(&self) -> Result<()> {
+        // Iterate on the entries of a table, return a cursor on each entry
+        let for_each_entry = |offset: i32, context: &'static str, f: &dyn Fn(BodyCursor) -> Result<()>| -> Result<()> {
+            let mut cursor = BodyCursor::new(&self.body, offset, context);
+            let count = cursor.read_u32()?;
+            for _ in 0..count {
+                f(cursor.subcursor()?)?;
+            }
+            Ok(())
+        };
+
+        for_each_entry(self.offset_flags, "flags", &|mut cursor| {
+            // Skip field offsets and flag ID, assume fixed ones (see `parse_flag_entry()`)
+            cursor.skip(4 + 4)?;
+            cursor.subcursor()?.read_checked_str()?;
+            Ok(())
+        })?;
+        for_each_entry(self.offset_files, "files", &|cursor| {
+            let cursor = cursor.fields_cursor()?;
+            cursor.get_checked_str(3)?;  // name
+            cursor.get_checked_str(9)?;  // link
+            Ok(())
+        })?;
+        for_each_entry(self.offset_directories, "directories", &|cursor| {
+            cursor.fields_cursor()?.get_checked_str(2)?;  // name
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Decode body data, detect compression from magic bytes
+    fn decode_body(data: Vec<u8>) -> Result<Vec<u8>> {
+        const ZSTD_MAGIC: &[u8] = b"\x28\xb5\x2f\xfd";
+        const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+
+        if data.starts_with(ZSTD_MAGIC) {
+            Ok(zstd::stream::decode_all(data.as_slice())?)
+        } else if data.starts_with(GZIP_MAGIC) {
+            let mut body = Vec::new();
+            flate2::read::GzDecoder::new(data.as_slice()).read_to_end(&mut body)?;
+            Ok(body)
+        } else {
+            // Uncompressed body starts with the offset to the body header table
+            let offset = data.get(..4).map(|b| i32::from_le_bytes(b.try_into().unwrap()));
+            match offset {
+                Some(offset) if offset >= 4 && (offset as usize) < data.len() => Ok(data),
+                _ => Err(RmanError::UnknownBodyEncoding),
+            }
+        }
     }
 
     /// Parse header, advance to the beginning of the body
-    fn parse_header<R: Read>(mut reader: R) -> Result<((u8, u8), u16, u64, u32)> {
+    fn parse_header<R: Read>(mut reader: R) -> Result<RmanHeader> {
         const MAGIC_VERSION_LEN: usize = 4 + 2;
         const FIELDS_LEN: usize = 2 + 4 + 4 + 8 + 4;
         const HEADER_LEN: usize = MAGIC_VERSION_LEN + FIELDS_LEN;
@@ -127,9 +339,9 @@ impl Rman {
             (major, minor)
         };
 
-        let (flags, manifest_id, zstd_length) = {
+        let (flags, manifest_id, zstd_length, body_length) = {
             let buf = reader.read_array::<FIELDS_LEN>()?;
-            let (flags, offset, zstd_length, manifest_id, _body_length) =
+            let (flags, offset, zstd_length, manifest_id, body_length) =
                 parse_buf!(buf, tuple((le_u16, le_u32, le_u32, le_u64, le_u32)));
             if flags & (1 << 9) == 0 {
                 return Err(RmanError::UnsupportedFlags(flags));
@@ -140,72 +352,305 @@ impl Rman {
                 let skipped_len = offset - HEADER_LEN as u32;
                 std::io::copy(&mut reader.take(skipped_len as u64), &mut std::io::sink())?;
             }
-            (flags, manifest_id, zstd_length)
+            (flags, manifest_id, zstd_length, body_length)
         };
 
-        Ok((version, flags, manifest_id, zstd_length))
+        Ok(RmanHeader { version, flags, manifest_id, zstd_length, body_length })
     }
 
     /// Parse body header
-    fn parse_body_header(body: &[u8]) -> (i32, i32, i32, i32) {
-        let mut cursor = BodyCursor::new(body, 0);
+    ///
+    /// The body header is an entry whose fields are the offsets to the tables.
+    /// The last two tables (keys and parameters) are absent from older files.
+    fn parse_body_header(body: &[u8]) -> Result<RmanBodyHeader> {
+        // Offsets are handled as `i32`
+        if i32::try_from(body.len()).is_err() {
+            return Err(ParseError::TooMuchData.into());
+        }
+        let mut cursor = BodyCursor::new(body, 0, "body header");
+        let header_len = cursor.peek_i32()?;
+        // Note: unknown header bytes are skipped by the fields cursor
+        let cursor = cursor.subcursor()?.fields_cursor()?;
+        let get_table_offset = |field| -> Result<Option<i32>> {
+            Ok(cursor.get_offset_cursor(field)?.map(|c| c.offset()))
+        };
+        let get_required_table_offset = |field, name| get_table_offset(field)?.ok_or(RmanError::MissingTable(name));
+
+        Ok(RmanBodyHeader {
+            header_len,
+            offset_bundles: get_required_table_offset(0, "bundles")?,
+            offset_flags: get_required_table_offset(1, "flags")?,
+            offset_files: get_required_table_offset(2, "files")?,
+            offset_directories: get_required_table_offset(3, "directories")?,
+            offset_keys: get_table_offset(4)?,
+            offset_params: get_table_offset(5)?,
+        })
+    }
+
+    /// Get parameters from the trailing tables
+    ///
+    /// Return default (empty) values for tables absent from the file.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cdragon_rman::Rman;
+    /// let rman = Rman::open("example.manifest").unwrap();
+    /// for params in rman.params().unwrap().chunking {
+    ///     println!("chunk size: {} to {}", params.min_chunk_size, params.max_chunk_size);
+    /// }
+    /// ```
+    pub fn params(&self) -> Result<RmanParams> {
+        let keys_count = self.offset_keys
+            .map(|offset| BodyCursor::new(&self.body, offset, "keys table").peek_u32())
+            .transpose()?;
+        let chunking = match self.offset_params {
+            Some(offset) => {
+                let cursor = BodyCursor::new(&self.body, offset, "params table");
+                OffsetTableIter::new(cursor, parse_chunking_params_entry).collect::<Result<_>>()?
+            }
+            None => Vec::new(),
+        };
+        Ok(RmanParams { keys_count, chunking })
+    }
+
+    /// Get the raw data of a table whose format is not (fully) known
+    ///
+    /// **Experimental:** this method is intended for reverse-engineering, returned data and
+    /// indexes may change in future versions.
+    ///
+    /// Index `0` is the keys table, index `1` is the parameters table (see [Self::params()]).
+    /// Return `None` if the table is not present.
+    ///
+    /// Tables don't store their length. Returned data spans from the table offset up to the
+    /// next table (or the end of the body). It starts with the table item count and offsets, and
+    /// may include data of items from other tables. Table offsets are available from
+    /// [Self::debug_info()].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cdragon_rman::Rman;
+    /// let rman = Rman::open("example.manifest").unwrap();
+    /// if let Some(data) = rman.unknown_table(0) {
+    ///     println!("keys table: {:02x?}", data);
+    /// }
+    /// ```
+    pub fn unknown_table(&self, index: usize) -> Option<&[u8]> {
+        let offset = [self.offset_keys, self.offset_params].get(index).copied().flatten()?;
+        let end = self.table_offsets().into_iter().flatten()
+            .filter(|&o| o > offset)
+            .min()
+            .map_or(self.body.len(), |o| o as usize);
+        self.body.get(offset as usize .. end)
+    }
 
-        // Unknown header, skip it
-        let header_len = cursor.read_i32();
-        cursor.skip(header_len);
+    /// Get low-level information on the body, for debugging
+    pub fn debug_info(&self) -> RmanDebugInfo<'_> {
+        RmanDebugInfo {
+            body_len: self.body.len(),
+            header_len: self.header_len,
+            header: self.body.get(4 .. self.header_len as usize).unwrap_or_default(),
+            table_offsets: self.table_offsets(),
+        }
+    }
 
-        (
-            cursor.read_offset(),
-            cursor.read_offset(),
-            cursor.read_offset(),
-            cursor.read_offset(),
-            // Note: the last two tables are unknown
-        )
+    /// Get the offsets of all tables (bundles, flags, files, directories, keys, params)
+    fn table_offsets(&self) -> [Option<i32>; 6] {
+        [
+            Some(self.offset_bundles),
+            Some(self.offset_flags),
+            Some(self.offset_files),
+            Some(self.offset_directories),
+            self.offset_keys,
+            self.offset_params,
+        ]
     }
 
     /// Iterate on flags (locales, platforms)
-    pub fn iter_flags(&self) -> OffsetTableIter<'_, FileFlagEntry> {
-        let cursor = BodyCursor::new(&self.body, self.offset_flags);
+    pub fn iter_flags(&self) -> OffsetTableIter<'_, FileFlagEntry<'_>> {
+        let cursor = BodyCursor::new(&self.body, self.offset_flags, "flag table");
         OffsetTableIter::new(cursor, parse_flag_entry)
     }
 
     /// Iterate on bundles
     pub fn iter_bundles(&self) -> OffsetTableIter<'_, BundleEntry<'_>> {
-        let cursor = BodyCursor::new(&self.body, self.offset_bundles);
+        let cursor = BodyCursor::new(&self.body, self.offset_bundles, "bundle table");
         OffsetTableIter::new(cursor, parse_bundle_entry)
     }
 
     /// Iterate on files
     pub fn iter_files(&self) -> OffsetTableIter<'_, FileEntry<'_>> {
-        let cursor = BodyCursor::new(&self.body, self.offset_files);
+        let cursor = BodyCursor::new(&self.body, self.offset_files, "file table");
         OffsetTableIter::new(cursor, parse_file_entry)
     }
 
     /// Iterate on directories (raw entries)
     pub fn iter_directories(&self) -> OffsetTableIter<'_, DirectoryEntry<'_>> {
-        let cursor = BodyCursor::new(&self.body, self.offset_directories);
+        let cursor = BodyCursor::new(&self.body, self.offset_directories, "directory table");
         OffsetTableIter::new(cursor, parse_directory_entry)
     }
 
     /// Build map of directory paths
-    pub fn dir_paths(&self) -> DirPaths {
-        let directories: Vec<DirectoryEntry> = self.iter_directories().collect();
+    pub fn dir_paths(&self) -> Result<DirPaths> {
+        let directories: Vec<DirectoryEntry> = self.iter_directories().collect::<Result<_>>()?;
         DirectoryEntry::build_path_map(&directories)
     }
 
+    /// Build a lookup of flags, by ID
+    ///
+    /// Use it with [FileFlagSet::resolve()] to get flag names of files.
+    pub fn flags(&self) -> Result<FileFlags> {
+        self.iter_flags().map(|e| e.map(|e| (e.id, e.flag.into_owned()))).collect()
+    }
+
+    /// Get the names of the flags set on a file (e.g. locales)
+    ///
+    /// This is a shortcut for [FileFlagSet::resolve()] over [Self::flags()]: names are sorted by
+    /// flag ID, and IDs missing from the flag table are skipped.
+    /// Prefer using them directly to get names of multiple files, the flag table is parsed on
+    /// each call.
+    /// Return an empty list if the file has no flags.
+    pub fn file_flag_names(&self, entry: &FileEntry) -> Result<Vec<String>> {
+        match entry.flags {
+            Some(ref set) => {
+                let flags = self.flags()?;
+                Ok(set.resolve(&flags).map(str::to_owned).collect())
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
     /// Build a map of chunks, with bundle information
-    pub fn bundle_chunks(&self) -> BundleChunks {
-        self.iter_bundles().flat_map(|bundle| {
-            let bundle_id = bundle.id;
-            bundle.iter_chunks().map(move |chunk| {
-                (chunk.id, BundleChunk {
-                    bundle_id,
+    pub fn bundle_chunks(&self) -> Result<BundleChunks> {
+        let mut bundle_chunks = BundleChunks::new();
+        for bundle in self.iter_bundles() {
+            let bundle = bundle?;
+            for chunk in bundle.iter_chunks() {
+                let chunk = chunk?;
+                bundle_chunks.insert(chunk.id, BundleChunk {
+                    bundle_id: bundle.id,
                     bundle_offset: chunk.bundle_offset,
                     bundle_size: chunk.bundle_size,
                     target_size: chunk.target_size,
-                })
-            })
-        }).collect()
+                });
+            }
+        }
+        Ok(bundle_chunks)
+    }
+
+    /// Build a map of files using each bundle
+    ///
+    /// This is the reverse of [FileEntry::bundle_chunks()].
+    /// Files are listed once per bundle, in manifest order.
+    ///
+    /// Return an error if a file uses a chunk that is not in `bundle_chunks`.
+    pub fn bundle_files(&self, bundle_chunks: &BundleChunks) -> Result<BundleFiles> {
+        let mut bundle_files = BundleFiles::new();
+        for file in self.iter_files() {
+            let file = file?;
+            for chunk_id in file.iter_chunks() {
+                let chunk_id = chunk_id?;
+                let chunk = bundle_chunks.get(&chunk_id).ok_or(RmanError::UnknownChunk(chunk_id))?;
+                let file_ids = bundle_files.entry(chunk.bundle_id).or_default();
+                if file_ids.last() != Some(&file.id) {
+                    file_ids.push(file.id);
+                }
+            }
+        }
+        Ok(bundle_files)
+    }
+
+    /// Build an index of files, to look them up by path
+    ///
+    /// Use it instead of [Self::iter_files()] to look up multiple files.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cdragon_rman::{Rman, PathPattern};
+    /// let rman = Rman::open("example.manifest").unwrap();
+    /// let index = rman.file_index().unwrap();
+    /// if let Some(file) = index.get("DATA/FINAL/Champions/Ahri.wad.client") {
+    ///     println!("{} bytes", file.filesize);
+    /// }
+    /// for file in index.glob(&PathPattern::new("DATA/FINAL/**.wad.client")) {
+    ///     println!("{}", file.name);
+    /// }
+    /// ```
+    pub fn file_index(&self) -> Result<RmanFileIndex<'_>> {
+        RmanFileIndex::new(self, false)
+    }
+
+    /// Same as [Self::file_index()], but ignore ASCII case of paths
+    pub fn file_index_ignore_case(&self) -> Result<RmanFileIndex<'_>> {
+        RmanFileIndex::new(self, true)
+    }
+}
+
+
+/// Index of RMAN files, by full path
+///
+/// This struct is created by [Rman::file_index()].
+/// Files are kept in manifest order. If multiple files have the same path, lookups return the
+/// first one.
+pub struct RmanFileIndex<'a> {
+    /// Files with their full path
+    files: Vec<(String, FileEntry<'a>)>,
+    /// Index in `files`, by path (lowercased when ignoring case)
+    by_path: HashMap<String, usize>,
+    ignore_case: bool,
+}
+
+impl<'a> RmanFileIndex<'a> {
+    fn new(rman: &'a Rman, ignore_case: bool) -> Result<Self> {
+        let dir_paths = rman.dir_paths()?;
+        let files: Vec<(String, FileEntry)> = rman.iter_files()
+            .map(|file| file.and_then(|file| Ok((file.path(&dir_paths)?, file))))
+            .collect::<Result<_>>()?;
+        let mut by_path = HashMap::with_capacity(files.len());
+        for (i, (path, _)) in files.iter().enumerate() {
+            let key = if ignore_case { path.to_ascii_lowercase() } else { path.clone() };
+            by_path.entry(key).or_insert(i);
+        }
+        Ok(Self { files, by_path, ignore_case })
+    }
+
+    /// Get the number of indexed files
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Return `true` if the index has no files
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Get a file from its full path
+    pub fn get(&self, path: &str) -> Option<&FileEntry<'a>> {
+        let index = if self.ignore_case {
+            self.by_path.get(&path.to_ascii_lowercase())
+        } else {
+            self.by_path.get(path)
+        };
+        index.map(|i| &self.files[*i].1)
+    }
+
+    /// Iterate on files, with their full path
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &FileEntry<'a>)> {
+        self.files.iter().map(|(path, file)| (path.as_str(), file))
+    }
+
+    /// Iterate on files whose full path matches a pattern, with their full path
+    ///
+    /// If the index ignores case, the pattern also ignores case.
+    pub fn iter_matching<'s>(&'s self, pattern: &PathPattern) -> impl Iterator<Item=(&'s str, &'s FileEntry<'a>)> {
+        let pattern = if self.ignore_case { pattern.clone().case_insensitive(true) } else { pattern.clone() };
+        self.iter().filter(move |(path, _)| pattern.is_match(path))
+    }
+
+    /// Get files whose full path matches a pattern
+    ///
+    /// See [Self::iter_matching()].
+    pub fn glob(&self, pattern: &PathPattern) -> Vec<&FileEntry<'a>> {
+        self.iter_matching(pattern).map(|(_, file)| file).collect()
     }
 }
 
@@ -213,85 +658,144 @@ impl Rman {
 /// Parse data from RMAN body
 ///
 /// RMAN parsing uses a lot of negative indexes. Regular slices don't allow to go backwards.
-/// Implement our own parsing helpers for cleaner and easier parsing.
-/// There is no error handling: parsers panic if there is not enough data.
+/// Reads are delegated to a [ByteCursor], which checks bounds.
+/// Methods return an error if there is not enough data, or on invalid offsets.
 ///
 /// # Implementation note
 ///
-/// Body size is guaranteed to fits in a `u32`, and should always fit in a `i32`.
+/// Body size is checked to fit in a `i32` when the body header is parsed.
 /// Use `i32` for all offsets to simplify use and avoid numerous casts.
+/// Offsets computed from values read in the body use `i64`, then are checked against the body.
 ///
 /// # Errors
 ///
-/// Parsing methods will panic on attempts to read outside the buffer.
+/// Errors include the kind of table being parsed and the invalid offset.
 #[derive(Clone, Debug)]
 struct BodyCursor<'a> {
-    body: &'a [u8],
-    offset: i32,
+    cursor: ByteCursor<'a>,
+    /// Kind of table being parsed, for error messages
+    context: &'static str,
 }
 
 impl<'a> BodyCursor<'a> {
-    fn new(body: &'a [u8], offset: i32) -> Self {
-        Self { body, offset }
+    fn new(body: &'a [u8], offset: i32, context: &'static str) -> Self {
+        // Negative offsets are invalid, map them out of the body
+        let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+        Self { cursor: ByteCursor::new(body, offset), context }
     }
 
     fn offset(&self) -> i32 {
-        self.offset
+        self.cursor.offset() as i32
+    }
+
+    /// Build an error for an access out of the body
+    fn out_of_body(&self, what: &'static str, offset: i64) -> RmanError {
+        RmanError::OutOfBody { context: self.context, what, offset, len: self.cursor.data().len() }
+    }
+
+    /// Move the cursor to an absolute offset, check it is in the body
+    fn checked_seek(&mut self, offset: i64) -> Result<()> {
+        usize::try_from(offset).ok()
+            .and_then(|o| self.cursor.seek(o).ok())
+            .ok_or_else(|| self.out_of_body("offset", offset))
     }
 
-    fn read_slice(&mut self, n: i32) -> &'a [u8] {
-        let slice = &self.body[self.offset as usize .. (self.offset + n) as usize];
-        self.offset += n;
-        slice
+    /// Read from the underlying cursor, return an error with context on failure
+    fn read<T>(&mut self, read: impl FnOnce(&mut ByteCursor<'a>) -> Result<T, ParseError>) -> Result<T> {
+        let offset = self.cursor.offset() as i64;
+        read(&mut self.cursor).map_err(|_| self.out_of_body("read at offset", offset))
     }
 
-    fn peek_slice(&self, n: i32) -> &'a [u8] {
-        &self.body[self.offset as usize .. (self.offset + n) as usize]
+    /// Peek from the underlying cursor, return an error with context on failure
+    fn peek<T>(&self, peek: impl FnOnce(&ByteCursor<'a>) -> Result<T, ParseError>) -> Result<T> {
+        peek(&self.cursor).map_err(|_| self.out_of_body("read at offset", self.cursor.offset() as i64))
     }
 
-    fn fields_cursor(mut self) -> BodyFieldsCursor<'a> {
-        let entry_offset = self.offset();
-        let fields_offset = entry_offset - self.read_i32() + 2 * 2;  // Note: skip the 2 header fields
-        BodyFieldsCursor { body: self.body, fields_offset, entry_offset }
+    /// Read a string, prefixed by its length
+    ///
+    /// Invalid UTF-8 sequences are replaced by `U+FFFD`.
+    fn read_str(&mut self) -> Result<Cow<'a, str>> {
+        Ok(String::from_utf8_lossy(self.read_str_bytes()?))
+    }
+
+    /// Read string bytes, prefixed by their length
+    fn read_str_bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.read(|c| c.read_i32())?;
+        // Negative lengths are invalid, make the read fail
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+        self.read(|c| c.read_slice(len))
+    }
+
+    /// Same as [Self::read_str()], but return an error if the string is not valid UTF-8
+    fn read_checked_str(&mut self) -> Result<&'a str> {
+        let offset = self.cursor.offset() as i64;
+        let bytes = self.read_str_bytes()?;
+        std::str::from_utf8(bytes).map_err(|_| RmanError::InvalidString { context: self.context, offset })
+    }
+
+    /// Get a cursor on the fields of the entry at the current offset
+    ///
+    /// Check that the field list is in the body. Field values are checked when read.
+    fn fields_cursor(mut self) -> Result<BodyFieldsCursor<'a>> {
+        let entry_offset = self.cursor.offset() as i64;
+        let list_offset = entry_offset - self.read(|c| c.read_i32())? as i64;
+        self.checked_seek(list_offset)?;
+        // Field list size is the first header field, and includes the 2 header fields
+        let list_size = self.peek(ByteCursor::peek_u16)? as i64;
+        let list_end = list_offset + list_size.max(2 * 2);
+        if list_end > self.cursor.data().len() as i64 {
+            return Err(self.out_of_body("field list end", list_end));
+        }
+        let fields_offset = list_offset + 2 * 2;  // Note: skip the 2 header fields
+        Ok(BodyFieldsCursor { body: self.cursor.data(), fields_offset, entry_offset, context: self.context })
     }
 
     /// Read an offset and return a new cursor pointing to it
-    fn subcursor(&mut self) -> Self {
-        Self::new(self.body, self.read_offset())
+    ///
+    /// Return an error if the offset is out of the body.
+    fn subcursor(&mut self) -> Result<Self> {
+        let offset = self.read_offset()?;
+        let mut cursor = self.clone();
+        cursor.checked_seek(offset)?;
+        Ok(cursor)
     }
 
     /// Skip `n` bytes, rewind of negative
-    fn skip(&mut self, n: i32) {
-        self.offset += n;
+    fn skip(&mut self, n: i32) -> Result<()> {
+        self.read(|c| c.skip(n as isize))
     }
 
-    fn read_u8(&mut self) -> u8 {
-        let v = self.body[self.offset as usize];
-        self.offset += 1;
-        v
+    fn read_u8(&mut self) -> Result<u8> {
+        self.read(|c| c.read_u8())
     }
 
-    fn read_i32(&mut self) -> i32 {
-        i32::from_le_bytes(self.read_slice(4).try_into().unwrap())
+    fn read_u32(&mut self) -> Result<u32> {
+        self.read(|c| c.read_u32())
     }
 
-    fn read_u32(&mut self) -> u32 {
-        u32::from_le_bytes(self.read_slice(4).try_into().unwrap())
+    fn read_u64(&mut self) -> Result<u64> {
+        self.read(|c| c.read_u64())
     }
 
-    fn read_u64(&mut self) -> u64 {
-        u64::from_le_bytes(self.read_slice(8).try_into().unwrap())
+    /// Read an offset value, return an absolute body offset
+    ///
+    /// The returned offset is not checked, it could be out of the body.
+    fn read_offset(&mut self) -> Result<i64> {
+        let base = self.cursor.offset() as i64;
+        Ok(base + self.read(|c| c.read_i32())? as i64)
     }
 
-    /// Read an offset value, return an absolute body offset
-    fn read_offset(&mut self) -> i32 {
-        let base_offset = self.offset;
-        let offset = self.read_i32();
-        base_offset + offset
+    fn peek_i32(&self) -> Result<i32> {
+        self.peek(ByteCursor::peek_i32)
     }
 
-    fn peek_u32(&self) -> u32 {
-        u32::from_le_bytes(self.peek_slice(4).try_into().unwrap())
+    fn peek_u32(&self) -> Result<u32> {
+        self.peek(ByteCursor::peek_u32)
+    }
+
+    /// Get the number of bytes remaining after the cursor
+    fn remaining(&self) -> usize {
+        self.cursor.remaining()
     }
 }
 
@@ -302,94 +806,152 @@ impl<'a> BodyCursor<'a> {
 /// - the size of the entry (which is the end of the fields)
 struct BodyFieldsCursor<'a> {
     body: &'a [u8],
-    fields_offset: i32,
-    entry_offset: i32,
+    fields_offset: i64,
+    entry_offset: i64,
+    context: &'static str,
 }
 
 impl<'a> BodyFieldsCursor<'a> {
-    fn field_slice(&self, field: u8, n: i32) -> Option<&'a [u8]> {
-        match self.field_offset(field) {
+    /// Read a value at given body offset
+    fn peek<T>(&self, offset: i64, peek: impl FnOnce(&ByteCursor<'a>) -> Result<T, ParseError>) -> Result<T> {
+        let cursor = ByteCursor::new(self.body, usize::try_from(offset).unwrap_or(usize::MAX));
+        peek(&cursor).map_err(|_| RmanError::OutOfBody {
+            context: self.context, what: "field value offset", offset, len: self.body.len(),
+        })
+    }
+
+    /// Build an error for a missing required field
+    fn missing_field(&self, field: &'static str) -> RmanError {
+        RmanError::MissingField { context: self.context, field, offset: self.entry_offset }
+    }
+
+    /// Get the body offset of a field value, `None` if the field is absent
+    fn value_offset(&self, field: u8) -> Result<Option<i64>> {
+        Ok(match self.field_offset(field)? {
             0 => None,
-            o => {
-                let offset = self.entry_offset + o;
-                Some(&self.body[offset as usize .. (offset + n) as usize])
-            }
-        }
+            o => Some(self.entry_offset + o),
+        })
     }
 
     /// Get field offset value
-    fn field_offset(&self, field: u8) -> i32 {
-        let offset = (self.fields_offset + 2 * field as i32) as usize;
-        let slice = &self.body[offset .. offset + 2];
-        u16::from_le_bytes(slice.try_into().unwrap()) as i32
+    ///
+    /// Fields beyond the end of the field list are absent (offset is 0).
+    fn field_offset(&self, field: u8) -> Result<i64> {
+        if field as i64 >= self.fields_count()? {
+            return Ok(0);
+        }
+        Ok(self.peek(self.fields_offset + 2 * field as i64, ByteCursor::peek_u16)? as i64)
     }
 
-    fn get_i32(&self, field: u8) -> Option<i32> {
-        self.field_slice(field, 4).map(|s| i32::from_le_bytes(s.try_into().unwrap()))
+    /// Get the number of fields in the field list
+    fn fields_count(&self) -> Result<i64> {
+        // Field list size is the first header field, and includes the 2 header fields
+        let size = self.peek(self.fields_offset - 2 * 2, ByteCursor::peek_u16)?;
+        Ok((size as i64 - 2 * 2) / 2)
     }
 
-    fn get_u32(&self, field: u8) -> Option<u32> {
-        self.field_slice(field, 4).map(|s| u32::from_le_bytes(s.try_into().unwrap()))
+    /// Read a field value, `None` if the field is absent
+    fn get<T>(&self, field: u8, peek: impl FnOnce(&ByteCursor<'a>) -> Result<T, ParseError>) -> Result<Option<T>> {
+        self.value_offset(field)?.map(|o| self.peek(o, peek)).transpose()
     }
 
-    fn get_u64(&self, field: u8) -> Option<u64> {
-        self.field_slice(field, 8).map(|s| u64::from_le_bytes(s.try_into().unwrap()))
+    fn get_u8(&self, field: u8) -> Result<Option<u8>> {
+        self.get(field, ByteCursor::peek_u8)
+    }
+
+    fn get_u16(&self, field: u8) -> Result<Option<u16>> {
+        self.get(field, ByteCursor::peek_u16)
+    }
+
+    fn get_u32(&self, field: u8) -> Result<Option<u32>> {
+        self.get(field, ByteCursor::peek_u32)
+    }
+
+    fn get_u64(&self, field: u8) -> Result<Option<u64>> {
+        self.get(field, ByteCursor::peek_u64)
     }
 
     /// Read an offset value, return a body cursor at this offset
-    fn get_offset_cursor(&self, field: u8) -> Option<BodyCursor<'a>> {
-        self.get_i32(field).map(|o| {
-            let offset = self.entry_offset + o + self.field_offset(field);
-            BodyCursor::new(self.body, offset)
-        })
+    ///
+    /// Return an error if the offset is out of the body.
+    fn get_offset_cursor(&self, field: u8) -> Result<Option<BodyCursor<'a>>> {
+        let Some(value_offset) = self.value_offset(field)? else {
+            return Ok(None);
+        };
+        let offset = value_offset + self.peek(value_offset, ByteCursor::peek_i32)? as i64;
+        let mut cursor = BodyCursor::new(self.body, 0, self.context);
+        cursor.checked_seek(offset).map_err(|_| RmanError::OutOfBody {
+            context: self.context, what: "field offset", offset, len: self.body.len(),
+        })?;
+        Ok(Some(cursor))
     }
 
     /// Read an offset value, then string at given offset
-    fn get_str(&self, field: u8) -> Option<&'a str> {
-        self.get_offset_cursor(field).map(|mut cursor| {
-            let len = cursor.read_i32();
-            let slice = cursor.read_slice(len);
-            std::str::from_utf8(slice).expect("invalid UTF-8 string in RMAN")
-        })
+    ///
+    /// Invalid UTF-8 sequences are replaced by `U+FFFD`.
+    fn get_str(&self, field: u8) -> Result<Option<Cow<'a, str>>> {
+        self.get_offset_cursor(field)?.map(|mut cursor| cursor.read_str()).transpose()
     }
-}
 
+    /// Same as [Self::get_str()], but return an error if the string is not valid UTF-8
+    fn get_checked_str(&self, field: u8) -> Result<Option<&'a str>> {
+        self.get_offset_cursor(field)?.map(|mut cursor| cursor.read_checked_str()).transpose()
+    }
+}
 
 /// An iterator over invidual entries of an RMAN table
 ///
 /// This struct is created by the various `iter_*()` methods on [Rman].
+///
+/// If an entry cannot be parsed, an error is returned and iteration stops.
 pub struct OffsetTableIter<'a, I> {
     cursor: BodyCursor<'a>,
     count: u32,
-    parser: fn(BodyCursor<'a>) -> I,
+    /// Error to return on next iteration, if the item count could not be read
+    error: Option<RmanError>,
+    parser: fn(BodyCursor<'a>) -> Result<I>,
 }
 
 impl<'a, I> OffsetTableIter<'a, I> {
     /// Initialize the iterator, read item count from the cursor
-    fn new(mut cursor: BodyCursor<'a>, parser: fn(BodyCursor<'a>) -> I) -> Self {
-        let count = cursor.read_u32();
-        Self { cursor, count, parser }
+    fn new(mut cursor: BodyCursor<'a>, parser: fn(BodyCursor<'a>) -> Result<I>) -> Self {
+        let (count, error) = match cursor.read_u32() {
+            Ok(count) => (count, None),
+            Err(e) => (0, Some(e)),
+        };
+        Self { cursor, count, error, parser }
     }
 }
 
 impl<'a, I> Iterator for OffsetTableIter<'a, I> {
-    type Item = I;
+    type Item = Result<I>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.count == 0 {
+        if let Some(e) = self.error.take() {
+            Some(Err(e))
+        } else if self.count == 0 {
             None
         } else {
             self.count -= 1;
-            Some((self.parser)(self.cursor.subcursor()))
+            let result = self.cursor.subcursor().and_then(self.parser);
+            if result.is_err() {
+                self.count = 0;
+            }
+            Some(result)
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.count as usize, Some(self.count as usize))
+        if self.error.is_some() {
+            return (1, Some(1));
+        }
+        // Don't trust the count for the lower bound, it could be invalid
+        let count = self.count as usize;
+        (count.min(self.cursor.remaining() / 4), Some(count))
     }
 
     fn count(self) -> usize {
-        self.count as usize
+        if self.error.is_some() { 1 } else { self.count as usize }
     }
 }
 
@@ -402,7 +964,7 @@ pub struct FileFlagEntry<'a> {
     /// Flag ID
     pub id: u8,
     /// Flag value
-    pub flag: &'a str,
+    pub flag: Cow<'a, str>,
 }
 
 
@@ -411,23 +973,27 @@ pub struct FileFlagEntry<'a> {
 pub struct BundleEntry<'a> {
     /// Bundle ID
     pub id: u64,
+    chunks_count: u32,
     cursor: BodyCursor<'a>,
 }
 
 impl<'a> BundleEntry<'a> {
     /// Iterate of bundle chunks
-    pub fn iter_chunks(&self) -> impl Iterator<Item=ChunkEntry> + 'a {
+    pub fn iter_chunks(&self) -> impl Iterator<Item=Result<ChunkEntry>> + 'a {
         OffsetTableIter::new(self.cursor.clone(), parse_chunk_entry)
-            .scan(0u32, |offset, mut e| {
-                e.bundle_offset = *offset;
-                *offset += e.bundle_size;
-                Some(e)
+            .scan(0u32, |offset, e| {
+                Some(e.and_then(|mut e| {
+                    e.bundle_offset = *offset;
+                    *offset = offset.checked_add(e.bundle_size)
+                        .ok_or(RmanError::SizeOverflow { context: "bundle" })?;
+                    Ok(e)
+                }))
             })
     }
 
     /// Return the number of chunks in the bundle
     pub fn chunks_count(&self) -> u32 {
-       self.cursor.peek_u32()
+        self.chunks_count
     }
 }
 
@@ -450,21 +1016,48 @@ pub struct FileEntry<'a> {
     /// File ID
     pub id: u64,
     /// File name (without directory)
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
     /// For links, target of the link
-    pub link: Option<&'a str>,
+    pub link: Option<Cow<'a, str>>,
     /// ID of the directory the file is into
     pub directory_id: Option<u64>,
     /// Size of the file, when extracted
     pub filesize: u32,
     /// Flags, used to filter which files need to be installed
     pub flags: Option<FileFlagSet>,
+    /// File type, if set
+    pub file_type: Option<RmanFileType>,
+    /// `true` for localized files (e.g. localized WADs)
+    pub is_localized: bool,
     chunks_cursor: BodyCursor<'a>,
 }
 
+/// Type of an RMAN file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RmanFileType {
+    /// Executable file, should be installed with the executable permission
+    Executable,
+    /// Regular file
+    Regular,
+    /// Unknown value
+    Unknown(u8),
+}
+
+impl From<u8> for RmanFileType {
+    fn from(v: u8) -> Self {
+        match v {
+            1 => Self::Executable,
+            2 => Self::Regular,
+            v => Self::Unknown(v),
+        }
+    }
+}
+
 /// Data byte range for an RMAN file
 #[derive(Debug)]
 pub struct FileChunkRange {
+    /// Chunk ID
+    pub chunk_id: u64,
     /// Byte range of the chunk in its bundle
     pub bundle: (u32, u32),
     /// Byte range of the chunk in the target file
@@ -483,92 +1076,206 @@ impl<'a> FileEntry<'a> {
         FileChunksIter::new(self.chunks_cursor.clone())
     }
 
+    /// Return `true` if the file is flagged as executable
+    pub fn is_executable(&self) -> bool {
+        self.file_type == Some(RmanFileType::Executable)
+    }
+
     /// Return full file path, using given directory path map
-    pub fn path(&self, dirs: &DirPaths) -> String {
+    ///
+    /// Return an error if the file directory is not in the map.
+    pub fn path(&self, dirs: &DirPaths) -> Result<String> {
         match self.directory_id {
-            None => self.name.to_owned(),
-            Some(id) => format!("{}/{}", dirs[&id], self.name),
+            None => Ok(self.name.to_string()),
+            Some(id) => {
+                let dir = dirs.get(&id).ok_or(RmanError::UnknownDirectory(id))?;
+                Ok(format!("{}/{}", dir, self.name))
+            }
         }
     }
 
     /// Collect file chunks grouped by bundle, and the total file size
-    pub fn bundle_chunks(&self, bundle_chunks: &BundleChunks) -> (u32, FileBundleRanges) {
+    ///
+    /// Return an error if a chunk is not in `bundle_chunks`.
+    pub fn bundle_chunks(&self, bundle_chunks: &BundleChunks) -> Result<(u32, FileBundleRanges)> {
         // Group chunks by bundle
         // For each bundle, get its list of ranges to download and target file ranges
         // Also compute the total file size
         let mut bundle_ranges = FileBundleRanges::new();
-        let file_size = self
-            .iter_chunks()
-            .fold(0u32, |offset, chunk_id| {
-                let chunk = &bundle_chunks[&chunk_id];
-                let ranges = &mut bundle_ranges.entry(chunk.bundle_id).or_default();
-                ranges.push(FileChunkRange {
-                    bundle: (chunk.bundle_offset, chunk.bundle_offset + chunk.bundle_size),
-                    target: (offset, offset + chunk.target_size),
-                });
-                offset + chunk.target_size
+        let mut file_size = 0u32;
+        for chunk_id in self.iter_chunks() {
+            let chunk_id = chunk_id?;
+            let chunk = bundle_chunks.get(&chunk_id).ok_or(RmanError::UnknownChunk(chunk_id))?;
+            let bundle_end = chunk.bundle_offset.checked_add(chunk.bundle_size)
+                .ok_or(RmanError::SizeOverflow { context: "bundle" })?;
+            let target_end = file_size.checked_add(chunk.target_size)
+                .ok_or(RmanError::SizeOverflow { context: "file" })?;
+            let ranges = &mut bundle_ranges.entry(chunk.bundle_id).or_default();
+            ranges.push(FileChunkRange {
+                chunk_id,
+                bundle: (chunk.bundle_offset, bundle_end),
+                target: (file_size, target_end),
             });
-        (file_size, bundle_ranges)
+            file_size = target_end;
+        }
+        Ok((file_size, bundle_ranges))
     }
 }
 
 /// An iterator over the chunks of an RMAN file
 ///
 /// This `struct` is created by `FileEntry::iter_chunks` method.
+///
+/// If a chunk ID cannot be read, an error is returned and iteration stops.
 pub struct FileChunksIter<'a> {
     cursor: BodyCursor<'a>,
     count: u32,
+    /// Error to return on next iteration, if the chunk count could not be read
+    error: Option<RmanError>,
 }
 
 impl<'a> FileChunksIter<'a> {
     fn new(mut cursor: BodyCursor<'a>) -> Self {
-        let count = cursor.read_u32();
-        Self { cursor, count }
+        let (count, error) = match cursor.read_u32() {
+            Ok(count) => (count, None),
+            Err(e) => (0, Some(e)),
+        };
+        Self { cursor, count, error }
     }
 }
 
 impl<'a> Iterator for FileChunksIter<'a> {
-    type Item = u64;
+    type Item = Result<u64>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.count == 0 {
+        if let Some(e) = self.error.take() {
+            Some(Err(e))
+        } else if self.count == 0 {
             None
         } else {
             self.count -= 1;
-            Some(self.cursor.read_u64())
+            let result = self.cursor.read_u64();
+            if result.is_err() {
+                self.count = 0;
+            }
+            Some(result)
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.count as usize, Some(self.count as usize))
+        if self.error.is_some() {
+            return (1, Some(1));
+        }
+        // Don't trust the count for the lower bound, it could be invalid
+        let count = self.count as usize;
+        (count.min(self.cursor.remaining() / 8), Some(count))
     }
 
     fn count(self) -> usize {
-        self.count as usize
+        if self.error.is_some() { 1 } else { self.count as usize }
+    }
+}
+
+
+/// Flags defined in RMAN, indexed by ID
+///
+/// See [Rman::flags()].
+#[derive(Clone, Debug, Default)]
+pub struct FileFlags {
+    names: Vec<Option<String>>,
+}
+
+impl FileFlags {
+    /// Get the name of a flag from its ID
+    pub fn get(&self, id: u8) -> Option<&str> {
+        self.names.get(id as usize)?.as_deref()
+    }
+
+    /// Iterate on flags, sorted by ID
+    pub fn iter(&self) -> impl Iterator<Item=(u8, &str)> {
+        self.names.iter().enumerate().filter_map(|(id, name)| Some((id as u8, name.as_deref()?)))
+    }
+}
+
+impl FromIterator<(u8, String)> for FileFlags {
+    fn from_iter<I: IntoIterator<Item=(u8, String)>>(iter: I) -> Self {
+        let mut names = Vec::new();
+        for (id, name) in iter {
+            let id = id as usize;
+            if names.len() <= id {
+                names.resize(id + 1, None);
+            }
+            names[id] = Some(name);
+        }
+        Self { names }
     }
 }
 
 
 /// Set of RMAN file flags, as a bitmask
-#[derive(Debug)]
+///
+/// # Example
+/// ```
+/// # use cdragon_rman::{FileFlags, FileFlagSet};
+/// let flags: FileFlags = [(1, "en_US".to_owned()), (2, "fr_FR".to_owned()), (5, "macos".to_owned())]
+///     .into_iter().collect();
+/// let set = FileFlagSet::from(0b100110);
+/// assert_eq!(set.ids().collect::<Vec<_>>(), vec![1, 2, 5]);
+/// assert_eq!(set.resolve(&flags).collect::<Vec<_>>(), vec!["en_US", "fr_FR", "macos"]);
+/// // Unknown IDs are skipped
+/// let set = FileFlagSet::from(0b1001);
+/// assert_eq!(set.resolve(&flags).count(), 0);
+/// // IDs beyond the mask are never set
+/// let set = FileFlagSet::from(u64::MAX);
+/// assert!(set.contains(63));
+/// assert!(!set.contains(64) && !set.contains(255));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct FileFlagSet {
     mask: u64,
 }
 
 impl FileFlagSet {
+    /// Return `true` if the flag with the given ID is set
+    ///
+    /// IDs that don't fit in the mask (64 and above) are never set.
+    pub fn contains(&self, id: u8) -> bool {
+        1u64.checked_shl(id as u32).is_some_and(|m| self.mask & m != 0)
+    }
+
+    /// Iterate on IDs of flags set in the mask
+    pub fn ids(&self) -> impl Iterator<Item=u8> {
+        let mask = self.mask;
+        (0..64).filter(move |id| mask & (1 << id) != 0)
+    }
+
+    /// Iterate on names of flags set in the mask
+    ///
+    /// IDs missing from `flags` are skipped.
+    pub fn resolve<'a>(&self, flags: &'a FileFlags) -> impl Iterator<Item=&'a str> {
+        self.ids().filter_map(|id| flags.get(id))
+    }
+
     /// Iterate on flags set in the mask
+    #[deprecated(note = "use `resolve()` with `Rman::flags()` instead")]
     pub fn iter<'a, I: Iterator<Item=&'a FileFlagEntry<'a>>>(&self, flags_it: I) -> impl Iterator<Item=&'a str> {
-        let mask = self.mask;
+        let set = *self;
         flags_it.filter_map(move |e| {
-            if mask & (1 << e.id) == 0 {
-                None
+            if set.contains(e.id) {
+                Some(e.flag.as_ref())
             } else {
-                Some(e.flag)
+                None
             }
         })
     }
 }
 
+impl From<u64> for FileFlagSet {
+    fn from(mask: u64) -> Self {
+        Self { mask }
+    }
+}
+
 
 /// Directory defined in RMAN
 #[derive(Debug)]
@@ -578,75 +1285,95 @@ pub struct DirectoryEntry<'a> {
     /// Parent directory, if any
     pub parent_id: Option<u64>,
     /// Directory name
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
 }
 
 impl<'a> DirectoryEntry<'a> {
     /// Build absolute path, using list of all directories
-    pub fn path(&self, dirs: &[DirectoryEntry]) -> String {
-        let mut path = self.name.to_owned();
+    ///
+    /// Return an error if a parent directory is not in the list, or if parents form a loop.
+    ///
+    /// # Example
+    /// ```
+    /// # use cdragon_rman::{DirectoryEntry, RmanError};
+    /// let dir = |id, parent_id, name: &'static str| DirectoryEntry { id, parent_id, name: name.into() };
+    /// let dirs = [
+    ///     dir(1, None, "DATA"),
+    ///     dir(2, Some(1), "FINAL"),
+    ///     dir(3, Some(4), "loop"),
+    ///     dir(4, Some(3), "loop"),
+    ///     dir(5, Some(9), "orphan"),
+    /// ];
+    /// assert_eq!(dirs[1].path(&dirs).unwrap(), "DATA/FINAL");
+    /// assert!(matches!(dirs[2].path(&dirs), Err(RmanError::DirectoryLoop(3))));
+    /// assert!(matches!(dirs[4].path(&dirs), Err(RmanError::UnknownDirectory(9))));
+    /// ```
+    pub fn path(&self, dirs: &[DirectoryEntry]) -> Result<String> {
+        let mut path = self.name.to_string();
         let mut parent_id = self.parent_id;
-        while parent_id.is_some() {
-            let pid = parent_id.unwrap();
-            let parent = dirs.iter().find(|e| e.id == pid).expect("RMAN parent directory ID not found");
+        // A path cannot have more parents than there are directories
+        for _ in 0..dirs.len() {
+            let Some(pid) = parent_id else {
+                return Ok(path);
+            };
+            let parent = dirs.iter().find(|e| e.id == pid).ok_or(RmanError::UnknownDirectory(pid))?;
             path = format!("{}/{}", parent.name, path);
             parent_id = parent.parent_id;
         }
-        path
+        match parent_id {
+            None => Ok(path),
+            Some(_) => Err(RmanError::DirectoryLoop(self.id)),
+        }
     }
 
     /// Resolve directory paths, return a map indexed by ID
-    pub fn build_path_map(entries: &[DirectoryEntry]) -> DirPaths {
+    pub fn build_path_map(entries: &[DirectoryEntry]) -> Result<DirPaths> {
         // Note: don't process recursively. Path of intermediate directories will be formatted
         // multiple times. There are only few directories, so that should not be an issue.
-        entries.iter().map(|e| (e.id, e.path(entries))).collect()
+        entries.iter().map(|e| Ok((e.id, e.path(entries)?))).collect()
     }
 }
 
 
-fn parse_flag_entry(mut cursor: BodyCursor) -> FileFlagEntry {
+fn parse_flag_entry(mut cursor: BodyCursor) -> Result<FileFlagEntry> {
     // Skip field offsets, assume fixed ones
-    cursor.skip(4);
-    cursor.skip(3);
-    let flag_id = cursor.read_u8();
-    let flag = {
-        let mut cursor = cursor.subcursor();
-        let len = cursor.read_i32();
-        let slice = cursor.read_slice(len);
-        std::str::from_utf8(slice).expect("invalid UTF-8 string for RMAN file flag")
-    };
-    FileFlagEntry { id: flag_id, flag }
+    cursor.skip(4)?;
+    cursor.skip(3)?;
+    let flag_id = cursor.read_u8()?;
+    let flag = cursor.subcursor()?.read_str()?;
+    Ok(FileFlagEntry { id: flag_id, flag })
 }
 
-fn parse_bundle_entry(cursor: BodyCursor) -> BundleEntry {
+fn parse_bundle_entry(cursor: BodyCursor) -> Result<BundleEntry> {
     // Field offsets
     //   0  bundle ID
     //   1  chunks offset
-    let cursor = cursor.fields_cursor();
+    let cursor = cursor.fields_cursor()?;
 
-    let bundle_id = cursor.get_u64(0).expect("missing bundle ID field");
-    let chunks_cursor = cursor.get_offset_cursor(1).expect("missing chunks offset field");
+    let bundle_id = cursor.get_u64(0)?.ok_or_else(|| cursor.missing_field("bundle ID"))?;
+    let chunks_cursor = cursor.get_offset_cursor(1)?.ok_or_else(|| cursor.missing_field("chunks offset"))?;
+    let chunks_count = chunks_cursor.peek_u32()?;
 
-    BundleEntry { id: bundle_id, cursor: chunks_cursor }
+    Ok(BundleEntry { id: bundle_id, chunks_count, cursor: chunks_cursor })
 }
 
-fn parse_chunk_entry(cursor: BodyCursor) -> ChunkEntry {
+fn parse_chunk_entry(cursor: BodyCursor) -> Result<ChunkEntry> {
     // Field offsets
     //   0  chunk ID
     //   1  bundle size, compressed
     //   2  chunk size, uncompressed
 
-    let cursor = cursor.fields_cursor();
+    let cursor = cursor.fields_cursor()?;
 
-    let chunk_id = cursor.get_u64(0).expect("missing chunk ID field");
-    let bundle_size = cursor.get_u32(1).expect("missing chunk compressed size");
-    let target_size = cursor.get_u32(2).expect("missing chunk uncompressed size");
+    let chunk_id = cursor.get_u64(0)?.ok_or_else(|| cursor.missing_field("chunk ID"))?;
+    let bundle_size = cursor.get_u32(1)?.ok_or_else(|| cursor.missing_field("chunk compressed size"))?;
+    let target_size = cursor.get_u32(2)?.ok_or_else(|| cursor.missing_field("chunk uncompressed size"))?;
 
     // Note: bundle_offset is set later, by `BundleEntry::iter_chunks()`
-    ChunkEntry { id: chunk_id, bundle_size, target_size, bundle_offset: 0 }
+    Ok(ChunkEntry { id: chunk_id, bundle_size, target_size, bundle_offset: 0 })
 }
 
-fn parse_file_entry(cursor: BodyCursor) -> FileEntry {
+fn parse_file_entry(cursor: BodyCursor) -> Result<FileEntry> {
     // Field offsets
     //   0  file ID
     //   1  directory ID
@@ -659,31 +1386,51 @@ fn parse_file_entry(cursor: BodyCursor) -> FileEntry {
     //   8  ?
     //   9  link (str, offset)
     //  10  ?
-    //  11  ? (present and set to 1 for localized WADs)
+    //  11  localized (present and set to 1 for localized WADs)
     //  12  file type (1: executable, 2: regular)
-    let cursor = cursor.fields_cursor();
-
-    let file_id = cursor.get_u64(0).expect("missing file ID field");
-    let directory_id = cursor.get_u64(1);
-    let filesize = cursor.get_u32(2).expect("missing file size field");
-    let name = cursor.get_str(3).expect("missing file name field");
-    let flags = cursor.get_u64(4).map(|mask| FileFlagSet { mask });
-    let chunks_cursor = cursor.get_offset_cursor(7).expect("missing chunks cursor field");
-    let link = cursor.get_str(9).filter(|v| !v.is_empty());
-
-    FileEntry {
+    let cursor = cursor.fields_cursor()?;
+
+    let file_id = cursor.get_u64(0)?.ok_or_else(|| cursor.missing_field("file ID"))?;
+    let directory_id = cursor.get_u64(1)?;
+    let filesize = cursor.get_u32(2)?.ok_or_else(|| cursor.missing_field("file size"))?;
+    let name = cursor.get_str(3)?.ok_or_else(|| cursor.missing_field("file name"))?;
+    let flags = cursor.get_u64(4)?.map(|mask| FileFlagSet { mask });
+    let chunks_cursor = cursor.get_offset_cursor(7)?.ok_or_else(|| cursor.missing_field("chunks offset"))?;
+    let link = cursor.get_str(9)?.filter(|v| !v.is_empty());
+    let is_localized = cursor.get_u8(11)?.map(|v| v != 0).unwrap_or(false);
+    let file_type = cursor.get_u8(12)?.map(RmanFileType::from);
+
+    Ok(FileEntry {
         id: file_id, name, link, directory_id,
-        filesize, flags, chunks_cursor,
-    }
+        filesize, flags, file_type, is_localized, chunks_cursor,
+    })
+}
+
+fn parse_chunking_params_entry(cursor: BodyCursor) -> Result<RmanChunkingParams> {
+    // Field offsets
+    //   0  ?
+    //   1  hash type
+    //   2  minimum chunk size
+    //   3  chunk size
+    //   4  maximum chunk size
+    let cursor = cursor.fields_cursor()?;
+
+    Ok(RmanChunkingParams {
+        unk0: cursor.get_u16(0)?.unwrap_or(0),
+        hash_type: cursor.get_u8(1)?.unwrap_or(0),
+        min_chunk_size: cursor.get_u32(2)?.unwrap_or(0),
+        chunk_size: cursor.get_u32(3)?.unwrap_or(0),
+        max_chunk_size: cursor.get_u32(4)?.unwrap_or(0),
+    })
 }
 
-fn parse_directory_entry(cursor: BodyCursor) -> DirectoryEntry {
-    let cursor = cursor.fields_cursor();
-    let directory_id = cursor.get_u64(0).unwrap_or(0);
-    let parent_id = cursor.get_u64(1);
-    let name = cursor.get_str(2).expect("missing directory name field");
+fn parse_directory_entry(cursor: BodyCursor) -> Result<DirectoryEntry> {
+    let cursor = cursor.fields_cursor()?;
+    let directory_id = cursor.get_u64(0)?.unwrap_or(0);
+    let parent_id = cursor.get_u64(1)?;
+    let name = cursor.get_str(2)?.ok_or_else(|| cursor.missing_field("directory name"))?;
 
-    DirectoryEntry { id: directory_id, parent_id, name }
+    Ok(DirectoryEntry { id: directory_id, parent_id, name })
 }
 
 
@@ -699,5 +1446,315 @@ pub enum RmanError {
     UnsupportedVersion(u8, u8),
     #[error("flags not supported: {0:b}")]
     UnsupportedFlags(u16),
+    #[error("unknown body encoding")]
+    UnknownBodyEncoding,
+    #[error("missing {0} table")]
+    MissingTable(&'static str),
+    #[error("{context}: {what} {offset:#x} out of body (len {len:#x})")]
+    OutOfBody { context: &'static str, what: &'static str, offset: i64, len: usize },
+    #[error("{context}: missing {field} field in entry at offset {offset:#x}")]
+    MissingField { context: &'static str, field: &'static str, offset: i64 },
+    #[error("{context}: invalid UTF-8 string at offset {offset:#x}")]
+    InvalidString { context: &'static str, offset: i64 },
+    #[error("directory not found: {0:#x}")]
+    UnknownDirectory(u64),
+    #[error("loop in parents of directory {0:#x}")]
+    DirectoryLoop(u64),
+    #[error("chunk not found: {0:#x}")]
+    UnknownChunk(u64),
+    #[error("{context} size overflow")]
+    SizeOverflow { context: &'static str },
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use super::*;
+    use super::fixtures::*;
+
+    #[test]
+    fn read_detects_body_encoding() {
+        let body = minimal_body();
+        let zstd_body = zstd::encode_all(&body[..], 0).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&body).unwrap();
+        let gzip_body = encoder.finish().unwrap();
+
+        for data in [&body, &zstd_body, &gzip_body] {
+            let rman = Rman::read_checked(rman_data(data, body.len()).as_slice()).unwrap();
+            assert_eq!(rman.manifest_id, 0x1234);
+            assert_eq!(rman.iter_files().count(), 0);
+        }
+
+        let result = Rman::read(rman_data(b"\xff\xff\xff\xffgarbage", body.len()).as_slice());
+        assert!(matches!(result, Err(RmanError::UnknownBodyEncoding)));
+    }
+
+    #[test]
+    fn huge_body_length_in_header_is_an_error() {
+        let body = minimal_body();
+        let mut data = rman_data(&body, body.len());
+        // Compressed length, right after magic, version, flags and offset
+        data[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        for result in [Rman::read(data.as_slice()), Rman::read_checked(data.as_slice())] {
+            assert!(matches!(result, Err(RmanError::Parsing(ParseError::NotEnoughData))));
+        }
+    }
+
+    #[test]
+    fn invalid_body_does_not_panic() {
+        let body = minimal_body();
+        let mut seed = 0x1234_5678u64;
+        let mut rand = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+        for i in 0..5000 {
+            let data: Vec<u8> = if i % 2 == 0 {
+                // Random bytes
+                (0..rand() % 64).map(|_| rand() as u8).collect()
+            } else {
+                // Random changes on a valid body
+                let mut data = body.clone();
+                for _ in 0..1 + rand() % 3 {
+                    let n = rand() % data.len();
+                    data[n] = rand() as u8;
+                }
+                data
+            };
+            let Ok(rman) = Rman::read_checked(rman_data(&data, data.len()).as_slice()) else { continue };
+            // Errors are expected, panics are not
+            rman.iter_bundles().flatten().for_each(|bundle| bundle.iter_chunks().for_each(drop));
+            rman.iter_flags().for_each(drop);
+            rman.iter_files().flatten().for_each(|file| file.iter_chunks().for_each(drop));
+            rman.iter_directories().for_each(drop);
+            let _ = rman.params();
+            let _ = rman.dir_paths();
+            let _ = rman.file_index();
+            if let Ok(bundle_chunks) = rman.bundle_chunks() {
+                let _ = rman.bundle_files(&bundle_chunks);
+                rman.iter_files().flatten().for_each(|file| { let _ = file.bundle_chunks(&bundle_chunks); });
+            }
+            rman.debug_info();
+        }
+    }
+
+    #[test]
+    fn unknown_chunks_are_errors() {
+        // Single file with a single chunk, no bundles
+        let rman = manifest_tables(|b, empty| {
+            let chunks = b.len();
+            b.extend(1u32.to_le_bytes());
+            b.extend(0xc1u64.to_le_bytes());
+            let name = string(b, b"a.bin");
+            let file = obj(b, &[Some(F::U64(10)), None, Some(F::U64(0)), Some(F::Ref(name)),
+                                None, None, None, Some(F::Ref(chunks))]);
+            [empty, empty, table(b, &[file]), empty]
+        });
+
+        let bundle_chunks = rman.bundle_chunks().unwrap();
+        assert!(bundle_chunks.is_empty());
+        assert!(matches!(rman.bundle_files(&bundle_chunks), Err(RmanError::UnknownChunk(0xc1))));
+        let file = rman.iter_files().next().unwrap().unwrap();
+        assert!(matches!(file.bundle_chunks(&bundle_chunks), Err(RmanError::UnknownChunk(0xc1))));
+    }
+
+    #[test]
+    fn file_flag_names_are_resolved() {
+        let rman = manifest_tables(|b, empty| {
+            // Flag table is not sorted by ID
+            let flags: Vec<usize> = [(5, "macos"), (1, "en_US"), (2, "fr_FR")].iter()
+                .map(|(id, name)| flag(b, *id, name.as_bytes()))
+                .collect();
+            let flags = table(b, &flags);
+            let files: Vec<usize> = [(10, Some((1 << 5) | (1 << 1) | (1 << 7))), (11, Some(0)), (12, None)].iter()
+                .map(|(id, mask)| {
+                    let name = string(b, b"file");
+                    obj(b, &[Some(F::U64(*id)), None, Some(F::U64(0)), Some(F::Ref(name)),
+                             mask.map(F::U64), None, None, Some(F::Ref(empty))])
+                })
+                .collect();
+            [empty, flags, table(b, &files), empty]
+        });
+
+        let names: Vec<Vec<String>> = rman.iter_files()
+            .map(|file| rman.file_flag_names(&file.unwrap()).unwrap())
+            .collect();
+        assert_eq!(names, [vec!["en_US", "macos"], vec![], vec![]]);
+    }
+
+    #[test]
+    fn file_type_and_localized_marker() {
+        // File ID, localized field, file type field
+        let items = [(10, Some(1), Some(1)), (11, Some(0), Some(2)), (12, None, Some(7)), (13, None, None)];
+        let rman = manifest_tables(|b, empty| {
+            let files: Vec<usize> = items.iter()
+                .map(|(id, localized, file_type)| {
+                    let name = string(b, b"file");
+                    obj(b, &[Some(F::U64(*id)), None, Some(F::U64(0)), Some(F::Ref(name)),
+                             None, None, None, Some(F::Ref(empty)), None, None, None,
+                             localized.map(F::U64), file_type.map(F::U64)])
+                })
+                .collect();
+            [empty, empty, table(b, &files), empty]
+        });
+
+        let files: Vec<(u64, Option<RmanFileType>, bool, bool)> = rman.iter_files()
+            .map(|file| file.unwrap())
+            .map(|file| (file.id, file.file_type, file.is_executable(), file.is_localized))
+            .collect();
+        assert_eq!(files, [
+            (10, Some(RmanFileType::Executable), true, true),
+            (11, Some(RmanFileType::Regular), false, false),
+            (12, Some(RmanFileType::Unknown(7)), false, false),
+            (13, None, false, false),
+        ]);
+    }
+
+    #[test]
+    fn bundle_files_of_shared_bundles() {
+        let rman = manifest_tables(|b, empty| {
+            // Bundle 0xb1 with chunks 0xc1 and 0xc2, bundle 0xb2 with chunk 0xc3
+            let bundles: Vec<usize> = [(0xb1, &[0xc1, 0xc2][..]), (0xb2, &[0xc3])].iter()
+                .map(|(id, chunk_ids)| {
+                    let chunks: Vec<usize> = chunk_ids.iter()
+                        .map(|chunk_id| obj(b, &[Some(F::U64(*chunk_id)), Some(F::U64(100)), Some(F::U64(200))]))
+                        .collect();
+                    let chunks = table(b, &chunks);
+                    obj(b, &[Some(F::U64(*id)), Some(F::Ref(chunks))])
+                })
+                .collect();
+            let bundles = table(b, &bundles);
+            // Files sharing bundles, a file may use a bundle more than once
+            let files: Vec<usize> = [(10, &[0xc1, 0xc3][..]), (11, &[0xc2, 0xc1]), (12, &[0xc3])].iter()
+                .map(|(id, chunk_ids)| {
+                    let chunks = b.len();
+                    b.extend((chunk_ids.len() as u32).to_le_bytes());
+                    for chunk_id in chunk_ids.iter() {
+                        b.extend((*chunk_id as u64).to_le_bytes());
+                    }
+                    let name = string(b, b"file");
+                    obj(b, &[Some(F::U64(*id)), None, Some(F::U64(0)), Some(F::Ref(name)),
+                             None, None, None, Some(F::Ref(chunks))])
+                })
+                .collect();
+            [bundles, empty, table(b, &files), empty]
+        });
+
+        let bundle_chunks = rman.bundle_chunks().unwrap();
+        assert_eq!(bundle_chunks.len(), 3);
+        assert_eq!(bundle_chunks[&0xc2].bundle_id, 0xb1);
+        assert_eq!(bundle_chunks[&0xc2].bundle_offset, 100);
+        let bundle_files = rman.bundle_files(&bundle_chunks).unwrap();
+        assert_eq!(bundle_files, BundleFiles::from([(0xb1, vec![10, 11]), (0xb2, vec![10, 12])]));
+    }
+
+    #[test]
+    fn read_checked_rejects_invalid_strings() {
+        let data = |dir_name: &[u8], file_name: &[u8]| manifest_data(&[(1, None, dir_name)], &[(10, Some(1), file_name)]);
+
+        let rman = Rman::read_checked(data(b"DATA", b"Ahri.wad.client").as_slice()).unwrap();
+        assert_eq!(rman.iter_files().next().unwrap().unwrap().path(&rman.dir_paths().unwrap()).unwrap(), "DATA/Ahri.wad.client");
+
+        // Invalid UTF-8 in a file name
+        let data_file = data(b"DATA", b"Ahri\xff.wad.client");
+        let rman = Rman::read(data_file.as_slice()).unwrap();
+        assert_eq!(rman.iter_files().next().unwrap().unwrap().name, "Ahri\u{fffd}.wad.client");
+        let err = Rman::read_checked(data_file.as_slice()).err().unwrap();
+        assert!(matches!(err, RmanError::InvalidString { context: "files", .. }));
+
+        // Invalid UTF-8 in a directory name
+        let data_dir = data(b"DA\xc3TA", b"Ahri.wad.client");
+        let rman = Rman::read(data_dir.as_slice()).unwrap();
+        assert_eq!(rman.iter_files().next().unwrap().unwrap().path(&rman.dir_paths().unwrap()).unwrap(), "DA\u{fffd}TA/Ahri.wad.client");
+        let err = Rman::read_checked(data_dir.as_slice()).err().unwrap();
+        assert!(matches!(err, RmanError::InvalidString { context: "directories", .. }));
+    }
+
+    /// Raw data of a params table, with a single entry
+    fn params_table() -> Vec<u8> {
+        let mut b = Vec::new();
+        // One entry, its vtable (5 fields) follows, then the entry itself
+        b.extend(1u32.to_le_bytes());
+        b.extend((4 + 14i32).to_le_bytes());
+        for v in [14u16, 20, 4, 6, 8, 12, 16] {
+            b.extend(v.to_le_bytes());
+        }
+        b.extend(14i32.to_le_bytes());
+        b.extend(1u16.to_le_bytes());
+        b.extend([2u8, 0]);
+        for v in [0x1000u32, 0x4000, 0x10000] {
+            b.extend(v.to_le_bytes());
+        }
+        b
+    }
+
+    #[test]
+    fn params_from_trailing_tables() {
+        // Recent files define all 6 tables
+        let params_table = params_table();
+        let rman = open_body(&tables_body(&[EMPTY_TABLE, EMPTY_TABLE, EMPTY_TABLE, EMPTY_TABLE, EMPTY_TABLE, &params_table]));
+        assert_eq!(rman.iter_files().count(), 0);
+        let info = rman.debug_info();
+        assert_eq!(info.header_len, 20);
+        assert_eq!(info.header.len(), 16);
+        assert_eq!(info.table_offsets, [48, 52, 56, 60, 64, 68].map(Some));
+        let params = rman.params().unwrap();
+        assert_eq!(params.keys_count, Some(0));
+        assert_eq!(params.chunking, vec![RmanChunkingParams {
+            unk0: 1, hash_type: 2, min_chunk_size: 0x1000, chunk_size: 0x4000, max_chunk_size: 0x10000,
+        }]);
+
+        // Older files only have the first 4 tables
+        let rman = open_body(&minimal_body());
+        assert_eq!(rman.iter_files().count(), 0);
+        assert_eq!(rman.debug_info().table_offsets[4..], [None, None]);
+        let params = rman.params().unwrap();
+        assert_eq!(params.keys_count, None);
+        assert!(params.chunking.is_empty());
+    }
+
+    #[test]
+    fn unknown_table_data() {
+        let rman = open_body(&tables_body(&[EMPTY_TABLE, EMPTY_TABLE, EMPTY_TABLE, EMPTY_TABLE, b"\x00\x00\x00\x00", b"\x00\x00\x00\x00\xaa\xbb"]));
+        assert_eq!(rman.unknown_table(0), Some(&[0u8, 0, 0, 0][..]));
+        assert_eq!(rman.unknown_table(1), Some(&[0u8, 0, 0, 0, 0xaa, 0xbb][..]));
+        assert_eq!(rman.unknown_table(2), None);
+
+        // Older files only have the first 4 tables
+        let rman = open_body(&minimal_body());
+        assert_eq!(rman.unknown_table(0), None);
+    }
+
+    #[test]
+    fn file_index_lookups() {
+        let rman = manifest(
+            &[(1, None, "DATA"), (2, Some(1), "FINAL")],
+            &[
+                (10, None, "LeagueClient.exe"),
+                (11, Some(2), "Champions/Ahri.wad.client"),
+                (12, Some(2), "Champions/Ahri.fr_FR.wad.client"),
+                (13, Some(1), "Menu/fontconfig.txt"),
+            ],
+        );
+
+        let index = rman.file_index().unwrap();
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.get("DATA/FINAL/Champions/Ahri.wad.client").unwrap().id, 11);
+        assert_eq!(index.get("LeagueClient.exe").unwrap().id, 10);
+        assert!(index.get("data/final/champions/ahri.wad.client").is_none());
+        assert!(index.get("DATA/FINAL").is_none());
+
+        let ids: Vec<u64> = index.glob(&PathPattern::new("DATA/FINAL/**.wad.client")).iter().map(|f| f.id).collect();
+        assert_eq!(ids, [11, 12]);
+        let paths: Vec<&str> = index.iter_matching(&PathPattern::new("DATA/*/*.txt")).map(|(path, _)| path).collect();
+        assert_eq!(paths, ["DATA/Menu/fontconfig.txt"]);
+        assert!(index.glob(&PathPattern::new("data/**")).is_empty());
+
+        // Ignore case, for lookups and patterns
+        let index = rman.file_index_ignore_case().unwrap();
+        assert_eq!(index.get("data/final/champions/ahri.wad.client").unwrap().id, 11);
+        assert_eq!(index.glob(&PathPattern::new("data/**")).len(), 3);
+    }
+}
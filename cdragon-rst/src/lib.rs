@@ -1,16 +1,17 @@
 //! Support of Riot translation files (RST)
 //!
 //! Use [Rst] to open an RST file (`.stringtable`) and access its content.
+//! Use [RstStack] to lookup entries in several files, with fallback.
 //!
 //! An RST file maps hashed translation keys to translation strings.
 //! When an instance is created, the file header is parsed, data is read, but strings are actually
 //! read and parsed (as UTF-8) only on access.
 //!
+//! Commonly used types, including the hash mapper, are available from the [prelude].
+//!
 //! # Example
 //! ```no_run
-//! # use cdragon_rst::Rst;
-//! # // Use explicit type annotation, required only by rustdoc
-//! # type RstHashMapper = cdragon_rst::RstHashMapper<39>;
+//! use cdragon_rst::prelude::*;
 //!
 //! let rst = Rst::open("main_en_us.stringtable").expect("failed to open or read data");
 //! // Get an entry by its key string
@@ -20,7 +21,7 @@
 //!
 //! // Entries can be iterated
 //! // Use a mapper to filter on (known) keys
-//! let hmapper = RstHashMapper::from_path("hashes.rst.txt").expect("failed to load hashes");
+//! let hmapper = RstHashMapper::<39>::from_path("hashes.rst.txt").expect("failed to load hashes");
 //! for (hash, value) in rst.iter() {
 //!     if let Some(key) = hmapper.get(hash) {
 //!         println!("{key} = {value}");
@@ -34,15 +35,19 @@
 //!
 //! Hashes from RST files used more bits.
 //! Number of bits used by an RST file can be retrieved with [Rst::hash_bits()].
-//! The default [RstHashMapper] is suitable for the latest RST version.
+//! Use [RstKey::hashed()] to hash a key with a given number of bits.
+//! The default [prelude::RstHashMapper] is suitable for the latest RST version.
 //!
 //! ## Encrypted entries
 //!
 //! Older RST versions could have encrypted entries whose data is not valid UTF-8.
 //! Use [Rst::get_raw()] to access both encrypted and non-encrypted entries.
 
+// `ReadArray::read_array()` collides with unstable `Read::read_array()`
+#![allow(unstable_name_collisions)]
+
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Seek, BufReader};
 use std::path::Path;
 use nom::{
@@ -51,12 +56,34 @@ use nom::{
     sequence::tuple,
 };
 use thiserror::Error;
-use cdragon_hashes::rst::compute_rst_hash_full;
+use cdragon_hashes::{
+    HashOrStr,
+    rst::{compute_rst_hash_full, compute_rst_hash_n},
+};
 use cdragon_utils::{
     parsing::{ParseError, ReadArray},
     parse_buf,
 };
-pub use cdragon_hashes::rst::RstHashMapper;
+
+/// Commonly used RST types and hash mapper
+///
+/// The hash mapper is defined in [cdragon_hashes::rst], which is its canonical path.
+/// It is re-exported here for convenience.
+pub mod prelude {
+    pub use super::{
+        IntoRstKey,
+        Rst,
+        RstError,
+        RstKey,
+        RstRawValue,
+        RstStack,
+    };
+    pub use cdragon_hashes::rst::RstHashMapper;
+}
+
+/// Deprecated re-export of [cdragon_hashes::rst::RstHashMapper]
+#[deprecated(note = "use cdragon_hashes::rst::RstHashMapper, or import it from cdragon_rst::prelude")]
+pub type RstHashMapper<const NBITS: usize = 39> = cdragon_hashes::rst::RstHashMapper<NBITS>;
 
 
 /// Result type for RST errors
@@ -186,19 +213,19 @@ impl Rst {
     }
 
     /// Get a raw value from its key
-    pub fn get_raw<K: IntoRstKey>(&self, key: K) -> Option<RstRawValue> {
+    pub fn get_raw<K: IntoRstKey>(&self, key: K) -> Option<RstRawValue<'_>> {
         self.get_raw_by_hash(key.into_rst_key())
     }
 
     /// Get a raw value from its hash key
-    fn get_raw_by_hash(&self, key: u64) -> Option<RstRawValue> {
+    fn get_raw_by_hash(&self, key: u64) -> Option<RstRawValue<'_>> {
         let key = self.truncate_hash_key(key);
         let offset = *self.entry_offsets.get(&key)?;
         self.get_raw_by_offset(offset)
     }
 
     /// Get a raw value from its offset
-    fn get_raw_by_offset(&self, offset: usize) -> Option<RstRawValue> {
+    fn get_raw_by_offset(&self, offset: usize) -> Option<RstRawValue<'_>> {
         let data = &self.entry_data[offset..];
         if data[0] == 0xff && self.has_trenc {
             let size = u16::from_le_bytes(data[1..3].try_into().unwrap());
@@ -209,17 +236,266 @@ impl Rst {
         }
     }
 
+    /// Get the number of entries, including encrypted ones
+    pub fn len(&self) -> usize {
+        self.entry_offsets.len()
+    }
+
+    /// Return true if there is no entry
+    pub fn is_empty(&self) -> bool {
+        self.entry_offsets.is_empty()
+    }
+
     /// Iterate on string entries
-    pub fn iter(&self) -> impl Iterator<Item=(u64, Cow<'_, str>)> {
-        self.entry_offsets.iter().filter_map(|(key, offset)| {
-            match self.get_raw_by_offset(*offset)? {
-                RstRawValue::String(s) => Some(String::from_utf8_lossy(s)),
-                _ => None
-            }.map(|value| (*key, value))
-        })
+    ///
+    /// Encrypted entries are skipped.
+    /// If the file has no encrypted entries, the iterator size hint is exact.
+    ///
+    /// ```
+    /// # use cdragon_rst::Rst;
+    /// // Version 5, with 2 entries: `0x1` at offset 0, `0x2` at offset 2
+    /// let mut data = b"RST\x05\x02\0\0\0".to_vec();
+    /// data.extend(0x1u64.to_le_bytes());
+    /// data.extend((0x2u64 | (2 << 39)).to_le_bytes());
+    /// data.extend(b"a\0b\0");
+    /// let rst = Rst::read(std::io::Cursor::new(data)).unwrap();
+    ///
+    /// assert_eq!(rst.len(), 2);
+    /// assert_eq!(rst.iter().size_hint(), (2, Some(2)));
+    /// assert_eq!(rst.iter().count(), rst.len());
+    /// ```
+    pub fn iter(&self) -> RstIter<'_> {
+        RstIter { rst: self, offsets: self.entry_offsets.iter() }
+    }
+
+    /// Iterate on string entries, sorted by hash
+    ///
+    /// Unlike [Self::iter()], order is stable, which is better suited to build dumps that can be
+    /// compared. Hashes are sorted when the iterator is created.
+    ///
+    /// ```
+    /// # use cdragon_rst::Rst;
+    /// // Version 5, with 3 entries, not sorted
+    /// let mut data = b"RST\x05\x03\0\0\0".to_vec();
+    /// data.extend(0x30u64.to_le_bytes());
+    /// data.extend((0x10u64 | (2 << 39)).to_le_bytes());
+    /// data.extend((0x20u64 | (4 << 39)).to_le_bytes());
+    /// data.extend(b"c\0a\0b\0");
+    /// let rst = Rst::read(std::io::Cursor::new(data)).unwrap();
+    ///
+    /// let entries: Vec<_> = rst.iter_sorted().collect();
+    /// assert_eq!(entries, [(0x10, "a".into()), (0x20, "b".into()), (0x30, "c".into())]);
+    /// assert_eq!(rst.iter_sorted().next_back(), Some((0x30, "c".into())));
+    /// ```
+    pub fn iter_sorted(&self) -> RstSortedIter<'_> {
+        let mut offsets: Vec<(u64, usize)> = self.entry_offsets.iter().map(|(k, o)| (*k, *o)).collect();
+        offsets.sort_unstable_by_key(|(key, _)| *key);
+        RstSortedIter { rst: self, offsets: offsets.into_iter() }
+    }
+
+    /// Get a string entry from its hash and offset, `None` if encrypted
+    fn get_string_entry(&self, key: u64, offset: usize) -> Option<(u64, Cow<'_, str>)> {
+        match self.get_raw_by_offset(offset) {
+            Some(RstRawValue::String(s)) => Some((key, String::from_utf8_lossy(s))),
+            _ => None,
+        }
     }
 }
 
+/// Iterator on RST string entries, see [Rst::iter()]
+pub struct RstIter<'a> {
+    rst: &'a Rst,
+    offsets: std::collections::hash_map::Iter<'a, u64, usize>,
+}
+
+impl<'a> Iterator for RstIter<'a> {
+    type Item = (u64, Cow<'a, str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rst = self.rst;
+        self.offsets.by_ref().find_map(|(key, offset)| rst.get_string_entry(*key, *offset))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.offsets.len();
+        if self.rst.has_trenc {
+            (0, Some(remaining))
+        } else {
+            (remaining, Some(remaining))
+        }
+    }
+}
+
+/// Iterator on RST string entries sorted by hash, see [Rst::iter_sorted()]
+pub struct RstSortedIter<'a> {
+    rst: &'a Rst,
+    offsets: std::vec::IntoIter<(u64, usize)>,
+}
+
+impl<'a> Iterator for RstSortedIter<'a> {
+    type Item = (u64, Cow<'a, str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rst = self.rst;
+        self.offsets.by_ref().find_map(|(key, offset)| rst.get_string_entry(key, offset))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.offsets.len();
+        if self.rst.has_trenc {
+            (0, Some(remaining))
+        } else {
+            (remaining, Some(remaining))
+        }
+    }
+}
+
+impl<'a> DoubleEndedIterator for RstSortedIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let rst = self.rst;
+        self.offsets.by_ref().rev().find_map(|(key, offset)| rst.get_string_entry(key, offset))
+    }
+}
+
+/// Ordered list of RST files, to lookup entries with fallback
+///
+/// Lookups try each file in order and return the first string found.
+/// This replicates the game, which uses the RST of the selected locale, then falls back to
+/// `en_US` for missing entries.
+///
+/// Files are expected to use the same number of hash bits.
+///
+/// # Example
+/// ```
+/// # use cdragon_rst::{Rst, RstStack};
+/// # fn build_rst(entries: &[(u64, &str)]) -> Rst {
+/// #     let mut data = b"RST\x05".to_vec();
+/// #     data.extend((entries.len() as u32).to_le_bytes());
+/// #     let mut strings = Vec::new();
+/// #     for (hash, value) in entries {
+/// #         data.extend((hash | ((strings.len() as u64) << 39)).to_le_bytes());
+/// #         strings.extend(value.as_bytes());
+/// #         strings.push(0);
+/// #     }
+/// #     data.extend(strings);
+/// #     Rst::read(std::io::Cursor::new(data)).unwrap()
+/// # }
+/// // `build_rst()` creates an RST from `(hash, value)` pairs
+/// let fr_fr = build_rst(&[(1, "un"), (2, "deux")]);
+/// let en_us = build_rst(&[(1, "one"), (2, "two"), (3, "three")]);
+/// let stack = RstStack::new(vec![fr_fr, en_us]);
+///
+/// // Entries of the first file override the fallback ones
+/// assert_eq!(stack.get(1u64), Some("un".into()));
+/// assert_eq!(stack.get(2u64), Some("deux".into()));
+/// // Missing entries use the fallback
+/// assert_eq!(stack.get(3u64), Some("three".into()));
+/// assert_eq!(stack.get(4u64), None);
+///
+/// // Get the index of the file providing the entry
+/// assert_eq!(stack.get_with_index(1u64), Some((0, "un".into())));
+/// assert_eq!(stack.get_with_index(3u64), Some((1, "three".into())));
+///
+/// // Iterate on effective entries
+/// let mut entries: Vec<_> = stack.iter_merged().collect();
+/// entries.sort();
+/// assert_eq!(entries, [(1, "un".into()), (2, "deux".into()), (3, "three".into())]);
+/// ```
+#[derive(Debug, Default)]
+pub struct RstStack {
+    rsts: Vec<Rst>,
+}
+
+impl RstStack {
+    /// Create a stack from RST files, in lookup order
+    pub fn new(rsts: Vec<Rst>) -> Self {
+        Self { rsts }
+    }
+
+    /// Open RST files from paths, in lookup order
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let rsts = paths.iter().map(Rst::open).collect::<Result<_>>()?;
+        Ok(Self { rsts })
+    }
+
+    /// Add a fallback file, used after all others
+    pub fn push(&mut self, rst: Rst) {
+        self.rsts.push(rst);
+    }
+
+    /// Get the RST files, in lookup order
+    pub fn tables(&self) -> &[Rst] {
+        &self.rsts
+    }
+
+    /// Get a string from its key, from the first file that provides it
+    ///
+    /// Encrypted entries are skipped, as with [Rst::get()].
+    pub fn get<K: IntoRstKey>(&self, key: K) -> Option<Cow<'_, str>> {
+        self.get_with_index(key).map(|(_, value)| value)
+    }
+
+    /// Get a string from its key, along with the index of the file that provided it
+    ///
+    /// The index can be used with [Self::tables()].
+    pub fn get_with_index<K: IntoRstKey>(&self, key: K) -> Option<(usize, Cow<'_, str>)> {
+        let key = key.into_rst_key();
+        self.rsts.iter().enumerate().find_map(|(i, rst)| Some((i, rst.get(key)?)))
+    }
+
+    /// Get the number of bits of hash keys shared by all files
+    ///
+    /// This is the smallest bit size of the files, 64 if the stack is empty.
+    pub fn hash_bits(&self) -> u8 {
+        self.rsts.iter().map(Rst::hash_bits).min().unwrap_or(64)
+    }
+
+    /// Iterate on effective string entries
+    ///
+    /// Each key is returned once, with the value of the first file that provides it.
+    /// Keys are truncated to [Self::hash_bits()], so that files of different versions can be
+    /// merged.
+    ///
+    /// ```
+    /// # use cdragon_rst::{Rst, RstStack};
+    /// # fn build_rst(version: u8, entries: &[(u64, &str)]) -> Rst {
+    /// #     let offset_shift = if version >= 4 { 39 } else { 40 };
+    /// #     let mut data = [b"RST", &[version][..]].concat();
+    /// #     data.extend((entries.len() as u32).to_le_bytes());
+    /// #     let mut strings = Vec::new();
+    /// #     for (hash, value) in entries {
+    /// #         data.extend((hash | ((strings.len() as u64) << offset_shift)).to_le_bytes());
+    /// #         strings.extend(value.as_bytes());
+    /// #         strings.push(0);
+    /// #     }
+    /// #     if version < 5 {
+    /// #         data.push(0);  // no encrypted entries
+    /// #     }
+    /// #     data.extend(strings);
+    /// #     Rst::read(std::io::Cursor::new(data)).unwrap()
+    /// # }
+    /// // 40-bit file, with a 39-bit fallback (`build_rst()` creates an RST from `(hash, value)` pairs)
+    /// let key = 0xab_1234_5678;
+    /// let main = build_rst(3, &[(key, "main")]);
+    /// let fallback = build_rst(5, &[(key & 0x7f_ffff_ffff, "fallback"), (2, "other")]);
+    /// let stack = RstStack::new(vec![main, fallback]);
+    /// assert_eq!(stack.hash_bits(), 39);
+    ///
+    /// let mut entries: Vec<_> = stack.iter_merged().collect();
+    /// entries.sort();
+    /// assert_eq!(entries, [(2, "other".into()), (key & 0x7f_ffff_ffff, "main".into())]);
+    /// ```
+    pub fn iter_merged(&self) -> impl Iterator<Item=(u64, Cow<'_, str>)> + '_ {
+        let mask = u64::MAX >> (64 - self.hash_bits());
+        let mut seen = HashSet::new();
+        self.rsts.iter()
+            .flat_map(Rst::iter)
+            .map(move |(key, value)| (key & mask, value))
+            .filter(move |(key, _)| seen.insert(*key))
+    }
+}
+
+
 impl std::fmt::Debug for Rst {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Rst")
@@ -233,7 +509,12 @@ impl std::fmt::Debug for Rst {
 }
 
 
+/// Value that can be used as an RST entry key
+///
+/// Key strings are hashed without truncation: getters truncate keys to the number of bits used
+/// by the file, a full hash is valid regardless of the file version.
 pub trait IntoRstKey {
+    /// Convert the value to a hash key, possibly not truncated
     fn into_rst_key(self) -> u64;
 }
 
@@ -249,6 +530,67 @@ impl IntoRstKey for &str {
     }
 }
 
+/// Hash key of an RST entry, truncated
+///
+/// Getters truncate keys to the number of bits used by the file, therefore keys truncated to more
+/// bits than needed can still be used (for instance, 40-bit keys for a 39-bit file).
+/// However, keys built manually (e.g. to be stored, or compared to [Rst::iter()] keys) must use
+/// the bit count of the file, provided by [Rst::hash_bits()].
+///
+/// # Example
+/// ```
+/// # use cdragon_rst::{Rst, RstKey};
+/// // Version 5 (39-bit hashes), with a single entry
+/// let mut data = b"RST\x05\x01\0\0\0".to_vec();
+/// data.extend(0x3376eae1dau64.to_le_bytes());
+/// data.extend(b"Boots\0");
+/// let rst = Rst::read(std::io::Cursor::new(data)).unwrap();
+///
+/// let key = RstKey::hashed("item_1001_name", rst.hash_bits());
+/// assert_eq!(key, RstKey(0x3376eae1da));
+/// assert_eq!(rst.iter().next().map(|(k, _)| k), Some(key.0));
+/// assert_eq!(rst.get(key), Some("Boots".into()));
+/// // Keys with more bits are truncated
+/// assert_eq!(rst.get(RstKey::hashed("item_1001_name", 40)), Some("Boots".into()));
+/// assert_eq!(rst.get("item_1001_name"), Some("Boots".into()));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RstKey(pub u64);
+
+impl RstKey {
+    /// Hash a key string, truncate the hash to `bits` bits
+    ///
+    /// If `bits` is 64 or more, the hash is not truncated.
+    pub fn hashed(s: &str, bits: u8) -> Self {
+        Self(compute_rst_hash_n(s, bits))
+    }
+
+    /// Get the string associated to the key
+    ///
+    /// ```
+    /// # use cdragon_rst::prelude::*;
+    /// let mut mapper = RstHashMapper::<39>::new();
+    /// mapper.insert(0x3376eae1da, "item_1001_name".into());
+    /// assert_eq!(RstKey(0x3376eae1da).try_str(&mapper), Some("item_1001_name"));
+    /// assert_eq!(RstKey(0x1234).try_str(&mapper), None);
+    /// assert_eq!(RstKey(0x1234).display(&mapper).to_string(), "{0000000000001234}");
+    /// ```
+    pub fn try_str<'a, const N: usize>(&self, mapper: &'a cdragon_hashes::rst::RstHashMapper<N>) -> Option<&'a str> {
+        mapper.get(self.0)
+    }
+
+    /// Get the string associated to the key or fallback to the key hash itself
+    pub fn display<'a, const N: usize>(&self, mapper: &'a cdragon_hashes::rst::RstHashMapper<N>) -> HashOrStr<u64, &'a str> {
+        mapper.seek(self.0)
+    }
+}
+
+impl IntoRstKey for RstKey {
+    fn into_rst_key(self) -> u64 {
+        self.0
+    }
+}
+
 
 
 /// Error in an RST file
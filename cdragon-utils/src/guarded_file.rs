@@ -21,7 +21,7 @@ impl<P: AsRef<Path>> GuardedFile<P> {
     pub fn for_scope<T, F: FnOnce(&mut File) -> std::io::Result<T>>(path: P, f: F) -> std::io::Result<T> {
         let mut gfile = Self::create(path)?;
         let result = f(gfile.as_file_mut())?;
-        gfile.persist();
+        gfile.persist()?;
         Ok(result)
     }
 
@@ -39,9 +39,16 @@ impl<P: AsRef<Path>> GuardedFile<P> {
     }
 
     /// Persist the temporary file
-    pub fn persist(mut self) -> File {
-        fs::rename(Self::build_tmp_path(self.path.as_ref()), self.path.as_ref()).expect("failed to persist file");
-        self.file.take().unwrap()
+    ///
+    /// On error, the temporary file is removed.
+    pub fn persist(mut self) -> std::io::Result<File> {
+        fs::rename(Self::build_tmp_path(self.path.as_ref()), self.path.as_ref())?;
+        Ok(self.file.take().unwrap())
+    }
+
+    /// Return the final path of the file
+    pub fn path(&self) -> &Path {
+        self.path.as_ref()
     }
 
     /// Return a reference to the underlying file
@@ -1,6 +1,11 @@
 //! Various utilities used by other CDragon crates
+#[cfg(feature = "locale")]
+pub mod locale;
 #[cfg(feature = "parsing")]
 pub mod parsing;
+#[cfg(feature = "pattern")]
+pub mod pattern;
+pub mod progress;
 #[cfg(feature = "guarded_file")]
 mod guarded_file;
 #[cfg(feature = "guarded_file")]
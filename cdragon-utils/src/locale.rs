@@ -0,0 +1,204 @@
+//! Riot locales, and localized file names
+//!
+//! Riot uses locale codes made of a language and a region, such as `en_US` or `ja_JP`.
+//! Their case depend on where they are used: RMAN flags and WAD filenames use `en_US`, paths
+//! inside WADs use `en_us`. Parsing is case-insensitive; [Locale] is displayed as `en_US`.
+//!
+//! Localized files are recognized from their name:
+//! - game WADs: `<Name>.<locale>.wad.client` (e.g. `Ahri.fr_FR.wad.client`)
+//! - client WADs: `<Name>.<locale>.wad`
+//! - stringtables: `main_<locale>.stringtable`
+//!
+//! # Example
+//! ```
+//! use cdragon_utils::locale::*;
+//!
+//! let locale: Locale = "ja_jp".parse().unwrap();
+//! assert_eq!(locale.as_str(), "ja_JP");
+//! assert_eq!(locale.to_lowercase(), "ja_jp");
+//! assert_eq!(locale, Locale::new("JA-JP").unwrap());
+//! assert!("japanese".parse::<Locale>().is_err());
+//!
+//! // WAD filenames
+//! let en_us = Locale::new("en_US").unwrap();
+//! assert_eq!(parse_wad_name("Ahri.wad.client"), Some(("Ahri", None)));
+//! assert_eq!(parse_wad_name("Ahri.en_US.wad.client"), Some(("Ahri", Some(en_us))));
+//! assert_eq!(parse_wad_name("Map11.zh_CN.wad.client").unwrap().1.unwrap().as_str(), "zh_CN");
+//! assert_eq!(parse_wad_name("Common.ko_KR.wad.client").unwrap().0, "Common");
+//! assert_eq!(parse_wad_name("UI.wad.client"), Some(("UI", None)));
+//! assert_eq!(parse_wad_name("Kaisa.pt_BR.WAD.CLIENT").unwrap().1.unwrap().as_str(), "pt_BR");
+//! assert_eq!(parse_wad_name("default-assets.wad"), Some(("default-assets", None)));
+//! assert_eq!(parse_wad_name("assets.de_DE.wad").unwrap().1.unwrap().as_str(), "de_DE");
+//! assert_eq!(parse_wad_name("Ahri.notalocale.wad.client"), None);
+//! assert_eq!(parse_wad_name("Ahri.en_US.extra.wad.client"), None);
+//! assert_eq!(parse_wad_name(".wad.client"), None);
+//! assert_eq!(parse_wad_name("Ahri.en_US.bin"), None);
+//! assert_eq!(wad_name_locale("Ahri.en_US.wad.client"), Some(en_us));
+//! assert_eq!(wad_name_locale("Ahri.wad.client"), None);
+//!
+//! // Stringtable filenames
+//! assert_eq!(parse_stringtable_name("main_en_us.stringtable"), Some(en_us));
+//! assert_eq!(parse_stringtable_name("main_ja_jp.stringtable").unwrap().as_str(), "ja_JP");
+//! assert_eq!(parse_stringtable_name("main_es_mx.stringtable").unwrap().as_str(), "es_MX");
+//! assert_eq!(parse_stringtable_name("main.stringtable"), None);
+//! assert_eq!(parse_stringtable_name("main_en_us.txt"), None);
+//! assert_eq!(parse_stringtable_name("lol_en_us.stringtable"), None);
+//!
+//! // Paths
+//! assert_eq!(path_locale("data/menu/main_en_us.stringtable"), Some(en_us));
+//! assert_eq!(path_locale("data/menu/en_us/lol.stringtable"), Some(en_us));
+//! assert_eq!(path_locale("assets/sounds/wwise2016/vo/fr_fr/characters/ahri/ahri_base_vo_audio.wpk").unwrap().as_str(), "fr_FR");
+//! assert_eq!(path_locale("DATA/FINAL/Champions/Ahri.en_US.wad.client"), Some(en_us));
+//! assert_eq!(path_locale(r"DATA\FINAL\Localized\Global.tr_TR.wad.client").unwrap().as_str(), "tr_TR");
+//! assert_eq!(path_locale("DATA/FINAL/Champions/Ahri.wad.client"), None);
+//! assert_eq!(path_locale("data/characters/ahri/ahri.bin"), None);
+//! // Directories are only matched against known locales
+//! assert_eq!(path_locale("assets/maps/sr_ui/icon.png"), None);
+//! assert!(en_us.is_known());
+//! assert!(!Locale::new("sr_ui").unwrap().is_known());
+//! ```
+use std::fmt;
+use std::str::FromStr;
+
+/// Riot locale code, such as `en_US`
+///
+/// A locale is made of a two-letter language and a two-letter region, separated by an underscore.
+/// Parsing is case-insensitive and also accepts a dash as separator.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Locale([u8; 5]);
+
+impl Locale {
+    /// Parse a locale code, return `None` if it is invalid
+    pub fn new(code: &str) -> Option<Self> {
+        match code.as_bytes() {
+            &[l0, l1, b'_' | b'-', r0, r1] if [l0, l1, r0, r1].iter().all(u8::is_ascii_alphabetic) => {
+                Some(Self([
+                    l0.to_ascii_lowercase(),
+                    l1.to_ascii_lowercase(),
+                    b'_',
+                    r0.to_ascii_uppercase(),
+                    r1.to_ascii_uppercase(),
+                ]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the locale code, as used in RMAN flags and WAD filenames (e.g. `en_US`)
+    pub fn as_str(&self) -> &str {
+        // Only ASCII letters and underscore, checked on creation
+        std::str::from_utf8(&self.0).unwrap()
+    }
+
+    /// Get the lowercase locale code, as used in game paths (e.g. `en_us`)
+    pub fn to_lowercase(&self) -> String {
+        self.as_str().to_ascii_lowercase()
+    }
+
+    /// Get the language part of the locale (e.g. `en`)
+    pub fn language(&self) -> &str {
+        &self.as_str()[..2]
+    }
+
+    /// Get the region part of the locale (e.g. `US`)
+    pub fn region(&self) -> &str {
+        &self.as_str()[3..]
+    }
+
+    /// Return `true` if the locale is one used by Riot, see [KNOWN_LOCALES]
+    pub fn is_known(&self) -> bool {
+        KNOWN_LOCALES.contains(&self.as_str())
+    }
+}
+
+/// Locales used by Riot for game and client files
+pub const KNOWN_LOCALES: &[&str] = &[
+    "ar_AE", "cs_CZ", "de_DE", "el_GR", "en_AU", "en_GB", "en_PH", "en_SG", "en_US",
+    "es_AR", "es_ES", "es_MX", "fr_FR", "hu_HU", "id_ID", "it_IT", "ja_JP", "ko_KR",
+    "ms_MY", "pl_PL", "pt_BR", "ro_RO", "ru_RU", "th_TH", "tr_TR", "vi_VN", "zh_CN",
+    "zh_MY", "zh_TW",
+];
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Locale({})", self.as_str())
+    }
+}
+
+/// Error returned when parsing an invalid locale code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidLocale(pub String);
+
+impl fmt::Display for InvalidLocale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid locale code: {:?} (expected a code such as `en_US`)", self.0)
+    }
+}
+
+impl std::error::Error for InvalidLocale {}
+
+impl FromStr for Locale {
+    type Err = InvalidLocale;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s).ok_or_else(|| InvalidLocale(s.to_owned()))
+    }
+}
+
+/// Strip a suffix, ignoring ASCII case
+fn strip_suffix_ignore_case<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let len = s.len().checked_sub(suffix.len())?;
+    if s.get(len..)?.eq_ignore_ascii_case(suffix) {
+        Some(&s[..len])
+    } else {
+        None
+    }
+}
+
+/// Parse a WAD filename, return its base name and locale, if any
+///
+/// Game WADs are named `<Name>.wad.client` or `<Name>.<locale>.wad.client`.
+/// Client WADs use the same naming, with a `.wad` extension.
+/// Return `None` if the filename is not a WAD filename, or if its locale is invalid.
+pub fn parse_wad_name(filename: &str) -> Option<(&str, Option<Locale>)> {
+    let base = strip_suffix_ignore_case(filename, ".wad.client")
+        .or_else(|| strip_suffix_ignore_case(filename, ".wad"))
+        .filter(|s| !s.is_empty())?;
+    match base.split_once('.') {
+        None => Some((base, None)),
+        Some((name, locale)) => Some((name, Some(Locale::new(locale)?))),
+    }
+}
+
+/// Get the locale of a WAD from its filename, `None` if it is not localized
+pub fn wad_name_locale(filename: &str) -> Option<Locale> {
+    parse_wad_name(filename)?.1
+}
+
+/// Parse a stringtable filename, return its locale
+///
+/// Stringtables are named `main_<locale>.stringtable`.
+pub fn parse_stringtable_name(filename: &str) -> Option<Locale> {
+    let base = strip_suffix_ignore_case(filename, ".stringtable")?;
+    let code = base.get(..5).filter(|s| s.eq_ignore_ascii_case("main_")).and(base.get(5..))?;
+    Locale::new(code)
+}
+
+/// Get the locale of a file from its path, `None` if it does not look localized
+///
+/// The locale is taken from the filename if it is a localized WAD or a stringtable, or from a
+/// directory named after a known locale (e.g. `data/menu/en_us/lol.stringtable`), see
+/// [Locale::is_known()]. Both `/` and `\` are accepted as separators.
+pub fn path_locale(path: &str) -> Option<Locale> {
+    let mut components = path.rsplit(['/', '\\']);
+    let filename = components.next()?;
+    wad_name_locale(filename)
+        .or_else(|| parse_stringtable_name(filename))
+        .or_else(|| components.filter_map(Locale::new).find(Locale::is_known))
+}
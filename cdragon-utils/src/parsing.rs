@@ -28,6 +28,134 @@ impl<R: std::io::Read> ReadArray for R {
     }
 }
 
+/// Cursor over a byte slice, with bounds-checked reads
+///
+/// Integers are read as little-endian.
+/// Reads outside the data return [ParseError::NotEnoughData] and leave the cursor unchanged,
+/// instead of panicking.
+///
+/// # Example
+/// ```
+/// # use cdragon_utils::parsing::{ByteCursor, ParseError};
+/// let data = [1, 0x34, 0x12, 0x08, 0, 0, 0, 0xff];
+/// let mut cursor = ByteCursor::new(&data, 0);
+/// assert_eq!(cursor.read_u8().unwrap(), 1);
+/// assert_eq!(cursor.read_u16().unwrap(), 0x1234);
+/// // Offsets are relative to their own position
+/// assert_eq!(cursor.read_offset().unwrap(), 11);
+/// assert_eq!(cursor.offset(), 7);
+/// assert!(matches!(cursor.read_u32(), Err(ParseError::NotEnoughData)));
+/// assert_eq!(cursor.offset(), 7);
+/// assert_eq!(cursor.read_u8().unwrap(), 0xff);
+/// // Move backwards
+/// cursor.skip(-5).unwrap();
+/// assert_eq!(cursor.peek_i32().unwrap(), 8);
+/// assert!(cursor.skip(-4).is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ByteCursor<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+/// Define methods to read and peek little-endian integers
+macro_rules! impl_cursor_read_int {
+    ($($t:ty: $read:ident, $peek:ident;)*) => {
+        $(
+            #[doc = concat!("Read a `", stringify!($t), "`, advance the cursor")]
+            pub fn $read(&mut self) -> Result<$t, ParseError> {
+                let v = self.$peek()?;
+                self.offset += std::mem::size_of::<$t>();
+                Ok(v)
+            }
+
+            #[doc = concat!("Read a `", stringify!($t), "`, don't advance the cursor")]
+            pub fn $peek(&self) -> Result<$t, ParseError> {
+                self.peek_array().map(<$t>::from_le_bytes)
+            }
+        )*
+    }
+}
+
+impl<'a> ByteCursor<'a> {
+    /// Create a new cursor on data, at given offset
+    ///
+    /// The offset is allowed to be out of data. Reads will fail.
+    pub fn new(data: &'a [u8], offset: usize) -> Self {
+        Self { data, offset }
+    }
+
+    /// Get the underlying data
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Get the current offset
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Get the number of bytes remaining after the cursor
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.offset)
+    }
+
+    /// Move the cursor to an absolute offset
+    ///
+    /// Offset can be equal to the data length (but not beyond).
+    pub fn seek(&mut self, offset: usize) -> Result<(), ParseError> {
+        if offset > self.data.len() {
+            return Err(ParseError::NotEnoughData);
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
+    /// Move the cursor by `n` bytes, rewind if negative
+    pub fn skip(&mut self, n: isize) -> Result<(), ParseError> {
+        let offset = self.offset.checked_add_signed(n).ok_or(ParseError::Error)?;
+        self.seek(offset)
+    }
+
+    /// Get a slice of `n` bytes, don't advance the cursor
+    pub fn peek_slice(&self, n: usize) -> Result<&'a [u8], ParseError> {
+        self.offset.checked_add(n)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or(ParseError::NotEnoughData)
+    }
+
+    /// Read a slice of `n` bytes, advance the cursor
+    pub fn read_slice(&mut self, n: usize) -> Result<&'a [u8], ParseError> {
+        let slice = self.peek_slice(n)?;
+        self.offset += n;
+        Ok(slice)
+    }
+
+    /// Get a fixed-size array, don't advance the cursor
+    pub fn peek_array<const N: usize>(&self) -> Result<[u8; N], ParseError> {
+        self.peek_slice(N).map(|s| s.try_into().unwrap())
+    }
+
+    impl_cursor_read_int! {
+        u8: read_u8, peek_u8;
+        u16: read_u16, peek_u16;
+        u32: read_u32, peek_u32;
+        u64: read_u64, peek_u64;
+        i32: read_i32, peek_i32;
+    }
+
+    /// Read a signed 32-bit offset, relative to its own position, return the absolute offset
+    ///
+    /// The returned offset is not checked against data length, but it cannot be negative.
+    pub fn read_offset(&mut self) -> Result<usize, ParseError> {
+        let base = self.offset;
+        let offset = self.peek_i32()?;
+        let target = base.checked_add_signed(offset as isize).ok_or(ParseError::Error)?;
+        self.offset += 4;
+        Ok(target)
+    }
+}
+
 
 /// Parsing error
 #[allow(missing_docs)]
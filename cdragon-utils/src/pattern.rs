@@ -0,0 +1,215 @@
+//! Match paths against glob-like patterns
+//!
+//! See [PathPattern] for the supported syntax.
+
+/// Glob-like pattern to match paths
+///
+/// Paths use `/` as separator. The whole path must match: patterns are anchored at both ends.
+///
+/// Supported syntax:
+///
+/// - `*` matches any sequence of characters, except `/`
+/// - `**` matches any sequence of characters, including `/`; when followed by a `/`, it also
+///   matches zero directories (`a/**/b` matches `a/b`)
+/// - `?` matches any single character, except `/`
+/// - `[abc]` matches one of the listed characters, ranges are supported (`[a-z]`); use `[!...]`
+///   or `[^...]` to negate the class; classes never match `/`
+/// - any other character matches itself
+///
+/// Unclosed brackets are matched literally.
+///
+/// Matching is case-sensitive, unless enabled with [Self::case_insensitive()] (only ASCII
+/// characters are folded).
+///
+/// # Example
+/// ```
+/// # use cdragon_utils::pattern::PathPattern;
+/// let pattern = PathPattern::new("data/*.bin");
+/// assert!(pattern.is_match("data/foo.bin"));
+/// assert!(!pattern.is_match("data/sub/foo.bin"));
+/// assert!(!pattern.is_match("data/foo.bin.old"));
+///
+/// let pattern = PathPattern::new("data/**/*.bin");
+/// assert!(pattern.is_match("data/foo.bin"));
+/// assert!(pattern.is_match("data/sub/dir/foo.bin"));
+/// assert!(!pattern.is_match("other/data/foo.bin"));
+///
+/// let pattern = PathPattern::new("a/**/b");
+/// assert!(pattern.is_match("a/b"));
+/// assert!(pattern.is_match("a/x/y/b"));
+/// assert!(!pattern.is_match("a/xb"));
+///
+/// let pattern = PathPattern::new("**.dds");
+/// assert!(pattern.is_match("assets/characters/foo.dds"));
+///
+/// let pattern = PathPattern::new("skin[0-9]?.bin");
+/// assert!(pattern.is_match("skin12.bin"));
+/// assert!(!pattern.is_match("skin1.bin"));
+/// assert!(!pattern.is_match("skinx2.bin"));
+///
+/// let pattern = PathPattern::new("[!a-c]*");
+/// assert!(pattern.is_match("data"));
+/// assert!(!pattern.is_match("assets"));
+///
+/// let pattern = PathPattern::new("file[1");
+/// assert!(pattern.is_match("file[1"));
+///
+/// let pattern = PathPattern::new("Data/*.BIN").case_insensitive(true);
+/// assert!(pattern.is_match("data/foo.bin"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PathPattern {
+    tokens: Vec<Token>,
+    case_insensitive: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Token {
+    /// Literal character
+    Char(char),
+    /// `?`
+    One,
+    /// `*`
+    Star,
+    /// `**`
+    DeepStar,
+    /// `**/`, match nothing, or anything ending with a `/`
+    DeepDir,
+    /// `[...]`
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+impl PathPattern {
+    /// Compile a pattern
+    pub fn new(pattern: &str) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    i += 2;
+                    while chars.get(i) == Some(&'*') {
+                        i += 1;
+                    }
+                    if chars.get(i) == Some(&'/') {
+                        tokens.push(Token::DeepDir);
+                        i += 1;
+                    } else {
+                        tokens.push(Token::DeepStar);
+                    }
+                    continue;
+                }
+                '*' => tokens.push(Token::Star),
+                '?' => tokens.push(Token::One),
+                '[' => {
+                    if let Some((token, len)) = Self::parse_class(&chars[i..]) {
+                        tokens.push(token);
+                        i += len;
+                        continue;
+                    }
+                    tokens.push(Token::Char('['));
+                }
+                c => tokens.push(Token::Char(c)),
+            }
+            i += 1;
+        }
+        Self { tokens, case_insensitive: false }
+    }
+
+    /// Parse a character class, return it with its length, `None` if not closed
+    fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+        let mut i = 1;
+        let negated = matches!(chars.get(i), Some('!' | '^'));
+        if negated {
+            i += 1;
+        }
+        let mut ranges = Vec::new();
+        // A `]` right after the opening bracket is literal
+        let start = i;
+        loop {
+            let c = *chars.get(i)?;
+            if c == ']' && i > start {
+                return Some((Token::Class { negated, ranges }, i + 1));
+            }
+            match (chars.get(i + 1), chars.get(i + 2)) {
+                (Some('-'), Some(&end)) if end != ']' => {
+                    ranges.push((c, end));
+                    i += 3;
+                }
+                _ => {
+                    ranges.push((c, c));
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    /// Enable or disable case-insensitive matching
+    pub fn case_insensitive(mut self, enable: bool) -> Self {
+        self.case_insensitive = enable;
+        self
+    }
+
+    /// Return `true` if the given path matches the pattern
+    pub fn is_match(&self, path: &str) -> bool {
+        // Simulate the pattern as an automaton: state `i` means "tokens before `i` matched"
+        let n = self.tokens.len();
+        let mut states = vec![false; n + 1];
+        let mut next = vec![false; n + 1];
+        self.enter(&mut states, 0);
+
+        for c in path.chars() {
+            next.fill(false);
+            for (i, token) in self.tokens.iter().enumerate() {
+                if !states[i] {
+                    continue;
+                }
+                match token {
+                    Token::Char(p) if self.char_eq(*p, c) => self.enter(&mut next, i + 1),
+                    Token::One if c != '/' => self.enter(&mut next, i + 1),
+                    Token::Class { negated, ranges } if c != '/' && self.class_match(ranges, c) != *negated => {
+                        self.enter(&mut next, i + 1)
+                    }
+                    Token::Star if c != '/' => self.enter(&mut next, i),
+                    Token::DeepStar => self.enter(&mut next, i),
+                    // Once something has been consumed, `**/` must end with a `/`
+                    Token::DeepDir if c == '/' => self.enter(&mut next, i),
+                    Token::DeepDir => next[i] = true,
+                    _ => {}
+                }
+            }
+            if !next.contains(&true) {
+                return false;
+            }
+            std::mem::swap(&mut states, &mut next);
+        }
+        states[n]
+    }
+
+    /// Activate a state, and following ones reachable by matching an empty string
+    fn enter(&self, states: &mut [bool], mut i: usize) {
+        states[i] = true;
+        while let Some(Token::Star | Token::DeepStar | Token::DeepDir) = self.tokens.get(i) {
+            i += 1;
+            states[i] = true;
+        }
+    }
+
+    fn char_eq(&self, a: char, b: char) -> bool {
+        if self.case_insensitive {
+            a.eq_ignore_ascii_case(&b)
+        } else {
+            a == b
+        }
+    }
+
+    fn class_match(&self, ranges: &[(char, char)], c: char) -> bool {
+        let in_ranges = |c: char| ranges.iter().any(|&(begin, end)| begin <= c && c <= end);
+        if self.case_insensitive {
+            in_ranges(c.to_ascii_lowercase()) || in_ranges(c.to_ascii_uppercase())
+        } else {
+            in_ranges(c)
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Report progress of long operations
+//!
+//! Long operations accept a [Progress] implementation to report their progress, as a sequence of
+//! [ProgressEvent]. Closures can be used directly (the event type must be annotated).
+//! Use `()` to ignore progress.
+//!
+//! # Example
+//! ```
+//! # use cdragon_utils::progress::{Progress, ProgressEvent};
+//! fn copy(name: &str, data: &[u8], progress: &mut dyn Progress) -> Vec<u8> {
+//!     progress.on_event(ProgressEvent::FileStarted { name, total_bytes: Some(data.len() as u64) });
+//!     let mut output = Vec::new();
+//!     for chunk in data.chunks(2) {
+//!         output.extend(chunk);
+//!         progress.on_event(ProgressEvent::BytesTransferred { n: chunk.len() as u64 });
+//!     }
+//!     progress.on_event(ProgressEvent::FileFinished);
+//!     output
+//! }
+//!
+//! let mut transferred = 0;
+//! copy("example", b"abcde", &mut |event: ProgressEvent| {
+//!     if let ProgressEvent::BytesTransferred { n } = event {
+//!         transferred += n;
+//!     }
+//! });
+//! assert_eq!(transferred, 5);
+//! // No progress reporting
+//! copy("example", b"abcde", &mut ());
+//! ```
+
+/// Progress event of a long operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressEvent<'a> {
+    /// Processing of a file started
+    FileStarted {
+        /// Name of the file, typically its path
+        name: &'a str,
+        /// Total size of the file, if known
+        total_bytes: Option<u64>,
+    },
+    /// Bytes of the current file have been processed
+    BytesTransferred {
+        /// Number of bytes processed since the previous event
+        n: u64,
+    },
+    /// Processing of the current file is finished
+    FileFinished,
+}
+
+/// Receive progress events
+pub trait Progress {
+    /// Called on each progress event
+    fn on_event(&mut self, event: ProgressEvent);
+}
+
+impl<F: FnMut(ProgressEvent)> Progress for F {
+    fn on_event(&mut self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+/// Ignore progress events
+impl Progress for () {
+    fn on_event(&mut self, _event: ProgressEvent) {}
+}
@@ -0,0 +1,145 @@
+//! Write WAD entries to archives
+use std::borrow::Borrow;
+use std::io::{self, Read, Seek, Write};
+use tar::{Builder, EntryType, Header};
+use cdragon_hashes::wad::{WadHashMapper, normalize_wad_path};
+use super::{
+    Result,
+    WadEntry,
+    WadReader,
+    relative_link_target,
+};
+
+impl<R: Read + Seek> WadReader<R> {
+    /// Write entries to a tar archive, without intermediate files
+    ///
+    /// Entries are stored with their decompressed data, at their path resolved from `hmapper`;
+    /// unknown paths are named after the entry hash. Redirection entries are stored as symbolic
+    /// links, using a relative path, like [Self::extract_entry_as_link()]; links or targets that
+    /// would be outside of the archive are rejected the same way.
+    ///
+    /// Return the writer, once the archive is finished.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cdragon_wad::prelude::*;
+    /// let mut wad = WadFile::open("example.wad.client").unwrap();
+    /// let hmapper = WadHashMapper::from_path("hashes.game.txt").unwrap();
+    /// let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+    /// let output = std::fs::File::create("example.tar").unwrap();
+    /// wad.write_to_tar(&entries, &hmapper, output).unwrap();
+    /// ```
+    pub fn write_to_tar<I, W>(&mut self, entries: I, hmapper: &WadHashMapper, writer: W) -> Result<W>
+    where I: IntoIterator, I::Item: Borrow<WadEntry>, W: Write {
+        let mut builder = Builder::new(writer);
+        for entry in entries {
+            let entry = entry.borrow();
+            let path = match hmapper.get(entry.path.hash) {
+                Some(path) => path.to_owned(),
+                None => format!("{:x}", entry.path),
+            };
+            let mut header = Header::new_gnu();
+            header.set_mode(0o644);
+            if entry.is_redirection() {
+                let target = normalize_wad_path(&self.read_redirection_target(entry)?);
+                let link = relative_link_target(&path, &target)?.join("/");
+                header.set_entry_type(EntryType::Symlink);
+                header.set_size(0);
+                builder.append_link(&mut header, &path, link)?;
+            } else {
+                let size = entry.target_size as u64;
+                header.set_size(size);
+                let reader = ExactReader { inner: self.read_entry(entry)?, remaining: size };
+                builder.append_data(&mut header, &path, reader)?;
+            }
+        }
+        Ok(builder.into_inner()?)
+    }
+}
+
+/// Reader that fails if inner data is shorter than expected, and truncate it if longer
+///
+/// Archive headers are written before data, the size must not change.
+struct ExactReader<R: Read> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for ExactReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+        let len = buf.len().min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use cdragon_hashes::wad::WadHashMapper;
+    use crate::fixtures::*;
+    use crate::{WadError, WadReader};
+
+    #[test]
+    fn write_to_tar_entries() {
+        // Entry `1` is known, `2` is not
+        let data = wad_data(&[TestEntry::new(1, b"hello"), TestEntry::new(2, b"abc")]);
+        let mut wad = WadReader::from_bytes(data).unwrap();
+        let mut hmapper = WadHashMapper::new();
+        hmapper.insert(1, "data/hello.txt".to_string());
+
+        let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+        let archive = wad.write_to_tar(&entries, &hmapper, Vec::new()).unwrap();
+
+        let mut archive = tar::Archive::new(&archive[..]);
+        let files: Vec<(String, String)> = archive.entries().unwrap().map(|entry| {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_str().unwrap().to_owned();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            (path, content)
+        }).collect();
+        assert_eq!(files, [
+            ("data/hello.txt".into(), "hello".into()),
+            ("0000000000000002".into(), "abc".into()),
+        ]);
+    }
+
+    #[test]
+    fn write_to_tar_redirections() {
+        let target = redirection_data("data/target.txt");
+        let data = wad_data(&[TestEntry::new(1, &target).with_format(2, 0)]);
+        let mut wad = WadReader::from_bytes(data).unwrap();
+        let mut hmapper = WadHashMapper::new();
+        hmapper.insert(1, "data/sub/link.txt".to_string());
+
+        let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+        let archive = wad.write_to_tar(&entries, &hmapper, Vec::new()).unwrap();
+        let mut archive = tar::Archive::new(&archive[..]);
+        let entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.link_name().unwrap().unwrap().to_str().unwrap(), "../../data/target.txt");
+
+        // Escaping target
+        let target = redirection_data("../../etc/passwd");
+        let data = wad_data(&[TestEntry::new(1, &target).with_format(2, 0)]);
+        let mut wad = WadReader::from_bytes(data).unwrap();
+        let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+        assert!(matches!(wad.write_to_tar(&entries, &hmapper, Vec::new()), Err(WadError::InvalidRedirection)));
+
+        // Escaping link path, from the hash mapper
+        let target = redirection_data("data/target.txt");
+        let data = wad_data(&[TestEntry::new(1, &target).with_format(2, 0)]);
+        let mut wad = WadReader::from_bytes(data).unwrap();
+        hmapper.insert(1, "../../link.txt".to_string());
+        let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+        assert!(matches!(wad.write_to_tar(&entries, &hmapper, Vec::new()), Err(WadError::InvalidRedirection)));
+    }
+}
@@ -0,0 +1,264 @@
+//! Guess file extensions from file data
+//!
+//! WAD entries are identified by a path hash. When the path is not known, the extension can
+//! still be guessed from the first bytes of the entry data.
+use std::io::Read;
+
+/// Magic bytes identifying a file format
+///
+/// Data matches if bytes at `offset` are equal to `pattern`. If a `mask` is set, data bytes are
+/// masked before comparison; null mask bytes match any value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileMagic {
+    /// Offset of the pattern in file data
+    pub offset: usize,
+    /// Bytes to match
+    pub pattern: &'static [u8],
+    /// Mask applied to data bytes, must have the same length as `pattern`
+    pub mask: Option<&'static [u8]>,
+    /// Extension of matching files (without leading dot)
+    pub ext: &'static str,
+}
+
+impl FileMagic {
+    /// Match data starting with `pattern`
+    pub const fn prefix(pattern: &'static [u8], ext: &'static str) -> Self {
+        Self { offset: 0, pattern, mask: None, ext }
+    }
+
+    /// Match data with `pattern` at given offset
+    pub const fn at(offset: usize, pattern: &'static [u8], ext: &'static str) -> Self {
+        Self { offset, pattern, mask: None, ext }
+    }
+
+    /// Match data starting with `pattern`, after applying `mask`
+    ///
+    /// Panic if `pattern` and `mask` lengths differ. Built in a constant, this is a compile error.
+    ///
+    /// ```should_panic
+    /// # use cdragon_wad::FileMagic;
+    /// FileMagic::masked(b"ABC", b"\xff\xff", "abc");
+    /// ```
+    pub const fn masked(pattern: &'static [u8], mask: &'static [u8], ext: &'static str) -> Self {
+        assert!(pattern.len() == mask.len(), "mask and pattern lengths differ");
+        Self { offset: 0, pattern, mask: Some(mask), ext }
+    }
+
+    /// Return true if data matches the magic
+    ///
+    /// ```
+    /// # use cdragon_wad::FileMagic;
+    /// assert!(FileMagic::prefix(b"PROP", "bin").matches(b"PROP\x03\0\0\0"));
+    /// assert!(!FileMagic::prefix(b"PROP", "bin").matches(b"PRO"));
+    /// assert!(FileMagic::at(4, b"ftyp", "mp4").matches(b"\0\0\0\x20ftypisom"));
+    /// assert!(!FileMagic::at(4, b"ftyp", "mp4").matches(b"ftyp"));
+    /// let magic = FileMagic::masked(b"AB\x00D", b"\xff\xff\x00\xff", "abd");
+    /// assert!(magic.matches(b"ABCD"));
+    /// assert!(magic.matches(b"ABxD"));
+    /// assert!(!magic.matches(b"ABCE"));
+    /// assert!(!FileMagic::at(usize::MAX, b"ftyp", "mp4").matches(b"ftyp"));
+    /// ```
+    pub fn matches(&self, data: &[u8]) -> bool {
+        let Some(end) = self.offset.checked_add(self.pattern.len()) else {
+            return false;
+        };
+        let data = match data.get(self.offset..end) {
+            Some(data) => data,
+            None => return false,
+        };
+        match self.mask {
+            None => data == self.pattern,
+            Some(mask) => data.iter().zip(mask).zip(self.pattern).all(|((b, m), p)| b & m == p & m),
+        }
+    }
+
+    /// Number of data bytes needed to check the magic
+    fn end(&self) -> usize {
+        self.offset + self.pattern.len()
+    }
+}
+
+/// Mask for RIFF containers: match the `RIFF` tag and the format, not the chunk size
+const RIFF_MASK: &[u8] = b"\xff\xff\xff\xff\0\0\0\0\xff\xff\xff\xff";
+
+/// Built-in magics, checked in order
+const BUILTIN_MAGICS: &[FileMagic] = &[
+    FileMagic::prefix(b"\xff\xd8\xff", "jpg"),
+    FileMagic::prefix(b"\x89PNG\x0d\x0a\x1a\x0a", "png"),
+    FileMagic::prefix(b"OggS", "ogg"),
+    FileMagic::prefix(b"\x00\x01\x00\x00", "ttf"),
+    FileMagic::prefix(b"\x1a\x45\xdf\xa3", "webm"),
+    FileMagic::prefix(b"true", "ttf"),
+    FileMagic::prefix(b"OTTO\0", "otf"),
+    FileMagic::prefix(b"\"use strict\";", "min.js"),
+    FileMagic::prefix(b"<template ", "template.html"),
+    FileMagic::prefix(b"<!-- Elements -->", "template.html"),
+    FileMagic::prefix(b"DDS ", "dds"),
+    FileMagic::prefix(b"<svg", "svg"),
+    FileMagic::prefix(b"PROP", "bin"),
+    FileMagic::prefix(b"PTCH", "bin"),
+    FileMagic::prefix(b"BKHD", "bnk"),
+    FileMagic::prefix(b"r3d2Mesh", "scb"),
+    FileMagic::prefix(b"r3d2anmd", "anm"),
+    FileMagic::prefix(b"r3d2canm", "anm"),
+    FileMagic::prefix(b"r3d2sklt", "skl"),
+    FileMagic::prefix(b"r3d2", "wpk"),
+    FileMagic::prefix(b"\x33\x22\x11\x00", "skn"),
+    FileMagic::prefix(b"PreLoadBuildingBlocks = {", "preload"),
+    FileMagic::prefix(b"\x1bLuaQ\x00\x01\x04\x04", "luabin"),
+    FileMagic::prefix(b"\x1bLuaQ\x00\x01\x04\x08", "luabin64"),
+    FileMagic::prefix(b"\x02\x3d\x00\x28", "troybin"),
+    FileMagic::prefix(b"[ObjectBegin]", "sco"),
+    FileMagic::prefix(b"OEGM", "mapgeo"),
+    FileMagic::prefix(b"TEX\0", "tex"),
+    FileMagic::masked(b"RIFF\0\0\0\0WEBP", RIFF_MASK, "webp"),
+    FileMagic::masked(b"RIFF\0\0\0\0WAVE", RIFF_MASK, "wav"),
+    FileMagic::prefix(b"\xabKTX 11\xbb\r\n\x1a\n", "ktx"),
+    FileMagic::prefix(b"\xabKTX 20\xbb\r\n\x1a\n", "ktx2"),
+    // Nested compressed data
+    FileMagic::prefix(b"\x1f\x8b", "gz"),
+    FileMagic::prefix(b"\x28\xb5\x2f\xfd", "zst"),
+];
+
+/// Number of bytes read to guess an extension
+///
+/// Extra magics needing more data than this never match.
+const GUESS_DATA_LEN: usize = 32;
+
+/// Guess file extension from a reader
+///
+/// Only the first bytes of data are read. Besides known magics, UTF-16 text and JSON data are
+/// detected.
+///
+/// ```
+/// # use cdragon_wad::guess_extension;
+/// fn guess(data: &[u8]) -> Option<&'static str> {
+///     guess_extension(&mut &data[..]).unwrap()
+/// }
+/// assert_eq!(guess(b"PROP\x03\0\0\0"), Some("bin"));
+/// assert_eq!(guess(b"RIFF\x24\x10\0\0WEBPVP8 "), Some("webp"));
+/// assert_eq!(guess(b"RIFF\x24\x10\0\0WAVEfmt "), Some("wav"));
+/// assert_eq!(guess(b"RIFF\x24\x10\0\0AVI LIST"), None);
+/// assert_eq!(guess(b"RIFF"), None);
+/// assert_eq!(guess(b"\xabKTX 11\xbb\r\n\x1a\n\x01\x02\x03\x04"), Some("ktx"));
+/// assert_eq!(guess(b"\xabKTX 20\xbb\r\n\x1a\n\0\0\0\0"), Some("ktx2"));
+/// assert_eq!(guess(b"\x1f\x8b\x08\0\0\0\0\0"), Some("gz"));
+/// assert_eq!(guess(b"\x28\xb5\x2f\xfd\x20\x04"), Some("zst"));
+/// assert_eq!(guess(b"{\"key\": [1, 2"), Some("json"));
+/// assert_eq!(guess(b"\xff\xfe{\0}\0"), Some("json"));
+/// assert_eq!(guess(b"\x01\x02\x03\x04"), None);
+/// assert_eq!(guess(b""), None);
+/// assert_eq!(guess(b"  \n"), None);
+/// ```
+///
+/// UTF-8 text with a BOM is guessed from the data following the BOM, and is at least `txt`.
+/// ```
+/// # use cdragon_wad::guess_extension;
+/// # fn guess(data: &[u8]) -> Option<&'static str> {
+/// #     guess_extension(&mut &data[..]).unwrap()
+/// # }
+/// assert_eq!(guess(b"\xef\xbb\xbf{\"key\": [1, 2"), Some("json"));
+/// assert_eq!(guess(b"\xef\xbb\xbf\n[1]"), Some("json"));
+/// assert_eq!(guess(b"\xef\xbb\xbf<svg xmlns=\"http://www.w3.org/2000/svg\">"), Some("svg"));
+/// assert_eq!(guess(b"\xef\xbb\xbf<?xml version=\"1.0\"?>"), Some("txt"));
+/// assert_eq!(guess(b"<?xml version=\"1.0\"?>"), None);
+/// assert_eq!(guess(b"\xef\xbb\xbf"), Some("txt"));
+/// // Same for UTF-16 BOMs
+/// assert_eq!(guess(b"\xfe\xff"), Some("txt"));
+/// assert_eq!(guess(b"\xff\xfe<\0?\0x\0m\0l\0"), Some("txt"));
+/// ```
+pub fn guess_extension(reader: &mut dyn Read) -> std::io::Result<Option<&'static str>> {
+    guess_extension_with(reader, &[])
+}
+
+/// Guess file extension from a reader, with additional magics
+///
+/// Extra magics are checked before built-in ones, and thus can override them.
+/// Magics must fit in the first 32 bytes of data.
+///
+/// ```
+/// # use cdragon_wad::{FileMagic, guess_extension_with};
+/// const EXTRA: &[FileMagic] = &[
+///     FileMagic::prefix(b"MYFMT", "my"),
+///     FileMagic::at(4, b"ftyp", "mp4"),
+///     FileMagic::prefix(b"\x1f\x8b\x08", "gzip"),
+/// ];
+/// fn guess(data: &[u8]) -> Option<&'static str> {
+///     guess_extension_with(&mut &data[..], EXTRA).unwrap()
+/// }
+/// assert_eq!(guess(b"MYFMT\x01"), Some("my"));
+/// assert_eq!(guess(b"\0\0\0\x20ftypisom"), Some("mp4"));
+/// assert_eq!(guess(b"\x1f\x8b\x08\0"), Some("gzip"));
+/// // Built-in magics are still used
+/// assert_eq!(guess(b"\x1f\x8b\x00\0"), Some("gz"));
+/// assert_eq!(guess(b"OggS\0"), Some("ogg"));
+/// ```
+pub fn guess_extension_with(reader: &mut dyn Read, extra: &[FileMagic]) -> std::io::Result<Option<&'static str>> {
+    let mut buf: [u8; GUESS_DATA_LEN] = [0; GUESS_DATA_LEN];
+    let n = read_up_to(reader, &mut buf)?;
+    let buf = &buf[..n];
+
+    // UTF-16 text, assume JSON if it looks like it
+    if let Some(is_le) = match buf.get(..2) {
+        Some(b"\xff\xfe") => Some(true),
+        Some(b"\xfe\xff") => Some(false),
+        _ => None,
+    } {
+        let units = buf[2..].chunks_exact(2).map(|b| {
+            let b = [b[0], b[1]];
+            if is_le { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) }
+        });
+        let first = char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .find(|c| !c.is_whitespace());
+        return match first {
+            Some('{') | Some('[') => Ok(Some("json")),
+            _ => Ok(Some("txt")),
+        };
+    }
+
+    let find_magic = |data: &[u8]| {
+        extra.iter().chain(BUILTIN_MAGICS)
+            .find(|magic| magic.matches(data))
+            .map(|magic| magic.ext)
+    };
+    if let Some(ext) = find_magic(buf) {
+        return Ok(Some(ext));
+    }
+    // UTF-8 text with a BOM: guess from the data after it, fallback to plain text
+    if let Some(text) = buf.strip_prefix(b"\xef\xbb\xbf") {
+        return Ok(find_magic(text).or_else(|| guess_json(text)).or(Some("txt")));
+    }
+    Ok(guess_json(buf))
+}
+
+/// Return `json` if data looks like the beginning of a JSON value
+///
+/// Data may be truncated, but must not be empty or only whitespace.
+fn guess_json(data: &[u8]) -> Option<&'static str> {
+    if data.iter().all(|b| b.is_ascii_whitespace()) {
+        return None;
+    }
+    match serde_json::from_slice::<serde_json::Value>(data) {
+        Ok(_) => Some("json"),
+        Err(e) if e.is_eof() => Some("json"),
+        _ => None,
+    }
+}
+
+/// Read until the buffer is full or the end of data is reached
+///
+/// Offset-based magics need more than what a single `read()` may return (e.g. on a decoder).
+fn read_up_to(reader: &mut dyn Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    debug_assert!(BUILTIN_MAGICS.iter().all(|magic| magic.end() <= buf.len()));
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(k) => n += k,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
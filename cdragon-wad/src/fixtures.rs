@@ -0,0 +1,118 @@
+//! Build WAD data for tests
+//!
+//! Available with the `testing` feature, for tests of dependent crates.
+
+/// Offset of the first entry in a version 3 WAD
+pub const ENTRIES_OFFSET: usize = 272;
+/// Size of a version 3 entry
+pub const ENTRY_LEN: usize = 32;
+/// Offset of the first entry in a version 1 WAD
+pub const ENTRIES_OFFSET_V1: usize = 12;
+/// Size of a version 1 entry
+pub const ENTRY_LEN_V1: usize = 24;
+
+/// Entry of a test WAD
+pub struct TestEntry<'a> {
+    /// Path hash
+    pub hash: u64,
+    /// Data stored in the WAD (possibly compressed)
+    pub data: &'a [u8],
+    /// Raw data format, with subchunk count for chunked entries
+    pub format: u8,
+    /// Uncompressed size
+    pub target_size: u32,
+    /// Duplicate flag
+    pub duplicate: bool,
+    /// Offset and size stored in the entry, instead of the actual data location
+    pub location: Option<(u32, u32)>,
+}
+
+impl<'a> TestEntry<'a> {
+    /// Create an uncompressed entry
+    pub fn new(hash: u64, data: &'a [u8]) -> Self {
+        Self { hash, data, format: 0, target_size: data.len() as u32, duplicate: false, location: None }
+    }
+
+    /// Set the raw data format and the uncompressed size
+    pub fn with_format(self, format: u8, target_size: u32) -> Self {
+        Self { format, target_size, ..self }
+    }
+
+    /// Flag the entry as a duplicate
+    pub fn with_duplicate(self) -> Self {
+        Self { duplicate: true, ..self }
+    }
+
+    /// Store the given offset and size, to build invalid entries
+    ///
+    /// Data is still stored after the entries.
+    pub fn with_location(self, offset: u32, size: u32) -> Self {
+        Self { location: Some((offset, size)), ..self }
+    }
+
+    /// Return the offset and size to store, `offset` being the actual data offset
+    fn stored_location(&self, offset: usize) -> (u32, u32) {
+        self.location.unwrap_or((offset as u32, self.data.len() as u32))
+    }
+}
+
+/// Build a version 3 WAD
+///
+/// Entry data is stored after the entries, in order.
+pub fn wad_data(entries: &[TestEntry]) -> Vec<u8> {
+    let mut data = b"RW\x03\0".to_vec();
+    data.extend([0; 264]);
+    data.extend((entries.len() as u32).to_le_bytes());
+    let mut offset = ENTRIES_OFFSET + entries.len() * ENTRY_LEN;
+    for entry in entries {
+        data.extend(entry.hash.to_le_bytes());
+        let (stored_offset, stored_size) = entry.stored_location(offset);
+        data.extend(stored_offset.to_le_bytes());
+        data.extend(stored_size.to_le_bytes());
+        data.extend(entry.target_size.to_le_bytes());
+        data.extend([entry.format, entry.duplicate as u8, 0, 0]);  // format, duplicate, first subchunk index
+        data.extend([0; 8]);  // data hash
+        offset += entry.data.len();
+    }
+    for entry in entries {
+        data.extend(entry.data);
+    }
+    data
+}
+
+/// Build a version 1 WAD
+///
+/// Entry data is stored after the entries, in order.
+pub fn wad_data_v1(entries: &[TestEntry]) -> Vec<u8> {
+    let mut data = b"RW\x01\0".to_vec();
+    data.extend((ENTRIES_OFFSET_V1 as u16).to_le_bytes());
+    data.extend((ENTRY_LEN_V1 as u16).to_le_bytes());
+    data.extend((entries.len() as u32).to_le_bytes());
+    let mut offset = ENTRIES_OFFSET_V1 + entries.len() * ENTRY_LEN_V1;
+    for entry in entries {
+        data.extend(entry.hash.to_le_bytes());
+        let (stored_offset, stored_size) = entry.stored_location(offset);
+        data.extend(stored_offset.to_le_bytes());
+        data.extend(stored_size.to_le_bytes());
+        data.extend(entry.target_size.to_le_bytes());
+        data.extend([entry.format, entry.duplicate as u8, 0, 0]);  // format, duplicate, padding
+        offset += entry.data.len();
+    }
+    for entry in entries {
+        data.extend(entry.data);
+    }
+    data
+}
+
+/// Build a version 3 WAD with empty entries, for given hashes
+pub fn wad_data_with_hashes(hashes: &[u64]) -> Vec<u8> {
+    let entries: Vec<_> = hashes.iter().map(|hash| TestEntry::new(*hash, b"")).collect();
+    wad_data(&entries)
+}
+
+/// Build the data of a redirection entry
+pub fn redirection_data(target: &str) -> Vec<u8> {
+    let mut data = (target.len() as u32).to_le_bytes().to_vec();
+    data.extend(target.as_bytes());
+    data
+}
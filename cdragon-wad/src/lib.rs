@@ -1,8 +1,10 @@
 //! Support of Riot WAD archive files
 //!
+//! Commonly used types, including hash mappers, are available from the [prelude].
+//!
 //! # Example: list files in wad
 //! ```no_run
-//! use cdragon_wad::{WadFile, WadHashMapper};
+//! use cdragon_wad::prelude::*;
 //! let wad = WadFile::open("Global.wad.client").expect("failed to open WAD file");
 //! let hmapper = WadHashMapper::from_path("hashes.game.txt").expect("failed to load hashes");
 //! for entry in wad.iter_entries() {
@@ -15,15 +17,19 @@
 //! files are used).
 //! ```
 //! # use cdragon_hashes::HashKind;
-//! # use cdragon_wad::WadHashMapper;
+//! # use cdragon_wad::prelude::*;
 //! if let Some(kind) = HashKind::from_wad_path("Global.wad.client") {
 //!   let mapper = WadHashMapper::from_path(kind.mapping_path());
 //! }
 //! ```
 
+// `ReadArray::read_array()` collides with unstable `Read::read_array()`
+#![allow(unstable_name_collisions)]
+
 use std::fs::File;
-use std::io::{Read, Seek, SeekFrom, BufReader};
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom, BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use nom::{
     number::complete::{le_u8, le_u16, le_u32, le_u64},
     bytes::complete::tag,
@@ -33,14 +39,57 @@ use nom::{
 use thiserror::Error;
 use cdragon_hashes::{
     define_hash_type,
-    wad::compute_wad_hash,
+    wad::{self, WadHashKind, compute_wad_hash_normalized, normalize_wad_path},
 };
 use cdragon_utils::{
     GuardedFile,
     parsing::{ParseError, ReadArray},
     parse_buf,
 };
-pub use cdragon_hashes::wad::WadHashMapper;
+
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "mmap")]
+pub use mmap::WadMmap;
+#[cfg(feature = "tar")]
+mod archive;
+mod extension;
+#[cfg(any(test, feature = "testing"))]
+pub mod fixtures;
+pub use extension::{FileMagic, guess_extension, guess_extension_with};
+
+/// Commonly used WAD types and hash mappers
+///
+/// Hash mappers are defined in [cdragon_hashes::wad], which is their canonical path.
+/// They are re-exported here for convenience.
+///
+/// ```
+/// use cdragon_wad::prelude::*;
+/// let hmapper = WadHashMapper::new();
+/// assert!(hmapper.is_empty());
+/// ```
+pub mod prelude {
+    pub use super::{
+        Wad,
+        WadDataFormat,
+        WadEntry,
+        WadError,
+        WadFile,
+        WadReader,
+        guess_extension,
+    };
+    #[cfg(feature = "mmap")]
+    pub use super::WadMmap;
+    pub use cdragon_hashes::wad::{WadHashKind, WadHashMapper, WadHashMappers};
+}
+
+// Deprecated aliases, kept for backward compatibility
+/// Deprecated re-export of [cdragon_hashes::wad::WadHashMapper]
+#[deprecated(note = "use cdragon_hashes::wad::WadHashMapper, or import it from cdragon_wad::prelude")]
+pub type WadHashMapper = cdragon_hashes::wad::WadHashMapper;
+/// Deprecated re-export of [cdragon_hashes::wad::WadHashMappers]
+#[deprecated(note = "use cdragon_hashes::wad::WadHashMappers, or import it from cdragon_wad::prelude")]
+pub type WadHashMappers = cdragon_hashes::wad::WadHashMappers;
 
 
 /// Result type for WAD errors
@@ -55,7 +104,15 @@ pub struct Wad {
     /// WAD version (`(major, minor)`)
     pub version: (u8, u8),
     entry_count: u32,
+    /// Size of an entry record, depends on the version
+    entry_len: usize,
     entry_data: Vec<u8>,
+    /// True if entries are sorted by hash, as expected
+    sorted_entries: bool,
+    /// Parsed entries, filled on first use
+    ///
+    /// If an entry is invalid, the index of the first invalid entry is stored instead.
+    entries: OnceLock<Result<Vec<WadEntry>, usize>>,
 }
 
 impl std::fmt::Debug for Wad {
@@ -68,20 +125,32 @@ impl std::fmt::Debug for Wad {
 }
 
 impl Wad {
+    /// Size of an entry record, for version 1
+    const ENTRY_LEN_V1: usize = 24;
+    /// Size of an entry record, for versions 2 and 3
     const ENTRY_LEN: usize = 32;
 
     /// Read a WAD file, check header, read entry headers
+    ///
+    /// Versions 1 to 3 are supported. Version 1 entries have no data hash.
     pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
         let (version, entry_count, entry_offset) = Self::parse_header(reader)?;
+        let entry_len = if version.0 == 1 { Self::ENTRY_LEN_V1 } else { Self::ENTRY_LEN };
 
-        let data_size = Self::ENTRY_LEN * entry_count as usize;
+        let data_size = entry_len * entry_count as usize;
         let mut entry_data = Vec::with_capacity(data_size);
         reader.seek(SeekFrom::Start(entry_offset))?;
         if reader.take(data_size as u64).read_to_end(&mut entry_data)? != data_size {
             return Err(ParseError::NotEnoughData.into());
         }
 
-        Ok(Self { version, entry_count, entry_data })
+        let mut this = Self {
+            version, entry_count, entry_len, entry_data,
+            sorted_entries: false,
+            entries: OnceLock::new(),
+        };
+        this.sorted_entries = (1..this.entry_count()).all(|i| this.entry_hash(i - 1) <= this.entry_hash(i));
+        Ok(this)
     }
 
     /// Parse header, advance to the beginning of the body
@@ -95,6 +164,14 @@ impl Wad {
         };
 
         let (entry_count, entry_offset) = match version.0 {
+            1 => {
+                let buf = reader.read_array::<{2 + 2 + 4}>()?;
+                let (entry_offset, entry_size, entry_count) = parse_buf!(buf, tuple((le_u16, le_u16, le_u32)));
+                if entry_size as usize != Self::ENTRY_LEN_V1 {
+                    return Err(WadError::UnsupportedV1EntrySize(entry_size));
+                }
+                (entry_count, entry_offset as u64)
+            }
             2 => {
                 // Skip "useless" fields
                 reader.seek(SeekFrom::Current(84 + 8))?;
@@ -114,34 +191,138 @@ impl Wad {
                 let entry_offset = reader.stream_position()?;
                 (entry_count, entry_offset)
             }
-            // Note: version 1 could be supported
             _ => return Err(WadError::UnsupportedVersion(version.0, version.1)),
         };
 
         Ok((version, entry_count, entry_offset))
     }
 
+    /// Get the number of entries
+    pub fn entry_count(&self) -> usize {
+        self.entry_count as usize
+    }
+
+    /// Find an entry from its path hash
+    ///
+    /// Entries are expected to be sorted by hash, which allows a binary search. If they are not,
+    /// entries are scanned.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cdragon_wad::prelude::*;
+    /// # use cdragon_hashes::wad::compute_wad_hash;
+    /// let wad = WadFile::open("example.wad.client").unwrap();
+    /// let hash = compute_wad_hash("data/characters/ahri/ahri.bin");
+    /// if let Some(entry) = wad.find_entry(hash).unwrap() {
+    ///     println!("{} bytes", entry.target_size());
+    /// }
+    /// ```
+    pub fn find_entry(&self, hash: u64) -> Result<Option<WadEntry>> {
+        match self.find_entry_index(hash) {
+            Some(index) => Ok(Some(self.entry_at(index)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return `true` if an entry exists for a given path hash
+    pub fn contains(&self, hash: u64) -> bool {
+        self.find_entry_index(hash).is_some()
+    }
+
+    /// Find the index of an entry from its path hash
+    fn find_entry_index(&self, hash: u64) -> Option<usize> {
+        if !self.sorted_entries {
+            return (0..self.entry_count()).find(|&i| self.entry_hash(i) == hash);
+        }
+        let (mut lo, mut hi) = (0, self.entry_count());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.entry_hash(mid).cmp(&hash) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    /// Get the path hash of the entry at given index, without parsing the whole entry
+    fn entry_hash(&self, index: usize) -> u64 {
+        let offset = index * self.entry_len;
+        u64::from_le_bytes(self.entry_data[offset .. offset + 8].try_into().unwrap())
+    }
+
+    /// Get all entries, parsed
+    ///
+    /// Entries are parsed on first call, then cached.
+    /// If an entry is invalid, an error is returned, with the entry index. Only the index of the
+    /// first invalid entry is cached: following calls parse this entry again to return the error.
+    pub fn entries(&self) -> Result<&[WadEntry]> {
+        let entries = self.entries.get_or_init(|| {
+            (0..self.entry_count())
+                .map(|i| self.parse_entry(i).map_err(|_| i))
+                .collect()
+        });
+        match entries {
+            Ok(entries) => Ok(entries),
+            // Errors cannot be cloned, parse the entry again to get it
+            Err(index) => match self.parse_entry(*index) {
+                Err(e) => Err(e),
+                Ok(_) => unreachable!("WAD entry {} is valid after failing to be parsed", index),
+            }
+        }
+    }
+
+    /// Get the entry at given index, use cached entries if available
+    fn entry_at(&self, index: usize) -> Result<WadEntry> {
+        match self.entries.get() {
+            Some(Ok(entries)) => Ok(entries[index].clone()),
+            _ => self.parse_entry(index),
+        }
+    }
+
     /// Iterate on file entries
-    pub fn iter_entries(&self) -> impl Iterator<Item=Result<WadEntry>> + '_ {
-        (0..self.entry_count as usize).map(move |i| self.parse_entry(i))
+    ///
+    /// Entries are parsed and cached on first iteration, see [Self::entries()].
+    /// If some entries are invalid, entries are parsed while iterating and an error is returned
+    /// for each invalid entry.
+    pub fn iter_entries(&self) -> WadEntryIter<'_> {
+        WadEntryIter { wad: self, entries: self.entries().ok(), indexes: 0..self.entry_count as usize }
+    }
+
+    /// Iterate on file entries, skip duplicate entries
+    ///
+    /// See [WadEntry::is_duplicate()].
+    pub fn iter_unique_entries(&self) -> impl Iterator<Item=Result<WadEntry>> + '_ {
+        self.iter_entries().filter(|e| !matches!(e, Ok(e) if e.is_duplicate()))
     }
 
     /// Parse entry at given index
+    ///
+    /// Version 1 entries have no data hash, and no subchunk index (chunked data did not exist).
     fn parse_entry(&self, index: usize) -> Result<WadEntry> {
-        let offset = index * Self::ENTRY_LEN;
-        let buf = &self.entry_data[offset .. offset + Self::ENTRY_LEN];
+        let parse = || -> Result<WadEntry> {
+            let offset = index * self.entry_len;
+            let buf = &self.entry_data[offset .. offset + self.entry_len];
 
-        let (path, offset, size, target_size, data_format, duplicate, first_subchunk_index, data_hash) =
-            parse_buf!(buf, tuple((
-                        map(le_u64, WadEntryHash::from), le_u32, le_u32, le_u32,
-                        map_res(le_u8, WadDataFormat::try_from),
-                        map(le_u8, |v| v != 0), le_u16, le_u64,
-            )));
-        Ok(WadEntry { path, offset, size, target_size, data_format, duplicate, first_subchunk_index, data_hash })
+            let (path, offset, size, target_size, data_format, duplicate, first_subchunk_index) =
+                parse_buf!(buf, tuple((
+                            map(le_u64, WadEntryHash::from), le_u32, le_u32, le_u32,
+                            map_res(le_u8, WadDataFormat::try_from),
+                            map(le_u8, |v| v != 0), le_u16,
+                )));
+            let (first_subchunk_index, data_hash) = if self.version.0 == 1 {
+                (0, 0)
+            } else {
+                (first_subchunk_index, parse_buf!(buf[Self::ENTRY_LEN_V1..], le_u64))
+            };
+            Ok(WadEntry { path, offset, size, target_size, data_format, duplicate, first_subchunk_index, data_hash })
+        };
+        parse().map_err(|e| WadError::InvalidEntry { index, source: Box::new(e) })
     }
 
     /// Find '.subchunktoc' file, if one exists
-    fn find_subchunk_toc(&self, hmapper: &WadHashMapper) -> Option<WadEntry> {
+    fn find_subchunk_toc(&self, hmapper: &wad::WadHashMapper) -> Option<WadEntry> {
         for entry in self.iter_entries().flatten() {
             if let Some(path) = hmapper.get(entry.path.hash) {
                 if path.ends_with(".subchunktoc") {
@@ -153,6 +334,50 @@ impl Wad {
     }
 }
 
+/// Iterator on WAD entries, see [Wad::iter_entries()]
+pub struct WadEntryIter<'a> {
+    wad: &'a Wad,
+    /// Cached entries, `None` if some entries are invalid
+    entries: Option<&'a [WadEntry]>,
+    indexes: std::ops::Range<usize>,
+}
+
+impl WadEntryIter<'_> {
+    fn get(&self, index: usize) -> Result<WadEntry> {
+        match self.entries {
+            Some(entries) => Ok(entries[index].clone()),
+            None => self.wad.parse_entry(index),
+        }
+    }
+}
+
+impl Iterator for WadEntryIter<'_> {
+    type Item = Result<WadEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.indexes.next().map(|i| self.get(i))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indexes.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.indexes.nth(n).map(|i| self.get(i))
+    }
+}
+
+impl DoubleEndedIterator for WadEntryIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.indexes.next_back().map(|i| self.get(i))
+    }
+}
+
+impl ExactSizeIterator for WadEntryIter<'_> {}
+
+impl std::iter::FusedIterator for WadEntryIter<'_> {}
+
+
 /// Read WAD archive files and their entries
 ///
 /// This should be the prefered way to read a WAD file.
@@ -167,22 +392,11 @@ impl<R: Read + Seek> WadReader<R> {
     /// Load subchunks data from a '.subchunktoc' file
     ///
     /// Return whether data has been found, and loaded
-    pub fn load_subchunk_toc(&mut self, hmapper: &WadHashMapper) -> Result<bool> {
+    pub fn load_subchunk_toc(&mut self, hmapper: &wad::WadHashMapper) -> Result<bool> {
         if let Some(entry) = self.wad.find_subchunk_toc(hmapper) {
-            const TOC_ITEM_LEN: usize = 4 + 4 + 8;
-            let nitems = entry.target_size as usize / TOC_ITEM_LEN;
             self.subchunk_toc.clear();
-            self.subchunk_toc.reserve_exact(nitems);
-
-            let mut subchunk_toc = Vec::new();
-            {
-                let mut reader = self.read_entry(&entry)?;
-                for _ in 0..nitems {
-                    let buf = reader.read_array::<TOC_ITEM_LEN>()?;
-                    let (size, target_size, data_hash) = parse_buf!(buf, tuple((le_u32, le_u32, le_u64)));
-                    subchunk_toc.push(WadSubchunkTocEntry { size, target_size, data_hash });
-                }
-            }
+            let reader = self.read_entry(&entry)?;
+            let subchunk_toc = parse_subchunk_toc(reader, entry.target_size as usize)?;
             self.subchunk_toc = subchunk_toc;
             Ok(true)
         } else {
@@ -190,44 +404,30 @@ impl<R: Read + Seek> WadReader<R> {
         }
     }
 
+    /// Load subchunks data from a reader on '.subchunktoc' data
+    ///
+    /// `len` is the size of the TOC data, which must be a multiple of the size of an item.
+    /// This can be used to load a TOC from another WAD, or from a loose file.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cdragon_wad::WadFile;
+    /// let mut wad = WadFile::open("example.wad.client").unwrap();
+    /// let toc = std::fs::read("example.wad.subchunktoc").unwrap();
+    /// wad.load_subchunk_toc_from_reader(&toc[..], toc.len()).unwrap();
+    /// ```
+    pub fn load_subchunk_toc_from_reader<T: Read>(&mut self, reader: T, len: usize) -> Result<()> {
+        self.subchunk_toc = parse_subchunk_toc(reader, len)?;
+        Ok(())
+    }
+
     /// Read an entry data
     ///
     /// The entry must not be a redirection.
     pub fn read_entry(&mut self, entry: &WadEntry) -> Result<Box<dyn Read + '_>, WadError> {
         self.reader.seek(SeekFrom::Start(entry.offset as u64))?;
-        let mut reader = Read::take(&mut self.reader, entry.size as u64);
-        match entry.data_format {
-            WadDataFormat::Uncompressed => {
-                Ok(Box::new(reader))
-            }
-            WadDataFormat::Gzip => Err(WadError::UnsupportedDataFormat(entry.data_format)),
-            WadDataFormat::Redirection => Err(WadError::UnsupportedDataFormat(entry.data_format)),
-            WadDataFormat::Zstd => {
-                let decoder = zstd::stream::read::Decoder::new(reader)?;
-                Ok(Box::new(decoder))
-            }
-            WadDataFormat::Chunked(subchunk_count) => {
-                if self.subchunk_toc.is_empty() {
-                    Err(WadError::MissingSubchunkToc)
-                } else {
-                    // Allocate the whole final buffer and read everything right no
-                    // It would be possible to implement a custom reader but that's not worth the
-                    // complexity
-                    let mut result = Vec::with_capacity(entry.target_size as usize);
-                    for i in 0..subchunk_count {
-                        let subchunk_entry = &self.subchunk_toc[(entry.first_subchunk_index + i as u16) as usize];
-                        let mut subchunk_reader = Read::take(&mut reader, subchunk_entry.size as u64);
-                        if subchunk_entry.size == subchunk_entry.target_size {
-                            // Assume no compression
-                            subchunk_reader.read_to_end(&mut result)?;
-                        } else {
-                            zstd::stream::read::Decoder::new(subchunk_reader)?.read_to_end(&mut result)?;
-                        }
-                    }
-                    Ok(Box::new(std::io::Cursor::new(result)))
-                }
-            }
-        }
+        let reader = Read::take(&mut self.reader, entry.size as u64);
+        decode_entry_data(reader, entry, &self.subchunk_toc)
     }
 
     /// Extract an entry to the given path
@@ -239,19 +439,116 @@ impl<R: Read + Seek> WadReader<R> {
         Ok(())
     }
 
+    /// Read the target path of a redirection entry
+    pub fn read_redirection_target(&mut self, entry: &WadEntry) -> Result<String> {
+        if !entry.is_redirection() {
+            return Err(WadError::NotARedirection);
+        }
+        self.reader.seek(SeekFrom::Start(entry.offset as u64))?;
+        let mut data = vec![0; entry.size as usize];
+        self.reader.read_exact(&mut data)?;
+        parse_redirection_target(&data)
+    }
+
+    /// Extract a redirection entry as a link to its target
+    ///
+    /// The link is created at the entry path, under `output_root`; unknown paths are named after
+    /// the entry hash. It points to the (normalized) target path, under the same root, using a
+    /// relative path. On Unix, a symlink is created. On other platforms, a text file containing
+    /// the relative target path is written instead.
+    ///
+    /// Links or targets that would be outside of `output_root` are rejected with
+    /// [WadError::InvalidRedirection].
+    ///
+    /// Return the path of the created link.
+    pub fn extract_entry_as_link(&mut self, entry: &WadEntry, output_root: &Path, hmapper: &wad::WadHashMapper) -> Result<PathBuf> {
+        let target = normalize_wad_path(&self.read_redirection_target(entry)?);
+        let source = match hmapper.get(entry.path.hash) {
+            Some(path) => path.to_owned(),
+            None => format!("{:x}", entry.path),
+        };
+        let relative_target: PathBuf = relative_link_target(&source, &target)?.into_iter().collect();
+
+        let path = output_root.join(&source);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if path.symlink_metadata().is_ok() {
+            std::fs::remove_file(&path)?;
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&relative_target, &path)?;
+        #[cfg(not(unix))]
+        GuardedFile::for_scope(&path, |file| {
+            use std::io::Write;
+            file.write_all(relative_target.to_string_lossy().as_bytes())
+        })?;
+        Ok(path)
+    }
+
     /// Guess the extension of an entry
+    ///
+    /// Return `None` if the extension cannot be guessed, for any reason.
+    /// Use [Self::try_guess_entry_extension()] to know why.
     pub fn guess_entry_extension(&mut self, entry: &WadEntry) -> Option<&'static str> {
+        self.try_guess_entry_extension(entry).ok()?.extension()
+    }
+
+    /// Guess the extension of an entry, report why it cannot be guessed
+    ///
+    /// Entries whose data cannot be read return an error: for instance
+    /// [WadError::UnsupportedDataFormat] if the data format is not supported, or an I/O error.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cdragon_wad::{ExtensionGuess, WadFile};
+    /// let mut wad = WadFile::open("example.wad.client").unwrap();
+    /// let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+    /// for entry in &entries {
+    ///     match wad.try_guess_entry_extension(entry) {
+    ///         Ok(ExtensionGuess::Guessed(ext)) => println!("{:x}: {}", entry.path, ext),
+    ///         Ok(_) => println!("{:x}: unknown", entry.path),
+    ///         Err(e) => println!("{:x}: cannot be read: {}", entry.path, e),
+    ///     }
+    /// }
+    /// ```
+    pub fn try_guess_entry_extension(&mut self, entry: &WadEntry) -> Result<ExtensionGuess> {
         if entry.target_size == 0 {
-            return None;
+            return Ok(ExtensionGuess::Empty);
         }
-        let mut reader = self.read_entry(entry).ok()?;
-        guess_extension(&mut reader)
+        let mut reader = self.read_entry(entry)?;
+        Ok(guess_extension(&mut reader)?.into())
+    }
+
+    /// Get the number of entries
+    pub fn entry_count(&self) -> usize {
+        self.wad.entry_count()
+    }
+
+    /// Find an entry from its path hash, see [Wad::find_entry()]
+    pub fn find_entry(&self, hash: u64) -> Result<Option<WadEntry>> {
+        self.wad.find_entry(hash)
+    }
+
+    /// Get all entries, parsed, see [Wad::entries()]
+    pub fn entries(&self) -> Result<&[WadEntry]> {
+        self.wad.entries()
+    }
+
+    /// Return `true` if an entry exists for a given path hash
+    pub fn contains(&self, hash: u64) -> bool {
+        self.wad.contains(hash)
     }
 
     /// Iterate on entries
-    pub fn iter_entries(&self) -> impl Iterator<Item=Result<WadEntry>> + '_ {
+    pub fn iter_entries(&self) -> WadEntryIter<'_> {
         self.wad.iter_entries()
     }
+
+    /// Iterate on entries, skip duplicate entries
+    pub fn iter_unique_entries(&self) -> impl Iterator<Item=Result<WadEntry>> + '_ {
+        self.wad.iter_unique_entries()
+    }
 }
 
 /// Read WAD from a file
@@ -267,6 +564,106 @@ impl WadFile {
     }
 }
 
+impl WadReader<Cursor<Vec<u8>>> {
+    /// Read a WAD from in-memory data
+    ///
+    /// ```no_run
+    /// # use cdragon_wad::WadReader;
+    /// let data = std::fs::read("example.wad.client").unwrap();
+    /// let wad = WadReader::from_bytes(data).unwrap();
+    /// println!("{} entries", wad.entry_count());
+    /// ```
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let mut reader = Cursor::new(data);
+        let wad = Wad::read(&mut reader)?;
+        Ok(Self { reader, wad, subchunk_toc: Vec::new(), })
+    }
+}
+
+
+/// Decode entry data from a reader on the raw entry data
+///
+/// The entry must not be a redirection.
+fn decode_entry_data<'a, R: Read + 'a>(mut reader: R, entry: &WadEntry, subchunk_toc: &[WadSubchunkTocEntry]) -> Result<Box<dyn Read + 'a>> {
+    match entry.data_format {
+        WadDataFormat::Uncompressed => {
+            Ok(Box::new(reader))
+        }
+        WadDataFormat::Gzip => Err(WadError::UnsupportedDataFormat(entry.data_format)),
+        WadDataFormat::Redirection => Err(WadError::UnsupportedDataFormat(entry.data_format)),
+        WadDataFormat::Zstd => {
+            let decoder = zstd::stream::read::Decoder::new(reader)?;
+            Ok(Box::new(decoder))
+        }
+        WadDataFormat::Chunked(subchunk_count) => {
+            if subchunk_toc.is_empty() {
+                Err(WadError::MissingSubchunkToc)
+            } else {
+                let first = entry.first_subchunk_index as usize;
+                let end = first + subchunk_count as usize;
+                if end > subchunk_toc.len() {
+                    return Err(WadError::SubchunkIndexOutOfRange { index: end - 1, count: subchunk_toc.len() });
+                }
+                // Allocate the whole final buffer and read everything right no
+                // It would be possible to implement a custom reader but that's not worth the
+                // complexity
+                let mut result = Vec::with_capacity(entry.target_size as usize);
+                for subchunk_entry in &subchunk_toc[first..end] {
+                    let mut subchunk_reader = Read::take(&mut reader, subchunk_entry.size as u64);
+                    if subchunk_entry.size == subchunk_entry.target_size {
+                        // Assume no compression
+                        subchunk_reader.read_to_end(&mut result)?;
+                    } else {
+                        zstd::stream::read::Decoder::new(subchunk_reader)?.read_to_end(&mut result)?;
+                    }
+                }
+                Ok(Box::new(std::io::Cursor::new(result)))
+            }
+        }
+    }
+}
+
+/// Return the components of the relative path from a link to its target, both relative to the same root
+///
+/// The target comes from WAD data, the source from a hash mapper. They are rejected if they could
+/// point outside the root: absolute path, empty, `.` or `..` components.
+fn relative_link_target<'a>(source: &str, target: &'a str) -> Result<Vec<&'a str>> {
+    let is_unsafe = |path: &str| Path::new(path).is_absolute() || path.split('/').any(|c| matches!(c, "" | "." | ".."));
+    if is_unsafe(source) || is_unsafe(target) {
+        return Err(WadError::InvalidRedirection);
+    }
+    let depth = source.split('/').count() - 1;
+    Ok(std::iter::repeat_n("..", depth).chain(target.split('/')).collect())
+}
+
+/// Parse the target path from the data of a redirection entry
+fn parse_redirection_target(data: &[u8]) -> Result<String> {
+    // Data: path length, followed by the path
+    let target = data.get(..4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+        .and_then(|len| data.get(4..4 + len))
+        .ok_or(WadError::InvalidRedirection)?;
+    String::from_utf8(target.to_vec()).map_err(|_| WadError::InvalidRedirection)
+}
+
+/// Parse subchunk TOC items from the (decoded) data of a '.subchunktoc' entry
+///
+/// `len` is the length of the data, it must be a multiple of the size of an item.
+fn parse_subchunk_toc<R: Read>(mut reader: R, len: usize) -> Result<Vec<WadSubchunkTocEntry>> {
+    const TOC_ITEM_LEN: usize = 4 + 4 + 8;
+    if !len.is_multiple_of(TOC_ITEM_LEN) {
+        return Err(WadError::InvalidSubchunkTocSize(len));
+    }
+    let nitems = len / TOC_ITEM_LEN;
+    let mut subchunk_toc = Vec::with_capacity(nitems);
+    for _ in 0..nitems {
+        let buf = reader.read_array::<TOC_ITEM_LEN>()?;
+        let (size, target_size, data_hash) = parse_buf!(buf, tuple((le_u32, le_u32, le_u64)));
+        subchunk_toc.push(WadSubchunkTocEntry { size, target_size, data_hash });
+    }
+    Ok(subchunk_toc)
+}
+
 
 /// Subchunk TOC item data
 #[derive(Debug)]
@@ -283,7 +680,7 @@ struct WadSubchunkTocEntry {
 
 /// Information on a single file in a WAD
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct WadEntry {
     /// File path of the entry, hashed
     pub path: WadEntryHash,
@@ -299,7 +696,7 @@ pub struct WadEntry {
     duplicate: bool,
     /// Index of the first subchunk (only relevant for chunked data)
     first_subchunk_index: u16,
-    /// First 8 bytes of sha256 hash of data
+    /// First 8 bytes of sha256 hash of data (0 for version 1)
     data_hash: u64,
 }
 
@@ -308,12 +705,88 @@ impl WadEntry {
     pub fn is_redirection(&self) -> bool {
         self.data_format == WadDataFormat::Redirection
     }
+
+    /// Return `true` for a duplicate entry
+    ///
+    /// Duplicate entries share their data with another entry of the archive.
+    pub fn is_duplicate(&self) -> bool {
+        self.duplicate
+    }
+
+    /// Get the offset of the entry data in the WAD
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Get the size of the entry data in the WAD (possibly compressed)
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Get the size of the entry data, uncompressed
+    pub fn target_size(&self) -> u32 {
+        self.target_size
+    }
+
+    /// Get the format of the entry data
+    pub fn data_format(&self) -> WadDataFormat {
+        self.data_format
+    }
+
+    /// Get the number of subchunks, for chunked entries
+    pub fn subchunk_count(&self) -> Option<u8> {
+        match self.data_format {
+            WadDataFormat::Chunked(n) => Some(n),
+            _ => None,
+        }
+    }
 }
 
 
 define_hash_type! {
     /// Hash used by WAD entries
-    WadEntryHash(u64) => compute_wad_hash
+    ///
+    /// Paths are normalized before being hashed (see [normalize_wad_path()]).
+    /// ```
+    /// # use cdragon_wad::WadEntryHash;
+    /// # use cdragon_hashes::{HashDef, wad::compute_wad_hash};
+    /// let hash = WadEntryHash::hashed("Assets\\Foo.DDS");
+    /// assert_eq!(hash, WadEntryHash::from(compute_wad_hash("assets/foo.dds")));
+    /// assert_eq!(hash, WadEntryHash::from(0xabbc2d4d2f62b45a));
+    /// ```
+    WadEntryHash(u64) => compute_wad_hash_normalized
+}
+
+impl WadEntryHash {
+    /// Compute the hash of a WAD entry path
+    ///
+    /// The path is normalized first: it is lowercased and backslashes are replaced by forward
+    /// slashes (see [normalize_wad_path()]). This is the same as `WadEntryHash::hashed()`.
+    /// ```
+    /// # use cdragon_wad::WadEntryHash;
+    /// # use cdragon_hashes::wad::compute_wad_hash;
+    /// let hash = WadEntryHash::from_path("data/foo.bin");
+    /// assert_eq!(hash, WadEntryHash::from(compute_wad_hash("data/foo.bin")));
+    /// assert_eq!(hash, WadEntryHash::from_path("DATA\\Foo.bin"));
+    /// ```
+    pub fn from_path(path: &str) -> Self {
+        compute_wad_hash_normalized(path).into()
+    }
+
+    /// Compute the hash of a WAD entry path, for a given kind of WAD
+    ///
+    /// Game and launcher WADs use the same hashing (see [WadHashKind::compute_hash()]), the
+    /// result is the same as [Self::from_path()].
+    /// ```
+    /// # use cdragon_wad::WadEntryHash;
+    /// # use cdragon_hashes::wad::WadHashKind;
+    /// let path = "plugins/rcp-be-lol-game-data/global/default/v1/champions/103.json";
+    /// assert_eq!(WadEntryHash::from_path_for(WadHashKind::Lcu, path), WadEntryHash::from_path(path));
+    /// assert_eq!(WadEntryHash::from_path_for(WadHashKind::Game, path), WadEntryHash::from_path(path));
+    /// ```
+    pub fn from_path_for(kind: WadHashKind, path: &str) -> Self {
+        kind.compute_hash(path).into()
+    }
 }
 
 
@@ -350,59 +823,83 @@ impl TryFrom<u8> for WadDataFormat {
     }
 }
 
+impl WadDataFormat {
+    /// Get the value of the format, as stored in WAD entries
+    ///
+    /// The subchunk count of [Self::Chunked] must fit in 4 bits.
+    ///
+    /// ```
+    /// # use cdragon_wad::WadDataFormat;
+    /// assert_eq!(WadDataFormat::Zstd.to_byte(), 3);
+    /// assert_eq!(WadDataFormat::Chunked(2).to_byte(), 0x24);
+    /// for b in 0..=u8::MAX {
+    ///     if let Ok(format) = WadDataFormat::try_from(b) {
+    ///         assert_eq!(u8::from(format), b);
+    ///     }
+    /// }
+    /// ```
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::Uncompressed => 0,
+            Self::Gzip => 1,
+            Self::Redirection => 2,
+            Self::Zstd => 3,
+            Self::Chunked(n) => (n << 4) | 4,
+        }
+    }
 
-/// Guess file extension from a reader
-fn guess_extension(reader: &mut dyn Read) -> Option<&'static str> {
-    const PREFIX_TO_EXT: &[(&[u8], &str)] = &[
-        (b"\xff\xd8\xff", "jpg"),
-        (b"\x89PNG\x0d\x0a\x1a\x0a", "png"),
-        (b"OggS", "ogg"),
-        (b"\x00\x01\x00\x00", "ttf"),
-        (b"\x1a\x45\xdf\xa3", "webm"),
-        (b"true", "ttf"),
-        (b"OTTO\0", "otf"),
-        (b"\"use strict\";", "min.js"),
-        (b"<template ", "template.html"),
-        (b"<!-- Elements -->", "template.html"),
-        (b"DDS ", "dds"),
-        (b"<svg", "svg"),
-        (b"PROP", "bin"),
-        (b"PTCH", "bin"),
-        (b"BKHD", "bnk"),
-        (b"r3d2Mesh", "scb"),
-        (b"r3d2anmd", "anm"),
-        (b"r3d2canm", "anm"),
-        (b"r3d2sklt", "skl"),
-        (b"r3d2", "wpk"),
-        (b"\x33\x22\x11\x00", "skn"),
-        (b"PreLoadBuildingBlocks = {", "preload"),
-        (b"\x1bLuaQ\x00\x01\x04\x04", "luabin"),
-        (b"\x1bLuaQ\x00\x01\x04\x08", "luabin64"),
-        (b"\x02\x3d\x00\x28", "troybin"),
-        (b"[ObjectBegin]", "sco"),
-        (b"OEGM", "mapgeo"),
-        (b"TEX\0", "tex"),
-    ];
-
-    // Use a sufficient length for all extensions (this is not checked)
-    let mut buf: [u8; 32] = [0; 32];
-    let n = reader.read(&mut buf).ok()?;
-    let buf = &buf[..n];
-    PREFIX_TO_EXT
-        .iter()
-        .find(|(prefix, _)| buf.starts_with(prefix))
-        .map(|(_, ext)| *ext)
-        // Try to parse as JSON
-        // Note: it won't detected JSON files that start with a BOM
-        .or_else(|| if match serde_json::from_slice::<serde_json::Value>(buf) {
-            Ok(_) => true,
-            Err(e) if e.is_eof() => true,
-            _ => false,
-        } {
-            Some("json")
-        } else {
-            None
-        })
+    /// Get a short name of the format, in lowercase
+    ///
+    /// The subchunk count of [Self::Chunked] is not part of the name.
+    ///
+    /// ```
+    /// # use cdragon_wad::WadDataFormat;
+    /// assert_eq!(WadDataFormat::Uncompressed.name(), "uncompressed");
+    /// assert_eq!(WadDataFormat::Chunked(2).name(), "chunked");
+    /// ```
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Uncompressed => "uncompressed",
+            Self::Gzip => "gzip",
+            Self::Redirection => "redirection",
+            Self::Zstd => "zstd",
+            Self::Chunked(_) => "chunked",
+        }
+    }
+}
+
+impl From<WadDataFormat> for u8 {
+    fn from(format: WadDataFormat) -> Self {
+        format.to_byte()
+    }
+}
+
+
+/// Result of an entry extension guessing, see [WadReader::try_guess_entry_extension()]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtensionGuess {
+    /// Extension guessed from the entry data
+    Guessed(&'static str),
+    /// Entry is empty, there is no data to guess from
+    Empty,
+    /// Entry data has been read, but its format is not known
+    Unknown,
+}
+
+impl ExtensionGuess {
+    /// Get the guessed extension, if any
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Guessed(ext) => Some(ext),
+            _ => None,
+        }
+    }
+}
+
+impl From<Option<&'static str>> for ExtensionGuess {
+    fn from(ext: Option<&'static str>) -> Self {
+        ext.map_or(Self::Unknown, Self::Guessed)
+    }
 }
 
 
@@ -420,9 +917,218 @@ pub enum WadError {
     UnsupportedVersion(u8, u8),
     #[error("WAD entry data format not supported for reading: {0:?}")]
     UnsupportedDataFormat(WadDataFormat),
+    #[error("WAD V1 entry size not supported: {0}")]
+    UnsupportedV1EntrySize(u16),
     #[error("WAD V2 entry size not supported: {0}")]
     UnsupportedV2EntrySize(u16),
     #[error("missing subchunk TOC to read chunked entry")]
     MissingSubchunkToc,
+    #[error("WAD entry is not a redirection")]
+    NotARedirection,
+    #[error("invalid WAD redirection entry data")]
+    InvalidRedirection,
+    #[error("subchunk index out of range: {index} (TOC has {count} subchunks)")]
+    SubchunkIndexOutOfRange { index: usize, count: usize },
+    #[error("invalid subchunk TOC size: {0}")]
+    InvalidSubchunkTocSize(usize),
+    #[error("invalid WAD entry at index {index}")]
+    InvalidEntry { index: usize, source: Box<WadError> },
 }
 
+
+#[cfg(test)]
+mod tests {
+    use cdragon_hashes::wad::WadHashMapper;
+    use super::*;
+    use super::fixtures::*;
+
+    /// Offset of the data format of an entry, in entry data
+    fn data_format_offset(index: usize) -> usize {
+        index * ENTRY_LEN + 20
+    }
+
+    #[test]
+    fn entries_are_parsed_once() {
+        // Invalid data format on the first entry, only detected when entries are parsed
+        let mut wad = Wad::read(&mut Cursor::new(wad_data_with_hashes(&[1, 2, 3]))).unwrap();
+        wad.entry_data[data_format_offset(0)] = 0x0f;
+        assert!(matches!(wad.entries(), Err(WadError::InvalidEntry { index: 0, .. })));
+
+        let mut wad = Wad::read(&mut Cursor::new(wad_data_with_hashes(&[1, 2, 3]))).unwrap();
+        let entries = wad.entries().unwrap().as_ptr();
+        // Entries are not parsed again: invalid data is not seen
+        wad.entry_data[data_format_offset(0)] = 0x0f;
+        for _ in 0..3 {
+            assert_eq!(wad.entries().unwrap().as_ptr(), entries);
+            assert_eq!(wad.iter_entries().filter(Result::is_ok).count(), 3);
+            assert!(wad.find_entry(1).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn invalid_entry_error_is_cached() {
+        let mut data = wad_data_with_hashes(&[1, 2, 3]);
+        // Invalid data format on the second entry
+        data[ENTRIES_OFFSET + data_format_offset(1)] = 0x0f;
+        let mut wad = Wad::read(&mut Cursor::new(data)).unwrap();
+        assert!(matches!(wad.entries(), Err(WadError::InvalidEntry { index: 1, .. })));
+        // Only the invalid entry is parsed again: the first entry is not checked anymore
+        wad.entry_data[data_format_offset(0)] = 0x0f;
+        for _ in 0..3 {
+            assert!(matches!(wad.entries(), Err(WadError::InvalidEntry { index: 1, .. })));
+        }
+    }
+
+    #[test]
+    fn find_entry_sorted_or_not() {
+        // Entries sorted by hash
+        let wad = Wad::read(&mut Cursor::new(wad_data_with_hashes(&[1, 5, 7, 20]))).unwrap();
+        assert_eq!(wad.find_entry(7).unwrap().unwrap().path.hash, 7);
+        assert!(wad.find_entry(6).unwrap().is_none());
+        assert!(wad.contains(20));
+        assert!(!wad.contains(0));
+
+        // Unsorted entries are still found
+        let wad = Wad::read(&mut Cursor::new(wad_data_with_hashes(&[20, 5, 7, 1]))).unwrap();
+        assert_eq!(wad.find_entry(1).unwrap().unwrap().path.hash, 1);
+        assert!(wad.contains(20));
+        assert!(!wad.contains(6));
+    }
+
+    #[test]
+    fn iter_entries_reports_invalid_entries() {
+        let mut data = wad_data_with_hashes(&[1, 2, 3]);
+        let wad = Wad::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(wad.iter_entries().len(), 3);
+        let hashes: Vec<u64> = wad.iter_entries().rev().map(|e| e.unwrap().path.hash).collect();
+        assert_eq!(hashes, [3, 2, 1]);
+
+        // Invalid data format on the second entry
+        data[ENTRIES_OFFSET + ENTRY_LEN + 20] = 0x0f;
+        let wad = Wad::read(&mut Cursor::new(&data)).unwrap();
+        let results: Vec<_> = wad.iter_entries().collect();
+        assert!(results[0].is_ok() && results[2].is_ok());
+        assert!(matches!(results[1], Err(WadError::InvalidEntry { index: 1, .. })));
+    }
+
+    #[test]
+    fn read_entry_from_bytes() {
+        let mut wad = WadReader::from_bytes(wad_data(&[TestEntry::new(42, b"hello")])).unwrap();
+        let entry = wad.find_entry(42).unwrap().unwrap();
+        let mut content = String::new();
+        wad.read_entry(&entry).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello");
+    }
+
+    #[test]
+    fn load_subchunk_toc_from_reader() {
+        // Chunked entry, with 2 subchunks
+        let data = wad_data(&[TestEntry::new(1, b"abcdef").with_format(0x24, 6)]);
+        let mut wad = WadReader::from_bytes(data).unwrap();
+        let entry = wad.iter_entries().next().unwrap().unwrap();
+
+        // Uncompressed subchunks: 3 bytes each
+        let toc_item = [&3u32.to_le_bytes()[..], &3u32.to_le_bytes(), &[0; 8]].concat();
+
+        // Invalid TOC size
+        assert!(matches!(wad.load_subchunk_toc_from_reader(&toc_item[..], 10), Err(WadError::InvalidSubchunkTocSize(10))));
+
+        // Short TOC: only one subchunk
+        wad.load_subchunk_toc_from_reader(&toc_item[..], toc_item.len()).unwrap();
+        assert!(matches!(wad.read_entry(&entry), Err(WadError::SubchunkIndexOutOfRange { index: 1, count: 1 })));
+
+        // Valid TOC
+        let toc = toc_item.repeat(2);
+        wad.load_subchunk_toc_from_reader(&toc[..], toc.len()).unwrap();
+        let mut content = String::new();
+        wad.read_entry(&entry).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "abcdef");
+    }
+
+    #[test]
+    fn read_v1_entries() {
+        let data = wad_data_v1(&[
+            TestEntry::new(1, b"hello"),
+            TestEntry::new(2, b"world").with_duplicate(),
+        ]);
+        let mut wad = WadReader::from_bytes(data).unwrap();
+        assert_eq!(wad.entry_count(), 2);
+        let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+        assert_eq!(entries.iter().map(|e| e.path.hash).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(entries[0].data_format(), WadDataFormat::Uncompressed);
+        assert!(entries.iter().all(|e| e.data_hash == 0 && e.first_subchunk_index == 0));
+        assert!(!entries[0].is_duplicate() && entries[1].is_duplicate());
+        assert_eq!(wad.iter_unique_entries().count(), 1);
+
+        for (entry, expected) in entries.iter().zip(["hello", "world"]) {
+            let mut content = String::new();
+            wad.read_entry(entry).unwrap().read_to_string(&mut content).unwrap();
+            assert_eq!(content, expected);
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("world.txt");
+        wad.extract_entry(&entries[1], &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"world");
+    }
+
+    #[test]
+    fn try_guess_entry_extension() {
+        // Entries: PROP data, unknown data, empty data, gzip data (not supported)
+        let data = wad_data(&[
+            TestEntry::new(1, b"PROP"),
+            TestEntry::new(2, b"\x00\x01\x02\x03"),
+            TestEntry::new(3, b""),
+            TestEntry::new(4, b"\x1f\x8b\x08\x00").with_format(1, 4),
+        ]);
+        let mut wad = WadReader::from_bytes(data).unwrap();
+        let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+        assert_eq!(wad.try_guess_entry_extension(&entries[0]).unwrap(), ExtensionGuess::Guessed("bin"));
+        assert_eq!(wad.try_guess_entry_extension(&entries[1]).unwrap(), ExtensionGuess::Unknown);
+        assert_eq!(wad.try_guess_entry_extension(&entries[2]).unwrap(), ExtensionGuess::Empty);
+        assert!(matches!(wad.try_guess_entry_extension(&entries[3]), Err(WadError::UnsupportedDataFormat(_))));
+        assert_eq!(wad.guess_entry_extension(&entries[0]), Some("bin"));
+        assert_eq!(wad.guess_entry_extension(&entries[3]), None);
+    }
+
+    #[test]
+    fn relative_link_target_is_checked() {
+        assert_eq!(relative_link_target("a/b/link", "c/target").unwrap(), ["..", "..", "c", "target"]);
+        assert_eq!(relative_link_target("link", "target").unwrap(), ["target"]);
+        for target in ["../target", "c/../../target", "/etc/passwd", "c//target", "./target", "c/", ""] {
+            assert!(matches!(relative_link_target("a/link", target), Err(WadError::InvalidRedirection)), "{target}");
+        }
+        for source in ["../link", "a/../../link", "/tmp/link", "a//link", "./link", ""] {
+            assert!(matches!(relative_link_target(source, "target"), Err(WadError::InvalidRedirection)), "{source}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_entry_as_link() {
+        let tmp = tempfile::tempdir().unwrap();
+        let valid = redirection_data("Data/Target.txt");
+        let escaping = redirection_data("data/../../outside.txt");
+        let data = wad_data(&[
+            TestEntry::new(1, &valid).with_format(2, 0),
+            TestEntry::new(2, &escaping).with_format(2, 0),
+        ]);
+        let mut wad = WadReader::from_bytes(data).unwrap();
+        let mut hmapper = WadHashMapper::new();
+        hmapper.insert(1, "data/sub/link.txt".to_string());
+        let entries: Vec<_> = wad.iter_entries().collect::<Result<_, _>>().unwrap();
+
+        let path = wad.extract_entry_as_link(&entries[0], tmp.path(), &hmapper).unwrap();
+        assert_eq!(path, tmp.path().join("data/sub/link.txt"));
+        assert_eq!(std::fs::read_link(&path).unwrap(), Path::new("../../data/target.txt"));
+
+        assert!(matches!(wad.extract_entry_as_link(&entries[1], tmp.path(), &hmapper), Err(WadError::InvalidRedirection)));
+        assert!(tmp.path().join("0000000000000002").symlink_metadata().is_err());
+
+        // Escaping link path, from the hash mapper
+        let root = tmp.path().join("root");
+        hmapper.insert(1, "../escaped.txt".to_string());
+        assert!(matches!(wad.extract_entry_as_link(&entries[0], &root, &hmapper), Err(WadError::InvalidRedirection)));
+        assert!(tmp.path().join("escaped.txt").symlink_metadata().is_err());
+    }
+}
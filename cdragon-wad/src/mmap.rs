@@ -0,0 +1,214 @@
+//! Memory-mapped WAD files
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use memmap2::Mmap;
+use cdragon_hashes::wad::WadHashMapper;
+use cdragon_utils::{
+    GuardedFile,
+    parsing::ParseError,
+};
+use super::{
+    ExtensionGuess,
+    Result,
+    Wad,
+    WadDataFormat,
+    WadEntry,
+    WadEntryIter,
+    WadError,
+    WadSubchunkTocEntry,
+    decode_entry_data,
+    guess_extension,
+    parse_redirection_target,
+    parse_subchunk_toc,
+};
+
+/// Read a memory-mapped WAD file
+///
+/// Provide the same reading methods as [WadReader](super::WadReader), but entry data is read
+/// directly from the mapping. Reading methods don't require a mutable reference, which allows to
+/// read entries concurrently, and uncompressed entries can be accessed without copy.
+///
+/// # Example
+/// ```no_run
+/// # use cdragon_wad::WadMmap;
+/// let wad = WadMmap::open("example.wad.client").unwrap();
+/// for entry in wad.iter_entries() {
+///     let entry = entry.unwrap();
+///     // Uncompressed data is not copied
+///     let data = wad.read_entry_bytes(&entry).unwrap();
+///     println!("{:x}: {} bytes", entry.path, data.len());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct WadMmap {
+    mmap: Mmap,
+    wad: Wad,
+    subchunk_toc: Vec<WadSubchunkTocEntry>,
+}
+
+impl WadMmap {
+    /// Open a WAD from its path and map it to memory
+    ///
+    /// The file must not be modified while it is mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref())?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        let wad = Wad::read(&mut std::io::Cursor::new(&mmap[..]))?;
+        Ok(Self { mmap, wad, subchunk_toc: Vec::new() })
+    }
+
+    /// Load subchunks data from a '.subchunktoc' file
+    ///
+    /// Return whether data has been found, and loaded
+    pub fn load_subchunk_toc(&mut self, hmapper: &WadHashMapper) -> Result<bool> {
+        if let Some(entry) = self.wad.find_subchunk_toc(hmapper) {
+            self.subchunk_toc.clear();
+            let subchunk_toc = parse_subchunk_toc(self.read_entry(&entry)?, entry.target_size as usize)?;
+            self.subchunk_toc = subchunk_toc;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Load subchunks data from a reader on '.subchunktoc' data
+    ///
+    /// See [WadReader::load_subchunk_toc_from_reader()](super::WadReader::load_subchunk_toc_from_reader()).
+    pub fn load_subchunk_toc_from_reader<R: Read>(&mut self, reader: R, len: usize) -> Result<()> {
+        self.subchunk_toc = parse_subchunk_toc(reader, len)?;
+        Ok(())
+    }
+
+    /// Get the raw data of an entry, as stored in the WAD (possibly compressed)
+    pub fn entry_raw_data(&self, entry: &WadEntry) -> Result<&[u8]> {
+        let start = entry.offset as usize;
+        let end = start + entry.size as usize;
+        self.mmap.get(start..end).ok_or_else(|| ParseError::NotEnoughData.into())
+    }
+
+    /// Read an entry data
+    ///
+    /// The entry must not be a redirection.
+    pub fn read_entry(&self, entry: &WadEntry) -> Result<Box<dyn Read + '_>> {
+        decode_entry_data(self.entry_raw_data(entry)?, entry, &self.subchunk_toc)
+    }
+
+    /// Read an entry data to bytes
+    ///
+    /// Data of uncompressed entries is borrowed from the mapping, without copy.
+    /// The entry must not be a redirection.
+    pub fn read_entry_bytes(&self, entry: &WadEntry) -> Result<Cow<'_, [u8]>> {
+        if entry.data_format == WadDataFormat::Uncompressed {
+            Ok(Cow::Borrowed(self.entry_raw_data(entry)?))
+        } else {
+            let mut data = Vec::with_capacity(entry.target_size as usize);
+            self.read_entry(entry)?.read_to_end(&mut data)?;
+            Ok(Cow::Owned(data))
+        }
+    }
+
+    /// Extract an entry to the given path
+    pub fn extract_entry(&self, entry: &WadEntry, path: &Path) -> Result<()> {
+        let mut reader = self.read_entry(entry)?;
+        GuardedFile::for_scope(path, |file| {
+            std::io::copy(&mut *reader, file)
+        })?;
+        Ok(())
+    }
+
+    /// Read the target path of a redirection entry
+    pub fn read_redirection_target(&self, entry: &WadEntry) -> Result<String> {
+        if !entry.is_redirection() {
+            return Err(WadError::NotARedirection);
+        }
+        parse_redirection_target(self.entry_raw_data(entry)?)
+    }
+
+    /// Guess the extension of an entry
+    ///
+    /// Return `None` if the extension cannot be guessed, for any reason.
+    /// Use [Self::try_guess_entry_extension()] to know why.
+    pub fn guess_entry_extension(&self, entry: &WadEntry) -> Option<&'static str> {
+        self.try_guess_entry_extension(entry).ok()?.extension()
+    }
+
+    /// Guess the extension of an entry, report why it cannot be guessed
+    ///
+    /// See [WadReader::try_guess_entry_extension()](super::WadReader::try_guess_entry_extension()).
+    pub fn try_guess_entry_extension(&self, entry: &WadEntry) -> Result<ExtensionGuess> {
+        if entry.target_size == 0 {
+            return Ok(ExtensionGuess::Empty);
+        }
+        let mut reader = self.read_entry(entry)?;
+        Ok(guess_extension(&mut reader)?.into())
+    }
+
+    /// Get the number of entries
+    pub fn entry_count(&self) -> usize {
+        self.wad.entry_count()
+    }
+
+    /// Find an entry from its path hash, see [Wad::find_entry()]
+    pub fn find_entry(&self, hash: u64) -> Result<Option<WadEntry>> {
+        self.wad.find_entry(hash)
+    }
+
+    /// Get all entries, parsed, see [Wad::entries()]
+    pub fn entries(&self) -> Result<&[WadEntry]> {
+        self.wad.entries()
+    }
+
+    /// Return `true` if an entry exists for a given path hash
+    pub fn contains(&self, hash: u64) -> bool {
+        self.wad.contains(hash)
+    }
+
+    /// Iterate on entries
+    pub fn iter_entries(&self) -> WadEntryIter<'_> {
+        self.wad.iter_entries()
+    }
+
+    /// Iterate on entries, skip duplicate entries
+    pub fn iter_unique_entries(&self) -> impl Iterator<Item=Result<WadEntry>> + '_ {
+        self.wad.iter_unique_entries()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use crate::fixtures::*;
+    use crate::WadFile;
+    use super::WadMmap;
+
+    #[test]
+    fn read_entries_like_wad_file() {
+        // Uncompressed entry and zstd-compressed one
+        let zstd = zstd::encode_all(&b"compressed data"[..], 0).unwrap();
+        let data = wad_data(&[
+            TestEntry::new(1, b"uncompressed data"),
+            TestEntry::new(2, &zstd).with_format(3, 15),
+        ]);
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("test.wad");
+        std::fs::write(&path, data).unwrap();
+
+        let wad = WadMmap::open(&path).unwrap();
+        let mut wad_file = WadFile::open(&path).unwrap();
+        assert_eq!(wad.entry_count(), 2);
+        for entry in wad.iter_entries() {
+            let entry = entry.unwrap();
+            let mut expected = Vec::new();
+            wad_file.read_entry(&entry).unwrap().read_to_end(&mut expected).unwrap();
+            assert_eq!(wad.read_entry_bytes(&entry).unwrap(), expected);
+            let mut data = Vec::new();
+            wad.read_entry(&entry).unwrap().read_to_end(&mut data).unwrap();
+            assert_eq!(data, expected);
+        }
+        let entry = wad.iter_entries().next().unwrap().unwrap();
+        assert_eq!(wad.read_entry_bytes(&entry).unwrap(), &b"uncompressed data"[..]);
+    }
+}
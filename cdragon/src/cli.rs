@@ -1,6 +1,6 @@
 //! Helpers for building clap commands
 use std::path::PathBuf;
-pub use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command, value_parser};
+pub use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
 
 pub type CliResult = Result<(), Box<dyn std::error::Error>>;
 pub type Subcommand = (Command, fn(&ArgMatches) -> CliResult);
@@ -24,6 +24,14 @@ pub fn arg_hashes_dir() -> Arg {
         .help("Directory with lists of known hashes")
 }
 
+pub fn arg_quiet() -> Arg {
+    Arg::new("quiet")
+        .short('q')
+        .long("quiet")
+        .action(ArgAction::SetTrue)
+        .help("Don't report progress")
+}
+
 /// Get hashes directory from `hashes` arg or `CDRAGON_DATA` 
 pub fn get_hashes_dir(matches: &ArgMatches) -> Option<PathBuf> {
     if let Some(path) = matches.get_one::<PathBuf>("hashes") {
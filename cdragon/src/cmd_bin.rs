@@ -1,5 +1,10 @@
-use std::io;
-use std::path::PathBuf;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::convert::Infallible;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::fs;
+use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
 use cdragon_hashes::bin::binhash_from_str;
 use cdragon_prop::{
@@ -8,11 +13,19 @@ use cdragon_prop::{
     BinClassName,
     BinEntriesSerializer,
     PropFile,
+    BinSerializer,
+    BinTraversal,
+    BinVisitor,
+    EntryFilter,
+    TextTreeSerializer,
+    BinFieldName,
+    visitor::{BinStats, CollectLinksVisitor, StatsVisitor},
 };
 use crate::cli::*;
 use crate::utils::{
     bin_files_from_dir,
     build_bin_entry_serializer,
+    sanitize_relative_path,
 };
 
 pub fn subcommand(name: &'static str) -> Subcommand {
@@ -36,6 +49,77 @@ pub fn subcommand(name: &'static str) -> Subcommand {
                 .short('e')
                 .value_name("type")
                 .help("Dump only entries with the given type"))
+            .arg(Arg::new("out-dir")
+                .short('o')
+                .long("out-dir")
+                .value_name("dir")
+                .value_parser(value_parser!(PathBuf))
+                .help("Write one file per BIN file in the given directory, instead of stdout"))
+            .arg(Arg::new("split-entries")
+                .long("split-entries")
+                .action(ArgAction::SetTrue)
+                .requires("out-dir")
+                .help("Write one file per entry, in a directory per BIN file"))
+        )
+        .subcommand(
+            Command::new("deps")
+            .about("Print links between BIN entries")
+            .arg(Arg::new("input")
+                .value_name("bin")
+                .required(true)
+                .num_args(1..)
+                .value_parser(value_parser!(PathBuf))
+                .help("`.bin` files or directories to scan (recursively for directories)"))
+            .arg(arg_hashes_dir())
+            .arg(Arg::new("entry")
+                .short('e')
+                .long("entry")
+                .value_name("path")
+                .help("Print only links of the given entry"))
+            .arg(Arg::new("reverse")
+                .short('r')
+                .long("reverse")
+                .action(ArgAction::SetTrue)
+                .help("Print entries linking to each entry, instead of linked entries"))
+            .arg(Arg::new("dot")
+                .long("dot")
+                .action(ArgAction::SetTrue)
+                .help("Output a graph in DOT format"))
+        )
+        .subcommand(
+            Command::new("duplicates")
+            .about("List entries defined in more than one BIN file")
+            .arg(Arg::new("input")
+                .value_name("bin")
+                .required(true)
+                .num_args(1..)
+                .value_parser(value_parser!(PathBuf))
+                .help("`.bin` files or directories to scan (recursively for directories)"))
+            .arg(arg_hashes_dir())
+            .arg(Arg::new("json")
+                .short('j')
+                .action(ArgAction::SetTrue)
+                .help("Output as JSON"))
+        )
+        .subcommand(
+            Command::new("stats")
+            .about("Count entries per class, and optionally fields used by each class")
+            .arg(Arg::new("input")
+                .value_name("bin")
+                .required(true)
+                .num_args(1..)
+                .value_parser(value_parser!(PathBuf))
+                .help("`.bin` files or directories to scan (recursively for directories)"))
+            .arg(arg_hashes_dir())
+            .arg(Arg::new("fields")
+                .long("fields")
+                .action(ArgAction::SetTrue)
+                .help("Also count fields per class, field name and type (entries are parsed)"))
+            .arg(Arg::new("json")
+                .short('j')
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output as JSON"))
         )
         ;
     (cmd, handle)
@@ -44,44 +128,595 @@ pub fn subcommand(name: &'static str) -> Subcommand {
 fn handle(matches: &ArgMatches) -> CliResult {
     match matches.subcommand() {
         Some(("dump", matches)) => {
-            let hmappers = match get_hashes_dir(matches) {
-                Some(dir) => BinHashMappers::from_dirpath(&dir)
-                    .with_context(|| format!("failed to load hash mappers from {}", dir.display()))?,
-                _ => BinHashMappers::default(),
+            let hmappers = bin_hmappers_from_matches(matches)?;
+
+            let json = matches.get_flag("json");
+            let filter = match matches.get_one::<String>("entry-type") {
+                Some(s) => EntryFilter::with_type(binhash_from_str(s).into()),
+                None => EntryFilter::all(),
             };
 
+            if let Some(out_dir) = matches.get_one::<PathBuf>("out-dir") {
+                let dumper = BinDirDumper {
+                    out_dir,
+                    hmappers: &hmappers,
+                    filter: &filter,
+                    json,
+                    split_entries: matches.get_flag("split-entries"),
+                };
+                for path in matches.get_many::<PathBuf>("input").unwrap() {
+                    dumper.dump_input(path)?;
+                }
+                return Ok(());
+            }
+
             let mut writer = io::BufWriter::new(io::stdout());
-            let mut serializer = build_bin_entry_serializer(&mut writer, &hmappers, matches.get_flag("json"))?;
-            let filter: Box<dyn Fn(BinEntryPath, BinClassName) -> bool> = match matches.get_one::<String>("entry-type") {
+            let mut serializer = build_bin_entry_serializer(&mut writer, &hmappers, json)?;
+            for path in bin_paths_from_matches(matches) {
+                serialize_bin_path(&path, &mut *serializer, filter.predicate())?;
+            }
+
+            serializer.end()?;
+            Ok(())
+        }
+        Some(("deps", matches)) => {
+            let hmappers = bin_hmappers_from_matches(matches)?;
+
+            let mut visitor = CollectLinksVisitor::default();
+            for path in bin_paths_from_matches(matches) {
+                traverse_bin_path(&path, &mut visitor)?;
+            }
+
+            let reverse = matches.get_flag("reverse");
+            let links = if reverse {
+                CollectLinksVisitor::reverse_links(&visitor.links)
+            } else {
+                visitor.links
+            };
+
+            // Sort output, using resolved names
+            let format_path = |path: &BinEntryPath| path.seek_str(&hmappers).to_string();
+            let mut sources: Vec<(String, Vec<String>)> = match matches.get_one::<String>("entry") {
                 Some(s) => {
-                    let ctype: BinClassName = binhash_from_str(s).into();
-                    Box::new(move |_, t| t == ctype)
+                    let path: BinEntryPath = binhash_from_str(s).into();
+                    let targets = links.get(&path).with_context(|| format!("entry not found: {}", s))?;
+                    vec![(format_path(&path), targets.iter().map(format_path).collect())]
                 }
-                None => Box::new(|_, _| true)
+                None => links.iter().map(|(source, targets)| {
+                    (format_path(source), targets.iter().map(format_path).collect())
+                }).collect(),
             };
+            sources.sort_unstable();
 
-            for path in matches.get_many::<PathBuf>("input").unwrap() {
-                if path.is_dir() {
-                    for path in bin_files_from_dir(path) {
-                        serialize_bin_path(&path, &mut *serializer, &filter)?;
+            let mut writer = io::BufWriter::new(io::stdout());
+            if matches.get_flag("dot") {
+                writeln!(writer, "digraph deps {{")?;
+                for (source, mut targets) in sources {
+                    targets.sort_unstable();
+                    for target in targets {
+                        // Graph edges always follow link direction
+                        if reverse {
+                            writeln!(writer, "  {:?} -> {:?};", target, source)?;
+                        } else {
+                            writeln!(writer, "  {:?} -> {:?};", source, target)?;
+                        }
+                    }
+                }
+                writeln!(writer, "}}")?;
+            } else {
+                for (source, mut targets) in sources {
+                    targets.sort_unstable();
+                    writeln!(writer, "{}", source)?;
+                    for target in targets {
+                        writeln!(writer, "  {}", target)?;
                     }
-                } else {
-                    serialize_bin_path(path, &mut *serializer, &filter)?;
                 }
             }
+            Ok(())
+        }
+        Some(("duplicates", matches)) => {
+            let hmappers = bin_hmappers_from_matches(matches)?;
 
-            serializer.end()?;
+            let paths = bin_paths_from_matches(matches);
+
+            let mut writer = io::BufWriter::new(io::stdout());
+            write_duplicate_entries(&mut writer, &paths, &hmappers, matches.get_flag("json"))?;
+            Ok(())
+        }
+        Some(("stats", matches)) => {
+            let hmappers = bin_hmappers_from_matches(matches)?;
+
+            let paths = bin_paths_from_matches(matches);
+            let stats = collect_bin_stats(&paths, matches.get_flag("fields"))?;
+            let mut writer = io::BufWriter::new(io::stdout());
+            write_bin_stats(&mut writer, &stats, &hmappers, matches.get_flag("json"))?;
             Ok(())
         }
         _ => unreachable!(),
     }
 }
 
+/// Load bin hash mappers from the hashes directory parameter, if any
+fn bin_hmappers_from_matches(matches: &ArgMatches) -> Result<BinHashMappers> {
+    match get_hashes_dir(matches) {
+        Some(dir) => BinHashMappers::from_dirpath(&dir)
+            .with_context(|| format!("failed to load hash mappers from {}", dir.display())),
+        _ => Ok(BinHashMappers::default()),
+    }
+}
+
+/// Collect bin files from the `input` parameter, directories are walked
+fn bin_paths_from_matches(matches: &ArgMatches) -> Vec<PathBuf> {
+    let mut paths = Vec::<PathBuf>::new();
+    for path in matches.get_many::<PathBuf>("input").unwrap() {
+        if path.is_dir() {
+            paths.extend(bin_files_from_dir(path));
+        } else {
+            paths.push(path.clone());
+        }
+    }
+    paths
+}
+
+/// Field counts of a class, indexed by field name and type name
+type FieldCounts = Vec<((String, String), usize)>;
+
+/// Count entries of bin files, by type
+///
+/// If `fields` is `false`, only entry headers are scanned. Otherwise, entries are parsed and
+/// fields are counted too.
+fn collect_bin_stats(paths: &[PathBuf], fields: bool) -> Result<BinStats> {
+    if fields {
+        let mut visitor = StatsVisitor::default();
+        for path in paths.iter() {
+            traverse_bin_path(path, &mut visitor)?;
+        }
+        Ok(visitor.stats)
+    } else {
+        // Only scan headers, don't parse entries
+        let mut stats = BinStats::default();
+        for path in paths.iter() {
+            let scanner = PropFile::scan_entries_from_path(path)
+                .with_context(|| format!("failed to scan {}", path.display()))?;
+            for header in scanner.headers() {
+                let (_, ctype) = header?;
+                *stats.entries.entry(ctype).or_default() += 1;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// Write bin stats, sorted by decreasing count
+fn write_bin_stats<W: Write>(mut writer: W, stats: &BinStats, hmappers: &BinHashMappers, json: bool) -> Result<()> {
+    // Sort by decreasing count, then by resolved name
+    fn sorted_counts<K>(counts: impl Iterator<Item=(K, usize)>) -> Vec<(K, usize)> where K: Ord {
+        let mut counts: Vec<_> = counts.collect();
+        counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+    let format_class = |ctype: &BinClassName| ctype.seek_str(hmappers).to_string();
+    let format_field = |name: &BinFieldName| name.seek_str(hmappers).to_string();
+    // Also list classes only used by structs and embeds, with no entry
+    let classes = stats.entries.keys().chain(stats.fields.keys()).collect::<HashSet<_>>();
+    let entries = sorted_counts(classes.into_iter().map(|ctype| {
+        (format_class(ctype), stats.entries.get(ctype).copied().unwrap_or(0))
+    }));
+    let fields: HashMap<String, FieldCounts> = stats.fields.iter()
+        .map(|(ctype, counts)| {
+            let counts = sorted_counts(counts.iter().map(|((name, vtype), n)| {
+                ((format_field(name), format!("{:?}", vtype)), *n)
+            }));
+            (format_class(ctype), counts)
+        })
+        .collect();
+
+    if json {
+        let values: Vec<serde_json::Value> = entries.iter().map(|(ctype, n)| {
+            let mut value = serde_json::json!({
+                "class": ctype,
+                "count": n,
+            });
+            if let Some(counts) = fields.get(ctype) {
+                value["fields"] = counts.iter().map(|((name, vtype), n)| serde_json::json!({
+                    "name": name,
+                    "type": vtype,
+                    "count": n,
+                })).collect();
+            }
+            value
+        }).collect();
+        serde_json::to_writer_pretty(&mut writer, &values)?;
+        writeln!(writer)?;
+    } else {
+        for (ctype, n) in entries.iter() {
+            writeln!(writer, "{:8}  {}", n, ctype)?;
+            if let Some(counts) = fields.get(ctype) {
+                for ((name, vtype), n) in counts {
+                    writeln!(writer, "  {:8}  {}: {}", n, name, vtype)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Serialize entries from a given bin file path
-pub fn serialize_bin_path<F: Fn(BinEntryPath, BinClassName) -> bool>(path: &PathBuf, serializer: &mut dyn BinEntriesSerializer, filter: F) -> Result<()> {
+pub fn serialize_bin_path<F: Fn(BinEntryPath, BinClassName) -> bool>(path: &Path, serializer: &mut dyn BinEntriesSerializer, filter: F) -> Result<()> {
     let scanner = PropFile::scan_entries_from_path(path)?;
     scanner.filter_parse(filter).try_for_each(|entry| -> Result<(), _> {
         serializer.write_entry(&entry?).map_err(|e| e.into())
     })
 }
 
+
+/// Dump bin files to a directory tree
+struct BinDirDumper<'a> {
+    out_dir: &'a Path,
+    hmappers: &'a BinHashMappers,
+    filter: &'a EntryFilter,
+    json: bool,
+    split_entries: bool,
+}
+
+impl BinDirDumper<'_> {
+    fn extension(&self) -> &'static str {
+        if self.json { "json" } else { "txt" }
+    }
+
+    /// Dump an input bin file, or all bin files of an input directory
+    ///
+    /// Files from a directory keep their path relative to it.
+    fn dump_input(&self, path: &Path) -> Result<()> {
+        if path.is_dir() {
+            for file in bin_files_from_dir(path) {
+                self.dump_file(&file, &relative_input_path(&file, path))?;
+            }
+            Ok(())
+        } else {
+            self.dump_file(path, Path::new(path.file_name().unwrap_or(path.as_os_str())))
+        }
+    }
+
+    /// Dump a bin file, `rel_path` is the path of the bin file relative to the output directory
+    fn dump_file(&self, path: &Path, rel_path: &Path) -> Result<()> {
+        let output = self.out_dir.join(rel_path);
+        if self.split_entries {
+            let output_dir = output.with_extension("");
+            let scanner = PropFile::scan_entries_from_path(path)?;
+            let mut used_names = HashSet::new();
+            for entry in scanner.filter_parse(self.filter.predicate()) {
+                let entry = entry?;
+                let entry_path = self.entry_output_path(&output_dir, entry.path, &mut used_names);
+                self.write_file(&entry_path, |serializer| Ok(serializer.write_entry(&entry)?))
+                    .with_context(|| format!("failed to write {}", entry_path.display()))?;
+            }
+        } else {
+            let output = output.with_extension(self.extension());
+            self.write_file(&output, |serializer| {
+                serialize_bin_path(path, serializer, self.filter.predicate())
+            }).with_context(|| format!("failed to write {}", output.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Get the output path of an entry, when entries are split
+    ///
+    /// `used_names` collects the names already used in `output_dir`. Entry paths which are identical
+    /// after sanitizing (ignoring case, for case-insensitive file systems) are suffixed with the
+    /// entry hash, so that the file of a previous entry is not overwritten.
+    fn entry_output_path(&self, output_dir: &Path, path: BinEntryPath, used_names: &mut HashSet<String>) -> PathBuf {
+        let mut name = match path.get_str(self.hmappers) {
+            Some(s) => sanitize_relative_path(s).into_os_string(),
+            None => format!("{:x}", path).into(),
+        };
+        if !used_names.insert(name.to_string_lossy().to_lowercase()) {
+            name.push(format!(".{:x}", path));
+            used_names.insert(name.to_string_lossy().to_lowercase());
+        }
+        name.push(".");
+        name.push(self.extension());
+        output_dir.join(name)
+    }
+
+    /// Create an output file, write entries to it
+    fn write_file<F>(&self, output: &Path, f: F) -> Result<()>
+    where F: FnOnce(&mut dyn BinEntriesSerializer) -> Result<()> {
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = io::BufWriter::new(fs::File::create(output)?);
+        let mut serializer = build_bin_entry_serializer(&mut writer, self.hmappers, self.json)?;
+        f(&mut *serializer)?;
+        serializer.end()?;
+        drop(serializer);
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Get the path of a bin file relative to the input directory it has been found in
+fn relative_input_path(path: &Path, root: &Path) -> PathBuf {
+    // Bin file paths may have been canonicalized, try with a canonicalized root as fallback
+    path.strip_prefix(root).ok()
+        .map(Path::to_path_buf)
+        .or_else(|| {
+            let root = root.canonicalize().ok()?;
+            path.strip_prefix(root).ok().map(Path::to_path_buf)
+        })
+        .unwrap_or_else(|| path.file_name().unwrap_or(path.as_os_str()).into())
+}
+
+
+/// Traverse entries from a given bin file path with an infallible visitor
+fn traverse_bin_path<V: BinVisitor<Error=Infallible, Break=Infallible>>(path: &PathBuf, visitor: &mut V) -> Result<()> {
+    let scanner = PropFile::scan_entries_from_path(path)?;
+    for entry in scanner.parse() {
+        let Ok(ControlFlow::Continue(())) = entry?.traverse_bin(visitor);
+    }
+    Ok(())
+}
+
+/// Occurrence of an entry defined in multiple files
+struct DuplicateEntryOccurrence {
+    /// Index of the file defining the entry
+    file_index: usize,
+    ctype: BinClassName,
+    /// Hash of the serialized entry, to compare entry contents
+    digest: u64,
+}
+
+/// Find entries defined in multiple files
+///
+/// Files are scanned twice: first to find duplicate paths, then to compare duplicate entries.
+/// Only duplicate entries are parsed.
+fn find_duplicate_entries(paths: &[PathBuf]) -> Result<HashMap<BinEntryPath, Vec<DuplicateEntryOccurrence>>> {
+    let mut files_per_entry = HashMap::<BinEntryPath, Vec<usize>>::new();
+    for (file_index, path) in paths.iter().enumerate() {
+        let scanner = PropFile::scan_entries_from_path(path)
+            .with_context(|| format!("failed to scan {}", path.display()))?;
+        for header in scanner.headers() {
+            let (entry_path, _) = header?;
+            files_per_entry.entry(entry_path).or_default().push(file_index);
+        }
+    }
+    files_per_entry.retain(|_, files| files.len() > 1);
+
+    let mut duplicates = HashMap::<BinEntryPath, Vec<DuplicateEntryOccurrence>>::new();
+    let file_indexes: BTreeSet<usize> = files_per_entry.values().flatten().copied().collect();
+    let hmappers = BinHashMappers::default();
+    for file_index in file_indexes {
+        let scanner = PropFile::scan_entries_from_path(&paths[file_index])?;
+        for entry in scanner.filter_parse(|path, _| files_per_entry.contains_key(&path)) {
+            let entry = entry?;
+            let mut buf = Vec::new();
+            TextTreeSerializer::new(&mut buf, &hmappers).write_entry(&entry)?;
+            let mut hasher = DefaultHasher::new();
+            buf.hash(&mut hasher);
+            duplicates.entry(entry.path).or_default().push(DuplicateEntryOccurrence {
+                file_index,
+                ctype: entry.ctype,
+                digest: hasher.finish(),
+            });
+        }
+    }
+    Ok(duplicates)
+}
+
+/// Find entries defined in multiple files, write them sorted by path
+fn write_duplicate_entries<W: Write>(mut writer: W, paths: &[PathBuf], hmappers: &BinHashMappers, json: bool) -> Result<()> {
+    let duplicates = find_duplicate_entries(paths)?;
+    let format_class = |ctype: &BinClassName| ctype.seek_str(hmappers).to_string();
+
+    let mut duplicates: Vec<(String, Vec<DuplicateEntryOccurrence>)> = duplicates
+        .into_iter()
+        .map(|(path, occurrences)| (path.seek_str(hmappers).to_string(), occurrences))
+        .collect();
+    duplicates.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    if json {
+        let values: Vec<serde_json::Value> = duplicates.iter().map(|(path, occurrences)| {
+            let identical = occurrences.windows(2).all(|w| w[0].digest == w[1].digest);
+            let files: Vec<serde_json::Value> = occurrences.iter().map(|o| serde_json::json!({
+                "file": paths[o.file_index].to_string_lossy(),
+                "class": format_class(&o.ctype),
+            })).collect();
+            serde_json::json!({
+                "path": path,
+                "identical": identical,
+                "files": files,
+            })
+        }).collect();
+        serde_json::to_writer_pretty(&mut writer, &values)?;
+        writeln!(writer)?;
+    } else {
+        for (path, occurrences) in duplicates.iter() {
+            let identical = occurrences.windows(2).all(|w| w[0].digest == w[1].digest);
+            writeln!(writer, "{}  {}", path, if identical { "identical" } else { "different" })?;
+            for o in occurrences {
+                writeln!(writer, "  {}  {}", format_class(&o.ctype), paths[o.file_index].display())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cdragon_hashes::bin::BinHashKind;
+    use cdragon_prop::BinType;
+    use cdragon_prop::testing::*;
+
+    /// Hash mappers with given entry paths, type and field names
+    fn hash_mappers(paths: &[&str], names: &[&str]) -> BinHashMappers {
+        let mut hmappers = BinHashMappers::default();
+        for path in paths {
+            hmappers.get_mut(BinHashKind::EntryPath).insert(binhash_from_str(path), path.to_string());
+        }
+        for name in names {
+            hmappers.get_mut(BinHashKind::ClassName).insert(binhash_from_str(name), name.to_string());
+            hmappers.get_mut(BinHashKind::FieldName).insert(binhash_from_str(name), name.to_string());
+        }
+        hmappers
+    }
+
+    #[test]
+    fn stats_count_entries_and_fields() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = [tmp.path().join("a.bin"), tmp.path().join("b.bin")];
+        let value = 1u32.to_le_bytes();
+        let name = b"\x04\0Item";
+        write_bin_file(&paths[0], &[
+            ("Item", "Items/1", &[("mValue", BinType::U32 as u8, &value), ("mName", BinType::String as u8, name)]),
+            ("Item", "Items/2", &[("mValue", BinType::U32 as u8, &value)]),
+            ("Spell", "Spells/1", &[]),
+        ]);
+        write_bin_file(&paths[1], &[
+            ("Item", "Items/3", &[("mValue", BinType::U32 as u8, &value)]),
+        ]);
+        let hmappers = hash_mappers(&[], &["Item", "Spell", "mValue", "mName"]);
+
+        // Headers only
+        let stats = collect_bin_stats(&paths, false).unwrap();
+        assert_eq!(stats.entries, HashMap::from([(binhash_from_str("Item").into(), 3), (binhash_from_str("Spell").into(), 1)]));
+        assert!(stats.fields.is_empty());
+        let mut output = Vec::new();
+        write_bin_stats(&mut output, &stats, &hmappers, false).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "       3  Item\n       1  Spell\n");
+
+        // With fields
+        let stats = collect_bin_stats(&paths, true).unwrap();
+        let mut output = Vec::new();
+        write_bin_stats(&mut output, &stats, &hmappers, true).unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&output).unwrap(), serde_json::json!([
+            {"class": "Item", "count": 3, "fields": [
+                {"name": "mValue", "type": "U32", "count": 3},
+                {"name": "mName", "type": "String", "count": 1},
+            ]},
+            {"class": "Spell", "count": 1},
+        ]));
+    }
+
+    #[test]
+    fn duplicate_entries_are_compared() {
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = [tmp.path().join("a.bin"), tmp.path().join("b.bin")];
+        let u32_field = |n: u32| n.to_le_bytes();
+        let (one, two, three) = (u32_field(1), u32_field(2), u32_field(3));
+        write_bin_file(&paths[0], &[
+            ("Item", "Items/Same", &[("mValue", BinType::U32 as u8, &one)]),
+            ("Item", "Items/Diff", &[("mValue", BinType::U32 as u8, &two)]),
+            ("Item", "Items/Unique", &[]),
+        ]);
+        write_bin_file(&paths[1], &[
+            ("Item", "Items/Diff", &[("mValue", BinType::U32 as u8, &three)]),
+            ("Item", "Items/Same", &[("mValue", BinType::U32 as u8, &one)]),
+        ]);
+
+        let duplicates = find_duplicate_entries(&paths).unwrap();
+        assert_eq!(duplicates.len(), 2);
+        let same = &duplicates[&binhash_from_str("Items/Same").into()];
+        assert_eq!(same.iter().map(|o| o.file_index).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(same[0].digest, same[1].digest);
+        let diff = &duplicates[&binhash_from_str("Items/Diff").into()];
+        assert_eq!(diff.iter().map(|o| o.file_index).collect::<Vec<_>>(), [0, 1]);
+        assert_ne!(diff[0].digest, diff[1].digest);
+
+        let hmappers = hash_mappers(&["Items/Same", "Items/Diff"], &["Item"]);
+        let mut output = Vec::new();
+        write_duplicate_entries(&mut output, &paths, &hmappers, true).unwrap();
+        let files = serde_json::json!([
+            {"file": paths[0].to_string_lossy(), "class": "Item"},
+            {"file": paths[1].to_string_lossy(), "class": "Item"},
+        ]);
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&output).unwrap(), serde_json::json!([
+            {"path": "Items/Diff", "identical": false, "files": files},
+            {"path": "Items/Same", "identical": true, "files": files},
+        ]));
+
+        let mut output = Vec::new();
+        write_duplicate_entries(&mut output, &paths, &hmappers, false).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), format!(
+            "Items/Diff  different\n  Item  {a}\n  Item  {b}\nItems/Same  identical\n  Item  {a}\n  Item  {b}\n",
+            a = paths[0].display(), b = paths[1].display()));
+    }
+
+    #[test]
+    fn split_entries_output_paths_do_not_collide() {
+        let mut hmappers = BinHashMappers::default();
+        let names = ["Items/A:B", "Items/A?B", "items/a_b", "Items/C"];
+        for name in names {
+            hmappers.get_mut(BinHashKind::EntryPath).insert(binhash_from_str(name), name.into());
+        }
+        let filter = EntryFilter::all();
+        let dumper = BinDirDumper { out_dir: Path::new("out"), hmappers: &hmappers, filter: &filter, json: true, split_entries: true };
+
+        let output_dir = Path::new("out/data");
+        let mut used = HashSet::new();
+        let mut paths: Vec<PathBuf> = names.iter()
+            .map(|name| dumper.entry_output_path(output_dir, binhash_from_str(name).into(), &mut used))
+            .collect();
+        paths.push(dumper.entry_output_path(output_dir, BinEntryPath::from(0x12345678), &mut used));
+
+        let expected = |s: &str| Path::new("out/data").join(s);
+        assert_eq!(paths, [
+            expected("Items/A_B.json"),
+            expected(&format!("Items/A_B.{:x}.json", binhash_from_str("Items/A?B"))),
+            expected(&format!("items/a_b.{:x}.json", binhash_from_str("items/a_b"))),
+            expected("Items/C.json"),
+            expected("12345678.json"),
+        ]);
+    }
+
+    /// List files in a directory, relative to it
+    fn list_files(root: &Path) -> BTreeSet<String> {
+        walkdir::WalkDir::new(root).into_iter()
+            .map(Result::unwrap)
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.path().strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn dump_to_directory_tree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let input = tmp.path().join("bins");
+        write_bin_file(&input.join("a.bin"), &[("Item", "Items/1", &[]), ("Item", "Items/2", &[])]);
+        write_bin_file(&input.join("sub/b.bin"), &[("Spell", "Spells/1", &[]), ("Item", "Items/3", &[])]);
+        let single = tmp.path().join("c.bin");
+        write_bin_file(&single, &[("Item", "Items/4", &[])]);
+        let hmappers = hash_mappers(&["Items/1", "Items/2", "Spells/1"], &["Item", "Spell"]);
+        let filter = EntryFilter::all();
+
+        let out_dir = tmp.path().join("out");
+        let dumper = BinDirDumper { out_dir: &out_dir, hmappers: &hmappers, filter: &filter, json: false, split_entries: false };
+        dumper.dump_input(&input).unwrap();
+        dumper.dump_input(&single).unwrap();
+        assert_eq!(list_files(&out_dir), BTreeSet::from(["a.txt", "sub/b.txt", "c.txt"].map(String::from)));
+        let content = fs::read_to_string(out_dir.join("sub/b.txt")).unwrap();
+        assert_eq!(content, format!("<BinEntry 'Spells/1' Spell []>\n<BinEntry {{{:x}}} Item []>\n", binhash_from_str("Items/3")));
+
+        let out_dir = tmp.path().join("out-split");
+        let filter = EntryFilter::with_type(binhash_from_str("Item").into());
+        let dumper = BinDirDumper { out_dir: &out_dir, hmappers: &hmappers, filter: &filter, json: true, split_entries: true };
+        dumper.dump_input(&input).unwrap();
+        let expected = ["a/Items/1.json", "a/Items/2.json", &format!("sub/b/{:x}.json", binhash_from_str("Items/3"))];
+        assert_eq!(list_files(&out_dir), BTreeSet::from(expected.map(String::from)));
+    }
+
+    #[test]
+    fn relative_input_path_strips_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("bins");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        let file = root.join("sub/a.bin");
+        assert_eq!(relative_input_path(&file, &root), Path::new("sub/a.bin"));
+        // Canonicalized file path, non-canonical root
+        let file = root.canonicalize().unwrap().join("sub/a.bin");
+        assert_eq!(relative_input_path(&file, &root.join("sub/..")), Path::new("sub/a.bin"));
+        // Unrelated root, fallback to the file name
+        assert_eq!(relative_input_path(&file, Path::new("/elsewhere")), Path::new("a.bin"));
+    }
+}
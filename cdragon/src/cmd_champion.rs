@@ -0,0 +1,386 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use cdragon_hashes::wad::{WadHashKind, WadHashMapper, WadHashMappers};
+use cdragon_prop::{BinHashMappers, PropFile};
+use cdragon_utils::locale::{Locale, parse_wad_name};
+use cdragon_wad::WadFile;
+use crate::cli::*;
+use crate::utils::{build_bin_entry_serializer, sanitize_relative_path};
+
+pub fn subcommand(name: &'static str) -> Subcommand {
+    let cmd = parent_command(name)
+        .about("Work on champion files from a game install")
+        .subcommand(
+            Command::new("export")
+            .about("Export champion files from its WADs, and BIN files as JSON")
+            .arg(Arg::new("champion")
+                .required(true)
+                .help("Champion name (case and punctuation are ignored)"))
+            .arg(Arg::new("game-dir")
+                .short('g')
+                .long("game-dir")
+                .value_name("dir")
+                .required(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Game install directory (or its `Game` subdirectory)"))
+            .arg(Arg::new("output")
+                .short('o')
+                .value_name("dir")
+                .value_parser(value_parser!(PathBuf))
+                .default_value(".")
+                .help("Output directory for exported files"))
+            .arg(Arg::new("locale")
+                .short('l')
+                .long("locale")
+                .value_name("locale")
+                .value_parser(value_parser!(Locale))
+                .action(ArgAction::Append)
+                .help("Only export locale WADs of given locales (e.g. `en_US`), can be repeated"))
+            .arg(Arg::new("no-json")
+                .long("no-json")
+                .action(ArgAction::SetTrue)
+                .help("Don't dump BIN files as JSON"))
+            .arg(arg_hashes_dir())
+        )
+        ;
+    (cmd, handle)
+}
+
+fn handle(matches: &ArgMatches) -> CliResult {
+    match matches.subcommand() {
+        Some(("export", matches)) => {
+            let name = matches.get_one::<String>("champion").unwrap();
+            let game_dir = matches.get_one::<PathBuf>("game-dir").unwrap();
+            let output = matches.get_one::<PathBuf>("output").unwrap();
+            let locales: Option<Vec<&Locale>> = matches.get_many::<Locale>("locale").map(|v| v.collect());
+
+            let hashes_dir = get_hashes_dir(matches).context("hashes directory is required to resolve WAD paths")?;
+            let wad_hmappers = WadHashMappers::lazy_from_dirpath(&hashes_dir, false).with_path_normalization(true);
+            let wad_hmapper = wad_hmappers.mapper(WadHashKind::Game)?;
+            let bin_hmappers = if matches.get_flag("no-json") {
+                None
+            } else {
+                Some(BinHashMappers::from_dirpath(&hashes_dir)
+                    .with_context(|| format!("failed to load hash mappers from {}", hashes_dir.display()))?)
+            };
+
+            let champions_dir = find_champions_dir(game_dir)
+                .with_context(|| format!("champion WADs not found in {}", game_dir.display()))?;
+            let wads: Vec<ChampionWad> = find_champion_wads(&champions_dir, name)?
+                .into_iter()
+                .filter(|wad| match (&wad.locale, &locales) {
+                    (Some(locale), Some(locales)) => locales.contains(&locale),
+                    _ => true,
+                })
+                .collect();
+            if wads.is_empty() {
+                return Err(format!("no WAD found for champion {:?} in {}", name, champions_dir.display()).into());
+            }
+
+            let mut exporter = ChampionExporter {
+                champion: wads[0].champion.clone(),
+                output,
+                wad_hmapper,
+                bin_hmappers: bin_hmappers.as_ref(),
+                files: Vec::new(),
+                json_files: Vec::new(),
+            };
+            let mut wads_info = Vec::with_capacity(wads.len());
+            for wad in &wads {
+                println!("Export from {}", wad.path.display());
+                let (nfiles, nunknown) = exporter.export_wad(&wad.path)?;
+                wads_info.push(serde_json::json!({
+                    "path": wad.path,
+                    "locale": wad.locale.map(|l| l.to_string()),
+                    "files": nfiles,
+                    "skipped_unknown": nunknown,
+                }));
+            }
+
+            exporter.files.sort();
+            exporter.json_files.sort();
+            let manifest_path = output.join(format!("{}.export.json", exporter.champion.to_lowercase()));
+            let manifest = serde_json::json!({
+                "champion": exporter.champion,
+                "wads": wads_info,
+                "files": exporter.files,
+                "json": exporter.json_files,
+            });
+            // Create the output directory, in case no file was exported
+            if let Some(parent) = manifest_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut writer = io::BufWriter::new(fs::File::create(&manifest_path)?);
+            serde_json::to_writer_pretty(&mut writer, &manifest)?;
+            writeln!(writer)?;
+            println!("Exported {} files, manifest written to {}", exporter.files.len(), manifest_path.display());
+
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Champion WAD file found in a game install
+#[derive(Debug)]
+struct ChampionWad {
+    path: PathBuf,
+    /// Champion name, as used in the WAD name
+    champion: String,
+    /// Locale of the WAD, `None` for the main one
+    locale: Option<Locale>,
+}
+
+/// Export champion files from WADs
+struct ChampionExporter<'a> {
+    champion: String,
+    output: &'a Path,
+    wad_hmapper: &'a WadHashMapper,
+    bin_hmappers: Option<&'a BinHashMappers>,
+    /// Exported WAD files
+    files: Vec<String>,
+    /// Paths of JSON dumps of BIN files
+    json_files: Vec<String>,
+}
+
+impl<'a> ChampionExporter<'a> {
+    /// Export champion files from a WAD, return the number of exported and unknown files
+    fn export_wad(&mut self, path: &Path) -> Result<(usize, usize)> {
+        let mut wad = WadFile::open(path).with_context(|| format!("failed to open WAD file {}", path.display()))?;
+        wad.load_subchunk_toc(self.wad_hmapper)?;
+        let champion = self.champion.to_lowercase();
+
+        let entries = wad.iter_entries().collect::<Result<Vec<_>, _>>()?;
+        let (mut nfiles, mut nunknown) = (0, 0);
+        for entry in entries {
+            if entry.is_redirection() {
+                continue;
+            }
+            let Some(wad_path) = self.wad_hmapper.get(entry.path.hash) else {
+                nunknown += 1;
+                continue;
+            };
+            if !is_champion_path(wad_path, &champion) {
+                continue;
+            }
+            // Paths come from the hash mapper, don't write outside of the output directory
+            let output_path = self.output.join(sanitize_relative_path(wad_path));
+            wad.extract_entry(&entry, &output_path)
+                .with_context(|| format!("failed to extract {}", wad_path))?;
+            self.files.push(wad_path.to_owned());
+            nfiles += 1;
+
+            if let Some(hmappers) = self.bin_hmappers {
+                if wad_path.ends_with(".bin") {
+                    let json_path = format!("{}.json", wad_path);
+                    match dump_bin_as_json(&output_path, &self.output.join(sanitize_relative_path(&json_path)), hmappers) {
+                        Ok(()) => self.json_files.push(json_path),
+                        Err(e) => eprintln!("Failed to dump {} as JSON: {}", wad_path, e),
+                    }
+                }
+            }
+        }
+        Ok((nfiles, nunknown))
+    }
+}
+
+/// Dump a BIN file as JSON
+fn dump_bin_as_json(path: &Path, output: &Path, hmappers: &BinHashMappers) -> Result<()> {
+    let prop = PropFile::from_path(path)?;
+    let mut writer = io::BufWriter::new(fs::File::create(output)?);
+    let mut serializer = build_bin_entry_serializer(&mut writer, hmappers, true)?;
+    for entry in prop.entries.iter() {
+        serializer.write_entry(entry)?;
+    }
+    serializer.end()?;
+    Ok(())
+}
+
+/// Return `true` if a WAD path belongs to the given champion (lowercased)
+///
+/// Champion files are under a `characters/<champion>/` directory, possibly nested (e.g. for
+/// voice-over files).
+fn is_champion_path(path: &str, champion: &str) -> bool {
+    let dir = format!("characters/{}/", champion);
+    path.starts_with(&dir) || path.contains(&format!("/{}", dir))
+}
+
+/// Normalize a champion name for comparison: lowercase, keep only alphanumeric characters
+///
+/// For instance, `Kai'Sa` and `kaisa` are equivalent.
+fn normalize_champion_name(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Find the champion WADs directory in a game install
+///
+/// Directory names are matched case-insensitively.
+fn find_champions_dir(game_dir: &Path) -> Option<PathBuf> {
+    const CANDIDATES: &[&[&str]] = &[
+        &["Game", "DATA", "FINAL", "Champions"],
+        &["DATA", "FINAL", "Champions"],
+        &["FINAL", "Champions"],
+        &["Champions"],
+    ];
+    CANDIDATES.iter().find_map(|components| {
+        components.iter().try_fold(game_dir.to_path_buf(), |dir, component| {
+            fs::read_dir(&dir).ok()?
+                .filter_map(|e| e.ok())
+                .find(|e| e.file_name().to_str().is_some_and(|s| s.eq_ignore_ascii_case(component)))
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+        })
+    })
+}
+
+/// Find the WADs of a champion, main WAD first
+fn find_champion_wads(dir: &Path, name: &str) -> Result<Vec<ChampionWad>> {
+    let name = normalize_champion_name(name);
+    let mut wads = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(filename) = path.file_name().and_then(|s| s.to_str()) else { continue };
+        // Champion WADs are named `<Champion>.wad.client` or `<Champion>.<locale>.wad.client`
+        if let Some((champion, locale)) = parse_wad_name(filename) {
+            if normalize_champion_name(champion) == name {
+                let champion = champion.to_owned();
+                wads.push(ChampionWad { path, champion, locale });
+            }
+        }
+    }
+    wads.sort_by(|a, b| (&a.locale, &a.path).cmp(&(&b.locale, &b.path)));
+    Ok(wads)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn exported_paths_stay_in_output_directory() {
+        use cdragon_wad::fixtures::{TestEntry, wad_data};
+        let tmp = tempfile::tempdir().unwrap();
+        let wad_path = tmp.path().join("Ahri.wad.client");
+        fs::write(&wad_path, wad_data(&[TestEntry::new(1, b"valid"), TestEntry::new(2, b"escaping")])).unwrap();
+        let mut wad_hmapper = WadHashMapper::new();
+        wad_hmapper.insert(1, "characters/ahri/ahri.txt".to_string());
+        wad_hmapper.insert(2, "characters/ahri/../../../outside.txt".to_string());
+
+        let output = tmp.path().join("output");
+        let mut exporter = ChampionExporter {
+            champion: "Ahri".into(),
+            output: &output,
+            wad_hmapper: &wad_hmapper,
+            bin_hmappers: None,
+            files: Vec::new(),
+            json_files: Vec::new(),
+        };
+        assert_eq!(exporter.export_wad(&wad_path).unwrap(), (2, 0));
+        assert_eq!(fs::read(output.join("characters/ahri/ahri.txt")).unwrap(), b"valid");
+        assert_eq!(fs::read(output.join("characters/ahri/_/_/_/outside.txt")).unwrap(), b"escaping");
+        assert!(!tmp.path().join("outside.txt").exists());
+    }
+
+    #[test]
+    fn champion_names_are_normalized() {
+        assert_eq!(normalize_champion_name("Kai'Sa"), "kaisa");
+        assert_eq!(normalize_champion_name("kaisa"), "kaisa");
+        assert_eq!(normalize_champion_name("Dr. Mundo"), "drmundo");
+        assert_eq!(normalize_champion_name("MonkeyKing"), "monkeyking");
+        assert_eq!(normalize_champion_name("Nunu & Willump"), "nunuwillump");
+        assert_eq!(normalize_champion_name(""), "");
+    }
+
+    #[test]
+    fn champion_paths_are_detected() {
+        assert!(is_champion_path("characters/ahri/ahri.bin", "ahri"));
+        assert!(is_champion_path("characters/ahri/skins/skin0.bin", "ahri"));
+        assert!(is_champion_path("assets/characters/ahri/hud/ahri_circle.png", "ahri"));
+        assert!(is_champion_path("data/characters/ahri/ahri.bin", "ahri"));
+        // Other champions, with a common prefix
+        assert!(!is_champion_path("characters/ahrix/ahrix.bin", "ahri"));
+        assert!(!is_champion_path("characters/kaisa/kaisa.bin", "ahri"));
+        assert!(!is_champion_path("assets/xcharacters/ahri/ahri.png", "ahri"));
+        // Not in a champion directory
+        assert!(!is_champion_path("characters/ahri", "ahri"));
+        assert!(!is_champion_path("data/ahri.bin", "ahri"));
+        assert!(!is_champion_path("assets/sounds/characters_ahri.bnk", "ahri"));
+    }
+
+    #[test]
+    fn champions_dir_is_found_ignoring_case() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(find_champions_dir(tmp.path()), None);
+
+        let dir = tmp.path().join("game").join("data").join("Final").join("champions");
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(find_champions_dir(tmp.path()), Some(dir.clone()));
+        // From a subdirectory of the install
+        assert_eq!(find_champions_dir(&tmp.path().join("game")), Some(dir));
+    }
+
+    #[test]
+    fn champions_dir_must_be_a_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        touch(&tmp.path().join("Champions"));
+        assert_eq!(find_champions_dir(tmp.path()), None);
+        assert_eq!(find_champions_dir(&tmp.path().join("missing")), None);
+    }
+
+    #[test]
+    fn champion_wads_are_found_main_first() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for name in ["KaiSa.fr_FR.wad.client", "Kaisa.wad.client", "KaiSa.en_US.wad.client", "Kayle.wad.client", "KaiSa.txt"] {
+            touch(&dir.join(name));
+        }
+
+        let wads = find_champion_wads(dir, "Kai'Sa").unwrap();
+        let names: Vec<_> = wads.iter().map(|w| w.path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["Kaisa.wad.client", "KaiSa.en_US.wad.client", "KaiSa.fr_FR.wad.client"]);
+        assert_eq!(wads[0].champion, "Kaisa");
+        assert!(wads[0].locale.is_none());
+        assert_eq!(wads[1].locale.map(|l| l.to_string()).as_deref(), Some("en_US"));
+
+        assert!(find_champion_wads(dir, "Ahri").unwrap().is_empty());
+        assert!(find_champion_wads(&dir.join("missing"), "Ahri").is_err());
+    }
+
+    #[test]
+    fn champion_wad_names_are_parsed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for name in [
+            "Ahri.wad.client",
+            "Ahri.ko_KR.wad.client",
+            "Ahri.de_DE.wad",
+            "AHRI.ZH_CN.WAD.CLIENT",
+            "Ahri.notalocale.wad.client",
+            "Ahri.en_US.extra.wad.client",
+            "Ahri.wad.client.bak",
+            "Ahri.bin",
+            "Ahri",
+        ] {
+            touch(&dir.join(name));
+        }
+
+        let wads = find_champion_wads(dir, "ahri").unwrap();
+        let found: Vec<_> = wads.iter()
+            .map(|w| (w.path.file_name().unwrap().to_str().unwrap(), w.champion.as_str(), w.locale.map(|l| l.to_string())))
+            .collect();
+        assert_eq!(found, [
+            ("Ahri.wad.client", "Ahri", None),
+            ("Ahri.de_DE.wad", "Ahri", Some("de_DE".into())),
+            ("Ahri.ko_KR.wad.client", "Ahri", Some("ko_KR".into())),
+            ("AHRI.ZH_CN.WAD.CLIENT", "AHRI", Some("zh_CN".into())),
+        ]);
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use cdragon_rman::FileEntry;
+use cdragon_utils::locale::Locale;
+use cdragon_wad::{Wad, WadEntry};
+use walkdir::WalkDir;
+use crate::cli::*;
+use crate::utils::{file_matches_locale, open_rman};
+
+pub fn subcommand(name: &'static str) -> Subcommand {
+    let cmd = Command::new(name)
+        .about("Check files of an install directory against a release manifest")
+        .after_help(
+            "Report missing files, files with an unexpected size and unreadable WADs.\n\
+             Paths are compared ignoring case. Files not in the manifest are reported as extra,\n\
+             but are not considered as a problem: they are expected (e.g. logs, configuration).\n\
+             Exit status is 1 if a problem is found."
+        )
+        .arg(Arg::new("manifest")
+            .short('m')
+            .long("manifest")
+            .value_name("rman")
+            .required(true)
+            .value_parser(value_parser!(PathBuf))
+            .help("Manifest file of the install (path, URL, or `-` for stdin)"))
+        .arg(Arg::new("game-dir")
+            .short('g')
+            .long("game-dir")
+            .value_name("dir")
+            .required(true)
+            .value_parser(value_parser!(PathBuf))
+            .help("Install directory"))
+        .arg(Arg::new("locale")
+            .short('l')
+            .long("locale")
+            .value_name("locale")
+            .value_parser(value_parser!(Locale))
+            .help("Skip files localized for other locales (e.g. `en_US`), based on file flags or paths"))
+        .arg(Arg::new("json")
+            .long("json")
+            .action(ArgAction::SetTrue)
+            .help("Output a JSON report"))
+        ;
+    (cmd, handle)
+}
+
+fn handle(matches: &ArgMatches) -> CliResult {
+    let rman = open_rman(matches.get_one::<PathBuf>("manifest").unwrap())?;
+    let game_dir = matches.get_one::<PathBuf>("game-dir").unwrap();
+    let locale = matches.get_one::<Locale>("locale");
+    let flags = rman.flags()?;
+    let file_index = rman.file_index_ignore_case()?;
+
+    // Map installed files to manifest paths, collect extra files
+    let mut installed = HashMap::<String, PathBuf>::new();
+    let mut extra = Vec::<String>::new();
+    for entry in WalkDir::new(game_dir).min_depth(1) {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let path = manifest_path_from_disk(entry.path().strip_prefix(game_dir)?);
+        if file_index.get(&path).is_some() {
+            installed.insert(path.to_ascii_lowercase(), entry.into_path());
+        } else {
+            extra.push(path);
+        }
+    }
+    extra.sort();
+
+    // Check manifest files
+    let mut checked = 0;
+    let mut problems = Vec::<(&str, InstallProblem)>::new();
+    for (path, file) in file_index.iter() {
+        if !locale.is_none_or(|l| file_matches_locale(file, path, &flags, *l)) {
+            continue;
+        }
+        checked += 1;
+        let problem = match installed.get(&path.to_ascii_lowercase()) {
+            None => Some(InstallProblem::Missing),
+            Some(disk_path) => check_installed_file(disk_path, file),
+        };
+        if let Some(problem) = problem {
+            problems.push((path, problem));
+        }
+    }
+
+    if matches.get_flag("json") {
+        let problem_values: Vec<serde_json::Value> = problems.iter().map(|(path, problem)| {
+            let mut value = serde_json::json!({
+                "path": path,
+                "problem": problem.kind(),
+            });
+            match problem {
+                InstallProblem::WrongSize { expected, actual } => {
+                    value["expected_size"] = (*expected).into();
+                    value["actual_size"] = (*actual).into();
+                }
+                InstallProblem::Unreadable(error) => {
+                    value["error"] = error.as_str().into();
+                }
+                InstallProblem::Missing => {}
+            }
+            value
+        }).collect();
+        let report = serde_json::json!({
+            "checked": checked,
+            "problems": problem_values,
+            "extra": extra,
+        });
+        serde_json::to_writer_pretty(std::io::stdout().lock(), &report)?;
+        println!();
+    } else {
+        for (path, problem) in &problems {
+            match problem {
+                InstallProblem::Missing => println!("missing     {}", path),
+                InstallProblem::WrongSize { expected, actual } => println!("{:10}  {}  ({} bytes, expected {})", problem.kind(), path, actual, expected),
+                InstallProblem::Unreadable(error) => println!("unreadable  {}: {}", path, error),
+            }
+        }
+        for path in &extra {
+            println!("extra       {}", path);
+        }
+        let count = |kind: &str| problems.iter().filter(|(_, p)| p.kind() == kind).count();
+        println!("{} files checked: {} missing, {} truncated, {} wrong size, {} unreadable, {} extra",
+                 checked, count("missing"), count("truncated"), count("wrong-size"), count("unreadable"), extra.len());
+    }
+
+    if !problems.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Problem found on a manifest file
+enum InstallProblem {
+    /// File is not installed
+    Missing,
+    /// File size does not match the manifest
+    WrongSize { expected: u64, actual: u64 },
+    /// File cannot be read, or its content is invalid
+    Unreadable(String),
+}
+
+impl InstallProblem {
+    /// Short name of the problem, used in reports
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::WrongSize { expected, actual } if actual < expected => "truncated",
+            Self::WrongSize { .. } => "wrong-size",
+            Self::Unreadable(_) => "unreadable",
+        }
+    }
+}
+
+/// Convert a path relative to the install directory to a manifest path
+///
+/// Manifest paths use `/` as separator, regardless of the platform.
+fn manifest_path_from_disk(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Check an installed file, return the problem found, if any
+///
+/// WADs are opened to check their header and entries.
+fn check_installed_file(path: &Path, file: &FileEntry) -> Option<InstallProblem> {
+    if file.link.is_some() {
+        // Only check that links exist, they have no data
+        return None;
+    }
+    let actual = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => return Some(InstallProblem::Unreadable(e.to_string())),
+    };
+    let expected = file.filesize as u64;
+    if actual != expected {
+        return Some(InstallProblem::WrongSize { expected, actual });
+    }
+
+    let name = file.name.to_ascii_lowercase();
+    if name.ends_with(".wad.client") || name.ends_with(".wad") {
+        if let Err(e) = check_wad(path, actual) {
+            return Some(InstallProblem::Unreadable(e));
+        }
+    }
+    None
+}
+
+/// Check that a WAD header and entries are valid
+fn check_wad(path: &Path, file_size: u64) -> Result<(), String> {
+    let mut reader = std::io::BufReader::new(fs::File::open(path).map_err(|e| e.to_string())?);
+    let wad = Wad::read(&mut reader).map_err(|e| format!("invalid WAD header: {}", e))?;
+    let entries = wad.entries().map_err(|e| format!("invalid WAD entries: {}", e))?;
+    let is_out_of_wad = |e: &&WadEntry| {
+        (e.offset() as u64).checked_add(e.size() as u64).is_none_or(|end| end > file_size)
+    };
+    if let Some(entry) = entries.iter().find(is_out_of_wad) {
+        return Err(format!("WAD entry {:016x} is out of the WAD (offset {}, {} bytes)", entry.path.hash, entry.offset(), entry.size()));
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cdragon_wad::fixtures::{TestEntry, wad_data};
+
+    #[test]
+    fn manifest_path_from_disk_uses_slashes() {
+        assert_eq!(manifest_path_from_disk(Path::new("Game.exe")), "Game.exe");
+        assert_eq!(manifest_path_from_disk(&Path::new("DATA").join("FINAL").join("Champions").join("Ahri.wad.client")),
+                   "DATA/FINAL/Champions/Ahri.wad.client");
+    }
+
+    /// Write a WAD with a single uncompressed entry and 16 bytes of data, return the WAD size
+    fn write_wad(path: &Path, offset: u32, size: u32) -> u64 {
+        let data = wad_data(&[TestEntry::new(0x1234, &[0; 16]).with_location(offset, size)]);
+        fs::write(path, &data).unwrap();
+        data.len() as u64
+    }
+
+    #[test]
+    fn check_wad_entry_bounds() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("test.wad.client");
+        let wad_size = write_wad(&path, 0, 16);
+        assert_eq!(wad_size, 4 + 264 + 4 + 32 + 16);
+        assert!(check_wad(&path, wad_size).is_ok());
+
+        let data_offset = wad_size as u32 - 16;
+        write_wad(&path, data_offset, 16);
+        assert!(check_wad(&path, wad_size).is_ok());
+        // Entry smaller than the WAD, but ending after it
+        write_wad(&path, data_offset + 1, 16);
+        assert!(check_wad(&path, wad_size).is_err());
+        write_wad(&path, u32::MAX, u32::MAX);
+        assert!(check_wad(&path, wad_size).is_err());
+    }
+}
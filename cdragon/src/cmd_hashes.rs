@@ -1,21 +1,33 @@
 use std::fs;
 use std::io;
 use std::io::{BufRead, Write};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
 use std::path::{PathBuf, Path};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use num_traits::Num;
 use cdragon_hashes::{
-    bin::{BinHashKind, binhash_from_str},
+    bin::{BinHashKind, binhash_from_str, compute_binhash},
+    rst::{RstHashMapper, compute_rst_hash_n, insert_keys_from_reader},
+    wad::{WadHashMapper, compute_wad_hash, wad_path_variants},
     HashError,
+    HashKind,
+    HashMapper,
 };
 use cdragon_prop::{
     BinHashMappers,
     BinHashKindMapping,
     BinEntry,
+    BinFieldName,
     BinVisitor,
     PropFile,
     PropError,
 };
 use cdragon_utils::GuardedFile;
+use cdragon_wad::WadFile;
+use clap::ArgGroup;
 use crate::cli::*;
 use crate::utils::{
     bin_files_from_dir,
@@ -51,6 +63,11 @@ pub fn subcommand(name: &'static str) -> Subcommand {
                 .default_value(".")
                 .value_parser(value_parser!(PathBuf))
                 .help("Output directory for unknown hashes files (default: `.`)"))
+            .arg(Arg::new("append")
+                .short('a')
+                .long("append")
+                .action(ArgAction::SetTrue)
+                .help("Keep hashes from existing unknown hashes files, only add new ones"))
         )
         .subcommand(
             Command::new("guess")
@@ -62,6 +79,18 @@ pub fn subcommand(name: &'static str) -> Subcommand {
                 .value_name("dir")
                 .value_parser(value_parser!(PathBuf))
                 .help("Directory with unknown hash lists"))
+            .arg(Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .value_name("n")
+                .value_parser(value_parser!(usize))
+                .help("Number of threads used to read BIN files (default: number of CPUs)"))
+            .arg(Arg::new("numeric-paths")
+                .long("numeric-paths")
+                .value_name("max")
+                .value_parser(value_parser!(u64))
+                .help("Also try entry paths ending with a number up to `max` (e.g. `Items/{n}`)"))
+            .arg(arg_quiet())
         )
         .subcommand(
             Command::new("get-strings")
@@ -75,17 +104,35 @@ pub fn subcommand(name: &'static str) -> Subcommand {
             .arg(arg_hashes_dir().required(true))
             .arg(Arg::new("pattern")
                 .required(true)
-                .help("Value to search for (exact match)"))
+                .help("Value to search for (exact match, floats are compared within epsilon)"))
             .arg(Arg::new("string").short('s').action(ArgAction::SetTrue))
             .arg(Arg::new("hash").short('a').action(ArgAction::SetTrue))
             .arg(Arg::new("link").short('l').action(ArgAction::SetTrue))
+            .arg(Arg::new("number")
+                .long("number")
+                .action(ArgAction::SetTrue)
+                .help("Search an integer, in integer and float values"))
+            .arg(Arg::new("float")
+                .long("float")
+                .action(ArgAction::SetTrue)
+                .help("Search a float, in float values"))
             .group(ArgGroup::new("type")
                 .required(true)
-                .args(["string", "hash", "link"]))
+                .args(["string", "hash", "link", "number", "float"]))
+            .arg(Arg::new("field")
+                .long("field")
+                .value_name("name")
+                .help("Only search values of fields with the given name (or hash)"))
             .arg(Arg::new("json")
                 .short('j')
                 .action(ArgAction::SetTrue)
                 .help("Dump as JSON"))
+            .arg(Arg::new("limit")
+                .short('n')
+                .long("limit")
+                .value_name("N")
+                .value_parser(value_parser!(usize))
+                .help("Stop after N matching entries"))
         )
         .subcommand(
             Command::new("hashes-matching-entries")
@@ -93,6 +140,61 @@ pub fn subcommand(name: &'static str) -> Subcommand {
             .arg(arg_bin_dir())
             .arg(arg_hashes_dir().required(true))
         )
+        .subcommand(
+            Command::new("rst-from-keys")
+            .about("Build an RST hash mapping from a list of keys")
+            .arg(Arg::new("keys")
+                .value_name("keys")
+                .value_parser(value_parser!(PathBuf))
+                .required(true)
+                .help("Text file with one RST key per line"))
+            .arg(Arg::new("output")
+                .short('o')
+                .value_name("file")
+                .default_value(HashKind::Rst.mapping_path())
+                .value_parser(value_parser!(PathBuf))
+                .help("Mapping file to update (merged if it already exists)"))
+            .arg(Arg::new("bits")
+                .long("bits")
+                .value_name("n")
+                .default_value("39")
+                .value_parser(value_parser!(u8).range(39..=40))
+                .help("Number of bits of RST hashes (40 for older RST files)"))
+        )
+        .subcommand(
+            Command::new("wad-from-bins")
+            .about("Guess unknown WAD paths from strings in BIN files")
+            .long_about("Guess unknown WAD paths from strings in BIN files.\n\n\
+                Strings are normalized (lowercased, with forward slashes), and texture extensions \
+                are swapped (`.dds`, `.tex`, `.png`). Matching hashes are added to the game hashes file.")
+            .arg(arg_bin_dir())
+            .arg(arg_hashes_dir().required(true))
+            .arg(Arg::new("wad")
+                .short('w')
+                .value_name("wad")
+                .value_parser(value_parser!(PathBuf))
+                .action(ArgAction::Append)
+                .help("WAD file whose unknown hashes to guess"))
+            .arg(Arg::new("unknown")
+                .short('u')
+                .value_name("file")
+                .value_parser(value_parser!(PathBuf))
+                .help("File with unknown hashes to guess (one hex hash per line)"))
+            .group(ArgGroup::new("unknown-hashes")
+                .required(true)
+                .multiple(true)
+                .args(["wad", "unknown"]))
+        )
+        .subcommand(
+            Command::new("audit")
+            .about("Check hash mapping files for invalid hashes and collisions")
+            .long_about("Check hash mapping files for invalid hashes and collisions.\n\n\
+                Strings are checked against their hash, and each hash must be mapped to a single string, \
+                including across the different kinds of bin hashes. \
+                RST hashes are assumed to be 39-bit hashes.\n\
+                Exit with an error if problems are found.")
+            .arg(arg_hashes_dir().required(true))
+        )
         ;
     (cmd, handle)
 }
@@ -112,6 +214,9 @@ fn handle(matches: &ArgMatches) -> CliResult {
             remove_known_from_unknown(&mut hashes, &hmappers);
 
             let output = matches.get_one::<PathBuf>("output").unwrap();
+            if matches.get_flag("append") {
+                hashes = append_unknown_existing(output, &hashes)?;
+            }
             write_unknown(output.into(), &hashes)?;
 
             Ok(())
@@ -133,21 +238,55 @@ fn handle(matches: &ArgMatches) -> CliResult {
             remove_known_from_unknown(&mut hashes, &hmappers);
 
             println!("Guessing new hashes...");
+            let unknown_counts = BinHashKind::VARIANTS.map(|kind| hashes.get(kind).len());
+            let mut found = Vec::<(u32, String)>::new();
             let finder = BinHashFinder::new(hashes, hmappers)
-                .on_found(|h, s| println!("{:08x} {}", h, s));
+                .on_found(|h, s| {
+                    println!("{:08x} {}", h, s);
+                    found.push((h, s.to_owned()));
+                });
+            // Stop gracefully on Ctrl-C, hashes found so far are still written
+            let cancel = Arc::new(AtomicBool::new(false));
+            {
+                let cancel = cancel.clone();
+                ctrlc::set_handler(move || cancel.store(true, Ordering::Relaxed))?;
+            }
+            let quiet = matches.get_flag("quiet");
             let mut guesser = BinHashGuesser::new(finder)
-                .with_all_hooks();
+                .with_all_hooks()
+                .on_progress(|progress| {
+                    if !quiet && (progress.files % 500 == 0 || progress.files == progress.total) {
+                        eprintln!("{}/{} files processed, {} hashes found", progress.files, progress.total, progress.found);
+                    }
+                })
+                .with_cancel_flag(&cancel);
             //.with_entry_stats();
-            guesser.guess_dir(path);
+            if let Some(jobs) = matches.get_one::<usize>("jobs") {
+                guesser = guesser.with_jobs(*jobs);
+            }
+            if let Some(max) = matches.get_one::<u64>("numeric-paths") {
+                guesser = guesser.with_numeric_entry_paths(*max);
+            }
+            if !guesser.guess_dir(path) {
+                println!("Guessing cancelled");
+            }
             let finder = guesser.result();
 
             println!("Updating files...");
-            finder.hmappers.write_dirpath(&hdir)?;
+            // Only rewrite files with new hashes
+            let kinds: Vec<BinHashKind> = BinHashKind::VARIANTS.into_iter()
+                .zip(unknown_counts)
+                .filter(|(kind, count)| finder.hashes.get(*kind).len() < *count)
+                .map(|(kind, _)| kind)
+                .collect();
+            finder.hmappers.write_dirpath_selective(&hdir, &kinds)?;
 
             if let Some(udir) = udir {
                 write_unknown(udir.into(), &finder.hashes)?;
             }
+            drop(finder);  // release `found`
 
+            println!("{} new hashes found", found.len());
             Ok(())
         }
         Some(("get-strings", matches)) => {
@@ -163,6 +302,8 @@ fn handle(matches: &ArgMatches) -> CliResult {
         Some(("search-entries", matches)) => {
             let path = matches.get_one::<PathBuf>("input").unwrap();
             let pattern = matches.get_one::<String>("pattern").unwrap();
+            let limit = matches.get_one::<usize>("limit").copied();
+            let field: Option<BinFieldName> = matches.get_one::<String>("field").map(|s| binhash_from_str(s).into());
             let hdir = get_hashes_dir(matches).unwrap();
             let hmappers = BinHashMappers::from_dirpath(&hdir)?;
 
@@ -173,14 +314,20 @@ fn handle(matches: &ArgMatches) -> CliResult {
                 let on_match = move |entry: &BinEntry| { serializer.write_entry(entry).unwrap(); };
 
                 use cdragon_prop::data::*;
-                let mut visitor: Box<dyn BinVisitor<Error=()>> = if matches.get_flag("string") {
-                    Box::new(SearchBinValueVisitor::new(BinString(pattern.clone()), on_match))
+                let mut visitor: Box<dyn BinVisitor<Error=(), Break=()>> = if matches.get_flag("string") {
+                    Box::new(SearchBinValueVisitor::new(BinString(pattern.clone()), on_match).with_limit(limit).with_field(field))
                 } else if matches.get_flag("hash") {
                     let hash: BinHashValue = binhash_from_str(pattern).into();
-                    Box::new(SearchBinValueVisitor::new(BinHash(hash), on_match))
+                    Box::new(SearchBinValueVisitor::new(BinHash(hash), on_match).with_limit(limit).with_field(field))
                 } else if matches.get_flag("link") {
                     let hash: BinEntryPath = binhash_from_str(pattern).into();
-                    Box::new(SearchBinValueVisitor::new(BinLink(hash), on_match))
+                    Box::new(SearchBinValueVisitor::new(BinLink(hash), on_match).with_limit(limit).with_field(field))
+                } else if matches.get_flag("number") {
+                    let pattern = NumberPattern::parse_integer(pattern)?;
+                    Box::new(SearchBinValueVisitor::new(pattern, on_match).with_limit(limit).with_field(field))
+                } else if matches.get_flag("float") {
+                    let pattern = NumberPattern::parse_float(pattern)?;
+                    Box::new(SearchBinValueVisitor::new(pattern, on_match).with_limit(limit).with_field(field))
                 } else {
                     unreachable!();
                 };
@@ -198,6 +345,103 @@ fn handle(matches: &ArgMatches) -> CliResult {
             HashesMatchingEntriesVisitor::new(&hmappers).traverse_dir(path)?;
             Ok(())
         }
+        Some(("rst-from-keys", matches)) => {
+            let keys = matches.get_one::<PathBuf>("keys").unwrap();
+            let output = matches.get_one::<PathBuf>("output").unwrap();
+            match *matches.get_one::<u8>("bits").unwrap() {
+                39 => rst_mapping_from_keys::<39>(keys, output)?,
+                40 => rst_mapping_from_keys::<40>(keys, output)?,
+                _ => unreachable!(),
+            }
+            Ok(())
+        }
+        Some(("wad-from-bins", matches)) => {
+            let path = matches.get_one::<PathBuf>("input").unwrap();
+            let hpath = get_hashes_dir(matches).unwrap().join(HashKind::WadGame.mapping_path());
+            let mut hmapper = WadHashMapper::from_path(&hpath)?;
+
+            let mut unknown = HashSet::<u64>::new();
+            if let Some(wad_paths) = matches.get_many::<PathBuf>("wad") {
+                for wad_path in wad_paths {
+                    let wad = WadFile::open(wad_path)?;
+                    for entry in wad.iter_entries() {
+                        let hash = entry?.path.hash;
+                        if !hmapper.is_known(hash) {
+                            unknown.insert(hash);
+                        }
+                    }
+                }
+            }
+            if let Some(unknown_path) = matches.get_one::<PathBuf>("unknown") {
+                for hash in load_unknown_file::<u64, _>(unknown_path)? {
+                    if !hmapper.is_known(hash) {
+                        unknown.insert(hash);
+                    }
+                }
+            }
+
+            let strings = CollectStringsVisitor::default()
+                .traverse_dir(path)?
+                .take_result();
+            let mut nfound = 0;
+            for s in strings {
+                for candidate in wad_path_variants(&s) {
+                    let hash = compute_wad_hash(&candidate);
+                    if unknown.remove(&hash) {
+                        println!("{:016x} {}", hash, candidate);
+                        hmapper.insert(hash, candidate);
+                        nfound += 1;
+                    }
+                }
+            }
+
+            if nfound > 0 {
+                hmapper.write_path(&hpath)?;
+            }
+            Ok(())
+        }
+        Some(("audit", matches)) => {
+            let dir = get_hashes_dir(matches).unwrap();
+            let mut problems = 0;
+            // Bin hashes of all kinds, to detect collisions across kinds
+            let mut bin_hashes = HashMap::<u32, (HashKind, String)>::new();
+            for kind in HashKind::VARIANTS {
+                let path = dir.join(kind.mapping_path());
+                if !path.exists() {
+                    continue;
+                }
+                problems += match kind {
+                    HashKind::WadGame | HashKind::WadLcu => {
+                        audit_mapping::<u64, 64>(&path, compute_wad_hash, str::eq, |_, _| true)?
+                    }
+                    HashKind::Rst => {
+                        audit_mapping::<u64, 39>(&path, |s| compute_rst_hash_n(s, 39), str::eq, |_, _| true)?
+                    }
+                    HashKind::BinEntryPath | HashKind::BinClassName | HashKind::BinFieldName | HashKind::BinHashValue => {
+                        // Bin hashes are computed on lowercased strings
+                        audit_mapping::<u32, 32>(&path, compute_binhash, str::eq_ignore_ascii_case, |hash, value| {
+                            match bin_hashes.get(&hash) {
+                                Some((other_kind, other)) if !other.eq_ignore_ascii_case(value) => {
+                                    println!("{}: collision on {:08x}: {:?} ({}) / {:?}",
+                                        path.display(), hash, other, other_kind.mapping_path(), value);
+                                    false
+                                }
+                                Some(_) => true,
+                                None => {
+                                    bin_hashes.insert(hash, (kind, value.to_owned()));
+                                    true
+                                }
+                            }
+                        })?
+                    }
+                };
+            }
+            if problems == 0 {
+                Ok(())
+            } else {
+                Err(format!("{} problem(s) found", problems).into())
+            }
+        }
         _ => unreachable!(),
     }
 }
@@ -212,15 +456,25 @@ fn unknown_path(kind: BinHashKind) -> &'static str {
     }
 }
 
-fn load_unknown_file<P: AsRef<Path>>(path: P) -> Result<HashSet<u32>, HashError> {
+/// Load unknown hashes from a text file, one hexadecimal hash per line
+///
+/// Blank lines and `#` comments are skipped.
+fn load_unknown_file<T, P>(path: P) -> Result<HashSet<T>, HashError>
+where T: Num + Eq + Hash, P: AsRef<Path> {
     let file = fs::File::open(&path)?;
     let reader = io::BufReader::new(file);
     reader.lines()
-        .map(|line| -> Result<u32, HashError> {
-            line.map_err(HashError::Io).and_then(|line| {
-                let line = line.trim_end();
-                u32::from_str_radix(line, 16).map_err(|_| HashError::InvalidHashLine(line.to_owned()))
-            })
+        .filter_map(|line| -> Option<Result<T, HashError>> {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            // Skip blank lines and comments
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            Some(T::from_str_radix(line, 16).map_err(|_| HashError::InvalidHashLine(line.to_owned())))
         })
         .collect()
 }
@@ -234,13 +488,41 @@ fn load_unknown(path: PathBuf) -> Result<BinHashSets, HashError> {
     Ok(unknown)
 }
 
+/// Load unknown hashes from text files in a directory, ignore missing files
+fn load_unknown_existing(path: &Path) -> Result<BinHashSets, HashError> {
+    let mut unknown = BinHashSets::default();
+    for &kind in &BinHashKind::VARIANTS {
+        let path = path.join(unknown_path(kind));
+        if path.exists() {
+            *unknown.get_mut(kind) = load_unknown_file(path)?;
+        }
+    }
+    Ok(unknown)
+}
+
+/// Add hashes to unknown hashes loaded from a directory, return the merged hashes
+fn append_unknown_existing(path: &Path, hashes: &BinHashSets) -> Result<BinHashSets, HashError> {
+    let mut existing = load_unknown_existing(path)?;
+    for &kind in &BinHashKind::VARIANTS {
+        let existing = existing.get_mut(kind);
+        let before = existing.len();
+        existing.extend(hashes.get(kind));
+        println!("{}: {} new hashes", unknown_path(kind), existing.len() - before);
+    }
+    Ok(existing)
+}
+
 /// Write (unknown) hashes to text files in a directory
+///
+/// Hashes are sorted, so that output is deterministic.
 fn write_unknown(path: PathBuf, hashes: &BinHashSets) -> Result<(), HashError> {
     std::fs::create_dir_all(&path)?;
     for &kind in &BinHashKind::VARIANTS {
+        let mut sorted: Vec<u32> = hashes.get(kind).iter().copied().collect();
+        sorted.sort_unstable();
         GuardedFile::for_scope(path.join(unknown_path(kind)), |file| {
             let mut writer = io::BufWriter::new(file);
-            for hash in hashes.get(kind).iter() {
+            for hash in sorted {
                 writeln!(writer, "{:08x}", hash)?;
             }
             Ok(())
@@ -249,6 +531,56 @@ fn write_unknown(path: PathBuf, hashes: &BinHashSets) -> Result<(), HashError> {
     Ok(())
 }
 
+/// Add RST keys from a file to a mapping file, report collisions
+fn rst_mapping_from_keys<const NBITS: usize>(keys: &Path, output: &Path) -> Result<(), HashError> {
+    let mut hmapper = RstHashMapper::<NBITS>::new();
+    if output.exists() {
+        hmapper.load_path(output)?;
+    }
+    let reader = io::BufReader::new(fs::File::open(keys)?);
+    let collisions = insert_keys_from_reader(&mut hmapper, reader)?;
+    for collision in collisions {
+        println!("collision: {:0w$x} {} / {}", collision.hash, collision.known, collision.key, w = NBITS.div_ceil(4));
+    }
+    hmapper.write_path(output)?;
+    Ok(())
+}
+
+/// Check a mapping file, print problems and return their count
+///
+/// Values of the same hash are collisions, unless `same_value` considers them equal.
+/// `on_entry` is called on each new valid entry, and returns `false` if a problem was detected.
+fn audit_mapping<T, const NBITS: usize>(path: &Path, hasher: fn(&str) -> T, same_value: fn(&str, &str) -> bool, mut on_entry: impl FnMut(T, &str) -> bool) -> Result<usize, HashError>
+where T: Num + Eq + Hash + Copy + fmt::LowerHex {
+    let nchars = NBITS.div_ceil(4);
+    let mut hmapper = HashMapper::<T, NBITS>::new();
+    let mut problems = 0;
+    let reader = io::BufReader::new(fs::File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        let (hash, value) = HashMapper::<T, NBITS>::parse_line(&line)?;
+        if hasher(value) != hash {
+            println!("{}: invalid hash {:0w$x} for {:?}", path.display(), hash, value, w = nchars);
+            problems += 1;
+            continue;
+        }
+        match hmapper.get(hash) {
+            None => {
+                hmapper.insert(hash, value.to_owned());
+                if !on_entry(hash, value) {
+                    problems += 1;
+                }
+            }
+            Some(existing) if same_value(existing, value) => {}
+            Some(existing) => {
+                println!("{}: collision on {:0w$x}: {:?} / {:?}", path.display(), hash, existing, value, w = nchars);
+                problems += 1;
+            }
+        }
+    }
+    Ok(problems)
+}
+
 /// Remove known hashes from `BinHashSets`
 fn remove_known_from_unknown(unknown: &mut BinHashSets, hmappers: &BinHashMappers) {
     for &kind in &BinHashKind::VARIANTS {
@@ -260,11 +592,14 @@ fn remove_known_from_unknown(unknown: &mut BinHashSets, hmappers: &BinHashMapper
 
 /// Trait to visit a directory using a BinVisitor
 trait BinDirectoryVisitor: BinVisitor<Error=()> {
+    /// Traverse all entries of a directory, until the visitor stops the traversal
     fn traverse_dir<P: AsRef<Path>>(&mut self, root: P) -> Result<&mut Self, PropError> {
         for path in bin_files_from_dir(root) {
             let scanner = PropFile::scan_entries_from_path(path)?;
             for entry in scanner.parse() {
-                self.traverse_entry(&entry?).unwrap();  // never fails
+                if self.traverse_entry(&entry?).unwrap().is_break() {  // never fails
+                    return Ok(self);
+                }
             }
         }
         Ok(self)
@@ -273,3 +608,85 @@ trait BinDirectoryVisitor: BinVisitor<Error=()> {
 
 impl<T> BinDirectoryVisitor for T where T: BinVisitor<Error=()> + ?Sized {}
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_sets(kind: BinHashKind, hashes: &[u32]) -> BinHashSets {
+        let mut sets = BinHashSets::default();
+        sets.get_mut(kind).extend(hashes);
+        sets
+    }
+
+    #[test]
+    fn unknown_hashes_are_appended_sorted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("unknown");
+        // Missing files are ignored
+        let hashes = append_unknown_existing(&dir, &hash_sets(BinHashKind::EntryPath, &[0x30, 0x10])).unwrap();
+        write_unknown(dir.clone(), &hashes).unwrap();
+
+        let hashes = append_unknown_existing(&dir, &hash_sets(BinHashKind::EntryPath, &[0x20, 0x10, 0xabcdef])).unwrap();
+        write_unknown(dir.clone(), &hashes).unwrap();
+
+        let content = fs::read_to_string(dir.join(unknown_path(BinHashKind::EntryPath))).unwrap();
+        assert_eq!(content, "00000010\n00000020\n00000030\n00abcdef\n");
+        assert_eq!(fs::read_to_string(dir.join(unknown_path(BinHashKind::FieldName))).unwrap(), "");
+        let loaded = load_unknown_existing(&dir).unwrap();
+        assert_eq!(loaded.get(BinHashKind::EntryPath), &HashSet::from([0x10, 0x20, 0x30, 0xabcdef]));
+        assert!(loaded.get(BinHashKind::HashValue).is_empty());
+    }
+
+    #[test]
+    fn unknown_file_skips_blank_lines_and_comments() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("unknown.txt");
+        fs::write(&path, "# WAD hashes\r\n0123456789abcdef\r\n\r\n  fedcba9876543210  \n").unwrap();
+        let hashes = load_unknown_file::<u64, _>(&path).unwrap();
+        assert_eq!(hashes, HashSet::from([0x0123456789abcdef, 0xfedcba9876543210]));
+
+        fs::write(&path, "0123\nnot-a-hash\n").unwrap();
+        assert!(matches!(load_unknown_file::<u64, _>(&path), Err(HashError::InvalidHashLine(line)) if line == "not-a-hash"));
+    }
+
+    #[test]
+    fn audit_mapping_reports_problems() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("hashes.binfields.txt");
+        let line = |s: &str| format!("{:08x} {}", compute_binhash(s), s);
+        let lines = [
+            line("mName"),
+            line("mName"),  // duplicate, not a problem
+            format!("{:08x} mSpellName", compute_binhash("mName")),  // mismatching hash
+            line("mSpellName"),
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+        let mut entries = Vec::new();
+        let problems = audit_mapping::<u32, 32>(&path, compute_binhash, str::eq_ignore_ascii_case, |hash, value| {
+            entries.push((hash, value.to_owned()));
+            value != "mSpellName"
+        }).unwrap();
+        // Mismatching hash, and rejected entry
+        assert_eq!(problems, 2);
+        assert_eq!(entries, [(compute_binhash("mName"), "mName".to_owned()), (compute_binhash("mSpellName"), "mSpellName".to_owned())]);
+
+        fs::write(&path, "004bca34value\n").unwrap();
+        assert!(matches!(audit_mapping::<u32, 32>(&path, compute_binhash, str::eq_ignore_ascii_case, |_, _| true), Err(HashError::InvalidHashLine(_))));
+    }
+
+    #[test]
+    fn audit_mapping_compares_values() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("hashes.binfields.txt");
+        let lines = [
+            format!("{:08x} Foo", compute_binhash("foo")),
+            format!("{:08x} foo", compute_binhash("foo")),
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+        // Case variants of a bin hash are not collisions
+        assert_eq!(audit_mapping::<u32, 32>(&path, compute_binhash, str::eq_ignore_ascii_case, |_, _| true).unwrap(), 0);
+        // They are with an exact comparison
+        assert_eq!(audit_mapping::<u32, 32>(&path, compute_binhash, str::eq, |_, _| true).unwrap(), 1);
+    }
+}
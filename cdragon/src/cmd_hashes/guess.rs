@@ -1,41 +1,62 @@
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::ops::ControlFlow;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
 use std::collections::{HashMap, HashSet};
 use cdragon_prop::{
     data::*,
     BinEntry,
-    BinHashKind,
     BinHashMappers,
     BinTraversal,
     BinVisitor,
     PropFile,
+    ScannedEntry,
+    VisitOutcome,
     binget,
+    visitor::VisitResult,
 };
 use cdragon_hashes::{
     binh,
-    bin::compute_binhash,
+    bin::{BinHashKind, compute_binhash},
     HashOrStr,
 };
 use super::BinHashSets;
 use crate::utils::bin_files_from_dir;
 
 
+/// Number of files, per worker thread, that can be read ahead of dispatch
+const PENDING_FILES_PER_JOB: usize = 4;
+
+/// Callback called when a new hash is found
+type OnFoundFunc<'a> = Box<dyn FnMut(u32, &str) + 'a>;
+
 /// Base object to check bin hashes
-pub struct BinHashFinder {
+pub struct BinHashFinder<'a> {
     /// Unknown hashes to find
     pub hashes: BinHashSets,
     /// Hash mappers where found hashes are added
     pub hmappers: BinHashMappers,
     /// Callback called when a new hash is found
-    on_found: fn(u32, &str),
+    on_found: OnFoundFunc<'a>,
 }
 
-impl BinHashFinder {
+impl<'a> BinHashFinder<'a> {
     pub fn new(hashes: BinHashSets, hmappers: BinHashMappers) -> Self {
-        Self { hashes, hmappers, on_found: |_, _| {} }
+        Self { hashes, hmappers, on_found: Box::new(|_, _| {}) }
     }
 
-    pub fn on_found(mut self, f: fn(u32, &str)) -> Self {
-        self.on_found = f;
+    /// Return the number of hashes still unknown
+    fn unknown_count(&self) -> usize {
+        BinHashKind::VARIANTS.iter().map(|kind| self.hashes.get(*kind).len()).sum()
+    }
+
+    /// Set the callback called when a new hash is found
+    ///
+    /// The callback may capture state, e.g. to collect found hashes.
+    pub fn on_found<F: FnMut(u32, &str) + 'a>(mut self, f: F) -> Self {
+        self.on_found = Box::new(f);
         self
     }
 
@@ -85,11 +106,9 @@ impl BinHashFinder {
         let hmapper = self.hmappers.get_mut(kind);
         for value in values {
             let hash = compute_binhash(value.as_ref());
-            if selected.contains(&hash) {
-                if hashes.remove(&hash) {
-                    (self.on_found)(hash, value.as_ref());
-                    hmapper.insert(hash, value.into());
-                }
+            if selected.contains(&hash) && hashes.remove(&hash) {
+                (self.on_found)(hash, value.as_ref());
+                hmapper.insert(hash, value.into());
             }
         }
     }
@@ -129,6 +148,29 @@ impl BinHashFinder {
         }
         false
     }
+
+    /// Check strings `{prefix}{n}` for each `n` in `start..=last` to match any unknown hash of a kind
+    ///
+    /// The same buffer is reused for all numbers, strings are allocated only for matches.
+    ///
+    /// Return the number of hashes found.
+    pub fn check_numeric_range(&mut self, kind: BinHashKind, prefix: &str, start: u64, last: u64) -> usize {
+        let hashes = self.hashes.get_mut(kind);
+        let hmapper = self.hmappers.get_mut(kind);
+        let mut value = String::from(prefix);
+        let mut found = 0;
+        for n in start..=last {
+            value.truncate(prefix.len());
+            write!(value, "{}", n).unwrap();
+            let hash = compute_binhash(&value);
+            if hashes.remove(&hash) {
+                (self.on_found)(hash, &value);
+                hmapper.insert(hash, value.clone());
+                found += 1;
+            }
+        }
+        found
+    }
 }
 
 
@@ -144,28 +186,73 @@ pub trait GuessingHook {
 }
 
 
+/// Progress of a guessing run
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GuessProgress {
+    /// Number of bin files processed
+    pub files: usize,
+    /// Total number of bin files to process
+    pub total: usize,
+    /// Number of hashes found so far
+    pub found: usize,
+}
+
+/// Callback called to report progress
+type OnProgressFunc<'a> = Box<dyn FnMut(GuessProgress) + 'a>;
+
 /// Guess bin hashes from bin files and hashes
-pub struct BinHashGuesser {
+pub struct BinHashGuesser<'a> {
     /// Hooks added to the guesser
     hooks: Vec<Box<dyn GuessingHook>>,
     /// Indexes of hooks registered for each entry type
     registry: HashMap<BinClassName, Vec<usize>>,
     /// Finder used to guess hashes
-    finder: BinHashFinder,
+    finder: BinHashFinder<'a>,
     /// Collected entries paths, grouped by type
     entries_by_type: HashMap<BinClassName, Vec<BinEntryPath>>,
+    /// Callback called after each processed file
+    on_progress: Option<OnProgressFunc<'a>>,
+    /// Flag to cancel guessing, checked before each file
+    cancel: Option<&'a AtomicBool>,
+    /// Number of threads used to read bin files
+    jobs: usize,
 }
 
-impl BinHashGuesser {
-    pub fn new(finder: BinHashFinder) -> Self {
+impl<'a> BinHashGuesser<'a> {
+    pub fn new(finder: BinHashFinder<'a>) -> Self {
         Self {
             hooks: Vec::default(),
             registry: HashMap::default(),
             finder,
             entries_by_type: HashMap::default(),
+            on_progress: None,
+            cancel: None,
+            jobs: std::thread::available_parallelism().map_or(1, |n| n.get()),
         }
     }
 
+    /// Set the number of threads used to read bin files
+    ///
+    /// Default is the number of available CPUs.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Set a callback to report progress, called after each processed file
+    pub fn on_progress<F: FnMut(GuessProgress) + 'a>(mut self, f: F) -> Self {
+        self.on_progress = Some(Box::new(f));
+        self
+    }
+
+    /// Set a flag to cancel guessing
+    ///
+    /// When the flag is set, remaining files are skipped. Hashes found so far are kept.
+    pub fn with_cancel_flag(mut self, cancel: &'a AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
     pub fn with_hook(mut self, hook: Box<dyn GuessingHook>) -> Self {
         let i = self.hooks.len();
         for t in hook.entry_types().iter() {
@@ -368,12 +455,13 @@ impl BinHashGuesser {
             .with_single_hook(binh!("ViewControllerList"), |entry, finder| {
                 // Assume all strings are entry paths (true in practice)
                 // No maps, visit only lists and structs
-                struct CheckStrings<'a> {
-                    finder: &'a mut BinHashFinder,
+                struct CheckStrings<'a, 'b> {
+                    finder: &'a mut BinHashFinder<'b>,
                 }
 
-                impl<'a> BinVisitor for CheckStrings<'a> {
-                    type Error = ();
+                impl BinVisitor for CheckStrings<'_, '_> {
+                    type Error = Infallible;
+                    type Break = Infallible;
 
                     fn visit_type(&mut self, btype: BinType) -> bool {
                         matches!(btype,
@@ -384,14 +472,14 @@ impl BinHashGuesser {
                             BinType::Embed)
                     }
 
-                    fn visit_string(&mut self, value: &BinString) -> Result<(), ()> {
+                    fn visit_string(&mut self, value: &BinString) -> VisitResult<Self> {
                         self.finder.check_any(BinHashKind::EntryPath, &value.0);
-                        Ok(())
+                        Ok(VisitOutcome::Continue)
                     }
                 }
 
                 let mut visitor = CheckStrings { finder };
-                entry.traverse_bin(&mut visitor).unwrap()
+                let Ok(ControlFlow::Continue(())) = entry.traverse_bin(&mut visitor);
             })
 
             // Guess from ViewControllerSet
@@ -410,6 +498,18 @@ impl BinHashGuesser {
                 }
             })
 
+            // Guess TFT trait and augment paths from their name
+            .with_single_hook(binh!("TftTraitData"), |entry, finder| {
+                if let Some(name) = binget!(entry => mName(BinString)) {
+                    check_tft_set_path(entry, finder, "Traits", &name.0);
+                }
+            })
+            .with_single_hook(binh!("TftAugmentData"), |entry, finder| {
+                if let Some(name) = binget!(entry => name(BinString)) {
+                    check_tft_set_path(entry, finder, "Augments", &name.0);
+                }
+            })
+
             // Guess SpellObject path from mScriptName
             // This does more than `EntryPathPatternHook!(SpellObject.mScriptName => "Items/Spells/{}"))`
             .with_single_hook(binh!("SpellObject"), |entry, finder| {
@@ -529,10 +629,18 @@ impl BinHashGuesser {
     pub fn with_collecting_hooks(self) -> Self {
         self
             .with_hook(Box::<ItemHashListsHook>::default())
+            .with_hook(Box::<TftSetCharactersHook>::default())
+    }
+
+    /// Add a hook to guess entry paths ending with a number, up to `max`
+    ///
+    /// It is not part of [Self::with_all_hooks()]: large values are slow to check.
+    pub fn with_numeric_entry_paths(self, max: u64) -> Self {
+        self.with_hook(Box::new(NumericEntryPathsHook::new(max)))
     }
 
     /// End guessing, return the updated finder
-    pub fn result(mut self) -> BinHashFinder {
+    pub fn result(mut self) -> BinHashFinder<'a> {
         for mut hook in self.hooks {
             hook.on_end(&mut self.finder, &self.entries_by_type)
         }
@@ -540,22 +648,91 @@ impl BinHashGuesser {
     }
 
     /// Run the guesser
-    pub fn guess_dir<P: AsRef<Path>>(&mut self, root: P) {
-        for path in bin_files_from_dir(root) {
-            if let Ok(scanner) = PropFile::scan_entries_from_path(path) {
-                let mut scanner = scanner.scan();
-                while let Some(Ok(item)) = scanner.next() {
-                    self.entries_by_type.entry(item.ctype).or_default().push(item.path);
-                    if let Some(indexes) = self.registry.get(&item.ctype) {
-                        if let Ok(entry) = item.read() {
-                            for i in indexes {
-                                self.hooks[*i].on_entry(&entry, &mut self.finder);
-                            }
+    ///
+    /// Files are read and parsed by worker threads, see [Self::with_jobs()]. Parsed entries are
+    /// sent back to the calling thread, which dispatches them to hooks in file order. Hooks and
+    /// the finder are only used from the calling thread, so the result does not depend on the
+    /// number of threads. Workers only read a few files ahead of the next file to dispatch.
+    ///
+    /// Return false if guessing has been cancelled.
+    pub fn guess_dir<P: AsRef<Path>>(&mut self, root: P) -> bool {
+        let initial_unknown = self.finder.unknown_count();
+        let paths: Vec<_> = bin_files_from_dir(root).collect();
+        let mut progress = GuessProgress { total: paths.len(), ..Default::default() };
+        let jobs = self.jobs.min(paths.len()).max(1);
+
+        let Self { hooks, registry, finder, entries_by_type, on_progress, cancel, .. } = self;
+        let cancel = *cancel;
+        let is_cancelled = || cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed));
+        let next_path = AtomicUsize::new(0);
+        // Number of dispatched files; workers don't read files too far ahead of it, so that the
+        // number of files waiting for dispatch is bounded, even if an early file is slow to read.
+        // Set to `usize::MAX` to release waiting workers when dispatch stops.
+        let dispatched = (Mutex::new(0usize), Condvar::new());
+        let window = jobs * PENDING_FILES_PER_JOB;
+
+        std::thread::scope(|scope| {
+            let (tx, rx) = mpsc::sync_channel::<(usize, ScannedBinFile)>(jobs);
+            for _ in 0..jobs {
+                let tx = tx.clone();
+                let (paths, next_path, registry, dispatched) = (&paths, &next_path, &*registry, &dispatched);
+                scope.spawn(move || {
+                    loop {
+                        let index = next_path.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(index) else { break };
+                        let (lock, cvar) = dispatched;
+                        let mut n = cvar.wait_while(lock.lock().unwrap(), |n| index >= n.saturating_add(window)).unwrap();
+                        if is_cancelled() {
+                            // Other workers may wait for a file that will not be read
+                            *n = usize::MAX;
+                            cvar.notify_all();
+                            break;
+                        }
+                        drop(n);
+                        let file = ScannedBinFile::read(path, |ctype| registry.contains_key(&ctype));
+                        // Send fails if the calling thread stopped dispatching
+                        if tx.send((index, file)).is_err() {
+                            break;
                         }
                     }
-                }
+                });
             }
-        }
+            drop(tx);
+
+            let set_dispatched = |n: usize| {
+                let (lock, cvar) = &dispatched;
+                let mut guard = lock.lock().unwrap();
+                *guard = (*guard).max(n);
+                cvar.notify_all();
+            };
+
+            // Files are received in any order, keep them until previous ones are dispatched
+            let mut pending = HashMap::new();
+            let completed = (|| {
+                for (index, file) in rx {
+                    pending.insert(index, file);
+                    while let Some(file) = pending.remove(&progress.files) {
+                        if is_cancelled() {
+                            return false;
+                        }
+                        for (ctype, path) in &file.headers {
+                            entries_by_type.entry(*ctype).or_default().push(*path);
+                        }
+                        file.dispatch(hooks, registry, finder);
+                        progress.files += 1;
+                        set_dispatched(progress.files);
+                        progress.found = initial_unknown - finder.unknown_count();
+                        if let Some(on_progress) = on_progress.as_mut() {
+                            on_progress(progress);
+                        }
+                    }
+                }
+                // Workers stop early only when cancelled
+                progress.files == progress.total
+            })();
+            set_dispatched(usize::MAX);
+            completed
+        })
     }
 
     /*TODO
@@ -623,6 +800,48 @@ impl GuessingHook for MultiHook {
 }
 
 
+/// Entries read from a bin file, to be dispatched to hooks
+#[derive(Default)]
+struct ScannedBinFile {
+    /// Type and path of all entries
+    headers: Vec<(BinClassName, BinEntryPath)>,
+    /// Parsed entries, only those with hooks
+    entries: Vec<BinEntry>,
+}
+
+impl ScannedBinFile {
+    /// Register all entries, then parse only those with hooks
+    ///
+    /// Errors are ignored: entries are read until the first invalid one.
+    fn read(path: &Path, is_hooked: impl Fn(BinClassName) -> bool) -> Self {
+        let mut file = Self::default();
+        if let Ok(scanner) = PropFile::scan_entries_from_path(path) {
+            let mut scan = scanner.scan_owned();
+            let hooked: Vec<ScannedEntry> = scan
+                .by_ref()
+                .map_while(Result::ok)
+                .inspect(|item| file.headers.push((item.ctype, item.path)))
+                .filter(|item| is_hooked(item.ctype))
+                .collect();
+            let mut reader = scan.into_reader();
+            file.entries = hooked.iter()
+                .filter_map(|item| item.read_from(&mut reader).ok())
+                .collect();
+        }
+        file
+    }
+
+    /// Call registered hooks on parsed entries
+    fn dispatch(&self, hooks: &mut [Box<dyn GuessingHook>], registry: &HashMap<BinClassName, Vec<usize>>, finder: &mut BinHashFinder) {
+        for entry in &self.entries {
+            for i in &registry[&entry.ctype] {
+                hooks[*i].on_entry(entry, finder);
+            }
+        }
+    }
+}
+
+
 /// Guess hashes from character data: derived pattern, spells
 fn on_character_record_entry(entry: &BinEntry, finder: &mut BinHashFinder) {
     let cname = match &binget!(entry => mCharacterName(BinString)) {
@@ -703,6 +922,26 @@ fn on_character_record_entry(entry: &BinEntry, finder: &mut BinHashFinder) {
     }
 }
 
+/// Guess the path of a TFT entry stored in a Map22 subdirectory
+///
+/// Entries are either directly under `Maps/Shipping/Map22/{dir}`, or under the directory of their
+/// set. The set number is taken from the `TFT{N}_` name prefix.
+fn check_tft_set_path(entry: &BinEntry, finder: &mut BinHashFinder, dir: &str, name: &str) {
+    if !finder.is_unknown(BinHashKind::EntryPath, entry.path.hash) {
+        return;
+    }
+    if finder.check_one(BinHashKind::EntryPath, entry.path.hash, format!("Maps/Shipping/Map22/{}/{}", dir, name)) {
+        return;
+    }
+    let set = name.strip_prefix("TFT")
+        .and_then(|s| s.split_once('_'))
+        .map(|(n, _)| n)
+        .filter(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    if let Some(set) = set {
+        finder.check_one(BinHashKind::EntryPath, entry.path.hash, format!("Maps/Shipping/Map22/Sets/TFTSet{}/{}/{}", set, dir, name));
+    }
+}
+
 /// Guess hashes from skin data
 fn on_skin_character_data_entry(entry: &BinEntry, finder: &mut BinHashFinder) {
     let path = finder.get_str(BinHashKind::EntryPath, entry.path.hash)
@@ -790,6 +1029,90 @@ impl GuessingHook for ItemHashListsHook {
     }
 }
 
+/// Guess TFT set champion lists, referencing TFT characters
+///
+/// Hashes are collected from all hash lists of `TftSetData`. They are checked against the path
+/// of `TFTCharacterRecord` entries and their character name.
+#[derive(Default)]
+pub struct TftSetCharactersHook {
+    hashes: HashSet<u32>,
+}
+
+impl GuessingHook for TftSetCharactersHook {
+    fn entry_types(&self) -> &[BinClassName] {
+        const TYPES: [BinClassName; 1] = [
+            binh!(BinClassName, "TftSetData"),
+        ];
+        &TYPES
+    }
+
+    fn on_entry(&mut self, entry: &BinEntry, _finder: &mut BinHashFinder) {
+        for field in &entry.fields {
+            if let Some(list) = field.downcast::<BinList>().and_then(|v| binget!(v => (BinHash))) {
+                self.hashes.extend(list.iter().map(|v| v.0.hash));
+            }
+        }
+    }
+
+    fn on_end(&mut self, finder: &mut BinHashFinder, entries_by_type: &HashMap<BinClassName, Vec<BinEntryPath>>) {
+        // Filter out known hashes
+        self.hashes.retain(|h| finder.is_unknown(BinHashKind::HashValue, *h));
+        if !self.hashes.is_empty() {
+            if let Some(candidates) = entries_by_type.get(&binh!("TFTCharacterRecord")) {
+                let paths: Vec<String> = candidates.iter()
+                    .filter_map(|h| finder.get_str(BinHashKind::EntryPath, h.hash))
+                    .map(|s| s.to_owned())
+                    .collect();
+                // Records are usually named `Characters/{name}/CharacterRecords/Root`
+                let names = paths.iter().filter_map(|path| {
+                    let (name, _) = path.strip_prefix("Characters/")?.split_once('/')?;
+                    Some(name)
+                });
+                let candidates: Vec<&str> = paths.iter().map(|s| s.as_str()).chain(names).collect();
+                finder.check_selected_from_iter(BinHashKind::HashValue, &self.hashes, candidates.into_iter());
+            }
+        }
+    }
+}
+
+
+/// Guess entry paths ending with a number, for types whose entries are often unknown
+///
+/// Numbers from 1 to `max` are tried, only if there are unknown entries of the type.
+pub struct NumericEntryPathsHook {
+    max: u64,
+}
+
+impl NumericEntryPathsHook {
+    /// Entry type, path prefix
+    const PATTERNS: [(BinClassName, &'static str); 2] = [
+        (binh!(BinClassName, "ItemData"), "Items/"),
+        (binh!(BinClassName, "SummonerEmote"), "Loadouts/SummonerEmotes/"),
+    ];
+
+    pub fn new(max: u64) -> Self {
+        Self { max }
+    }
+}
+
+impl GuessingHook for NumericEntryPathsHook {
+    fn entry_types(&self) -> &[BinClassName] {
+        &[]
+    }
+
+    fn on_entry(&mut self, _entry: &BinEntry, _finder: &mut BinHashFinder) {}
+
+    fn on_end(&mut self, finder: &mut BinHashFinder, entries_by_type: &HashMap<BinClassName, Vec<BinEntryPath>>) {
+        for (ctype, prefix) in Self::PATTERNS {
+            let has_unknown = entries_by_type.get(&ctype)
+                .is_some_and(|paths| paths.iter().any(|h| finder.is_unknown(BinHashKind::EntryPath, h.hash)));
+            if has_unknown {
+                finder.check_numeric_range(BinHashKind::EntryPath, prefix, 1, self.max);
+            }
+        }
+    }
+}
+
 
 /// Hook to dump some information about entry types
 #[derive(Default)]
@@ -813,3 +1136,234 @@ impl GuessingHook for EntryTypesStatsHook {
     }
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cdragon_prop::testing::{write_bin_file, string_value, hash_list_field, TestEntry, TestField};
+
+    fn finder_with_unknown(kind: BinHashKind, values: &[&str]) -> BinHashFinder<'static> {
+        let mut hashes = BinHashSets::default();
+        hashes.get_mut(kind).extend(values.iter().map(|s| compute_binhash(s)));
+        BinHashFinder::new(hashes, BinHashMappers::default())
+    }
+
+    /// Run a guesser on bin files, return the finder
+    fn guess_bin_files<'a>(guesser: BinHashGuesser<'a>, files: &[&[TestEntry]]) -> BinHashFinder<'a> {
+        let tmp = tempfile::tempdir().unwrap();
+        for (i, entries) in files.iter().enumerate() {
+            write_bin_file(&tmp.path().join(format!("{}.bin", i)), entries);
+        }
+        let mut guesser = guesser;
+        assert!(guesser.guess_dir(tmp.path()));
+        guesser.result()
+    }
+
+    #[test]
+    fn guess_dir_reads_files_from_workers() {
+        let tmp = tempfile::tempdir().unwrap();
+        // Each entry references the index of its path, the same entry is in several files
+        let names: Vec<String> = (0..8).map(|i| format!("Tests/{}", i)).collect();
+        let values: Vec<[u8; 4]> = (0..8u32).map(|i| i.to_le_bytes()).collect();
+        for (i, value) in values.iter().enumerate() {
+            let field: TestField = ("index", BinType::U32 as u8, value);
+            let shared: TestField = ("index", BinType::U32 as u8, &values[0]);
+            write_bin_file(&tmp.path().join(format!("{}.bin", i)), &[
+                ("TestData", &names[i], &[field]),
+                ("TestData", &names[0], &[shared]),
+            ]);
+        }
+
+        let hashes = {
+            let mut hashes = BinHashSets::default();
+            hashes.get_mut(BinHashKind::EntryPath).extend(names.iter().map(|s| compute_binhash(s)));
+            hashes
+        };
+        let mut found = Vec::new();
+        let mut last_progress = GuessProgress::default();
+        let finder = BinHashFinder::new(hashes, BinHashMappers::default())
+            .on_found(|h, _| found.push(h));
+        let mut guesser = BinHashGuesser::new(finder)
+            .with_jobs(3)
+            .on_progress(|progress| last_progress = progress)
+            .with_single_hook(binh!("TestData"), |entry, finder| {
+                if let Some(index) = binget!(entry => index(BinU32)) {
+                    finder.check_any(BinHashKind::EntryPath, format!("Tests/{}", index.0));
+                }
+            });
+        assert!(guesser.guess_dir(tmp.path()));
+
+        // Headers are collected in file order
+        let paths: Vec<BinEntryPath> = bin_files_from_dir(tmp.path())
+            .flat_map(|path| PropFile::from_path(path).unwrap().entries)
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(guesser.entries_by_type[&binh!("TestData")], paths);
+        let finder = guesser.result();
+        assert_eq!(finder.unknown_count(), 0);
+        drop(finder);
+        // Hashes are found in file order
+        let mut expected: Vec<u32> = paths.iter().map(|p| p.hash).collect();
+        let mut seen = HashSet::new();
+        expected.retain(|h| seen.insert(*h));
+        assert_eq!(found, expected);
+        assert_eq!((last_progress.files, last_progress.total, last_progress.found), (8, 8, 8));
+    }
+
+    #[test]
+    fn guess_dir_reads_ahead_of_dispatch_and_can_be_cancelled() {
+        let tmp = tempfile::tempdir().unwrap();
+        // More files than workers can read ahead
+        let nfiles = 2 * PENDING_FILES_PER_JOB * 3;
+        for i in 0..nfiles {
+            write_bin_file(&tmp.path().join(format!("{:02}.bin", i)), &[("TestData", &format!("Tests/{}", i), &[])]);
+        }
+
+        let run = |cancel_after| {
+            let cancel = AtomicBool::new(false);
+            let mut last_progress = GuessProgress::default();
+            let finder = BinHashFinder::new(BinHashSets::default(), BinHashMappers::default());
+            let mut guesser = BinHashGuesser::new(finder)
+                .with_jobs(2)
+                .with_cancel_flag(&cancel)
+                .on_progress(|progress| {
+                    last_progress = progress;
+                    if progress.files == cancel_after {
+                        cancel.store(true, Ordering::Relaxed);
+                    }
+                });
+            let completed = guesser.guess_dir(tmp.path());
+            drop(guesser);
+            (completed, last_progress.files)
+        };
+        assert_eq!(run(usize::MAX), (true, nfiles));
+        // Workers waiting for dispatch are released
+        assert_eq!(run(3), (false, 3));
+    }
+
+    #[test]
+    fn guess_dir_dispatches_entries_in_file_order() {
+        // The consumer entry needs the path found from the provider entry
+        let name = string_value("Characters/Annie");
+        let hash = compute_binhash("Characters/Annie").to_le_bytes();
+        let consumer: &[TestEntry] = &[("Consumer", "Characters/Annie/Child", &[("target", BinType::Hash as u8, &hash)])];
+        let provider: &[TestEntry] = &[("Provider", "Characters/Annie", &[("name", BinType::String as u8, &name)])];
+        let guesser = |jobs| {
+            let finder = finder_with_unknown(BinHashKind::EntryPath, &["Characters/Annie", "Characters/Annie/Child"]);
+            BinHashGuesser::new(finder)
+                .with_jobs(jobs)
+                .with_single_hook(binh!("Provider"), |entry, finder| {
+                    if let Some(s) = binget!(entry => name(BinString)) {
+                        finder.check_any(BinHashKind::EntryPath, &s.0);
+                    }
+                })
+                .with_single_hook(binh!("Consumer"), |entry, finder| {
+                    if let Some(h) = binget!(entry => target(BinHash)) {
+                        if let Some(name) = finder.get_str(BinHashKind::EntryPath, h.0.hash).map(str::to_owned) {
+                            finder.check_any(BinHashKind::EntryPath, format!("{}/Child", name));
+                        }
+                    }
+                })
+        };
+
+        // Result does not depend on the number of jobs
+        for jobs in [1, 2] {
+            // Consumer is dispatched before the provider
+            let finder = guess_bin_files(guesser(jobs), &[consumer, provider]);
+            assert_eq!(finder.unknown_count(), 1);
+            // Provider is dispatched first
+            let finder = guess_bin_files(guesser(jobs), &[provider, consumer]);
+            assert_eq!(finder.unknown_count(), 0);
+            assert_eq!(finder.get_str(BinHashKind::EntryPath, compute_binhash("Characters/Annie/Child")), Some("Characters/Annie/Child"));
+        }
+    }
+
+    #[test]
+    fn check_numeric_range_finds_hashes_in_range() {
+        let mut finder = finder_with_unknown(BinHashKind::EntryPath, &["Items/3031", "Items/7000"]);
+        assert_eq!(finder.check_numeric_range(BinHashKind::EntryPath, "Items/", 3000, 3099), 1);
+        assert_eq!(finder.get_str(BinHashKind::EntryPath, compute_binhash("Items/3031")), Some("Items/3031"));
+        assert!(!finder.is_unknown(BinHashKind::EntryPath, compute_binhash("Items/3031")));
+        // Out of range, or other kind
+        assert!(finder.is_unknown(BinHashKind::EntryPath, compute_binhash("Items/7000")));
+        assert_eq!(finder.check_numeric_range(BinHashKind::HashValue, "Items/", 7000, 7000), 0);
+        // Last is included
+        assert_eq!(finder.check_numeric_range(BinHashKind::EntryPath, "Items/", 6000, 6999), 0);
+        assert_eq!(finder.check_numeric_range(BinHashKind::EntryPath, "Items/", 6000, 7000), 1);
+        // Largest number does not overflow
+        let mut finder = finder_with_unknown(BinHashKind::EntryPath, &["Items/18446744073709551615"]);
+        assert_eq!(finder.check_numeric_range(BinHashKind::EntryPath, "Items/", u64::MAX - 1, u64::MAX), 1);
+    }
+
+    #[test]
+    fn tft_trait_and_augment_paths_are_guessed() {
+        let paths = [
+            "Maps/Shipping/Map22/Traits/TFT_Bruiser",
+            "Maps/Shipping/Map22/Sets/TFTSet9/Traits/TFT9_Bruiser",
+            "Maps/Shipping/Map22/Sets/TFTSet12/Augments/TFT12_Augment_Gold",
+            // Not a set number, not guessed
+            "Maps/Shipping/Map22/Sets/TFTSetX/Augments/TFTX_Augment_Gold",
+        ];
+        let finder = finder_with_unknown(BinHashKind::EntryPath, &paths);
+        let guesser = BinHashGuesser::new(finder).with_simple_hooks();
+
+        let names: Vec<Vec<u8>> = paths.iter().map(|p| string_value(p.rsplit_once('/').unwrap().1)).collect();
+        let finder = guess_bin_files(guesser, &[&[
+            ("TftTraitData", paths[0], &[("mName", BinType::String as u8, &names[0])]),
+            ("TftTraitData", paths[1], &[("mName", BinType::String as u8, &names[1])]),
+            ("TftAugmentData", paths[2], &[("name", BinType::String as u8, &names[2])]),
+            ("TftAugmentData", paths[3], &[("name", BinType::String as u8, &names[3])]),
+        ]]);
+        for path in &paths[..3] {
+            assert_eq!(finder.get_str(BinHashKind::EntryPath, compute_binhash(path)), Some(*path));
+        }
+        assert!(finder.is_unknown(BinHashKind::EntryPath, compute_binhash(paths[3])));
+    }
+
+    #[test]
+    fn tft_set_characters_are_guessed_from_records() {
+        let records = [
+            "Characters/TFT9_Ahri/CharacterRecords/Root",
+            "Characters/TFT9_Zed/CharacterRecords/Root",
+        ];
+        let values = [records[0], "TFT9_Zed", "TFT9_Unknown"];
+        let mut finder = finder_with_unknown(BinHashKind::HashValue, &values);
+        for record in records {
+            finder.hmappers.get_mut(BinHashKind::EntryPath).insert(compute_binhash(record), record.into());
+        }
+        let guesser = BinHashGuesser::new(finder).with_hook(Box::<TftSetCharactersHook>::default());
+
+        let hashes: Vec<u32> = values.iter().map(|s| compute_binhash(s)).collect();
+        let (list_type, list) = hash_list_field(&hashes);
+        let finder = guess_bin_files(guesser, &[
+            &[("TftSetData", "Maps/Shipping/Map22/Sets/TFTSet9", &[("characters", list_type, &list)])],
+            &[("TFTCharacterRecord", records[0], &[]), ("TFTCharacterRecord", records[1], &[])],
+        ]);
+        assert_eq!(finder.get_str(BinHashKind::HashValue, hashes[0]), Some(records[0]));
+        assert_eq!(finder.get_str(BinHashKind::HashValue, hashes[1]), Some("TFT9_Zed"));
+        assert!(finder.is_unknown(BinHashKind::HashValue, hashes[2]));
+    }
+
+    #[test]
+    fn numeric_entry_paths_are_guessed_up_to_max() {
+        let paths = ["Items/42", "Items/500", "Loadouts/SummonerEmotes/7"];
+        let guesser = |max| {
+            let finder = finder_with_unknown(BinHashKind::EntryPath, &paths);
+            BinHashGuesser::new(finder).with_numeric_entry_paths(max)
+        };
+        let files: &[&[TestEntry]] = &[&[
+            ("ItemData", paths[0], &[]),
+            ("ItemData", paths[1], &[]),
+        ]];
+
+        let finder = guess_bin_files(guesser(100), files);
+        assert_eq!(finder.get_str(BinHashKind::EntryPath, compute_binhash(paths[0])), Some(paths[0]));
+        assert!(finder.is_unknown(BinHashKind::EntryPath, compute_binhash(paths[1])));
+        // No unknown entry of the type
+        assert!(finder.is_unknown(BinHashKind::EntryPath, compute_binhash(paths[2])));
+        // Max is included
+        let finder = guess_bin_files(guesser(500), files);
+        assert_eq!(finder.unknown_count(), 1);
+    }
+}
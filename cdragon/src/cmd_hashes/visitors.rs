@@ -1,10 +1,13 @@
 use std::collections::HashSet;
+use std::ops::ControlFlow;
 use cdragon_prop::{
     BinEntry,
     BinHashMappers,
     BinTraversal,
     BinVisitor,
+    VisitOutcome,
     data::*,
+    visitor::{TraverseResult, VisitResult},
 };
 use super::BinHashSets;
 
@@ -23,6 +26,7 @@ impl CollectHashesVisitor {
 
 impl BinVisitor for CollectHashesVisitor {
     type Error = ();
+    type Break = ();
 
     // Note: Don't collect WAD paths (BinPath)
 
@@ -30,35 +34,35 @@ impl BinVisitor for CollectHashesVisitor {
         btype == BinType::Hash || btype == BinType::Link || btype.is_nested()
     }
 
-    fn visit_entry(&mut self, value: &BinEntry) -> Result<bool, ()> {
+    fn visit_entry(&mut self, value: &BinEntry) -> VisitResult<Self> {
         self.hashes.entry_path.insert(value.path.hash);
         self.hashes.class_name.insert(value.ctype.hash);
-        Ok(true)
+        Ok(VisitOutcome::Continue)
     }
 
-    fn visit_field(&mut self, value: &BinField) -> Result<bool, ()> {
+    fn visit_field(&mut self, value: &BinField) -> VisitResult<Self> {
         self.hashes.field_name.insert(value.name.hash);
-        Ok(self.visit_type(value.vtype))
+        Ok(self.visit_type(value.vtype).into())
     }
 
-    fn visit_hash(&mut self, value: &BinHash) -> Result<(), ()> {
+    fn visit_hash(&mut self, value: &BinHash) -> VisitResult<Self> {
         self.hashes.hash_value.insert(value.0.hash);
-        Ok(())
+        Ok(VisitOutcome::Continue)
     }
 
-    fn visit_struct(&mut self, value: &BinStruct) -> Result<bool, ()> {
+    fn visit_struct(&mut self, value: &BinStruct) -> VisitResult<Self> {
         self.hashes.class_name.insert(value.ctype.hash);
-        Ok(true)
+        Ok(VisitOutcome::Continue)
     }
 
-    fn visit_embed(&mut self, value: &BinEmbed) -> Result<bool, ()> {
+    fn visit_embed(&mut self, value: &BinEmbed) -> VisitResult<Self> {
         self.hashes.class_name.insert(value.ctype.hash);
-        Ok(true)
+        Ok(VisitOutcome::Continue)
     }
 
-    fn visit_link(&mut self, value: &BinLink) -> Result<(), ()> {
+    fn visit_link(&mut self, value: &BinLink) -> VisitResult<Self> {
         self.hashes.entry_path.insert(value.0.hash);
-        Ok(())
+        Ok(VisitOutcome::Continue)
     }
 }
 
@@ -76,58 +80,113 @@ impl CollectStringsVisitor {
 
 impl BinVisitor for CollectStringsVisitor {
     type Error = ();
+    type Break = ();
 
     fn visit_type(&mut self, btype: BinType) -> bool {
         btype == BinType::String || btype.is_nested()
     }
 
-    fn visit_string(&mut self, value: &BinString) -> Result<(), ()> {
+    fn visit_string(&mut self, value: &BinString) -> VisitResult<Self> {
         if !self.strings.contains(&value.0) {
             self.strings.insert(value.0.clone());
         }
-        Ok(())
+        Ok(VisitOutcome::Continue)
     }
 }
 
 
-/// Visitor to search entries containing a given bin value (hash, string, ...)
+/// Visitor to search entries containing a given bin value (hash, string, number, ...)
 #[derive(Default)]
 pub struct SearchBinValueVisitor<T, F: FnMut(&BinEntry)> {
     pattern: T,
     on_match: F,
-    matched: bool,
+    nmatches: usize,
+    limit: Option<usize>,
+    field: Option<BinFieldName>,
 }
 
 impl<T, F: FnMut(&BinEntry)> SearchBinValueVisitor<T, F> {
     pub fn new(pattern: T, on_match: F) -> Self {
-        Self { pattern, on_match, matched: false }
+        Self { pattern, on_match, nmatches: 0, limit: None, field: None }
+    }
+
+    /// Stop the traversal after `limit` matching entries
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Only match values of fields with the given name (including nested values)
+    pub fn with_field(mut self, field: Option<BinFieldName>) -> Self {
+        self.field = field;
+        self
     }
 }
 
+impl<T, F: FnMut(&BinEntry)> SearchBinValueVisitor<T, F> where Self: BinVisitor<Error=(), Break=()> {
+    fn visit_filtered_field(&mut self, value: &BinField) -> VisitResult<Self> {
+        match self.field {
+            Some(name) if name == value.name => {
+                // Search the whole field value, without filtering on nested field names
+                self.field = None;
+                let result = value.traverse_bin(self);
+                self.field = Some(name);
+                match result? {
+                    ControlFlow::Continue(()) => Ok(VisitOutcome::SkipChildren),
+                    ControlFlow::Break(()) => Ok(VisitOutcome::Stop(())),
+                }
+            }
+            // Look for the field in nested values
+            Some(_) => Ok((value.vtype.is_nested() && self.visit_type(value.vtype)).into()),
+            None => Ok(self.visit_type(value.vtype).into()),
+        }
+    }
 
-macro_rules! impl_search_bin_value_visitor {
-    ($typ:ty, $visit_func:ident) => {
-        impl<F: FnMut(&BinEntry)> BinVisitor for SearchBinValueVisitor<$typ, F> {
-            type Error = ();
+    /// Stop on a matching value, unless it is outside the searched field
+    fn visit_searched_value(&self, matched: bool) -> VisitResult<Self> {
+        if matched && self.field.is_none() {
+            Ok(VisitOutcome::Stop(()))
+        } else {
+            Ok(VisitOutcome::Continue)
+        }
+    }
+}
 
-            fn traverse_entry(&mut self, entry: &BinEntry) -> Result<(), ()> {
-                self.matched = false;
-                entry.traverse_bin(self)?;
-                if self.matched {
-                    (self.on_match)(entry);
+macro_rules! impl_search_visitor_common {
+    () => {
+        type Error = ();
+        /// Stop on the first matching value of an entry, and once the limit of matching entries
+        /// is reached
+        type Break = ();
+
+        fn traverse_entry(&mut self, entry: &BinEntry) -> TraverseResult<Self> {
+            if entry.traverse_bin(self)?.is_break() {
+                self.nmatches += 1;
+                (self.on_match)(entry);
+                if self.limit.is_some_and(|n| self.nmatches >= n) {
+                    return Ok(ControlFlow::Break(()));
                 }
-                Ok(())
             }
+            Ok(ControlFlow::Continue(()))
+        }
+
+        fn visit_field(&mut self, value: &BinField) -> VisitResult<Self> {
+            self.visit_filtered_field(value)
+        }
+    }
+}
+
+macro_rules! impl_search_bin_value_visitor {
+    ($typ:ty, $visit_func:ident) => {
+        impl<F: FnMut(&BinEntry)> BinVisitor for SearchBinValueVisitor<$typ, F> {
+            impl_search_visitor_common!();
 
             fn visit_type(&mut self, btype: BinType) -> bool {
-                !self.matched && (btype == <$typ as BinValue>::TYPE || btype.is_nested())
+                btype == <$typ as BinValue>::TYPE || btype.is_nested()
             }
 
-            fn $visit_func(&mut self, value: &$typ) -> Result<(), ()> {
-                if value == &self.pattern {
-                    self.matched = true;
-                }
-                Ok(())
+            fn $visit_func(&mut self, value: &$typ) -> VisitResult<Self> {
+                self.visit_searched_value(value == &self.pattern)
             }
         }
     }
@@ -138,6 +197,90 @@ impl_search_bin_value_visitor!(BinHash, visit_hash);
 impl_search_bin_value_visitor!(BinLink, visit_link);
 
 
+/// Numeric value to search
+#[derive(Clone, Copy, Debug)]
+pub enum NumberPattern {
+    /// Match integers with the given value, and floats within epsilon
+    Integer(i128),
+    /// Match floats within epsilon
+    Float(f32),
+}
+
+impl NumberPattern {
+    /// Relative tolerance used to compare floats
+    const FLOAT_EPSILON: f32 = 1e-6;
+
+    /// Parse an integer pattern, any value of signed or unsigned 64-bit integers is accepted
+    pub fn parse_integer(s: &str) -> Result<Self, String> {
+        s.parse::<i128>().ok()
+            .filter(|n| (i64::MIN as i128..=u64::MAX as i128).contains(n))
+            .map(Self::Integer)
+            .ok_or_else(|| format!("invalid integer: {}", s))
+    }
+
+    /// Parse a float pattern, NaN and infinite values are rejected
+    pub fn parse_float(s: &str) -> Result<Self, String> {
+        s.parse::<f32>().ok()
+            .filter(|f| f.is_finite())
+            .map(Self::Float)
+            .ok_or_else(|| format!("invalid float: {}", s))
+    }
+
+    fn matches_type(&self, btype: BinType) -> bool {
+        match self {
+            Self::Integer(_) => matches!(btype,
+                BinType::S8 | BinType::U8 |
+                BinType::S16 | BinType::U16 |
+                BinType::S32 | BinType::U32 |
+                BinType::S64 | BinType::U64 |
+                BinType::Float),
+            Self::Float(_) => btype == BinType::Float,
+        }
+    }
+
+    fn matches_integer(&self, value: i128) -> bool {
+        matches!(self, Self::Integer(n) if *n == value)
+    }
+
+    fn matches_float(&self, value: f32) -> bool {
+        let expected = match self {
+            Self::Integer(n) => *n as f32,
+            Self::Float(f) => *f,
+        };
+        (value - expected).abs() <= Self::FLOAT_EPSILON * expected.abs().max(1.0)
+    }
+}
+
+macro_rules! impl_visit_integer {
+    ($($visit_func:ident($typ:ty)),* $(,)?) => {
+        $(
+            fn $visit_func(&mut self, value: &$typ) -> VisitResult<Self> {
+                self.visit_searched_value(self.pattern.matches_integer(value.0 as i128))
+            }
+        )*
+    }
+}
+
+impl<F: FnMut(&BinEntry)> BinVisitor for SearchBinValueVisitor<NumberPattern, F> {
+    impl_search_visitor_common!();
+
+    fn visit_type(&mut self, btype: BinType) -> bool {
+        self.pattern.matches_type(btype) || btype.is_nested()
+    }
+
+    impl_visit_integer!(
+        visit_s8(BinS8), visit_u8(BinU8),
+        visit_s16(BinS16), visit_u16(BinU16),
+        visit_s32(BinS32), visit_u32(BinU32),
+        visit_s64(BinS64), visit_u64(BinU64),
+    );
+
+    fn visit_float(&mut self, value: &BinFloat) -> VisitResult<Self> {
+        self.visit_searched_value(self.pattern.matches_float(value.0))
+    }
+}
+
+
 pub struct HashesMatchingEntriesVisitor<'a> {
     mappers: &'a BinHashMappers,
     types_seen: HashSet<BinClassName>,
@@ -158,34 +301,134 @@ impl<'a> HashesMatchingEntriesVisitor<'a> {
 
 impl<'a> BinVisitor for HashesMatchingEntriesVisitor<'a> {
     type Error = ();
+    type Break = ();
 
     fn visit_type(&mut self, btype: BinType) -> bool {
         self.current_entry.is_some() && btype == BinType::Hash || btype.is_nested()
     }
 
-    fn visit_entry(&mut self, value: &BinEntry) -> Result<bool, ()> {
+    fn visit_entry(&mut self, value: &BinEntry) -> VisitResult<Self> {
         // Note: each type is checked only once
         // Even if the first entry does not cover all uses of hashes
         if self.types_seen.insert(value.ctype) {
             self.current_entry = Some((value.path, value.ctype));
             self.hashes_seen.clear();
-            Ok(true)
+            Ok(VisitOutcome::Continue)
         } else {
-            Ok(false)
+            Ok(VisitOutcome::SkipChildren)
         }
     }
 
-    fn visit_hash(&mut self, value: &BinHash) -> Result<(), ()> {
-        if self.hashes_seen.insert(value.0) {
-            if !self.mappers.hash_value.is_known(value.0.hash) && self.mappers.entry_path.is_known(value.0.hash) {
-                let (path, htype) = self.current_entry.unwrap();
-                println!("type {} , path {} , hash {:x}",
-                    htype.seek_str(self.mappers),
-                    path.seek_str(self.mappers),
-                    value.0);
-            }
+    fn visit_hash(&mut self, value: &BinHash) -> VisitResult<Self> {
+        if self.hashes_seen.insert(value.0) && !self.mappers.hash_value.is_known(value.0.hash) && self.mappers.entry_path.is_known(value.0.hash) {
+            let (path, htype) = self.current_entry.unwrap();
+            println!("type {} , path {} , hash {:x}",
+                htype.seek_str(self.mappers),
+                path.seek_str(self.mappers),
+                value.0);
         }
-        Ok(())
+        Ok(VisitOutcome::Continue)
     }
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cdragon_prop::PropFile;
+    use cdragon_hashes::bin::compute_binhash;
+    use cdragon_prop::testing::{bin_file_data, list_value, raw_type};
+
+    fn test_entry() -> BinEntry {
+        let data = bin_file_data(&[("TestClass", "Test/Entry", &[
+            ("mCount", BinType::U32 as u8, &1001u32.to_le_bytes()),
+            ("mOffset", BinType::S8 as u8, &(-3i8).to_le_bytes()),
+            ("mBig", BinType::U64 as u8, &u64::MAX.to_le_bytes()),
+            ("mRatio", BinType::Float as u8, &0.25f32.to_le_bytes()),
+            ("mScale", BinType::Float as u8, &2.0f32.to_le_bytes()),
+            ("mItems", raw_type(BinType::List), &list_value(BinType::U16, 2, &[7, 0, 8, 0])),
+        ])]);
+        PropFile::from_slice(&data).unwrap().entries.remove(0)
+    }
+
+    fn matches(pattern: NumberPattern, field: Option<&str>) -> bool {
+        let field = field.map(|name| BinFieldName::from(compute_binhash(name)));
+        let mut visitor = SearchBinValueVisitor::new(pattern, |_: &BinEntry| {})
+            .with_limit(Some(1))
+            .with_field(field);
+        visitor.traverse_entry(&test_entry()).unwrap().is_break()
+    }
+
+    #[test]
+    fn parse_integer_pattern() {
+        assert!(matches!(NumberPattern::parse_integer("42"), Ok(NumberPattern::Integer(42))));
+        assert!(matches!(NumberPattern::parse_integer("-3"), Ok(NumberPattern::Integer(-3))));
+        assert!(matches!(NumberPattern::parse_integer("18446744073709551615"), Ok(NumberPattern::Integer(n)) if n == u64::MAX as i128));
+        assert!(matches!(NumberPattern::parse_integer("-9223372036854775808"), Ok(NumberPattern::Integer(n)) if n == i64::MIN as i128));
+    }
+
+    #[test]
+    fn parse_integer_pattern_invalid() {
+        for s in ["", "abc", "1.5", "0x10", "18446744073709551616", "-9223372036854775809"] {
+            assert_eq!(NumberPattern::parse_integer(s).unwrap_err(), format!("invalid integer: {}", s));
+        }
+    }
+
+    #[test]
+    fn parse_float_pattern() {
+        assert!(matches!(NumberPattern::parse_float("0.25"), Ok(NumberPattern::Float(f)) if f == 0.25));
+        assert!(matches!(NumberPattern::parse_float("-2"), Ok(NumberPattern::Float(f)) if f == -2.0));
+        assert!(matches!(NumberPattern::parse_float("1e3"), Ok(NumberPattern::Float(f)) if f == 1000.0));
+    }
+
+    #[test]
+    fn parse_float_pattern_invalid() {
+        for s in ["", "abc", "1.5.0", "NaN", "inf", "1e40"] {
+            assert_eq!(NumberPattern::parse_float(s).unwrap_err(), format!("invalid float: {}", s));
+        }
+    }
+
+    #[test]
+    fn integer_pattern_matches_integer_fields() {
+        assert!(matches(NumberPattern::Integer(1001), None));
+        assert!(matches(NumberPattern::Integer(-3), None));
+        assert!(matches(NumberPattern::Integer(u64::MAX as i128), None));
+        assert!(!matches(NumberPattern::Integer(1000), None));
+        assert!(!matches(NumberPattern::Integer(253), None));  // -3 as u8
+        assert!(!matches(NumberPattern::Integer(-1), None));  // u64::MAX as i64
+    }
+
+    #[test]
+    fn integer_pattern_matches_float_fields() {
+        assert!(matches(NumberPattern::Integer(2), None));
+        assert!(!matches(NumberPattern::Integer(0), None));
+    }
+
+    #[test]
+    fn float_pattern_matches_float_fields_only() {
+        assert!(matches(NumberPattern::Float(0.25), None));
+        assert!(matches(NumberPattern::Float(0.250_000_1), None));
+        assert!(matches(NumberPattern::Float(2.0), None));
+        assert!(!matches(NumberPattern::Float(0.26), None));
+        assert!(!matches(NumberPattern::Float(1001.0), None));
+        assert!(!matches(NumberPattern::Float(-3.0), None));
+    }
+
+    #[test]
+    fn number_pattern_matches_nested_values() {
+        assert!(matches(NumberPattern::Integer(8), None));
+        assert!(!matches(NumberPattern::Integer(9), None));
+    }
+
+    #[test]
+    fn number_pattern_field_filter() {
+        assert!(matches(NumberPattern::Integer(1001), Some("mCount")));
+        assert!(!matches(NumberPattern::Integer(1001), Some("mOffset")));
+        assert!(matches(NumberPattern::Integer(7), Some("mItems")));
+        assert!(!matches(NumberPattern::Integer(7), Some("mCount")));
+        assert!(matches(NumberPattern::Float(0.25), Some("mRatio")));
+        assert!(!matches(NumberPattern::Float(0.25), Some("mScale")));
+        assert!(!matches(NumberPattern::Integer(1001), Some("mUnknown")));
+    }
+}
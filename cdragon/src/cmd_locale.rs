@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use cdragon_utils::locale::{Locale, parse_stringtable_name, parse_wad_name};
+use walkdir::WalkDir;
+use crate::cli::*;
+
+pub fn subcommand(name: &'static str) -> Subcommand {
+    let cmd = parent_command(name)
+        .about("Work on localized files")
+        .subcommand(
+            Command::new("list")
+            .about("List locales of localized WADs and stringtables from an install directory")
+            .arg(Arg::new("game-dir")
+                .short('g')
+                .long("game-dir")
+                .value_name("dir")
+                .required(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Install directory, searched recursively"))
+            .arg(Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output counts as a JSON object"))
+        )
+        ;
+    (cmd, handle)
+}
+
+fn handle(matches: &ArgMatches) -> CliResult {
+    match matches.subcommand() {
+        Some(("list", matches)) => {
+            let game_dir = matches.get_one::<PathBuf>("game-dir").unwrap();
+            let mut counts = BTreeMap::<Locale, LocaleFileCounts>::new();
+            for entry in WalkDir::new(game_dir) {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let Some(filename) = entry.file_name().to_str() else { continue };
+                if let Some((_, Some(locale))) = parse_wad_name(filename) {
+                    let counts = counts.entry(locale).or_default();
+                    // Game WADs use `.wad.client`, client WADs use `.wad`
+                    if filename.to_ascii_lowercase().ends_with(".wad.client") {
+                        counts.game_wads += 1;
+                    } else {
+                        counts.client_wads += 1;
+                    }
+                } else if let Some(locale) = parse_stringtable_name(filename) {
+                    counts.entry(locale).or_default().stringtables += 1;
+                }
+            }
+
+            if matches.get_flag("json") {
+                let values: serde_json::Map<String, serde_json::Value> = counts.iter().map(|(locale, counts)| {
+                    (locale.to_string(), serde_json::json!({
+                        "game_wads": counts.game_wads,
+                        "client_wads": counts.client_wads,
+                        "stringtables": counts.stringtables,
+                    }))
+                }).collect();
+                serde_json::to_writer_pretty(std::io::stdout(), &values)?;
+                println!();
+            } else if counts.is_empty() {
+                eprintln!("No localized file found");
+            } else {
+                println!("locale  game WADs  client WADs  stringtables");
+                for (locale, counts) in &counts {
+                    println!("{:6}  {:>9}  {:>11}  {:>12}", locale, counts.game_wads, counts.client_wads, counts.stringtables);
+                }
+            }
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Number of localized files found for a locale
+#[derive(Default)]
+struct LocaleFileCounts {
+    game_wads: usize,
+    client_wads: usize,
+    stringtables: usize,
+}
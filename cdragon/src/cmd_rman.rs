@@ -1,15 +1,17 @@
 use std::fs;
+use std::collections::{HashMap, HashSet};
 use std::path::{PathBuf, Path};
-use cdragon_cdn::CdnDownloader;
-use cdragon_rman::{Rman, FileEntry};
+use cdragon_cdn::{CdnDownloader, ChunkStore, LocalBundleStore};
+use cdragon_rman::{FileEntry, RmanError, RmanFileType};
+use cdragon_utils::locale::Locale;
 use crate::cli::*;
-use crate::utils::PathPattern;
+use crate::utils::{PathPattern, file_matches_locale, open_rman, stderr_progress};
 
 pub fn subcommand(name: &'static str) -> Subcommand {
     let arg_manifest = || Arg::new("manifest")
         .required(true)
         .value_parser(value_parser!(PathBuf))
-        .help("Manifest file to parse");
+        .help("Manifest file to parse (path, URL, or `-` for stdin)");
 
     let cmd = parent_command(name)
         .about("Work on release manifests (RMAN files)")
@@ -21,6 +23,10 @@ pub fn subcommand(name: &'static str) -> Subcommand {
                 .short('c')
                 .action(ArgAction::SetTrue)
                 .help("Also list chunks within each bundle"))
+            .arg(Arg::new("files")
+                .long("files")
+                .action(ArgAction::SetTrue)
+                .help("Also list files using each bundle"))
         )
         .subcommand(
             Command::new("files")
@@ -30,6 +36,16 @@ pub fn subcommand(name: &'static str) -> Subcommand {
                 .short('c')
                 .action(ArgAction::SetTrue)
                 .help("Also list chunks within each bundle"))
+            .arg(Arg::new("long")
+                .short('l')
+                .long("long")
+                .action(ArgAction::SetTrue)
+                .help("Also show file type (`x`: executable), localized marker (`L`), size and flags (e.g. locales)"))
+            .arg(Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("long")
+                .help("Output a JSON array of `{path, size, flags}` objects"))
         )
         .subcommand(
             Command::new("download")
@@ -40,12 +56,42 @@ pub fn subcommand(name: &'static str) -> Subcommand {
                 .value_parser(value_parser!(PathBuf))
                 .default_value(".")
                 .help("Output directory for downloaded files"))
+            .arg(Arg::new("cache")
+                .long("cache")
+                .value_name("dir")
+                .value_parser(value_parser!(PathBuf))
+                .help("Cache directory for downloaded bundles"))
+            .arg(Arg::new("cache-max-size")
+                .long("cache-max-size")
+                .value_name("bytes")
+                .value_parser(value_parser!(u64))
+                .requires("cache")
+                .help("Maximum size of the bundle cache"))
+            .arg(Arg::new("bundles")
+                .long("bundles")
+                .value_name("dir")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with("cache")
+                .help("Read bundles from a local directory (e.g. a CDN mirror) instead of downloading them"))
+            .arg(Arg::new("chunk-store")
+                .long("chunk-store")
+                .value_name("dir")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with("bundles")
+                .help("Read chunks from a chunk store, add downloaded chunks to it (shared by all manifests)"))
+            .arg(Arg::new("locale")
+                .short('l')
+                .long("locale")
+                .value_name("locale")
+                .value_parser(value_parser!(Locale))
+                .help("Skip files localized for other locales (e.g. `en_US`), based on file flags or paths"))
+            .arg(arg_quiet())
             .arg(arg_manifest().index(1))
             .arg(Arg::new("patterns")
                 .required(true)
                 .index(2)
                 .num_args(1..)
-                .help("Paths of files to download, `*` wildcards are supported"))
+                .help("Paths of files to download, glob patterns are supported (`*`, `**`, `?`, `[...]`); `*` does not match `/`, use `**` to match nested files"))
         )
         ;
 
@@ -55,65 +101,134 @@ pub fn subcommand(name: &'static str) -> Subcommand {
 fn handle(matches: &ArgMatches) -> CliResult {
     match matches.subcommand() {
         Some(("bundles", matches)) => {
-            let rman = Rman::open(matches.get_one::<PathBuf>("manifest").unwrap())?;
+            let rman = open_rman(matches.get_one::<PathBuf>("manifest").unwrap())?;
             let show_chunks = matches.get_flag("chunks");
+            // Bundle files, with file paths
+            let files = if matches.get_flag("files") {
+                let dir_paths = rman.dir_paths()?;
+                let file_paths: HashMap<u64, String> = rman.iter_files()
+                    .map(|file| file.and_then(|file| Ok((file.id, file.path(&dir_paths)?))))
+                    .collect::<Result<_, _>>()?;
+                Some((rman.bundle_files(&rman.bundle_chunks()?)?, file_paths))
+            } else {
+                None
+            };
             for bundle in rman.iter_bundles() {
+                let bundle = bundle?;
                 println!("{:016x}  chunks: {}", bundle.id, bundle.chunks_count());
                 if show_chunks {
                     for chunk in bundle.iter_chunks() {
+                        let chunk = chunk?;
                         println!("  {:016x}  size: {} -> {}", chunk.id, chunk.bundle_size, chunk.target_size);
                     }
                 }
+                if let Some((bundle_files, file_paths)) = &files {
+                    for file_id in bundle_files.get(&bundle.id).into_iter().flatten() {
+                        println!("  file: {}", file_paths[file_id]);
+                    }
+                }
             }
 
             Ok(())
         }
         Some(("files", matches)) => {
-            let rman = Rman::open(matches.get_one::<PathBuf>("manifest").unwrap())?;
-            let dir_paths = rman.dir_paths();
+            let rman = open_rman(matches.get_one::<PathBuf>("manifest").unwrap())?;
+            let dir_paths = rman.dir_paths()?;
+            let long = matches.get_flag("long");
+            let flags = rman.flags()?;
+            if matches.get_flag("json") {
+                let values: Vec<serde_json::Value> = rman.iter_files().map(|file| {
+                    let file = file?;
+                    let file_flags: Vec<&str> = file.flags.map(|f| f.resolve(&flags).collect()).unwrap_or_default();
+                    Ok(serde_json::json!({
+                        "path": file.path(&dir_paths)?,
+                        "size": file.filesize,
+                        "flags": file_flags,
+                    }))
+                }).collect::<Result<_, RmanError>>()?;
+                serde_json::to_writer_pretty(std::io::stdout().lock(), &values)?;
+                println!();
+                return Ok(());
+            }
             for file in rman.iter_files() {
-                println!("{}", file.path(&dir_paths));
+                let file = file?;
+                let path = file.path(&dir_paths)?;
+                if long {
+                    let file_type = match file.file_type {
+                        Some(RmanFileType::Executable) => 'x',
+                        Some(RmanFileType::Regular) => '-',
+                        _ => '?',
+                    };
+                    let localized = if file.is_localized { 'L' } else { '-' };
+                    let locales: Vec<&str> = file.flags.map(|f| f.resolve(&flags).collect()).unwrap_or_default();
+                    if locales.is_empty() {
+                        println!("{}{} {:>10}  {}", file_type, localized, file.filesize, path);
+                    } else {
+                        println!("{}{} {:>10}  {}  [{}]", file_type, localized, file.filesize, path, locales.join(","));
+                    }
+                } else {
+                    println!("{}", path);
+                }
             }
 
             Ok(())
         }
         Some(("download", matches)) => {
-            let rman = Rman::open(matches.get_one::<PathBuf>("manifest").unwrap())?;
+            let rman = open_rman(matches.get_one::<PathBuf>("manifest").unwrap())?;
             let patterns = matches.get_many::<String>("patterns").unwrap();
             let path_patterns: Vec<PathPattern> = patterns.map(|v| PathPattern::new(v)).collect();
+            let locale = matches.get_one::<Locale>("locale");
+            let flags = rman.flags()?;
 
-            // Collect file entries to fetch
-            let file_entries: Vec<(String, FileEntry)> = {
-                let dir_paths = rman.dir_paths();
-                rman
-                    .iter_files()
-                    .filter_map(|entry| {
-                        let path = entry.path(&dir_paths);
-                        if path_patterns.iter().any(|pat| pat.is_match(&path)) {
-                            Some((path, entry))
-                        } else {
-                            None
-                        }
-                    }).collect()
-            };
+            // Collect file entries to fetch, once each, in pattern order
+            let file_index = rman.file_index()?;
+            let mut file_ids = HashSet::new();
+            let file_entries: Vec<(&str, &FileEntry)> = path_patterns
+                .iter()
+                .flat_map(|pat| file_index.iter_matching(pat))
+                .filter(|(path, entry)| locale.is_none_or(|l| file_matches_locale(entry, path, &flags, *l)))
+                .filter(|(_, entry)| file_ids.insert(entry.id))
+                .collect();
             if file_entries.is_empty() {
                 eprintln!("No matching file found in manifest");
                 std::process::exit(2);
             }
             println!("Downloading {} file(s)", file_entries.len());
 
-            let bundle_chunks = rman.bundle_chunks();
+            let bundle_chunks = rman.bundle_chunks()?;
 
             let output = Path::new(matches.get_one::<PathBuf>("output").unwrap());
             fs::create_dir_all(output)?;
 
-            let cdn = CdnDownloader::new()?;
+            let store = matches.get_one::<PathBuf>("bundles").map(LocalBundleStore::new);
+            let chunk_store = matches.get_one::<PathBuf>("chunk-store").map(ChunkStore::new);
+            let mut cdn = CdnDownloader::new()?;
+            if let Some(dir) = matches.get_one::<PathBuf>("cache") {
+                cdn = cdn.with_cache(dir);
+                if let Some(size) = matches.get_one::<u64>("cache-max-size") {
+                    cdn = cdn.with_cache_max_size(*size);
+                }
+            }
+
+            let mut progress = stderr_progress(matches.get_flag("quiet"), file_entries.len());
 
             // Process each file, one by one
             for (path, file_entry) in file_entries.into_iter() {
-                let (file_size, ranges) = file_entry.bundle_chunks(&bundle_chunks);
+                let (file_size, ranges) = file_entry.bundle_chunks(&bundle_chunks)?;
                 println!("Downloading {} ({} bytes)", path, file_size);
-                cdn.download_bundle_chunks(file_size as u64, &ranges, &output.join(path))?;
+                let output_path = output.join(path);
+                match (&store, &chunk_store) {
+                    (Some(store), _) => store.build_file_with_progress(file_size as u64, &ranges, &output_path, &mut *progress)?,
+                    (None, Some(chunk_store)) => cdn.download_bundle_chunks_with_store_and_progress(file_size as u64, &ranges, &output_path, chunk_store, &mut *progress)?,
+                    (None, None) => cdn.download_bundle_chunks_with_progress(file_size as u64, &ranges, &output_path, &mut *progress)?,
+                }
+                #[cfg(unix)]
+                if file_entry.is_executable() {
+                    use std::os::unix::fs::PermissionsExt;
+                    let mut perms = fs::metadata(&output_path)?.permissions();
+                    perms.set_mode(perms.mode() | 0o111);
+                    fs::set_permissions(&output_path, perms)?;
+                }
             }
 
             Ok(())
@@ -121,4 +236,3 @@ fn handle(matches: &ArgMatches) -> CliResult {
         _ => unreachable!(),
     }
 }
-
@@ -1,7 +1,10 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io;
 use std::path::{PathBuf, Path};
 use anyhow::{Context, Result};
-use cdragon_hashes::HashKind;
-use cdragon_rst::{Rst, RstHashMapper};
+use cdragon_hashes::{HashKind, rst::RstHashMapper};
+use cdragon_rst::{Rst, RstStack};
 use crate::cli::*;
 
 pub fn subcommand(name: &'static str) -> Subcommand {
@@ -9,6 +12,12 @@ pub fn subcommand(name: &'static str) -> Subcommand {
         .required(true)
         .value_parser(value_parser!(PathBuf))
         .help("RST file to parse");
+    let arg_fallback = || Arg::new("fallback")
+        .long("fallback")
+        .value_name("file")
+        .value_parser(value_parser!(PathBuf))
+        .action(ArgAction::Append)
+        .help("Fallback RST file for missing entries (e.g. `en_US` one), can be repeated");
 
     let cmd = parent_command(name)
         .about("Work on RST files")
@@ -20,6 +29,40 @@ pub fn subcommand(name: &'static str) -> Subcommand {
                 .action(ArgAction::SetTrue)
                 .help("Dump keys as hexadecimal instead of reversed strings"))
             .arg(arg_rst())
+            .arg(arg_fallback())
+            .arg(arg_hashes_dir())
+        )
+        .subcommand(
+            Command::new("dump")
+            .about("Dump RST entries as `key = value` lines, sorted by hash")
+            .arg(Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Dump entries as a JSON object"))
+            .arg(arg_rst())
+            .arg(arg_fallback())
+            .arg(arg_hashes_dir())
+        )
+        .subcommand(
+            Command::new("get")
+            .about("Get a single RST entry")
+            .arg(arg_rst())
+            .arg(arg_fallback())
+            .arg(Arg::new("key")
+                .required(true)
+                .help("Key string, or hash value formatted as `{hex}` or `0xhex`"))
+        )
+        .subcommand(
+            Command::new("diff")
+            .about("List entries added, removed or changed between two RST files")
+            .arg(Arg::new("old")
+                .required(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Old RST file"))
+            .arg(Arg::new("new")
+                .required(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("New RST file"))
             .arg(arg_hashes_dir())
         )
         ;
@@ -29,20 +72,89 @@ pub fn subcommand(name: &'static str) -> Subcommand {
 fn handle(matches: &ArgMatches) -> CliResult {
     match matches.subcommand() {
         Some(("list", matches)) => {
-            let rst = rst_from_path(matches.get_one::<PathBuf>("rst").unwrap())?;
+            let (rst, _) = rst_stack_from_matches(matches)?;
             if matches.get_flag("hexa") {
                 let nchars = rst.hash_bits().div_ceil(4) as usize;
-                for (hash, value) in rst.iter() {
+                for (hash, value) in rst.iter_merged() {
                     println!("{:0w$x} {}", hash, value, w = nchars);
                 }
             } else {
-                let hmapper = hmapper_from_path(get_hashes_dir(matches))?;
-                for (hash, value) in rst.iter() {
+                let hmapper = hmapper_from_path(get_hashes_dir(matches), rst.hash_bits())?;
+                for (hash, value) in rst.iter_merged() {
                     println!("{} {}", hmapper.get(hash).unwrap_or("?"), value);
                 }
             }
             Ok(())
         }
+        Some(("dump", matches)) => {
+            let (rst, _) = rst_stack_from_matches(matches)?;
+            // Keys of merged entries use the smallest bit size of all files
+            let hash_bits = rst.hash_bits();
+            let hmapper = hmapper_from_path(get_hashes_dir(matches), hash_bits)?;
+            let nchars = hash_bits.div_ceil(4) as usize;
+            let entries = sorted_entries(rst.iter_merged());
+            if matches.get_flag("json") {
+                let map: serde_json::Map<String, serde_json::Value> = entries
+                    .into_iter()
+                    .map(|(hash, value)| (format_key(hash, &hmapper, nchars), value.into_owned().into()))
+                    .collect();
+                serde_json::to_writer_pretty(io::stdout().lock(), &map)?;
+                println!();
+            } else {
+                for (hash, value) in entries {
+                    println!("{} = {}", format_key(hash, &hmapper, nchars), value);
+                }
+            }
+            Ok(())
+        }
+        Some(("get", matches)) => {
+            let (rst, paths) = rst_stack_from_matches(matches)?;
+            let key = matches.get_one::<String>("key").unwrap();
+            let value = match parse_hash_key(key) {
+                Some(hash) => rst.get_with_index(hash),
+                None => rst.get_with_index(key.as_str()),
+            };
+            match value {
+                Some((index, value)) => {
+                    if index > 0 {
+                        eprintln!("Entry found in fallback {}", paths[index].display());
+                    }
+                    println!("{}", value);
+                }
+                None => return Err(format!("RST entry not found: {}", key).into()),
+            }
+            Ok(())
+        }
+        Some(("diff", matches)) => {
+            let old = rst_from_path(matches.get_one::<PathBuf>("old").unwrap())?;
+            let new = rst_from_path(matches.get_one::<PathBuf>("new").unwrap())?;
+
+            // Compare hashes using the smallest bit size of both files
+            let hash_bits = old.hash_bits().min(new.hash_bits());
+            let hmapper = hmapper_from_path(get_hashes_dir(matches), hash_bits)?;
+            let nchars = hash_bits.div_ceil(4) as usize;
+            let mask = (1u64 << hash_bits) - 1;
+            let old_entries: BTreeMap<u64, Cow<str>> = old.iter().map(|(h, v)| (h & mask, v)).collect();
+            let new_entries: BTreeMap<u64, Cow<str>> = new.iter().map(|(h, v)| (h & mask, v)).collect();
+
+            for (hash, old_value) in old_entries.iter() {
+                match new_entries.get(hash) {
+                    None => println!("- {} = {}", format_key(*hash, &hmapper, nchars), old_value),
+                    Some(new_value) if new_value != old_value => {
+                        let key = format_key(*hash, &hmapper, nchars);
+                        println!("- {} = {}", key, old_value);
+                        println!("+ {} = {}", key, new_value);
+                    }
+                    _ => {}
+                }
+            }
+            for (hash, new_value) in new_entries.iter() {
+                if !old_entries.contains_key(hash) {
+                    println!("+ {} = {}", format_key(*hash, &hmapper, nchars), new_value);
+                }
+            }
+            Ok(())
+        }
         _ => unreachable!(),
     }
 }
@@ -52,13 +164,44 @@ fn rst_from_path(rst_path: &Path) -> Result<Rst> {
     Rst::open(rst_path).with_context(|| format!("failed to open RST file {}", rst_path.display()))
 }
 
-/// Read RstHashMapper from path parameter
-fn hmapper_from_path(hashes_dir: Option<PathBuf>) -> Result<RstHashMapper> {
-    let mut hmapper = RstHashMapper::new();
+/// Read RST files from `rst` and `fallback` parameters, return them with their paths
+fn rst_stack_from_matches(matches: &ArgMatches) -> Result<(RstStack, Vec<&PathBuf>)> {
+    let paths: Vec<&PathBuf> = std::iter::once(matches.get_one::<PathBuf>("rst").unwrap())
+        .chain(matches.get_many::<PathBuf>("fallback").into_iter().flatten())
+        .collect();
+    let rsts = paths.iter().map(|path| rst_from_path(path)).collect::<Result<_>>()?;
+    Ok((RstStack::new(rsts), paths))
+}
+
+/// Read RstHashMapper from path parameter, for hashes truncated to `hash_bits`
+fn hmapper_from_path(hashes_dir: Option<PathBuf>, hash_bits: u8) -> Result<RstHashMapper<64>> {
+    let mut hmapper: RstHashMapper = RstHashMapper::new();
     if let Some(dir) = hashes_dir {
         let path = dir.join(HashKind::Rst.mapping_path());
         hmapper.load_path(&path).with_context(|| format!("failed to load hash mapping {}", path.display()))?;
     }
-    Ok(hmapper)
+    Ok(hmapper.rehashed(hash_bits))
+}
+
+
+/// Collect RST string entries, sorted by hash
+fn sorted_entries<'a>(entries: impl Iterator<Item=(u64, Cow<'a, str>)>) -> Vec<(u64, Cow<'a, str>)> {
+    let mut entries: Vec<_> = entries.collect();
+    entries.sort_unstable_by_key(|(hash, _)| *hash);
+    entries
 }
 
+/// Format an entry key, use `{hex}` for unknown hashes
+fn format_key(hash: u64, hmapper: &RstHashMapper<64>, nchars: usize) -> String {
+    match hmapper.get(hash) {
+        Some(key) => key.to_owned(),
+        None => format!("{{{:0w$x}}}", hash, w = nchars),
+    }
+}
+
+/// Parse a hash key formatted as `{hex}` or `0xhex`
+fn parse_hash_key(s: &str) -> Option<u64> {
+    let hex = s.strip_prefix('{').and_then(|s| s.strip_suffix('}'))
+        .or_else(|| s.strip_prefix("0x"))?;
+    u64::from_str_radix(hex, 16).ok()
+}
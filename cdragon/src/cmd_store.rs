@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use cdragon_cdn::ChunkStore;
+use crate::cli::*;
+use crate::utils::open_rman;
+
+pub fn subcommand(name: &'static str) -> Subcommand {
+    let cmd = parent_command(name)
+        .about("Maintain chunk stores (see `rman download --chunk-store`)")
+        .subcommand(
+            Command::new("gc")
+            .about("Remove chunks not used by the given manifests")
+            .arg(Arg::new("store")
+                .required(true)
+                .value_name("dir")
+                .value_parser(value_parser!(PathBuf))
+                .help("Chunk store directory"))
+            .arg(Arg::new("keep-manifests")
+                .long("keep-manifests")
+                .value_name("rman")
+                .required(true)
+                .num_args(1..)
+                .value_parser(value_parser!(PathBuf))
+                .help("Manifests whose chunks are kept (path, URL, or `-` for stdin)"))
+        )
+        ;
+    (cmd, handle)
+}
+
+fn handle(matches: &ArgMatches) -> CliResult {
+    match matches.subcommand() {
+        Some(("gc", matches)) => {
+            let store = ChunkStore::new(matches.get_one::<PathBuf>("store").unwrap());
+            let mut keep = HashSet::new();
+            for path in matches.get_many::<PathBuf>("keep-manifests").unwrap() {
+                let rman = open_rman(path)?;
+                for bundle in rman.iter_bundles() {
+                    for chunk in bundle?.iter_chunks() {
+                        keep.insert(chunk?.id);
+                    }
+                }
+            }
+            let (count, size) = store.gc(&keep)?;
+            println!("Removed {} chunk(s), {} bytes", count, size);
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
@@ -1,15 +1,27 @@
+use std::io;
+use std::collections::HashMap;
 use std::path::{PathBuf, Path};
 use anyhow::{Context, Result};
-use cdragon_hashes::HashKind;
-use cdragon_wad::{WadEntry, WadFile, WadHashMapper};
+use cdragon_hashes::wad::{WadHashMapper, WadHashMappers, normalize_wad_path};
+use cdragon_wad::{WadDataFormat, WadEntry, WadEntryHash, WadFile};
 use crate::cli::*;
-use crate::utils::HashValuePattern;
+use cdragon_utils::{
+    locale::{Locale, path_locale, wad_name_locale},
+    progress::ProgressEvent,
+};
+use crate::utils::{HashValuePattern, open_input, stderr_progress};
 
 pub fn subcommand(name: &'static str) -> Subcommand {
     let arg_wad = || Arg::new("wad")
         .required(true)
         .value_parser(value_parser!(PathBuf))
-        .help("WAD file to parse");
+        .help("WAD file to parse (path, URL, or `-` for stdin)");
+    let arg_format = || Arg::new("format")
+        .long("format")
+        .value_name("format")
+        .value_parser(DATA_FORMAT_NAMES)
+        .action(ArgAction::Append)
+        .help("Only use entries with given data format (can be repeated)");
 
     let cmd = parent_command(name)
         .about("Work on WAD archives")
@@ -17,6 +29,27 @@ pub fn subcommand(name: &'static str) -> Subcommand {
             Command::new("list")
             .about("List WAD entries")
             .arg(arg_wad())
+            .arg(Arg::new("dedup")
+                .long("dedup")
+                .action(ArgAction::SetTrue)
+                .help("Skip duplicate entries"))
+            .arg(Arg::new("long")
+                .short('l')
+                .long("long")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("json")
+                .help("Also print format, sizes and duplicate flag, and totals"))
+            .arg(Arg::new("json")
+                .short('j')
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output entries as JSON"))
+            .arg(Arg::new("sort")
+                .long("sort")
+                .value_name("key")
+                .value_parser(["path", "size"])
+                .help("Sort entries by path, or by uncompressed size (largest first)"))
+            .arg(arg_format())
             .arg(arg_hashes_dir())
         )
         .subcommand(
@@ -34,10 +67,32 @@ pub fn subcommand(name: &'static str) -> Subcommand {
                 .value_name("subdir")
                 .value_parser(value_parser!(PathBuf))
                 .help("Output unknown files to given subdirectory (empty to not output them)"))
+            .arg(Arg::new("links")
+                .long("links")
+                .action(ArgAction::SetTrue)
+                .help("Extract redirection entries as links to their target (symlinks on Unix)"))
+            .arg(Arg::new("flatten")
+                .long("flatten")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["links", "hash-names"])
+                .help("Extract known files to the output directory, using their basename (with the hash on name collisions)"))
+            .arg(Arg::new("hash-names")
+                .long("hash-names")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("links")
+                .help("Extract all files to the output directory, named after their hash, with their known or guessed extension"))
+            .arg(arg_format())
+            .arg(Arg::new("locale")
+                .short('l')
+                .long("locale")
+                .value_name("locale")
+                .value_parser(value_parser!(Locale))
+                .help("Skip files localized for other locales (e.g. `en_US`), based on their path or on the WAD name"))
+            .arg(arg_quiet())
             .arg(arg_hashes_dir())
             .arg(Arg::new("patterns")
                 .num_args(0..)
-                .help("Hashes or paths of files to download, `*` wildcards are supported for paths"))
+                .help("Hashes or paths of files to download, glob patterns are supported for paths (`*`, `**`, `?`, `[...]`); `*` does not match `/`, use `**` to match nested files"))
         )
         ;
     (cmd, handle)
@@ -46,47 +101,163 @@ pub fn subcommand(name: &'static str) -> Subcommand {
 fn handle(matches: &ArgMatches) -> CliResult {
     match matches.subcommand() {
         Some(("list", matches)) => {
-            let (wad, hmapper) = wad_and_hmapper_from_paths(matches.get_one::<PathBuf>("wad").unwrap(), get_hashes_dir(matches))?;
-            for entry in wad.iter_entries() {
-                let entry = entry?;
-                println!("{:x}  {}", entry.path, hmapper.get(entry.path.hash).unwrap_or("?"));
+            let mut input = open_input(matches.get_one::<PathBuf>("wad").unwrap())?;
+            let (wad, hmappers) = wad_and_hmappers_from_paths(input.path()?, get_hashes_dir(matches))?;
+            let empty_hmapper = WadHashMapper::new();
+            let hmapper = hmappers.mapper_from_wad_path(input.name())?.unwrap_or(&empty_hmapper);
+            let formats = data_formats_from_matches(matches);
+            let entries: Box<dyn Iterator<Item=_>> = if matches.get_flag("dedup") {
+                Box::new(wad.iter_unique_entries())
+            } else {
+                Box::new(wad.iter_entries())
+            };
+            let mut entries = entries
+                .filter(|entry| entry.as_ref().map_or(true, |e| formats.matches(e)))
+                .map(|entry| entry.map(|e| {
+                    let path = e.path.try_str(hmapper);
+                    (e, path)
+                }))
+                .collect::<Result<Vec<_>, _>>()?;
+            match matches.get_one::<String>("sort").map(|s| s.as_str()) {
+                // Unknown paths last
+                Some("path") => entries.sort_by(|(e1, p1), (e2, p2)| {
+                    (p1.is_none(), p1, e1.path).cmp(&(p2.is_none(), p2, e2.path))
+                }),
+                Some("size") => entries.sort_by_key(|(e, _)| std::cmp::Reverse(e.target_size())),
+                _ => {}
+            }
+
+            if matches.get_flag("json") {
+                let values: Vec<serde_json::Value> = entries.iter().map(|(entry, path)| serde_json::json!({
+                    "hash": format!("{:x}", entry.path),
+                    "path": path,
+                    "format": entry.data_format().name(),
+                    "size": entry.size(),
+                    "target_size": entry.target_size(),
+                    "duplicate": entry.is_duplicate(),
+                    "subchunks": entry.subchunk_count(),
+                })).collect();
+                serde_json::to_writer_pretty(io::stdout().lock(), &values)?;
+                println!();
+            } else if matches.get_flag("long") {
+                let (mut total_size, mut total_target_size) = (0u64, 0u64);
+                for (entry, path) in entries.iter() {
+                    let format = match entry.subchunk_count() {
+                        Some(n) => format!("{}:{}", entry.data_format().name(), n),
+                        None => entry.data_format().name().to_string(),
+                    };
+                    println!("{:x}  {:<12} {:>10} {:>10} {}  {}",
+                        entry.path, format, entry.size(), entry.target_size(),
+                        if entry.is_duplicate() { "D" } else { "-" },
+                        path.unwrap_or("?"));
+                    total_size += entry.size() as u64;
+                    total_target_size += entry.target_size() as u64;
+                }
+                println!("{} entries, {} bytes, {} bytes uncompressed", entries.len(), total_size, total_target_size);
+            } else {
+                for (entry, path) in entries.iter() {
+                    println!("{:x}  {}", entry.path, path.unwrap_or("?"));
+                }
             }
             Ok(())
         }
         Some(("extract", matches)) => {
-            let (mut wad, hmapper) = wad_and_hmapper_from_paths(matches.get_one::<PathBuf>("wad").unwrap(), get_hashes_dir(matches))?;
-            let patterns = matches.get_many::<String>("patterns");
+            let mut input = open_input(matches.get_one::<PathBuf>("wad").unwrap())?;
+            let (mut wad, hmappers) = wad_and_hmappers_from_paths(input.path()?, get_hashes_dir(matches))?;
+            let empty_hmapper = WadHashMapper::new();
+            let hmapper = hmappers.mapper_from_wad_path(input.name())?.unwrap_or(&empty_hmapper);
+            // Normalize patterns, like paths from hash mappers
+            let patterns: Option<Vec<String>> = matches.get_many::<String>("patterns")
+                .map(|p| p.map(|v| normalize_wad_path(v)).collect());
             let hash_patterns: Option<Vec<HashValuePattern<u64>>> =
-                patterns.map(|p| p.map(|v| HashValuePattern::new(v)).collect());
+                patterns.as_ref().map(|p| p.iter().map(|v| HashValuePattern::new(v)).collect());
 
             let output = Path::new(matches.get_one::<PathBuf>("output").unwrap());
             let unknown = matches.get_one::<PathBuf>("unknown").map(|p| output.join(p));
+            let links = matches.get_flag("links");
+            let formats = data_formats_from_matches(matches);
 
-            let entries = wad
-                .iter_entries()
-                .map(|res| res.expect("entry error"))
-                .filter(|e| !e.is_redirection());
             let entries: Vec<WadEntry> = match hash_patterns {
-                Some(patterns) => {
-                    let hmapper = &hmapper;
-                    entries.filter(move |e| {
-                        patterns.iter().any(|pat| pat.is_match(e.path.hash, hmapper))
+                // Only hashes: look them up, don't scan all entries
+                Some(patterns) if patterns.iter().all(|pat| matches!(pat, HashValuePattern::Hash(_))) => {
+                    let mut entries = Vec::with_capacity(patterns.len());
+                    for pat in patterns {
+                        if let HashValuePattern::Hash(hash) = pat {
+                            match wad.find_entry(hash)? {
+                                Some(entry) => entries.push(entry),
+                                None => println!("Entry not found: {:016x}", hash),
+                            }
+                        }
+                    }
+                    entries.retain(|e| (links || !e.is_redirection()) && formats.matches(e));
+                    entries
+                }
+                hash_patterns => {
+                    let entries = wad
+                        .iter_entries()
+                        .map(|res| res.expect("entry error"))
+                        .filter(|e| (links || !e.is_redirection()) && formats.matches(e));
+                    match hash_patterns {
+                        Some(patterns) => {
+                            entries.filter(move |e| {
+                                patterns.iter().any(|pat| pat.is_match(e.path.hash, hmapper))
+                            }).collect()
+                        }
+                        None => entries.collect(),
+                    }
+                }
+            };
+            let entries = match matches.get_one::<Locale>("locale") {
+                Some(locale) => {
+                    // Entries without localized path inherit the locale of the WAD
+                    let wad_locale = Path::new(input.name()).file_name()
+                        .and_then(|s| s.to_str())
+                        .and_then(wad_name_locale);
+                    entries.into_iter().filter(|e| {
+                        let entry_locale = e.path.try_str(hmapper).and_then(path_locale).or(wad_locale);
+                        entry_locale.is_none_or(|l| l == *locale)
                     }).collect()
                 }
-                None => entries.collect(),
+                None => entries,
+            };
+            let naming = if matches.get_flag("flatten") {
+                ExtractNaming::Flatten
+            } else if matches.get_flag("hash-names") {
+                ExtractNaming::HashNames
+            } else {
+                ExtractNaming::Paths
             };
+            let output_paths = ExtractPaths::new(
+                output, unknown, naming,
+                entries.iter().filter_map(|e| e.path.try_str(hmapper)),
+            );
+
+            let mut progress = stderr_progress(matches.get_flag("quiet"), entries.len());
             for entry in entries {
-                let path = match hmapper.get(entry.path.hash) {
-                    Some(path) => output.join(path),
-                    None => if let Some(p) = unknown.as_ref() {
-                        p.join(format!("{:x}", entry.path))
+                if entry.is_redirection() {
+                    if hmapper.is_known(entry.path.hash) {
+                        let path = wad.extract_entry_as_link(&entry, output, hmapper)?;
+                        println!("Link {:x} to {}", entry.path, path.display());
                     } else {
-                        println!("Skip unknown file: {:x}", entry.path);
-                        continue;
+                        println!("Skip unknown redirection: {:x}", entry.path);
                     }
+                    progress.on_event(ProgressEvent::FileFinished);
+                    continue;
+                }
+                let known_path = entry.path.try_str(hmapper);
+                let Some(path) = output_paths.entry_path(entry.path, known_path, || wad.guess_entry_extension(&entry)) else {
+                    println!("Skip unknown file: {:x}", entry.path);
+                    progress.on_event(ProgressEvent::FileFinished);
+                    continue;
                 };
                 println!("Extract {:x} to {}", entry.path, path.display());
+                progress.on_event(ProgressEvent::FileStarted {
+                    name: &path.to_string_lossy(),
+                    total_bytes: Some(entry.target_size() as u64),
+                });
                 wad.extract_entry(&entry, &path)?;
+                progress.on_event(ProgressEvent::BytesTransferred { n: entry.target_size() as u64 });
+                progress.on_event(ProgressEvent::FileFinished);
             }
 
             Ok(())
@@ -95,16 +266,173 @@ fn handle(matches: &ArgMatches) -> CliResult {
     }
 }
 
-/// Read WAD from path parameter
-fn wad_and_hmapper_from_paths(wad_path: &Path, hashes_dir: Option<PathBuf>) -> Result<(WadFile, WadHashMapper)> {
+/// Read WAD from path parameter, prepare hash mappers
+///
+/// Mappers are loaded lazily, only the one needed by the WAD is loaded.
+/// Paths are normalized, for consistent display.
+fn wad_and_hmappers_from_paths(wad_path: &Path, hashes_dir: Option<PathBuf>) -> Result<(WadFile, WadHashMappers)> {
     let wad = WadFile::open(wad_path).with_context(|| format!("failed to open WAD file {}", wad_path.display()))?;
-    let mut hmapper = WadHashMapper::new();
-    if let Some(dir) = hashes_dir {
-        if let Some(kind) = HashKind::from_wad_path(wad_path) {
-            let path = dir.join(kind.mapping_path());
-            hmapper.load_path(&path).with_context(|| format!("failed to load hash mapping {}", path.display()))?;
+    let hmappers = match hashes_dir {
+        Some(dir) => WadHashMappers::lazy_from_dirpath(dir, false).with_path_normalization(true),
+        None => WadHashMappers::default(),
+    };
+    Ok((wad, hmappers))
+}
+
+/// Names of data formats, as used by the `--format` filter
+const DATA_FORMAT_NAMES: [&str; 5] = [
+    WadDataFormat::Uncompressed.name(),
+    WadDataFormat::Gzip.name(),
+    WadDataFormat::Redirection.name(),
+    WadDataFormat::Zstd.name(),
+    WadDataFormat::Chunked(0).name(),
+];
+
+/// Filter on entry data formats, empty to match all entries
+struct DataFormatFilter(Vec<&'static str>);
+
+impl DataFormatFilter {
+    fn matches(&self, entry: &WadEntry) -> bool {
+        if self.0.is_empty() {
+            return true;
+        }
+        self.0.contains(&entry.data_format().name())
+    }
+}
+
+/// Get the data format filter from the `--format` parameter
+fn data_formats_from_matches(matches: &ArgMatches) -> DataFormatFilter {
+    let formats = matches.get_many::<String>("format")
+        .map(|values| values.filter_map(|v| DATA_FORMAT_NAMES.iter().copied().find(|name| name == v)).collect())
+        .unwrap_or_default();
+    DataFormatFilter(formats)
+}
+
+/// Get the basename of a WAD path
+fn path_basename(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(_, name)| name)
+}
+
+/// Naming of extracted files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ExtractNaming {
+    /// Use WAD paths, relative to the output directory
+    Paths,
+    /// Use the basename of WAD paths, in the output directory
+    Flatten,
+    /// Use hashes, in the output directory, with the known or guessed extension
+    HashNames,
+}
+
+/// Compute output paths of extracted files
+struct ExtractPaths<'a> {
+    output: &'a Path,
+    /// Output directory of unknown files, `None` to skip them
+    unknown: Option<PathBuf>,
+    naming: ExtractNaming,
+    /// Number of extracted files per basename, to detect collisions when flattening
+    basename_counts: HashMap<&'a str, usize>,
+}
+
+impl<'a> ExtractPaths<'a> {
+    /// Prepare output paths, `paths` are the known paths of all extracted files
+    fn new<I: Iterator<Item=&'a str>>(output: &'a Path, unknown: Option<PathBuf>, naming: ExtractNaming, paths: I) -> Self {
+        let mut basename_counts = HashMap::new();
+        if naming == ExtractNaming::Flatten {
+            for path in paths {
+                *basename_counts.entry(path_basename(path)).or_default() += 1;
+            }
+        }
+        Self { output, unknown, naming, basename_counts }
+    }
+
+    /// Get the output path of an entry, `None` if it should be skipped
+    ///
+    /// `path` is the known path of the entry.
+    /// `guess_extension` is only called for unknown entries named after their hash.
+    fn entry_path<F>(&self, hash: WadEntryHash, path: Option<&str>, guess_extension: F) -> Option<PathBuf>
+    where F: FnOnce() -> Option<&'static str> {
+        match (self.naming, path) {
+            (ExtractNaming::HashNames, path) => {
+                let extension = match path {
+                    Some(path) => path_basename(path).rsplit_once('.').map(|(_, ext)| ext),
+                    None => guess_extension(),
+                };
+                match extension {
+                    Some(ext) => Some(self.output.join(format!("{:x}.{}", hash, ext))),
+                    None => Some(self.output.join(format!("{:x}", hash))),
+                }
+            }
+            (ExtractNaming::Flatten, Some(path)) => {
+                let basename = path_basename(path);
+                if self.basename_counts.get(basename).copied().unwrap_or(0) > 1 {
+                    // Insert the hash before the extension
+                    match basename.rsplit_once('.') {
+                        Some((stem, ext)) => Some(self.output.join(format!("{}.{:x}.{}", stem, hash, ext))),
+                        None => Some(self.output.join(format!("{}.{:x}", basename, hash))),
+                    }
+                } else {
+                    Some(self.output.join(basename))
+                }
+            }
+            (_, Some(path)) => Some(self.output.join(path)),
+            (_, None) => self.unknown.as_ref().map(|dir| dir.join(format!("{:x}", hash))),
         }
     }
-    Ok((wad, hmapper))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_path(paths: &ExtractPaths, hash: u64, path: Option<&str>) -> Option<PathBuf> {
+        paths.entry_path(hash.into(), path, || Some("guessed"))
+    }
+
+    #[test]
+    fn extract_paths_from_wad_paths() {
+        let output = Path::new("out");
+        let paths = ExtractPaths::new(output, None, ExtractNaming::Paths, ["data/a.bin"].into_iter());
+        assert_eq!(entry_path(&paths, 0x1, Some("data/a.bin")), Some(PathBuf::from("out/data/a.bin")));
+        assert_eq!(entry_path(&paths, 0x2, None), None);
+
+        let paths = ExtractPaths::new(output, Some(output.join("unknown")), ExtractNaming::Paths, std::iter::empty());
+        assert_eq!(entry_path(&paths, 0x2, None), Some(PathBuf::from("out/unknown/0000000000000002")));
+    }
+
+    #[test]
+    fn extract_paths_flatten_collisions() {
+        let output = Path::new("out");
+        let known = ["data/a.bin", "assets/a.bin", "data/b.bin", "data/noext", "assets/noext"];
+        let paths = ExtractPaths::new(output, Some(output.join("unknown")), ExtractNaming::Flatten, known.into_iter());
+        assert_eq!(entry_path(&paths, 0x1, Some("data/a.bin")), Some(PathBuf::from("out/a.0000000000000001.bin")));
+        assert_eq!(entry_path(&paths, 0x2, Some("assets/a.bin")), Some(PathBuf::from("out/a.0000000000000002.bin")));
+        assert_eq!(entry_path(&paths, 0x3, Some("data/b.bin")), Some(PathBuf::from("out/b.bin")));
+        assert_eq!(entry_path(&paths, 0x4, Some("data/noext")), Some(PathBuf::from("out/noext.0000000000000004")));
+        assert_eq!(entry_path(&paths, 0x5, None), Some(PathBuf::from("out/unknown/0000000000000005")));
+    }
+
+    #[test]
+    fn extract_paths_hash_names() {
+        let output = Path::new("out");
+        let paths = ExtractPaths::new(output, Some(output.join("unknown")), ExtractNaming::HashNames, ["data/a.bin"].into_iter());
+        // Known extension, the extension is not guessed
+        let path = paths.entry_path(0x1.into(), Some("data/a.bin"), || unreachable!());
+        assert_eq!(path, Some(PathBuf::from("out/0000000000000001.bin")));
+        assert_eq!(entry_path(&paths, 0x2, Some("data/noext")), Some(PathBuf::from("out/0000000000000002")));
+        // Unknown files are put in the output directory, with a guessed extension
+        assert_eq!(entry_path(&paths, 0x3, None), Some(PathBuf::from("out/0000000000000003.guessed")));
+        let path = paths.entry_path(0x4.into(), None, || None);
+        assert_eq!(path, Some(PathBuf::from("out/0000000000000004")));
+    }
+
+    #[test]
+    fn data_format_names_are_accepted_by_filter() {
+        for b in 0..=u8::MAX {
+            if let Ok(format) = WadDataFormat::try_from(b) {
+                assert!(DATA_FORMAT_NAMES.contains(&format.name()), "{:?}", format);
+            }
+        }
+    }
+}
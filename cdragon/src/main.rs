@@ -4,8 +4,12 @@ use std::collections::HashMap;
 mod cli;
 mod utils;
 mod cmd_bin;
+mod cmd_champion;
+mod cmd_check_install;
+mod cmd_locale;
 mod cmd_rman;
 mod cmd_rst;
+mod cmd_store;
 mod cmd_wad;
 #[cfg(feature = "hashes")]
 mod cmd_hashes;
@@ -57,8 +61,12 @@ impl Cli {
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     Cli::new()
         .register("bin", cmd_bin::subcommand)
+        .register("champion", cmd_champion::subcommand)
+        .register("check-install", cmd_check_install::subcommand)
+        .register("locale", cmd_locale::subcommand)
         .register("rman", cmd_rman::subcommand)
         .register("rst", cmd_rst::subcommand)
+        .register("store", cmd_store::subcommand)
         .register("wad", cmd_wad::subcommand)
         .register_hashes()
         .process()
@@ -1,9 +1,11 @@
 //! Tools shared by different subcommands
 use std::io;
-use std::hash::Hash;
 use std::path::{Path, PathBuf};
-use num_traits::Num;
+use anyhow::Context;
+use tempfile::NamedTempFile;
 use walkdir::{WalkDir, DirEntry};
+use cdragon_cdn::CdnDownloader;
+use cdragon_rman::{Rman, FileEntry, FileFlags};
 use cdragon_prop::{
     is_binfile_path,
     BinHashMappers,
@@ -12,86 +14,12 @@ use cdragon_prop::{
     BinSerializer,
     BinEntriesSerializer,
 };
-use cdragon_hashes::HashMapper;
-
-
-/// Match strings against pattern with `*` wildcards
-pub struct PathPattern<'a> {
-    prefix: &'a str,
-    suffix: Option<&'a str>,
-    parts: Vec<&'a str>,
-}
-
-impl<'a> PathPattern<'a> {
-    pub fn new(pattern: &'a str) -> Self {
-        let mut it = pattern.split('*');
-        let prefix = it.next().unwrap();  // `pattern` cannot be empty
-        let mut parts: Vec<&str> = it.collect();
-        let suffix = parts.pop();
-        Self { prefix, suffix, parts }
-    }
-
-    pub fn is_match(&self, mut s: &str) -> bool {
-        // No suffix means no `*`, compare the whole string
-        if self.suffix.is_none() {
-            return self.prefix == s;
-        }
-
-        // Prefix and suffix must match
-        if !s.starts_with(self.prefix) {
-            return false;
-        }
-        s = &s[self.prefix.len()..];
-        if !s.ends_with(self.suffix.unwrap()) {
-            return false;
-        }
-        s = &s[.. s.len() - self.suffix.unwrap().len()];
-
-        // Find parts, one after the other
-        for part in self.parts.iter() {
-            s = match s.find(part) {
-                None => return false,
-                Some(i) => &s[i + part.len() ..],
-            };
-        }
-        true
-    }
-}
-
-/// Match hash value against pattern
-///
-/// Pattern can be the hex representation of a hash value or a string pattern with `*` wildcards.
-pub enum HashValuePattern<'a, T: Num + Eq + Hash + Copy> {
-    Hash(T),
-    Path(PathPattern<'a>),
-}
-
-impl<'a, T: Num + Eq + Hash + Copy> HashValuePattern<'a, T> {
-    pub fn new(pattern: &'a str) -> Self {
-        // If pattern matches a hash value, consider it's a hash
-        if pattern.len() == std::mem::size_of::<T>() * 2 {
-            if let Ok(hash) = T::from_str_radix(pattern, 16) {
-                return Self::Hash(hash);
-            }
-        }
-
-        // Otherwise, parse as a path pattern
-        Self::Path(PathPattern::new(pattern))
-    }
-
-    pub fn is_match<const N: usize>(&self, hash: T, mapper: &HashMapper<T, N>) -> bool {
-        match self {
-            Self::Hash(h) => hash == *h,
-            Self::Path(pattern) => {
-                if let Some(path) = mapper.get(hash) {
-                    pattern.is_match(path)
-                } else {
-                    false
-                }
-            }
-        }
-    }
-}
+use cdragon_utils::{
+    locale::{Locale, path_locale},
+    progress::{Progress, ProgressEvent},
+};
+pub use cdragon_hashes::HashValuePattern;
+pub use cdragon_utils::pattern::PathPattern;
 
 
 /// Canonicalize a path, avoid errors on long file names
@@ -133,6 +61,59 @@ pub fn bin_files_from_dir<P: AsRef<Path>>(root: P) -> impl Iterator<Item=PathBuf
 }
 
 
+/// Sanitize a string to be used as a file name, on all platforms
+///
+/// Characters invalid on Windows are replaced by `_`, as well as trailing dots and spaces.
+/// Reserved Windows names (e.g. `CON`, `NUL`) are prefixed by `_`.
+/// Names are truncated to [MAX_FILENAME_LEN] bytes.
+pub fn sanitize_filename(name: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL",
+        "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+        "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    let mut result: String = name.chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect();
+    if result.is_empty() {
+        return "_".into();
+    }
+    let stem = result.split('.').next().unwrap_or("");
+    if RESERVED.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        result.insert(0, '_');
+    }
+    if result.len() > MAX_FILENAME_LEN {
+        let mut end = MAX_FILENAME_LEN;
+        while !result.is_char_boundary(end) {
+            end -= 1;
+        }
+        result.truncate(end);
+    }
+    let trimmed_len = result.trim_end_matches(['.', ' ']).len();
+    result.replace_range(trimmed_len.., &"_".repeat(result.len() - trimmed_len));
+    result
+}
+
+/// Maximum length of file names returned by [sanitize_filename()]
+///
+/// Keep a margin below usual 255-byte limits, to allow adding an extension.
+pub const MAX_FILENAME_LEN: usize = 200;
+
+/// Sanitize a `/`-separated path, to be used as a relative file path
+///
+/// Each component is sanitized using [sanitize_filename()]. Empty, `.` and `..` components are
+/// replaced by `_`.
+pub fn sanitize_relative_path(path: &str) -> PathBuf {
+    path.split('/')
+        .map(|part| match part {
+            "" | "." | ".." => "_".into(),
+            _ => sanitize_filename(part),
+        })
+        .collect()
+}
+
+
 /// Create bin entry serializer
 pub fn build_bin_entry_serializer<'a, W: io::Write>(writer: &'a mut W, hmappers: &'a BinHashMappers, json: bool) -> io::Result<Box<dyn BinEntriesSerializer + 'a>> {
     if json {
@@ -142,3 +123,313 @@ pub fn build_bin_entry_serializer<'a, W: io::Write>(writer: &'a mut W, hmappers:
     }
 }
 
+
+/// Create a progress reporter on stderr, for `total_files` files
+///
+/// Progress is not reported if `quiet` is set, or if stderr is not a terminal: a line updated in
+/// place would clutter logs.
+pub fn stderr_progress(quiet: bool, total_files: usize) -> Box<dyn Progress> {
+    use io::IsTerminal;
+    if quiet || !io::stderr().is_terminal() {
+        Box::new(())
+    } else {
+        Box::new(StderrProgress::default().with_total_files(total_files))
+    }
+}
+
+/// Report progress on stderr, on a single line updated in place
+///
+/// The line is cleared when a file is finished.
+#[derive(Default)]
+pub struct StderrProgress {
+    /// Name of the current file
+    name: String,
+    /// Size of the current file, if known
+    total_bytes: Option<u64>,
+    /// Bytes processed for the current file
+    bytes: u64,
+    /// Number of finished files
+    files: usize,
+    /// Total number of files, if known
+    total_files: Option<usize>,
+}
+
+impl StderrProgress {
+    /// Set the total number of files, to display it in the progress line
+    pub fn with_total_files(mut self, n: usize) -> Self {
+        self.total_files = Some(n);
+        self
+    }
+
+    fn print(&self) {
+        use io::Write;
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[K");
+        if let Some(total) = self.total_files {
+            let _ = write!(stderr, "[{}/{}] ", self.files + 1, total);
+        }
+        let _ = match self.total_bytes {
+            Some(total) if total > 0 => write!(stderr, "{}: {}% ({}/{} bytes)", self.name, self.bytes * 100 / total, self.bytes, total),
+            _ => write!(stderr, "{}: {} bytes", self.name, self.bytes),
+        };
+        let _ = stderr.flush();
+    }
+}
+
+impl Progress for StderrProgress {
+    fn on_event(&mut self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::FileStarted { name, total_bytes } => {
+                self.name = name.to_owned();
+                self.total_bytes = total_bytes;
+                self.bytes = 0;
+                self.print();
+            }
+            ProgressEvent::BytesTransferred { n } => {
+                self.bytes += n;
+                self.print();
+            }
+            ProgressEvent::FileFinished => {
+                self.files += 1;
+                eprint!("\r\x1b[K");
+            }
+        }
+    }
+}
+
+
+/// Kind of an input argument
+#[derive(Debug, PartialEq, Eq)]
+pub enum InputKind<'a> {
+    /// Local file path
+    Path(&'a Path),
+    /// Standard input, given as `-`
+    Stdin,
+    /// HTTP(S) URL
+    Url(&'a str),
+}
+
+impl<'a> InputKind<'a> {
+    /// Determine the kind of an input argument
+    pub fn from_arg(arg: &'a Path) -> Self {
+        match arg.to_str() {
+            Some("-") => Self::Stdin,
+            Some(s) if s.starts_with("http://") || s.starts_with("https://") => Self::Url(s),
+            _ => Self::Path(arg),
+        }
+    }
+}
+
+/// Input data given on the command line, see [open_input()]
+///
+/// Temporary files are removed when the input is dropped.
+#[derive(Debug)]
+pub struct InputSource {
+    /// Name of the input: path, URL or `-`
+    name: String,
+    data: InputData,
+}
+
+/// Location of input data
+#[derive(Debug)]
+enum InputData {
+    /// Local file
+    Path(PathBuf),
+    /// Temporary file, for downloaded or buffered data
+    Temporary(NamedTempFile),
+    /// Unbuffered stdin
+    Stdin,
+}
+
+/// Open an input from a command line argument
+///
+/// The argument can be a local path, an HTTP(S) URL or `-` to read from stdin.
+/// URLs are downloaded to a temporary file.
+pub fn open_input(arg: &Path) -> anyhow::Result<InputSource> {
+    let name = arg.to_string_lossy().into_owned();
+    let data = match InputKind::from_arg(arg) {
+        InputKind::Path(path) => InputData::Path(path.to_path_buf()),
+        InputKind::Stdin => InputData::Stdin,
+        InputKind::Url(url) => {
+            let mut file = temporary_input_file(url_file_name(url))?;
+            CdnDownloader::new()?.download_url_to_writer(url, file.as_file_mut())
+                .with_context(|| format!("failed to download {}", url))?;
+            InputData::Temporary(file)
+        }
+    };
+    Ok(InputSource { name, data })
+}
+
+impl InputSource {
+    /// Get the name of the input (as given on the command line)
+    ///
+    /// The file name part can be used to guess the file type.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get a reader on input data
+    pub fn reader(&self) -> io::Result<Box<dyn io::Read + '_>> {
+        match &self.data {
+            InputData::Path(path) => Ok(Box::new(io::BufReader::new(std::fs::File::open(path)?))),
+            InputData::Temporary(file) => Ok(Box::new(io::BufReader::new(file.reopen()?))),
+            InputData::Stdin => Ok(Box::new(io::stdin().lock())),
+        }
+    }
+
+    /// Get a path to input data
+    ///
+    /// Stdin is buffered to a temporary file, which is needed by formats requiring `Seek`.
+    pub fn path(&mut self) -> anyhow::Result<&Path> {
+        if let InputData::Stdin = self.data {
+            let mut file = temporary_input_file("stdin")?;
+            io::copy(&mut io::stdin().lock(), file.as_file_mut()).context("failed to read stdin")?;
+            self.data = InputData::Temporary(file);
+        }
+        match &self.data {
+            InputData::Path(path) => Ok(path),
+            InputData::Temporary(file) => Ok(file.path()),
+            InputData::Stdin => unreachable!(),
+        }
+    }
+}
+
+/// Create a temporary input file, keep the file name as suffix
+///
+/// The file is created securely, with a random name, and removed when dropped.
+fn temporary_input_file(name: &str) -> io::Result<NamedTempFile> {
+    let name = sanitize_filename(if name.is_empty() { "input" } else { name });
+    tempfile::Builder::new()
+        .prefix("cdragon-")
+        .suffix(&format!("-{}", name))
+        .tempfile()
+}
+
+/// Get the file name of a URL, without query string nor fragment
+fn url_file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit('/').next().unwrap_or_default()
+}
+
+
+/// Return `true` if a file is not localized, or localized for the given locale
+///
+/// Use locales from file flags if there are any, fall back to the file path.
+pub fn file_matches_locale(entry: &FileEntry, path: &str, flags: &FileFlags, locale: Locale) -> bool {
+    let file_locales: Vec<Locale> = entry.flags
+        .map(|f| f.resolve(flags).filter_map(Locale::new).collect())
+        .unwrap_or_default();
+    if file_locales.is_empty() {
+        path_locale(path).is_none_or(|l| l == locale)
+    } else {
+        file_locales.contains(&locale)
+    }
+}
+
+/// Open a manifest from a command line argument
+pub fn open_rman(arg: &Path) -> anyhow::Result<Rman> {
+    let input = open_input(arg)?;
+    let rman = Rman::read(input.reader()?).with_context(|| format!("failed to read manifest {}", input.name()))?;
+    Ok(rman)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn input_kind_from_arg() {
+        assert_eq!(InputKind::from_arg(Path::new("-")), InputKind::Stdin);
+        assert_eq!(InputKind::from_arg(Path::new("a.manifest")), InputKind::Path(Path::new("a.manifest")));
+        assert_eq!(InputKind::from_arg(Path::new("./-")), InputKind::Path(Path::new("./-")));
+        assert_eq!(InputKind::from_arg(Path::new("https://example.com/a.manifest")), InputKind::Url("https://example.com/a.manifest"));
+        assert_eq!(InputKind::from_arg(Path::new("http://example.com/a")), InputKind::Url("http://example.com/a"));
+        assert_eq!(InputKind::from_arg(Path::new("ftp://example.com/a")), InputKind::Path(Path::new("ftp://example.com/a")));
+    }
+
+    #[test]
+    fn open_input_local_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("data.bin");
+        std::fs::write(&path, b"content").unwrap();
+
+        let mut input = open_input(&path).unwrap();
+        assert_eq!(input.name(), path.to_string_lossy());
+        let mut data = Vec::new();
+        input.reader().unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"content");
+        assert_eq!(input.path().unwrap(), path);
+        drop(input);
+        // Local files are never removed
+        assert!(path.is_file());
+    }
+
+    #[test]
+    fn open_input_stdin_is_not_read_eagerly() {
+        let input = open_input(Path::new("-")).unwrap();
+        assert_eq!(input.name(), "-");
+        assert!(matches!(input.data, InputData::Stdin));
+    }
+
+    #[test]
+    fn open_input_url_download_error() {
+        let err = open_input(Path::new("http://127.0.0.1:1/a.manifest")).unwrap_err();
+        assert!(err.to_string().contains("failed to download"), "{}", err);
+    }
+
+    #[test]
+    fn temporary_input_file_is_removed_on_drop() {
+        let file = temporary_input_file("a.manifest").unwrap();
+        let path = file.path().to_path_buf();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("cdragon-") && name.ends_with("-a.manifest"), "{}", name);
+        let input = InputSource { name: "-".into(), data: InputData::Temporary(file) };
+        assert!(path.is_file());
+        drop(input);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn url_file_name_strips_query_and_fragment() {
+        assert_eq!(url_file_name("https://example.com/dir/a.manifest"), "a.manifest");
+        assert_eq!(url_file_name("https://example.com/a.manifest?token=x/y#frag"), "a.manifest");
+        assert_eq!(url_file_name("https://example.com/a.bin#part"), "a.bin");
+        assert_eq!(url_file_name("https://example.com/"), "");
+    }
+
+    #[test]
+    fn sanitize_filename_replaces_invalid_characters() {
+        assert_eq!(sanitize_filename("Items/A:B?"), "Items_A_B_");
+        assert_eq!(sanitize_filename("a\tb<c>|\"d\\*"), "a_b_c___d__");
+        assert_eq!(sanitize_filename("name. "), "name__");
+        assert_eq!(sanitize_filename(""), "_");
+        assert_eq!(sanitize_filename("Ahri.bin"), "Ahri.bin");
+    }
+
+    #[test]
+    fn sanitize_filename_escapes_reserved_names() {
+        assert_eq!(sanitize_filename("CON"), "_CON");
+        assert_eq!(sanitize_filename("nul.txt"), "_nul.txt");
+        assert_eq!(sanitize_filename("Com1.json"), "_Com1.json");
+        assert_eq!(sanitize_filename("CONSOLE"), "CONSOLE");
+    }
+
+    #[test]
+    fn sanitize_filename_truncates_long_names() {
+        let name = "é".repeat(MAX_FILENAME_LEN);
+        let result = sanitize_filename(&name);
+        assert!(result.len() <= MAX_FILENAME_LEN);
+        assert_eq!(result, "é".repeat(MAX_FILENAME_LEN / 2));
+        assert_eq!(sanitize_filename(&"a".repeat(300)).len(), MAX_FILENAME_LEN);
+    }
+
+    #[test]
+    fn sanitize_relative_path_sanitizes_components() {
+        assert_eq!(sanitize_relative_path("Characters/Ahri/Skins/Skin0"), Path::new("Characters/Ahri/Skins/Skin0"));
+        assert_eq!(sanitize_relative_path("../a//b/./c:d"), Path::new("_/a/_/b/_/c_d"));
+        assert_eq!(sanitize_relative_path("/abs"), Path::new("_/abs"));
+        assert_eq!(sanitize_relative_path("a\\b"), Path::new("a_b"));
+    }
+}